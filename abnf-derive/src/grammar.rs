@@ -0,0 +1,138 @@
+//! The `abnf!` macro.
+//!
+//! The macro turns its input back into ABNF text, reads it via
+//! `abnf::grammar::RuleList`, and hands the rule list to the code
+//! generator of `abnf::grammar::codegen`.
+
+use abnf::grammar::{validate, Diagnostic, GrammarError, RuleList};
+use abnf::grammar::codegen::Generator;
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+use item::{unescape, Error};
+
+
+//------------ expand --------------------------------------------------------
+
+/// Expands an invocation of the macro.
+pub fn expand(input: TokenStream) -> Result<TokenStream, Error> {
+    let text = Text::from_tokens(input)?;
+    let rules = match RuleList::from_slice(text.data.as_bytes()) {
+        Ok(rules) => rules,
+        Err(GrammarError::Syntax(line)) => {
+            return Err(Error::new(text.line_span(line), "invalid ABNF"))
+        }
+        Err(GrammarError::Duplicate(name)) => {
+            return Err(Error::new(
+                Span::call_site(), format!("rule '{}' defined twice", name)
+            ))
+        }
+        Err(GrammarError::Undefined(name)) => {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("alternatives added to undefined rule '{}'", name)
+            ))
+        }
+    };
+    for diagnostic in validate(&rules, None) {
+        if let Diagnostic::LeftRecursion(names) = diagnostic {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("left-recursive rules: {}", names.join(", "))
+            ))
+        }
+    }
+    let mut gen = Generator::new();
+    gen.set_wrappers(true);
+    gen.generate(&rules).parse().map_err(|_| {
+        Error::new(Span::call_site(), "generated code is invalid")
+    })
+}
+
+
+//------------ Text ----------------------------------------------------------
+
+/// The ABNF text of the macro input.
+struct Text {
+    /// The text itself.
+    data: String,
+
+    /// The span of the first token of each line.
+    lines: Vec<Span>,
+}
+
+impl Text {
+    /// Recreates the text from the input tokens.
+    ///
+    /// If the input is a single string literal, its content is the text.
+    /// Otherwise, the tokens are written out with line breaks and spaces
+    /// placed where they were in the source.
+    fn from_tokens(input: TokenStream) -> Result<Self, Error> {
+        let tokens: Vec<_> = input.into_iter().collect();
+        if let [TokenTree::Literal(ref lit)] = tokens.as_slice() {
+            let repr = lit.to_string();
+            if !(repr.starts_with('"') || repr.starts_with("r\"")
+                    || repr.starts_with("r#")) {
+                return Err(Error::new(lit.span(), "expected string literal"))
+            }
+            return Ok(Text {
+                data: unescape(&repr),
+                lines: vec![lit.span()],
+            })
+        }
+        let mut res = Text { data: String::new(), lines: Vec::new() };
+        let mut pos = None;
+        for token in tokens {
+            res.push_tree(token, &mut pos);
+        }
+        res.data.push('\n');
+        Ok(res)
+    }
+
+    fn push_tree(&mut self, tree: TokenTree,
+                 pos: &mut Option<(usize, usize)>) {
+        let group = match tree {
+            TokenTree::Group(group) => group,
+            tree => return self.push(&tree.to_string(), tree.span(), pos)
+        };
+        let (open, close) = match group.delimiter() {
+            Delimiter::Parenthesis => ("(", ")"),
+            Delimiter::Bracket => ("[", "]"),
+            Delimiter::Brace => ("{", "}"),
+            Delimiter::None => ("", ""),
+        };
+        self.push(open, group.span_open(), pos);
+        for tree in group.stream() {
+            self.push_tree(tree, pos);
+        }
+        self.push(close, group.span_close(), pos);
+    }
+
+    /// Appends the text of a token at the position of its span.
+    ///
+    /// `pos` is the line and column where the previous token ended.
+    fn push(&mut self, text: &str, span: Span,
+            pos: &mut Option<(usize, usize)>) {
+        if text.is_empty() {
+            return
+        }
+        match *pos {
+            None => self.lines.push(span),
+            Some((line, _)) if span.line() > line => {
+                self.data.push('\n');
+                self.lines.push(span);
+            }
+            Some((_, column)) if span.column() > column => {
+                self.data.push(' ')
+            }
+            _ => { }
+        }
+        self.data.push_str(text);
+        let end = span.end();
+        *pos = Some((end.line(), end.column()));
+    }
+
+    /// Returns the span for a line number starting at 1.
+    fn line_span(&self, line: usize) -> Span {
+        self.lines.get(line - 1).or_else(|| self.lines.last()).cloned()
+                  .unwrap_or_else(Span::call_site)
+    }
+}
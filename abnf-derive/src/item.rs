@@ -0,0 +1,455 @@
+//! Parsing the items the derive macros are applied to.
+//!
+//! In order to not depend on any crates, this module contains a minimal
+//! parser for the token stream of a struct or enum definition. It only
+//! extracts what the derives need: the name of the type, its fields or
+//! variants, and the `#[abnf(…)]` attributes attached to all of these.
+
+use std::fmt;
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span,
+                 TokenStream, TokenTree};
+
+
+//------------ Item ----------------------------------------------------------
+
+/// A struct or enum definition.
+pub struct Item {
+    /// The `abnf` attributes of the type.
+    pub attrs: Vec<Attr>,
+
+    /// The name of the type.
+    pub name: String,
+
+    /// The span of the name, used for errors.
+    pub span: Span,
+
+    /// The content of the type.
+    pub kind: ItemKind,
+}
+
+/// The content of a struct or enum.
+pub enum ItemKind {
+    Struct(Fields),
+    Enum(Vec<Variant>),
+}
+
+impl Item {
+    /// Parses an item from the token stream given to a derive.
+    pub fn from_tokens(tokens: TokenStream) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(tokens);
+        let attrs = cursor.attrs()?;
+        cursor.visibility();
+        let keyword = cursor.ident()?;
+        let (name, span) = {
+            let ident = cursor.ident()?;
+            (ident.to_string(), ident.span())
+        };
+        if cursor.is_punct('<') {
+            return Err(Error::new(span, "generic types are not supported"))
+        }
+        let kind = match keyword.to_string().as_str() {
+            "struct" => ItemKind::Struct(cursor.fields()?),
+            "enum" => {
+                let group = cursor.group(Delimiter::Brace)?;
+                ItemKind::Enum(Cursor::new(group.stream()).variants()?)
+            }
+            _ => {
+                return Err(Error::new(keyword.span(),
+                                      "only structs and enums are supported"))
+            }
+        };
+        Ok(Item { attrs, name, span, kind })
+    }
+}
+
+
+//------------ Fields --------------------------------------------------------
+
+/// The fields of a struct or an enum variant.
+pub enum Fields {
+    Named(Vec<Field>),
+    Unnamed(Vec<Field>),
+    Unit,
+}
+
+impl Fields {
+    /// Returns a slice of the fields.
+    pub fn as_slice(&self) -> &[Field] {
+        match *self {
+            Fields::Named(ref fields) | Fields::Unnamed(ref fields) => fields,
+            Fields::Unit => &[],
+        }
+    }
+}
+
+
+//------------ Field ---------------------------------------------------------
+
+/// A single field.
+pub struct Field {
+    /// The `abnf` attributes of the field.
+    pub attrs: Vec<Attr>,
+
+    /// The name of the field or `None` for a tuple field.
+    pub name: Option<String>,
+
+    /// The span of the field, used for errors.
+    pub span: Span,
+}
+
+
+//------------ Variant -------------------------------------------------------
+
+/// A variant of an enum.
+pub struct Variant {
+    /// The `abnf` attributes of the variant.
+    pub attrs: Vec<Attr>,
+
+    /// The name of the variant.
+    pub name: String,
+
+    /// The fields of the variant.
+    pub fields: Fields,
+
+    /// The span of the variant name, used for errors.
+    pub span: Span,
+}
+
+
+//------------ Attr ----------------------------------------------------------
+
+/// A single entry in an `#[abnf(…)]` attribute.
+///
+/// Entries are either of the form `key = "value"` or just `key`.
+pub struct Attr {
+    /// The key of the entry.
+    pub key: String,
+
+    /// The value of the entry if it has one.
+    pub value: Option<Literal>,
+
+    /// The span of the key, used for errors.
+    pub span: Span,
+}
+
+impl Attr {
+    /// Returns the value as source text of a string literal.
+    ///
+    /// This is the literal including its quotes as it appeared in the
+    /// source.
+    pub fn literal(&self) -> Result<String, Error> {
+        match self.value {
+            Some(ref value) => {
+                let res = value.to_string();
+                if res.starts_with('"') || res.starts_with("r\"")
+                        || res.starts_with("r#") {
+                    Ok(res)
+                }
+                else {
+                    Err(Error::new(value.span(), "expected string literal"))
+                }
+            }
+            None => {
+                Err(Error::new(self.span,
+                               format!("missing value for '{}'", self.key)))
+            }
+        }
+    }
+
+    /// Returns the value as the content of a string literal.
+    pub fn string(&self) -> Result<String, Error> {
+        self.literal().map(|lit| unescape(&lit))
+    }
+
+    /// Returns an error if the entry has a value.
+    pub fn flag(&self) -> Result<(), Error> {
+        match self.value {
+            Some(ref value) => {
+                Err(Error::new(value.span(),
+                               format!("'{}' doesn’t take a value",
+                                       self.key)))
+            }
+            None => Ok(())
+        }
+    }
+
+    /// Returns an error for an unknown key.
+    pub fn unknown(&self) -> Error {
+        Error::new(self.span, format!("unknown attribute '{}'", self.key))
+    }
+}
+
+
+//------------ unescape ------------------------------------------------------
+
+/// Returns the content of a string literal given in its source form.
+pub fn unescape(lit: &str) -> String {
+    if let Some(lit) = lit.strip_prefix('r') {
+        let lit = lit.trim_matches('#');
+        return lit[1..lit.len() - 1].into()
+    }
+    let mut res = String::new();
+    let mut chars = lit[1..lit.len() - 1].chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            res.push(ch);
+            continue
+        }
+        match chars.next() {
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            Some('t') => res.push('\t'),
+            Some('0') => res.push('\0'),
+            Some(ch) => res.push(ch),
+            None => { }
+        }
+    }
+    res
+}
+
+
+//------------ Error ---------------------------------------------------------
+
+/// An error while deriving.
+pub struct Error {
+    span: Span,
+    msg: String,
+}
+
+impl Error {
+    /// Creates a new error at the given span.
+    pub fn new<S: fmt::Display>(span: Span, msg: S) -> Self {
+        Error { span, msg: msg.to_string() }
+    }
+
+    /// Converts the error into an invocation of `compile_error!()`.
+    pub fn into_compile_error(self) -> TokenStream {
+        let mut msg = Literal::string(&self.msg);
+        msg.set_span(self.span);
+        let mut group = Group::new(
+            Delimiter::Parenthesis, TokenTree::from(msg).into()
+        );
+        group.set_span(self.span);
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.span);
+        let mut semi = Punct::new(';', Spacing::Alone);
+        semi.set_span(self.span);
+        vec![
+            TokenTree::from(Ident::new("compile_error", self.span)),
+            bang.into(), group.into(), semi.into(),
+        ].into_iter().collect()
+    }
+}
+
+
+//------------ Cursor --------------------------------------------------------
+
+/// A cursor over a sequence of tokens.
+struct Cursor {
+    tokens: Vec<TokenTree>,
+    pos: usize,
+}
+
+impl Cursor {
+    fn new(tokens: TokenStream) -> Self {
+        Cursor { tokens: tokens.into_iter().collect(), pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.get(self.pos)
+    }
+
+    fn span(&self) -> Span {
+        match self.peek() {
+            Some(token) => token.span(),
+            None => Span::call_site()
+        }
+    }
+
+    fn is_punct(&self, ch: char) -> bool {
+        match self.peek() {
+            Some(TokenTree::Punct(punct)) => punct.as_char() == ch,
+            _ => false
+        }
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(TokenTree::Ident(ident)) => ident.to_string() == name,
+            _ => false
+        }
+    }
+
+    fn ident(&mut self) -> Result<Ident, Error> {
+        match self.peek().cloned() {
+            Some(TokenTree::Ident(ident)) => {
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(Error::new(self.span(), "expected identifier"))
+        }
+    }
+
+    fn punct(&mut self, ch: char) -> Result<(), Error> {
+        if self.is_punct(ch) {
+            self.pos += 1;
+            Ok(())
+        }
+        else {
+            Err(Error::new(self.span(), format!("expected '{}'", ch)))
+        }
+    }
+
+    fn group(&mut self, delimiter: Delimiter) -> Result<Group, Error> {
+        match self.peek().cloned() {
+            Some(TokenTree::Group(ref group))
+                    if group.delimiter() == delimiter => {
+                self.pos += 1;
+                Ok(group.clone())
+            }
+            _ => Err(Error::new(self.span(), "unexpected token"))
+        }
+    }
+
+    /// Skips over a visibility qualifier.
+    fn visibility(&mut self) {
+        if self.is_ident("pub") {
+            self.pos += 1;
+            let _ = self.group(Delimiter::Parenthesis);
+        }
+    }
+
+    /// Parses outer attributes, keeping only the `abnf` ones.
+    fn attrs(&mut self) -> Result<Vec<Attr>, Error> {
+        let mut res = Vec::new();
+        while self.is_punct('#') {
+            self.pos += 1;
+            let group = self.group(Delimiter::Bracket)?;
+            let mut inner = Cursor::new(group.stream());
+            if !inner.is_ident("abnf") {
+                continue
+            }
+            inner.pos += 1;
+            let group = inner.group(Delimiter::Parenthesis)?;
+            Cursor::new(group.stream()).attr_entries(&mut res)?;
+        }
+        Ok(res)
+    }
+
+    /// Parses the comma-separated entries of an `abnf` attribute.
+    fn attr_entries(&mut self, res: &mut Vec<Attr>) -> Result<(), Error> {
+        while !self.is_empty() {
+            let key = self.ident()?;
+            let value = if self.is_punct('=') {
+                self.pos += 1;
+                match self.peek().cloned() {
+                    Some(TokenTree::Literal(lit)) => {
+                        self.pos += 1;
+                        Some(lit)
+                    }
+                    _ => {
+                        return Err(Error::new(self.span(),
+                                              "expected literal"))
+                    }
+                }
+            }
+            else {
+                None
+            };
+            res.push(Attr { key: key.to_string(), value, span: key.span() });
+            if !self.is_empty() {
+                self.punct(',')?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the fields of a struct or variant.
+    fn fields(&mut self) -> Result<Fields, Error> {
+        if self.is_empty() || self.is_punct(';') || self.is_punct(',')
+                || self.is_punct('=') {
+            return Ok(Fields::Unit)
+        }
+        if let Ok(group) = self.group(Delimiter::Brace) {
+            return Cursor::new(group.stream()).field_list(true)
+                                               .map(Fields::Named)
+        }
+        if let Ok(group) = self.group(Delimiter::Parenthesis) {
+            return Cursor::new(group.stream()).field_list(false)
+                                               .map(Fields::Unnamed)
+        }
+        Err(Error::new(self.span(), "unexpected token"))
+    }
+
+    /// Parses a comma-separated list of fields.
+    fn field_list(&mut self, named: bool) -> Result<Vec<Field>, Error> {
+        let mut res = Vec::new();
+        while !self.is_empty() {
+            let attrs = self.attrs()?;
+            self.visibility();
+            let span = self.span();
+            let name = if named {
+                let name = self.ident()?.to_string();
+                self.punct(':')?;
+                Some(name)
+            }
+            else {
+                None
+            };
+            self.until_comma();
+            if !self.is_empty() {
+                self.punct(',')?;
+            }
+            res.push(Field { attrs, name, span });
+        }
+        Ok(res)
+    }
+
+    /// Parses the comma-separated variants of an enum.
+    fn variants(&mut self) -> Result<Vec<Variant>, Error> {
+        let mut res = Vec::new();
+        while !self.is_empty() {
+            let attrs = self.attrs()?;
+            let ident = self.ident()?;
+            let fields = self.fields()?;
+            if self.is_punct('=') {
+                self.until_comma();
+            }
+            if !self.is_empty() {
+                self.punct(',')?;
+            }
+            res.push(Variant {
+                attrs, name: ident.to_string(), fields, span: ident.span()
+            });
+        }
+        Ok(res)
+    }
+
+    /// Skips over the tokens up to the next top-level comma.
+    ///
+    /// Commas inside angle brackets don’t count.
+    fn until_comma(&mut self) {
+        let mut depth = 0usize;
+        let mut arrow = false;
+        while let Some(token) = self.peek().cloned() {
+            if let TokenTree::Punct(ref punct) = token {
+                match punct.as_char() {
+                    ',' if depth == 0 => break,
+                    '<' => depth += 1,
+                    '>' if !arrow => depth = depth.saturating_sub(1),
+                    _ => { }
+                }
+                arrow = punct.as_char() == '-'
+                        && punct.spacing() == Spacing::Joint;
+            }
+            else {
+                arrow = false;
+            }
+            self.pos += 1;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Derive macros for the `abnf` crate.
+//!
+//! # `AbnfParse`
+//!
+//! Derives a `parse()` function for a struct that is a concatenation of
+//! rules, one for each field. The function has the signature
+//!
+//! ```ignore
+//! pub fn parse<B: Buffer>(buf: &mut B) -> Poll<Self, Error>
+//! ```
+//!
+//! and parses all fields in order, wrapped in `abnf::parse::rule::group()`
+//! so the buffer is rewound if parsing fails or is undecided.
+//!
+//! Each field needs an attribute `#[abnf(rule = "…")]` naming the parsing
+//! function for its value. This is either the path to a function taking
+//! only the buffer, such as `"abnf::core::u8_digits"`, or a call with
+//! further arguments that follow the buffer, such as
+//! `"token::parse_literal(b\"foo\")"`. In addition, fields can have the
+//! following attributes, each of which can appear multiple times and is
+//! applied in order:
+//!
+//! * `prefix = "…"` skips the given literal before the field,
+//! * `suffix = "…"` skips the given literal after the field,
+//! * `before = "…"` applies the given rule before the field, dropping its
+//!   result, and
+//! * `after = "…"` applies the given rule after the field, dropping its
+//!   result.
+//!
+//! These four attributes can also be given to the struct itself in which
+//! case they apply before the first and after the last field. Finally, the
+//! struct attribute `error = "…"` determines the error type of `parse()`.
+//! It defaults to `abnf::parse::token::TokenError`. The error types of all
+//! the rules need to be convertible into this type.
+//!
+//! ```ignore
+//! #[derive(AbnfParse)]
+//! #[abnf(suffix = "\r\n")]
+//! struct Version {
+//!     #[abnf(prefix = "HTTP/", rule = "u8_digits")]
+//!     major: u8,
+//!     #[abnf(prefix = ".", rule = "u8_digits")]
+//!     minor: u8,
+//! }
+//! ```
+//!
+//!
+//! # `AbnfLiterals`
+//!
+//! Derives functions for an enum of keywords. The enum must only have unit
+//! variants. Each variant is spelled like its name unless it has an
+//! attribute `#[abnf(literal = "…")]` giving its canonical spelling.
+//! Additional spellings can be given via any number of
+//! `#[abnf(alias = "…")]` attributes. Keywords are matched ignoring ASCII
+//! case unless the variant or the whole enum have the attribute
+//! `#[abnf(case_sensitive)]`.
+//!
+//! The derive generates three functions:
+//!
+//! * `parse()` with the signature
+//!   `fn parse<B: Buffer>(buf: &mut B) -> Poll<Self, TokenError>` parses
+//!   the longest matching keyword via `abnf::parse::token::parse_keyword()`,
+//! * `as_str()` returns the canonical spelling as a `&'static str`, and
+//! * `compose()` appends the canonical spelling to anything that
+//!   implements `Extend<u8>`, such as a `BytesMut` or a `Vec<u8>`.
+//!
+//! ```ignore
+//! #[derive(AbnfLiterals)]
+//! enum Method {
+//!     #[abnf(literal = "GET")]
+//!     Get,
+//!     #[abnf(literal = "HEAD")]
+//!     Head,
+//!     #[abnf(literal = "M-SEARCH", alias = "MSEARCH")]
+//!     MSearch,
+//! }
+//! ```
+//!
+//!
+//! # `abnf!`
+//!
+//! With the feature `grammar` enabled, the macro `abnf!` generates rule
+//! functions from ABNF given inline:
+//!
+//! ```ignore
+//! abnf! {
+//!     greeting = ("HELO" / "EHLO") SP domain CRLF
+//!     domain   = 1*(ALPHA / DIGIT / "-" / ".")
+//! }
+//! ```
+//!
+//! The code is generated by `abnf::grammar::codegen` with buffer functions
+//! enabled. For each rule, there is a token function with the rule’s name,
+//! such as `greeting()`, plus the buffer functions `parse_greeting()` and
+//! `skip_greeting()`. The grammar is checked when compiling: syntax errors
+//! and left recursion are reported as errors. References to rules that the
+//! grammar doesn’t define, other than the core rules, call the token
+//! function of that name in the surrounding module.
+//!
+//! Since the input has to consist of Rust tokens, not all ABNF can be
+//! given this way. Comments have to be Rust comments, and numeric values
+//! such as `%x1E` that Rust can’t read as tokens are rejected. In these
+//! cases, the grammar can be given as a single string literal instead:
+//!
+//! ```ignore
+//! abnf!(r#"
+//! line = *(%x1E / VCHAR) CRLF  ; with an ABNF comment
+//! "#);
+//! ```
+
+extern crate proc_macro;
+#[cfg(feature = "grammar")] extern crate abnf;
+
+#[cfg(feature = "grammar")] mod grammar;
+mod item;
+mod literals;
+mod parse;
+
+use proc_macro::TokenStream;
+use item::Item;
+
+
+/// Derives a `parse()` function for a struct of concatenated rules.
+#[proc_macro_derive(AbnfParse, attributes(abnf))]
+pub fn derive_abnf_parse(input: TokenStream) -> TokenStream {
+    match Item::from_tokens(input).and_then(|item| parse::derive(&item)) {
+        Ok(res) => res,
+        Err(err) => err.into_compile_error()
+    }
+}
+
+/// Derives keyword functions for an enum.
+#[proc_macro_derive(AbnfLiterals, attributes(abnf))]
+pub fn derive_abnf_literals(input: TokenStream) -> TokenStream {
+    match Item::from_tokens(input).and_then(|item| literals::derive(&item)) {
+        Ok(res) => res,
+        Err(err) => err.into_compile_error()
+    }
+}
+
+/// Generates rule functions from ABNF.
+#[cfg(feature = "grammar")]
+#[proc_macro]
+pub fn abnf(input: TokenStream) -> TokenStream {
+    match grammar::expand(input) {
+        Ok(res) => res,
+        Err(err) => err.into_compile_error()
+    }
+}
@@ -0,0 +1,111 @@
+//! The `AbnfLiterals` derive.
+
+use proc_macro::TokenStream;
+use item::{Error, Fields, Item, ItemKind};
+
+
+//------------ derive --------------------------------------------------------
+
+/// Generates the keyword functions for an enum.
+pub fn derive(item: &Item) -> Result<TokenStream, Error> {
+    let variants = match item.kind {
+        ItemKind::Enum(ref variants) => variants,
+        ItemKind::Struct(_) => {
+            return Err(Error::new(
+                item.span, "AbnfLiterals can only be derived for enums"
+            ))
+        }
+    };
+
+    let mut case_sensitive = false;
+    for attr in &item.attrs {
+        if attr.key == "case_sensitive" {
+            attr.flag()?;
+            case_sensitive = true;
+        }
+        else {
+            return Err(attr.unknown())
+        }
+    }
+
+    let mut table = String::new();
+    let mut values = String::new();
+    let mut spellings = String::new();
+    for (index, variant) in variants.iter().enumerate() {
+        if let Fields::Unit = variant.fields { }
+        else {
+            return Err(Error::new(variant.span,
+                                  "only unit variants are supported"))
+        }
+        let mut literal = None;
+        let mut aliases = Vec::new();
+        let mut variant_case = case_sensitive;
+        for attr in &variant.attrs {
+            match attr.key.as_str() {
+                "literal" => literal = Some(attr.literal()?),
+                "alias" => aliases.push(attr.literal()?),
+                "case_sensitive" => {
+                    attr.flag()?;
+                    variant_case = true;
+                }
+                _ => return Err(attr.unknown())
+            }
+        }
+        let literal = literal.unwrap_or_else(|| {
+            format!("\"{}\"", variant.name)
+        });
+        for spelling in Some(&literal).into_iter().chain(aliases.iter()) {
+            table.push_str(&format!(
+                "::abnf::parse::token::Keyword {{
+                    literal: {}.as_bytes(),
+                    value: {}usize,
+                    case_sensitive: {},
+                }},",
+                spelling, index, variant_case
+            ));
+        }
+        values.push_str(&format!("{} => {}::{},\n",
+                                 index, item.name, variant.name));
+        spellings.push_str(&format!("{}::{} => {},\n",
+                                    item.name, variant.name, literal));
+    }
+
+    format!(
+        "impl {name} {{
+            /// Parses the keyword from the beginning of the buffer.
+            #[allow(dead_code)]
+            pub fn parse<B: ::abnf::parse::buffer::Buffer>(buf: &mut B)
+                          -> ::abnf::Poll<{name},
+                                          ::abnf::parse::token::TokenError> {{
+                const TABLE: &[::abnf::parse::token::Keyword<usize>] = &[
+                    {table}
+                ];
+                ::abnf::parse::token::parse_keyword(buf, TABLE).map(|res| {{
+                    res.map(|index| match index {{
+                        {values}
+                        _ => unreachable!()
+                    }})
+                }})
+            }}
+
+            /// Returns the canonical spelling of the keyword.
+            #[allow(dead_code)]
+            pub fn as_str(&self) -> &'static str {{
+                match *self {{
+                    {spellings}
+                }}
+            }}
+
+            /// Appends the canonical spelling of the keyword to `target`.
+            #[allow(dead_code)]
+            pub fn compose<T>(&self, target: &mut T)
+                           where T: ::std::iter::Extend<u8> {{
+                target.extend(self.as_str().bytes())
+            }}
+        }}",
+        name = item.name, table = table, values = values,
+        spellings = spellings
+    ).parse().map_err(|_| {
+        Error::new(item.span, "invalid literal in 'abnf' attribute")
+    })
+}
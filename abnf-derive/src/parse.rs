@@ -0,0 +1,149 @@
+//! The `AbnfParse` derive.
+
+use proc_macro::TokenStream;
+use item::{Attr, Error, Fields, Item, ItemKind};
+
+
+//------------ derive --------------------------------------------------------
+
+/// Generates the `parse()` function for a struct.
+pub fn derive(item: &Item) -> Result<TokenStream, Error> {
+    let fields = match item.kind {
+        ItemKind::Struct(ref fields) => fields,
+        ItemKind::Enum(_) => {
+            return Err(Error::new(item.span,
+                                  "AbnfParse can only be derived for structs"))
+        }
+    };
+    let mut error = String::from("::abnf::parse::token::TokenError");
+    let mut outer = Steps::default();
+    for attr in &item.attrs {
+        if attr.key == "error" {
+            error = attr.string()?;
+        }
+        else if !outer.push(attr)? {
+            return Err(attr.unknown())
+        }
+    }
+
+    let mut body = outer.before;
+    let mut names = Vec::new();
+    for (i, field) in fields.as_slice().iter().enumerate() {
+        let mut steps = Steps::default();
+        let mut rule = None;
+        for attr in &field.attrs {
+            if attr.key == "rule" {
+                rule = Some(attr.string()?);
+            }
+            else if !steps.push(attr)? {
+                return Err(attr.unknown())
+            }
+        }
+        let rule = match rule {
+            Some(rule) => rule,
+            None => {
+                return Err(Error::new(field.span,
+                                      "missing 'rule' attribute for field"))
+            }
+        };
+        let var = format!("field_{}", i);
+        body.push_str(&steps.before);
+        body.push_str(&format!("let {} = {};\n", var, ready(&call(&rule))));
+        body.push_str(&steps.after);
+        names.push(var);
+    }
+    body.push_str(&outer.after);
+
+    let value = match *fields {
+        Fields::Named(ref fields) => {
+            let inits: Vec<_> = fields.iter().zip(names.iter()).map(|(f, v)| {
+                format!("{}: {}", f.name.as_ref().unwrap(), v)
+            }).collect();
+            format!("{} {{ {} }}", item.name, inits.join(", "))
+        }
+        Fields::Unnamed(_) => format!("{}({})", item.name, names.join(", ")),
+        Fields::Unit => item.name.clone(),
+    };
+
+    format!(
+        "impl {name} {{
+            /// Parses a value from the beginning of the buffer.
+            #[allow(dead_code)]
+            pub fn parse<B: ::abnf::parse::buffer::Buffer>(buf: &mut B)
+                          -> ::abnf::Poll<{name}, {error}> {{
+                ::abnf::parse::rule::group(buf, |buf| {{
+                    {body}
+                    Ok(::abnf::Async::Ready({value}))
+                }})
+            }}
+        }}",
+        name = item.name, error = error, body = body, value = value
+    ).parse().map_err(|_| {
+        Error::new(item.span, "invalid rule in 'abnf' attribute")
+    })
+}
+
+
+//------------ Steps ---------------------------------------------------------
+
+/// The code for the rules skipped before and after a value.
+#[derive(Default)]
+struct Steps {
+    before: String,
+    after: String,
+}
+
+impl Steps {
+    /// Adds the step for an attribute.
+    ///
+    /// Returns whether the attribute was one of the step attributes.
+    fn push(&mut self, attr: &Attr) -> Result<bool, Error> {
+        let code = match attr.key.as_str() {
+            "prefix" | "suffix" => {
+                format!("::abnf::parse::token::skip_literal(buf, \
+                         ::std::convert::AsRef::<[u8]>::as_ref({}))",
+                        attr.literal()?)
+            }
+            "before" | "after" => call(&attr.string()?),
+            _ => return Ok(false)
+        };
+        let code = format!("{};\n", ready(&code));
+        match attr.key.as_str() {
+            "prefix" | "before" => self.before.push_str(&code),
+            _ => self.after.push_str(&code),
+        }
+        Ok(true)
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Returns the code calling a rule on the buffer.
+///
+/// The rule is either a path to a function taking only the buffer or a
+/// call to a function with further arguments following the buffer.
+fn call(rule: &str) -> String {
+    let rule = rule.trim();
+    if rule.ends_with(')') {
+        if let Some(pos) = rule.find('(') {
+            let args = rule[pos + 1..rule.len() - 1].trim();
+            if args.is_empty() {
+                return format!("{}(buf)", &rule[..pos])
+            }
+            return format!("{}(buf, {})", &rule[..pos], args)
+        }
+    }
+    format!("{}(buf)", rule)
+}
+
+/// Returns the code evaluating a poll to its ready value.
+///
+/// This is what `try_ready!()` does.
+fn ready(expr: &str) -> String {
+    format!("match {} {{
+        Ok(::abnf::Async::Ready(res)) => res,
+        Ok(::abnf::Async::NotReady) => return Ok(::abnf::Async::NotReady),
+        Err(err) => return Err(::std::convert::From::from(err)),
+    }}", expr)
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "grammar")]
+
+extern crate abnf;
+#[macro_use] extern crate abnf_derive;
+extern crate bytes;
+extern crate futures;
+
+use abnf::parse::token::{Token, TokenError};
+use bytes::BytesMut;
+use futures::{Async, Poll};
+
+
+abnf! {
+    greeting = ("HELO" / %s"EHLO") SP domain CRLF
+    domain   = label *("." label) / "[" address "]"
+    label    = let-dig [*61(let-dig / "-")]
+    let-dig  = ALPHA / DIGIT
+    code     = 3%x30-39
+}
+
+abnf!(r#"
+line = *(%x1E / VCHAR / WSP) CRLF  ; with an ABNF comment
+"#);
+
+/// The rule left undefined by the grammar.
+fn address<B: abnf::parse::buffer::Buffer>(token: &mut Token<B>)
+                                           -> Poll<(), TokenError> {
+    abnf::parse::token::repeat(token, 1, None, |token| {
+        abnf::parse::token::cat(token, |ch| ch == b'.' || ch.is_ascii_digit())
+    })
+}
+
+
+fn buf(slice: &[u8]) -> BytesMut {
+    BytesMut::from(slice)
+}
+
+#[test]
+fn parse() {
+    let mut data = buf(b"helo example.com\r\nQUIT");
+    assert_eq!(parse_greeting(&mut data).map(|res| res.map(|_| ())),
+               Ok(Async::Ready(())));
+    assert_eq!(&data[..], b"QUIT");
+
+    let mut data = buf(b"HELO [192.0.2.1]\r\n");
+    assert_eq!(skip_greeting(&mut data), Ok(Async::Ready(())));
+    assert!(data.is_empty());
+
+    let mut data = buf(b"ehlo example.com\r\n");
+    assert_eq!(skip_greeting(&mut data), Err(TokenError::Invalid));
+
+    let mut data = buf(b"HELO exam");
+    assert_eq!(skip_greeting(&mut data), Ok(Async::NotReady));
+    assert_eq!(&data[..], b"HELO exam");
+
+    let mut data = buf(b"2500");
+    assert_eq!(parse_code(&mut data).map(|res| res.map(|b| b.to_vec())),
+               Ok(Async::Ready(b"250".to_vec())));
+
+    let mut data = buf(b"a\x1eb\r\n");
+    assert_eq!(skip_line(&mut data), Ok(Async::Ready(())));
+}
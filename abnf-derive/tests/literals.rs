@@ -0,0 +1,68 @@
+extern crate abnf;
+#[macro_use] extern crate abnf_derive;
+extern crate bytes;
+extern crate futures;
+
+use abnf::parse::token::TokenError;
+use abnf::test_support::{assert_canonical, assert_roundtrip};
+use bytes::BytesMut;
+use futures::Async;
+
+
+#[derive(AbnfLiterals, Clone, Copy, Debug, PartialEq)]
+enum Method {
+    #[abnf(literal = "GET")]
+    Get,
+
+    #[abnf(literal = "HEAD")]
+    Head,
+
+    #[abnf(literal = "M-SEARCH", alias = "MSEARCH")]
+    MSearch,
+
+    #[abnf(literal = "M", case_sensitive)]
+    M,
+}
+
+
+fn buf(slice: &[u8]) -> BytesMut {
+    BytesMut::from(slice)
+}
+
+#[test]
+fn parse() {
+    let mut data = buf(b"get /");
+    assert_eq!(Method::parse(&mut data), Ok(Async::Ready(Method::Get)));
+    assert_eq!(&data[..], b" /");
+
+    let mut data = buf(b"M-Search *");
+    assert_eq!(Method::parse(&mut data), Ok(Async::Ready(Method::MSearch)));
+    assert_eq!(&data[..], b" *");
+
+    let mut data = buf(b"msearch *");
+    assert_eq!(Method::parse(&mut data), Ok(Async::Ready(Method::MSearch)));
+
+    assert_eq!(Method::parse(&mut buf(b"M *")), Ok(Async::Ready(Method::M)));
+    assert_eq!(Method::parse(&mut buf(b"m *")), Err(TokenError::Invalid));
+    assert_eq!(Method::parse(&mut buf(b"M")), Ok(Async::NotReady));
+    assert_eq!(Method::parse(&mut buf(b"HEA")), Ok(Async::NotReady));
+    assert_eq!(Method::parse(&mut buf(b"POST /")), Err(TokenError::Invalid));
+}
+
+#[test]
+fn compose() {
+    assert_eq!(Method::MSearch.as_str(), "M-SEARCH");
+    let mut target = Vec::new();
+    Method::Head.compose(&mut target);
+    assert_eq!(target, b"HEAD");
+}
+
+#[test]
+fn roundtrip() {
+    for method in &[Method::Get, Method::Head, Method::MSearch, Method::M] {
+        assert_roundtrip(method, |method, buf| method.compose(buf),
+                         Method::parse, b" ");
+    }
+    assert_canonical(b"M-SEARCH", Method::parse,
+                     |method, buf| method.compose(buf), b" ");
+}
@@ -0,0 +1,54 @@
+extern crate abnf;
+#[macro_use] extern crate abnf_derive;
+extern crate bytes;
+extern crate futures;
+
+use abnf::core::{u8_digits, skip_wsps};
+use abnf::parse::token;
+use bytes::BytesMut;
+use futures::Async;
+
+
+#[derive(AbnfParse, Debug, PartialEq)]
+#[abnf(suffix = "\r\n")]
+struct Version {
+    #[abnf(prefix = "HTTP/", rule = "u8_digits")]
+    major: u8,
+
+    #[abnf(prefix = ".", rule = "u8_digits")]
+    minor: u8,
+}
+
+#[derive(AbnfParse, Debug, PartialEq)]
+struct Pair(
+    #[abnf(rule = "u8_digits", after = "token::skip_octet(b'=')")] u8,
+    #[abnf(rule = "u8_digits", after = "skip_wsps")] u8,
+);
+
+
+fn buf(slice: &[u8]) -> BytesMut {
+    BytesMut::from(slice)
+}
+
+#[test]
+fn named() {
+    let mut data = buf(b"HTTP/1.1\r\nfoo");
+    assert_eq!(Version::parse(&mut data),
+               Ok(Async::Ready(Version { major: 1, minor: 1 })));
+    assert_eq!(&data[..], b"foo");
+
+    let mut data = buf(b"HTTP/1.1\r");
+    assert_eq!(Version::parse(&mut data), Ok(Async::NotReady));
+    assert_eq!(&data[..], b"HTTP/1.1\r");
+
+    let mut data = buf(b"HTTP/1,1\r\n");
+    assert!(Version::parse(&mut data).is_err());
+    assert_eq!(&data[..], b"HTTP/1,1\r\n");
+}
+
+#[test]
+fn unnamed() {
+    let mut data = buf(b"12=3 x");
+    assert_eq!(Pair::parse(&mut data), Ok(Async::Ready(Pair(12, 3))));
+    assert_eq!(&data[..], b"x");
+}
@@ -0,0 +1,7 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate abnf;
+
+fuzz_target!(|data: &[u8]| {
+    abnf::fuzz::http(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate abnf;
+
+fuzz_target!(|data: &[u8]| {
+    abnf::fuzz::ipv4_addr(data);
+    abnf::fuzz::ipv6_addr(data);
+});
@@ -0,0 +1,225 @@
+//! Normalizing line endings.
+//!
+//! A server that wants to be lenient towards peers using bare LF or bare
+//! CR as line endings doesn’t need to relax its grammar. Instead, it can
+//! wrap its reader in a [`CrlfReader`] which turns every line ending into
+//! CRLF before the parsers see it. Likewise, a [`CrlfWriter`] makes sure
+//! that outgoing data only ever contains CRLF line endings.
+//!
+//! Both are built upon [`CrlfNormalizer`] which can also be used directly,
+//! for instance to normalize the chunks of a stream.
+//!
+//! [`CrlfReader`]: struct.CrlfReader.html
+//! [`CrlfWriter`]: struct.CrlfWriter.html
+//! [`CrlfNormalizer`]: struct.CrlfNormalizer.html
+
+use std::{cmp, io};
+
+
+//------------ CrlfNormalizer ------------------------------------------------
+
+/// Converts bare LF and bare CR into CRLF.
+///
+/// Since data arrives in chunks, a CR at the end of a chunk may or may not
+/// be followed by an LF. The normalizer therefore holds back such a CR
+/// until it sees the next chunk or is told that there is no more data via
+/// `finish()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrlfNormalizer {
+    /// Whether a CR was held back.
+    pending_cr: bool,
+}
+
+impl CrlfNormalizer {
+    /// Creates a new normalizer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Normalizes `data` and appends the result to `target`.
+    pub fn normalize(&mut self, data: &[u8], target: &mut Vec<u8>) {
+        for &ch in data {
+            if self.pending_cr {
+                self.pending_cr = false;
+                target.extend_from_slice(b"\r\n");
+                if ch == b'\n' {
+                    continue
+                }
+            }
+            match ch {
+                b'\r' => self.pending_cr = true,
+                b'\n' => target.extend_from_slice(b"\r\n"),
+                _ => target.push(ch)
+            }
+        }
+    }
+
+    /// Appends whatever was held back to `target`.
+    ///
+    /// This needs to be called once there is no more data.
+    pub fn finish(&mut self, target: &mut Vec<u8>) {
+        if self.pending_cr {
+            self.pending_cr = false;
+            target.extend_from_slice(b"\r\n")
+        }
+    }
+}
+
+
+//------------ CrlfReader ----------------------------------------------------
+
+/// A reader normalizing all line endings to CRLF.
+#[derive(Debug)]
+pub struct CrlfReader<R> {
+    /// The underlying reader.
+    reader: R,
+
+    /// The normalizer.
+    normalizer: CrlfNormalizer,
+
+    /// Normalized data not yet read.
+    buf: Vec<u8>,
+
+    /// The position of the first octet in `buf` not yet read.
+    pos: usize,
+}
+
+impl<R: io::Read> CrlfReader<R> {
+    /// Creates a new normalizing reader atop `reader`.
+    pub fn new(reader: R) -> Self {
+        CrlfReader {
+            reader,
+            normalizer: CrlfNormalizer::new(),
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Trades the normalizing reader for the underlying reader.
+    ///
+    /// Any data already read from the underlying reader but not yet from
+    /// the normalizing reader is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: io::Read> io::Read for CrlfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        while self.pos == self.buf.len() {
+            let mut chunk = [0u8; 4096];
+            let len = self.reader.read(&mut chunk)?;
+            self.buf.clear();
+            self.pos = 0;
+            if len == 0 {
+                self.normalizer.finish(&mut self.buf);
+                if self.buf.is_empty() {
+                    return Ok(0)
+                }
+            }
+            else {
+                self.normalizer.normalize(&chunk[..len], &mut self.buf);
+            }
+        }
+        let len = cmp::min(buf.len(), self.buf.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+
+//------------ CrlfWriter ----------------------------------------------------
+
+/// A writer normalizing all line endings to CRLF.
+///
+/// Since a CR at the end of written data may be followed by an LF in the
+/// next write, it is held back. Call `finish()` once done writing in
+/// order to write it out.
+#[derive(Debug)]
+pub struct CrlfWriter<W> {
+    /// The underlying writer.
+    writer: W,
+
+    /// The normalizer.
+    normalizer: CrlfNormalizer,
+}
+
+impl<W: io::Write> CrlfWriter<W> {
+    /// Creates a new normalizing writer atop `writer`.
+    pub fn new(writer: W) -> Self {
+        CrlfWriter {
+            writer,
+            normalizer: CrlfNormalizer::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes out anything held back and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, io::Error> {
+        let mut buf = Vec::new();
+        self.normalizer.finish(&mut buf);
+        self.writer.write_all(&buf)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: io::Write> io::Write for CrlfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let mut normalized = Vec::with_capacity(buf.len());
+        self.normalizer.normalize(buf, &mut normalized);
+        self.writer.write_all(&normalized)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let mut normalizer = CrlfNormalizer::new();
+        let mut res = Vec::new();
+        normalizer.normalize(b"a\nb\r", &mut res);
+        normalizer.normalize(b"\nc\rd\r", &mut res);
+        assert_eq!(res, b"a\r\nb\r\nc\r\nd");
+        normalizer.finish(&mut res);
+        assert_eq!(res, b"a\r\nb\r\nc\r\nd\r\n");
+    }
+
+    #[test]
+    fn reader() {
+        let mut reader = CrlfReader::new(&b"a\nb\r\nc\r"[..]);
+        let mut res = Vec::new();
+        reader.read_to_end(&mut res).unwrap();
+        assert_eq!(res, b"a\r\nb\r\nc\r\n");
+    }
+
+    #[test]
+    fn writer() {
+        let mut writer = CrlfWriter::new(Vec::new());
+        writer.write_all(b"a\nb\r").unwrap();
+        assert_eq!(writer.get_ref(), b"a\r\nb");
+        writer.write_all(b"\nc\r").unwrap();
+        assert_eq!(writer.get_ref(), b"a\r\nb\r\nc");
+        assert_eq!(writer.finish().unwrap(), b"a\r\nb\r\nc\r\n");
+    }
+}
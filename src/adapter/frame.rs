@@ -0,0 +1,219 @@
+//! Reading lines interleaved with counted octets.
+//!
+//! Some line-oriented protocols embed data of a previously announced
+//! length within their stream of lines: IMAP literals, octet-counted
+//! syslog over TCP as defined in RFC 6587, or the chunks of the SMTP BDAT
+//! command of RFC 3030. For these, [`FrameReader`] produces a stream of
+//! frames that are lines by default. Once the consumer has learned from
+//! a line that a number of octets follows, it tells the reader via
+//! [`read_octets()`] and receives those octets next before the reader
+//! returns to lines.
+//!
+//! Counted octets are returned in pieces as they arrive so that large
+//! payloads don’t need to be buffered in full.
+//!
+//! [`FrameReader`]: struct.FrameReader.html
+//! [`read_octets()`]: struct.FrameReader.html#method.read_octets
+
+use std::cmp;
+use bytes::Bytes;
+use futures::{Async, Poll, Stream};
+use ::core::LineEnding;
+use ::parse::buffer::Buffer;
+use ::parse::cursor::CursorBuf;
+use ::parse::token::TokenError;
+use super::lines::LineParser;
+
+
+//------------ Frame ---------------------------------------------------------
+
+/// A frame produced by a frame reader.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Frame {
+    /// A line without its line ending.
+    Line(Bytes),
+
+    /// A piece of counted octets.
+    Octets {
+        /// The octets of this piece.
+        data: Bytes,
+
+        /// The number of octets still to come after this piece.
+        remaining: usize,
+    },
+}
+
+
+//------------ FrameReader ---------------------------------------------------
+
+/// A stream of lines and counted octets read from a stream of data.
+///
+/// The underlying stream `S` produces chunks of data in anything that
+/// can be referenced as a slice of octets. Its error type needs to be
+/// convertible from `TokenError` so that a line that is too long can be
+/// reported. After an error, the stream ends.
+///
+/// Lines are handled just like by `LineStream`, including a final line
+/// without a line ending. If the data ends before all counted octets
+/// have been received, however, the stream ends with an error.
+pub struct FrameReader<S> {
+    /// The stream of data.
+    stream: S,
+
+    /// The data received but not yet returned.
+    buf: CursorBuf,
+
+    /// The line parser.
+    lines: LineParser,
+
+    /// The number of counted octets still to be returned.
+    remaining: usize,
+
+    /// Whether we are done.
+    done: bool,
+}
+
+impl<S> FrameReader<S> {
+    /// Creates a new frame reader atop `stream`.
+    ///
+    /// The reader starts out producing lines.
+    pub fn new(stream: S) -> Self {
+        FrameReader {
+            stream,
+            buf: CursorBuf::new(),
+            lines: LineParser::new(),
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    /// Sets the accepted line endings.
+    pub fn set_ending(&mut self, ending: LineEnding) {
+        self.lines.ending = ending
+    }
+
+    /// Sets the maximum length of a line including its ending.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.lines.max_len = max_len
+    }
+
+    /// Switches to counted octets.
+    ///
+    /// The next `count` octets are returned as `Frame::Octets`, after
+    /// which the reader returns to lines. Any counted octets still
+    /// remaining from an earlier call are replaced.
+    pub fn read_octets(&mut self, count: usize) {
+        self.remaining = count
+    }
+
+    /// Returns the number of counted octets still to be returned.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns a reference to the data received but not yet returned.
+    pub fn buffer(&self) -> &CursorBuf {
+        &self.buf
+    }
+
+    /// Trades the reader for the stream and the unreturned data.
+    pub fn into_inner(self) -> (S, CursorBuf) {
+        (self.stream, self.buf)
+    }
+}
+
+impl<S> Stream for FrameReader<S>
+        where S: Stream,
+              S::Item: AsRef<[u8]>,
+              S::Error: From<TokenError> {
+    type Item = Frame;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Frame>, S::Error> {
+        while !self.done {
+            if self.remaining > 0 {
+                if !self.buf.is_empty() {
+                    let len = cmp::min(self.remaining, self.buf.len());
+                    let data = self.buf.split_to(len);
+                    self.remaining -= len;
+                    return Ok(Async::Ready(Some(Frame::Octets {
+                        data, remaining: self.remaining
+                    })))
+                }
+            }
+            else {
+                match self.lines.parse(&mut self.buf) {
+                    Ok(Async::Ready(line)) => {
+                        return Ok(Async::Ready(Some(Frame::Line(line))))
+                    }
+                    Ok(Async::NotReady) => { }
+                    Err(err) => {
+                        self.done = true;
+                        return Err(err.into())
+                    }
+                }
+            }
+            match try_ready!(self.stream.poll()) {
+                Some(chunk) => self.buf.extend_from_slice(chunk.as_ref()),
+                None => {
+                    self.done = true;
+                    if self.remaining > 0 {
+                        return Err(TokenError::Invalid.into())
+                    }
+                    let line = self.lines.finish(&mut self.buf);
+                    return Ok(Async::Ready(line.map(Frame::Line)))
+                }
+            }
+        }
+        Ok(Async::Ready(None))
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use futures::stream;
+    use super::*;
+
+    fn line(slice: &[u8]) -> Poll<Option<Frame>, TokenError> {
+        Ok(Async::Ready(Some(Frame::Line(Bytes::copy_from_slice(slice)))))
+    }
+
+    fn octets(slice: &[u8], remaining: usize)
+              -> Poll<Option<Frame>, TokenError> {
+        Ok(Async::Ready(Some(Frame::Octets {
+            data: Bytes::copy_from_slice(slice), remaining
+        })))
+    }
+
+    #[test]
+    fn frames() {
+        // An IMAP command with a literal.
+        let chunks = vec![&b"A1 APPEND x {5}\r\nhel"[..],
+                          &b"lo\r\nA2 NOOP\r\n"[..]];
+        let mut reader = FrameReader::new(
+            stream::iter_ok::<_, TokenError>(chunks)
+        );
+        assert_eq!(reader.poll(), line(b"A1 APPEND x {5}"));
+        reader.read_octets(5);
+        assert_eq!(reader.poll(), octets(b"hel", 2));
+        assert_eq!(reader.remaining(), 2);
+        assert_eq!(reader.poll(), octets(b"lo", 0));
+        assert_eq!(reader.poll(), line(b""));
+        assert_eq!(reader.poll(), line(b"A2 NOOP"));
+        assert_eq!(reader.poll(), Ok(Async::Ready(None)));
+    }
+
+    #[test]
+    fn truncated_octets() {
+        let mut reader = FrameReader::new(
+            stream::iter_ok::<_, TokenError>(vec![&b"12 abc"[..]])
+        );
+        reader.read_octets(12);
+        assert_eq!(reader.poll(), octets(b"12 abc", 6));
+        assert_eq!(reader.poll(), Err(TokenError::Invalid));
+        assert_eq!(reader.poll(), Ok(Async::Ready(None)));
+    }
+}
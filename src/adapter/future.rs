@@ -0,0 +1,693 @@
+//! Parsing with `std::future`.
+//!
+//! This module provides [`AsyncParser`], a trait for parsers that return a
+//! `std::future::Future` resolving into the parsed value. Parsers operate
+//! on a [`Source`], a shared handle to a buffer that some other part of
+//! the program fills with data as it arrives. It is only available with
+//! the `std-future` feature.
+//!
+//! Once the source is closed, its buffer is marked as final and the
+//! parsers run once more, so rules that wait for an octet past their end
+//! finish at the end of the data. See the [`complete`] module for details.
+//!
+//! Async parsers can be created from the `Poll`-based parsing functions of
+//! this crate via [`from_poll()`] and from closures returning futures – in
+//! particular async functions – via [`from_fn()`]. This way, higher-level
+//! rules can be written in straight-line async style while reusing the
+//! existing rules for the details.
+//!
+//! [`AsyncParser`]: trait.AsyncParser.html
+//! [`Source`]: struct.Source.html
+//! [`from_poll()`]: fn.from_poll.html
+//! [`from_fn()`]: fn.from_fn.html
+//! [`complete`]: ../../parse/complete/index.html
+
+use std::io;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Waker};
+use std::task::Poll as TaskPoll;
+use futures::{Async, Poll};
+use ::parse::buffer::Buffer;
+use ::parse::complete::Complete;
+use ::parse::cursor::CursorBuf;
+
+
+//------------ SourceBuf -----------------------------------------------------
+
+/// The buffer of a source.
+///
+/// The buffer is final once the source has been closed. Since it is a
+/// `CursorBuf`, rewinding after a failed group of rules is cheap.
+pub type SourceBuf = Complete<CursorBuf>;
+
+
+//------------ Source --------------------------------------------------------
+
+/// A shared handle to a buffer that is filled asynchronously.
+///
+/// Values of this type are cheap to clone. All clones refer to the same
+/// buffer. Data is added via `extend()` and the end of data signalled via
+/// `close()`. Both wake up a parser waiting for more data.
+#[derive(Clone, Default)]
+pub struct Source {
+    inner: Arc<Mutex<SourceInner>>,
+}
+
+#[derive(Default)]
+struct SourceInner {
+    /// The data received so far and whether there will be more.
+    buf: SourceBuf,
+
+    /// The waker of a parser waiting for more data.
+    waker: Option<Waker>,
+}
+
+impl Source {
+    /// Creates a new, empty source.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `data` to the source’s buffer.
+    pub fn extend(&self, data: &[u8]) {
+        let mut inner = self.lock();
+        inner.buf.get_mut().extend_from_slice(data);
+        if let Some(waker) = inner.waker.take() {
+            waker.wake()
+        }
+    }
+
+    /// Marks the end of the data.
+    pub fn close(&self) {
+        let mut inner = self.lock();
+        inner.buf.set_final();
+        if let Some(waker) = inner.waker.take() {
+            waker.wake()
+        }
+    }
+
+    /// Returns whether the source has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock().buf.is_final()
+    }
+
+    /// Returns the number of octets currently in the buffer.
+    pub fn len(&self) -> usize {
+        self.lock().buf.get_ref().len()
+    }
+
+    /// Returns whether the buffer is currently empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().buf.get_ref().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SourceInner> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+}
+
+
+//------------ AsyncParser ---------------------------------------------------
+
+/// A parser producing a future.
+///
+/// The future returned by `parse()` resolves into the parsed item or an
+/// error. Like all parsers in this crate, it should drain exactly what it
+/// parsed from the source on success.
+pub trait AsyncParser {
+    /// The type of a successfully parsed item.
+    type Item;
+
+    /// The type of a parse error.
+    type Error;
+
+    /// The future returned by `parse()`.
+    type Future: Future<Output = Result<Self::Item, Self::Error>>;
+
+    /// Starts parsing from `source`.
+    fn parse(&self, source: &Source) -> Self::Future;
+
+    /// Converts a successfully parsed item via `op`.
+    fn map<C, U>(self, op: C) -> Map<Self, C>
+           where Self: Sized, C: Fn(Self::Item) -> U + Clone {
+        Map { parser: self, op }
+    }
+
+    /// Converts an error via `op`.
+    fn map_err<C, F>(self, op: C) -> MapErr<Self, C>
+               where Self: Sized, C: Fn(Self::Error) -> F + Clone {
+        MapErr { parser: self, op }
+    }
+
+    /// Converts a successfully parsed item via the fallible `op`.
+    fn and_then<C, U>(self, op: C) -> AndThen<Self, C>
+                where Self: Sized,
+                      C: Fn(Self::Item) -> Result<U, Self::Error> + Clone {
+        AndThen { parser: self, op }
+    }
+
+    /// Parses an item via `self` followed by an item via `other`.
+    ///
+    /// The parser resolves into a pair of both items.
+    fn then<Q>(self, other: Q) -> Then<Self, Q>
+            where Self: Sized, Q: AsyncParser<Error = Self::Error> {
+        Then { first: self, second: other }
+    }
+
+    /// Parses an item via `self` or, if that fails, via `other`.
+    ///
+    /// Like with any alternative, `self` must leave the source untouched
+    /// if it fails.
+    fn or<Q>(self, other: Q) -> Or<Self, Q>
+          where Self: Sized, Q: AsyncParser<Item = Self::Item> {
+        Or { first: self, second: other }
+    }
+}
+
+
+//------------ from_poll and PollParser --------------------------------------
+
+/// Creates an async parser from a `Poll`-based parsing function.
+///
+/// The future of the parser applies `parse` to the source’s buffer. If it
+/// returns non-ready, the future waits for more data. Once the source is
+/// closed, `parse` is applied to the final buffer. If it still can’t
+/// decide, the future resolves into an error of kind
+/// `io::ErrorKind::UnexpectedEof`.
+pub fn from_poll<P, T, E>(parse: P) -> PollParser<P>
+                 where P: Fn(&mut SourceBuf) -> Poll<T, E> + Clone,
+                       E: From<io::Error> {
+    PollParser { parse }
+}
+
+/// An async parser wrapping a `Poll`-based parsing function.
+///
+/// Values of this type are created via [`from_poll()`].
+///
+/// [`from_poll()`]: fn.from_poll.html
+#[derive(Clone, Copy, Debug)]
+pub struct PollParser<P> {
+    parse: P,
+}
+
+impl<P, T, E> AsyncParser for PollParser<P>
+              where P: Fn(&mut SourceBuf) -> Poll<T, E> + Clone,
+                    E: From<io::Error> {
+    type Item = T;
+    type Error = E;
+    type Future = PollFuture<P>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        PollFuture { parse: self.parse.clone(), source: source.clone() }
+    }
+}
+
+/// The future of a [`PollParser`].
+///
+/// [`PollParser`]: struct.PollParser.html
+pub struct PollFuture<P> {
+    parse: P,
+    source: Source,
+}
+
+impl<P, T, E> Future for PollFuture<P>
+              where P: Fn(&mut SourceBuf) -> Poll<T, E>,
+                    E: From<io::Error> {
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        let mut inner = self.source.lock();
+        match (self.parse)(&mut inner.buf) {
+            Ok(Async::Ready(item)) => TaskPoll::Ready(Ok(item)),
+            Err(err) => TaskPoll::Ready(Err(err)),
+            Ok(Async::NotReady) => {
+                if inner.buf.is_final() {
+                    TaskPoll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "incomplete data at end of input"
+                    ).into()))
+                }
+                else {
+                    inner.waker = Some(cx.waker().clone());
+                    TaskPoll::Pending
+                }
+            }
+        }
+    }
+}
+
+
+//------------ from_fn and FnParser ------------------------------------------
+
+/// Creates an async parser from a closure returning a future.
+///
+/// This is typically used with an async function taking a `Source`.
+pub fn from_fn<C, R, T, E>(op: C) -> FnParser<C>
+               where C: Fn(Source) -> R, R: Future<Output = Result<T, E>> {
+    FnParser { op }
+}
+
+/// An async parser wrapping a closure.
+///
+/// Values of this type are created via [`from_fn()`].
+///
+/// [`from_fn()`]: fn.from_fn.html
+#[derive(Clone, Copy, Debug)]
+pub struct FnParser<C> {
+    op: C,
+}
+
+impl<C, R, T, E> AsyncParser for FnParser<C>
+                 where C: Fn(Source) -> R,
+                       R: Future<Output = Result<T, E>> {
+    type Item = T;
+    type Error = E;
+    type Future = R;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        (self.op)(source.clone())
+    }
+}
+
+
+//------------ Map -----------------------------------------------------------
+
+/// An async parser converting the result of another parser.
+///
+/// Values of this type are created via `AsyncParser::map()`.
+#[derive(Clone, Copy, Debug)]
+pub struct Map<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<P, C, U> AsyncParser for Map<P, C>
+              where P: AsyncParser, C: Fn(P::Item) -> U + Clone {
+    type Item = U;
+    type Error = P::Error;
+    type Future = MapFuture<P::Future, C>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        MapFuture {
+            future: Box::pin(self.parser.parse(source)),
+            op: self.op.clone()
+        }
+    }
+}
+
+/// The future of a [`Map`] parser.
+///
+/// [`Map`]: struct.Map.html
+pub struct MapFuture<R, C> {
+    future: Pin<Box<R>>,
+    op: C,
+}
+
+impl<R, C> Unpin for MapFuture<R, C> { }
+
+impl<R, C, T, U, E> Future for MapFuture<R, C>
+                    where R: Future<Output = Result<T, E>>, C: Fn(T) -> U {
+    type Output = Result<U, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        match self.future.as_mut().poll(cx) {
+            TaskPoll::Ready(res) => TaskPoll::Ready(res.map(&self.op)),
+            TaskPoll::Pending => TaskPoll::Pending
+        }
+    }
+}
+
+
+//------------ MapErr --------------------------------------------------------
+
+/// An async parser converting the error of another parser.
+///
+/// Values of this type are created via `AsyncParser::map_err()`.
+#[derive(Clone, Copy, Debug)]
+pub struct MapErr<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<P, C, F> AsyncParser for MapErr<P, C>
+              where P: AsyncParser, C: Fn(P::Error) -> F + Clone {
+    type Item = P::Item;
+    type Error = F;
+    type Future = MapErrFuture<P::Future, C>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        MapErrFuture {
+            future: Box::pin(self.parser.parse(source)),
+            op: self.op.clone()
+        }
+    }
+}
+
+/// The future of a [`MapErr`] parser.
+///
+/// [`MapErr`]: struct.MapErr.html
+pub struct MapErrFuture<R, C> {
+    future: Pin<Box<R>>,
+    op: C,
+}
+
+impl<R, C> Unpin for MapErrFuture<R, C> { }
+
+impl<R, C, T, E, F> Future for MapErrFuture<R, C>
+                    where R: Future<Output = Result<T, E>>, C: Fn(E) -> F {
+    type Output = Result<T, F>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        match self.future.as_mut().poll(cx) {
+            TaskPoll::Ready(res) => TaskPoll::Ready(res.map_err(&self.op)),
+            TaskPoll::Pending => TaskPoll::Pending
+        }
+    }
+}
+
+
+//------------ AndThen -------------------------------------------------------
+
+/// An async parser converting the result of another parser fallibly.
+///
+/// Values of this type are created via `AsyncParser::and_then()`.
+#[derive(Clone, Copy, Debug)]
+pub struct AndThen<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<P, C, U> AsyncParser for AndThen<P, C>
+              where P: AsyncParser,
+                    C: Fn(P::Item) -> Result<U, P::Error> + Clone {
+    type Item = U;
+    type Error = P::Error;
+    type Future = AndThenFuture<P::Future, C>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        AndThenFuture {
+            future: Box::pin(self.parser.parse(source)),
+            op: self.op.clone()
+        }
+    }
+}
+
+/// The future of an [`AndThen`] parser.
+///
+/// [`AndThen`]: struct.AndThen.html
+pub struct AndThenFuture<R, C> {
+    future: Pin<Box<R>>,
+    op: C,
+}
+
+impl<R, C> Unpin for AndThenFuture<R, C> { }
+
+impl<R, C, T, U, E> Future for AndThenFuture<R, C>
+                    where R: Future<Output = Result<T, E>>,
+                          C: Fn(T) -> Result<U, E> {
+    type Output = Result<U, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        match self.future.as_mut().poll(cx) {
+            TaskPoll::Ready(res) => TaskPoll::Ready(res.and_then(&self.op)),
+            TaskPoll::Pending => TaskPoll::Pending
+        }
+    }
+}
+
+
+//------------ Then ----------------------------------------------------------
+
+/// An async parser parsing two items in sequence.
+///
+/// Values of this type are created via `AsyncParser::then()`.
+#[derive(Clone, Copy, Debug)]
+pub struct Then<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<P, Q> AsyncParser for Then<P, Q>
+           where P: AsyncParser, Q: AsyncParser<Error = P::Error> {
+    type Item = (P::Item, Q::Item);
+    type Error = P::Error;
+    type Future = ThenFuture<P::Future, Q::Future, P::Item>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        ThenFuture {
+            first: Box::pin(self.first.parse(source)),
+            second: Box::pin(self.second.parse(source)),
+            item: None,
+        }
+    }
+}
+
+/// The future of a [`Then`] parser.
+///
+/// [`Then`]: struct.Then.html
+pub struct ThenFuture<R, S, T> {
+    first: Pin<Box<R>>,
+    second: Pin<Box<S>>,
+
+    /// The item of the first parser once it is done.
+    item: Option<T>,
+}
+
+impl<R, S, T> Unpin for ThenFuture<R, S, T> { }
+
+impl<R, S, T, U, E> Future for ThenFuture<R, S, T>
+                    where R: Future<Output = Result<T, E>>,
+                          S: Future<Output = Result<U, E>> {
+    type Output = Result<(T, U), E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        if self.item.is_none() {
+            match self.first.as_mut().poll(cx) {
+                TaskPoll::Ready(Ok(item)) => self.item = Some(item),
+                TaskPoll::Ready(Err(err)) => return TaskPoll::Ready(Err(err)),
+                TaskPoll::Pending => return TaskPoll::Pending
+            }
+        }
+        match self.second.as_mut().poll(cx) {
+            TaskPoll::Ready(Ok(second)) => {
+                let first = self.item.take().unwrap();
+                TaskPoll::Ready(Ok((first, second)))
+            }
+            TaskPoll::Ready(Err(err)) => TaskPoll::Ready(Err(err)),
+            TaskPoll::Pending => TaskPoll::Pending
+        }
+    }
+}
+
+
+//------------ Or ------------------------------------------------------------
+
+/// An async parser parsing one of two alternatives.
+///
+/// Values of this type are created via `AsyncParser::or()`.
+#[derive(Clone, Copy, Debug)]
+pub struct Or<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<P, Q> AsyncParser for Or<P, Q>
+           where P: AsyncParser, Q: AsyncParser<Item = P::Item> {
+    type Item = P::Item;
+    type Error = Q::Error;
+    type Future = OrFuture<P::Future, Q::Future>;
+
+    fn parse(&self, source: &Source) -> Self::Future {
+        OrFuture {
+            first: Some(Box::pin(self.first.parse(source))),
+            second: Box::pin(self.second.parse(source)),
+        }
+    }
+}
+
+/// The future of an [`Or`] parser.
+///
+/// [`Or`]: struct.Or.html
+pub struct OrFuture<R, S> {
+    /// The future of the first alternative until it has failed.
+    first: Option<Pin<Box<R>>>,
+    second: Pin<Box<S>>,
+}
+
+impl<R, S> Unpin for OrFuture<R, S> { }
+
+impl<R, S, T, E, F> Future for OrFuture<R, S>
+                    where R: Future<Output = Result<T, E>>,
+                          S: Future<Output = Result<T, F>> {
+    type Output = Result<T, F>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context)
+            -> TaskPoll<Self::Output> {
+        if let Some(ref mut first) = self.first {
+            match first.as_mut().poll(cx) {
+                TaskPoll::Ready(Ok(item)) => return TaskPoll::Ready(Ok(item)),
+                TaskPoll::Ready(Err(_)) => { }
+                TaskPoll::Pending => return TaskPoll::Pending
+            }
+        }
+        self.first = None;
+        self.second.as_mut().poll(cx)
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::future::Future;
+    use std::task::{Context, Waker};
+    use std::task::Poll as TaskPoll;
+    use futures::Poll;
+    use ::core::u16_digits;
+    use ::parse::token;
+    use super::*;
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Error {
+        Parse,
+        Eof,
+        Odd,
+    }
+
+    impl From<io::Error> for Error {
+        fn from(_: io::Error) -> Self {
+            Error::Eof
+        }
+    }
+
+    fn number(buf: &mut SourceBuf) -> Poll<u16, Error> {
+        u16_digits(buf).map_err(|_| Error::Parse)
+    }
+
+    fn space(buf: &mut SourceBuf) -> Poll<(), Error> {
+        token::skip_octet(buf, b' ').map_err(|_| Error::Parse)
+    }
+
+    fn poll<F: Future>(future: &mut Pin<Box<F>>) -> TaskPoll<F::Output> {
+        future.as_mut().poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn poll_parser() {
+        let source = Source::new();
+        let parser = from_poll(number).map(|x| x * 2);
+        let mut future = Box::pin(parser.parse(&source));
+        source.extend(b"12");
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        source.extend(b"3 ");
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(246)));
+        assert_eq!(source.len(), 1);
+    }
+
+    #[test]
+    fn closed() {
+        let source = Source::new();
+        let mut future = Box::pin(from_poll(number).parse(&source));
+        source.extend(b"12");
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        source.close();
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(12)));
+        assert!(source.is_empty());
+
+        let mut future = Box::pin(from_poll(number).parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Err(Error::Parse)));
+
+        let undecided = from_poll(|_: &mut SourceBuf| -> Poll<u8, Error> {
+            Ok(Async::NotReady)
+        });
+        let mut future = Box::pin(undecided.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Err(Error::Eof)));
+    }
+
+    #[test]
+    fn and_then() {
+        let source = Source::new();
+        let parser = from_poll(number).and_then(|x| {
+            if x % 2 == 0 { Ok(x) } else { Err(Error::Odd) }
+        });
+        source.extend(b"13 12 ");
+        let mut future = Box::pin(parser.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Err(Error::Odd)));
+    }
+
+    #[test]
+    fn then() {
+        let source = Source::new();
+        let parser = from_poll(number).then(from_poll(space))
+                                      .then(from_poll(number));
+        let mut future = Box::pin(parser.parse(&source));
+        source.extend(b"12 ");
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        assert_eq!(source.len(), 0);
+        source.extend(b"3");
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        source.close();
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(((12, ()), 3))));
+
+        let source = Source::new();
+        source.extend(b"12x");
+        let mut future = Box::pin(parser.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Err(Error::Parse)));
+    }
+
+    #[test]
+    fn or() {
+        let keyword = |buf: &mut SourceBuf| {
+            token::skip_literal(buf, b"any").map(|res| res.map(|_| 0))
+                                            .map_err(|_| Error::Parse)
+        };
+        let parser = from_poll(keyword).or(from_poll(number));
+        let source = Source::new();
+        source.extend(b"an");
+        let mut future = Box::pin(parser.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        source.extend(b"y");
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(0)));
+
+        source.extend(b"12 ");
+        let mut future = Box::pin(parser.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(12)));
+        assert_eq!(source.len(), 1);
+
+        let mut future = Box::pin(parser.parse(&source));
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Err(Error::Parse)));
+    }
+
+    #[test]
+    fn from_fn() {
+        let source = Source::new();
+        let parser = super::from_fn(|source: Source| {
+            from_poll(number).map(|x| x + 1).parse(&source)
+        });
+        let mut future = Box::pin(parser.parse(&source));
+        source.extend(b"41 ");
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(42)));
+
+        let source = Source::new();
+        let parser = super::from_fn(|_| {
+            ::std::future::ready(Err::<u16, _>(Error::Odd))
+        }).or(from_poll(number));
+        let mut future = Box::pin(parser.parse(&source));
+        source.extend(b"7");
+        assert_eq!(poll(&mut future), TaskPoll::Pending);
+        source.close();
+        assert_eq!(poll(&mut future), TaskPoll::Ready(Ok(7)));
+    }
+}
@@ -0,0 +1,295 @@
+//! Reading lines.
+//!
+//! Many protocols are line-oriented: each message or command occupies one
+//! line. For these, this module provides [`LineStream`], a stream of lines
+//! atop a stream of chunks of data, and [`LineIter`], its twin for
+//! synchronous readers. Both produce the content of each line without the
+//! line ending as a `Bytes` value.
+//!
+//! Which line endings are accepted is determined by a [`LineEnding`]
+//! policy and defaults to CRLF only. Lines are limited in length in order
+//! to avoid buffering endless amounts of data. The limit includes the
+//! line ending and defaults to 64 KiB.
+//!
+//! Once the underlying source ends, any remaining data is returned as a
+//! final line even if it lacks a line ending.
+//!
+//! The data received but not yet parsed is kept in a [`CursorBuf`].
+//!
+//! [`LineStream`]: struct.LineStream.html
+//! [`LineIter`]: struct.LineIter.html
+//! [`LineEnding`]: ../../core/enum.LineEnding.html
+//! [`CursorBuf`]: ../../parse/cursor/struct.CursorBuf.html
+
+use std::io;
+use bytes::Bytes;
+use futures::{Async, Poll, Stream};
+use ::core::{LineEnding, parse_line_limited_with};
+use ::parse::buffer::Buffer;
+use ::parse::cursor::CursorBuf;
+use ::parse::token::TokenError;
+use super::read::fill;
+
+
+//------------ LineParser ----------------------------------------------------
+
+/// The configuration and parsing logic shared by the line types.
+///
+/// This is also used by the frame reader for its lines.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct LineParser {
+    /// The accepted line endings.
+    pub ending: LineEnding,
+
+    /// The maximum length of a line including its ending.
+    pub max_len: usize,
+}
+
+impl LineParser {
+    pub fn new() -> Self {
+        LineParser {
+            ending: LineEnding::Crlf,
+            max_len: 0x1_0000,
+        }
+    }
+
+    /// Parses a line from the beginning of `buf`.
+    pub fn parse(&self, buf: &mut CursorBuf) -> Poll<Bytes, TokenError> {
+        let mut line = try_ready!(
+            parse_line_limited_with(buf, self.ending, self.max_len)
+        );
+        let len = line.len();
+        if line.ends_with(b"\r\n") {
+            line.truncate(len - 2)
+        }
+        else {
+            line.truncate(len - 1)
+        }
+        Ok(Async::Ready(line))
+    }
+
+    /// Returns the final line once there is no more data.
+    pub fn finish(&self, buf: &mut CursorBuf) -> Option<Bytes> {
+        if buf.is_empty() {
+            return None
+        }
+        let len = buf.len();
+        let mut line = buf.split_to(len);
+        // A trailing CR is only undecided if CR alone ends a line.
+        if self.ending == LineEnding::Any && line.ends_with(b"\r") {
+            let len = line.len();
+            line.truncate(len - 1)
+        }
+        Some(line)
+    }
+}
+
+
+//------------ LineStream ----------------------------------------------------
+
+/// A stream of lines read from a stream of data.
+///
+/// The underlying stream `S` produces chunks of data in anything that
+/// can be referenced as a slice of octets. Its error type needs to be
+/// convertible from `TokenError` so that a line that is too long can be
+/// reported. After an error, the stream ends.
+pub struct LineStream<S> {
+    /// The stream of data.
+    stream: S,
+
+    /// The data received but not yet parsed.
+    buf: CursorBuf,
+
+    /// The line parser.
+    parser: LineParser,
+
+    /// Whether we are done.
+    done: bool,
+}
+
+impl<S> LineStream<S> {
+    /// Creates a new line stream atop `stream`.
+    pub fn new(stream: S) -> Self {
+        LineStream {
+            stream,
+            buf: CursorBuf::new(),
+            parser: LineParser::new(),
+            done: false,
+        }
+    }
+
+    /// Sets the accepted line endings.
+    pub fn set_ending(&mut self, ending: LineEnding) {
+        self.parser.ending = ending
+    }
+
+    /// Sets the maximum length of a line including its ending.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.parser.max_len = max_len
+    }
+
+    /// Returns a reference to the data received but not yet parsed.
+    pub fn buffer(&self) -> &CursorBuf {
+        &self.buf
+    }
+
+    /// Trades the line stream for the stream and the unparsed data.
+    pub fn into_inner(self) -> (S, CursorBuf) {
+        (self.stream, self.buf)
+    }
+}
+
+impl<S> Stream for LineStream<S>
+        where S: Stream,
+              S::Item: AsRef<[u8]>,
+              S::Error: From<TokenError> {
+    type Item = Bytes;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, S::Error> {
+        while !self.done {
+            match self.parser.parse(&mut self.buf) {
+                Ok(Async::Ready(line)) => {
+                    return Ok(Async::Ready(Some(line)))
+                }
+                Ok(Async::NotReady) => { }
+                Err(err) => {
+                    self.done = true;
+                    return Err(err.into())
+                }
+            }
+            match try_ready!(self.stream.poll()) {
+                Some(chunk) => self.buf.extend_from_slice(chunk.as_ref()),
+                None => {
+                    self.done = true;
+                    return Ok(Async::Ready(self.parser.finish(&mut self.buf)))
+                }
+            }
+        }
+        Ok(Async::Ready(None))
+    }
+}
+
+
+//------------ LineIter ------------------------------------------------------
+
+/// An iterator over the lines read from a reader.
+///
+/// This is the synchronous twin of `LineStream`. Errors while reading
+/// are returned as they are. A line that is too long is returned as an
+/// error of kind `io::ErrorKind::InvalidData`. After any error, the
+/// iterator stops.
+pub struct LineIter<R> {
+    /// The reader to read data from.
+    reader: R,
+
+    /// The data read but not yet parsed.
+    buf: CursorBuf,
+
+    /// The line parser.
+    parser: LineParser,
+
+    /// Whether we are done.
+    done: bool,
+}
+
+impl<R: io::BufRead> LineIter<R> {
+    /// Creates a new line iterator reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        LineIter {
+            reader,
+            buf: CursorBuf::new(),
+            parser: LineParser::new(),
+            done: false,
+        }
+    }
+
+    /// Sets the accepted line endings.
+    pub fn set_ending(&mut self, ending: LineEnding) {
+        self.parser.ending = ending
+    }
+
+    /// Sets the maximum length of a line including its ending.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.parser.max_len = max_len
+    }
+
+    /// Returns a reference to the data read but not yet parsed.
+    pub fn buffer(&self) -> &CursorBuf {
+        &self.buf
+    }
+
+    /// Trades the iterator for the reader and the unparsed data.
+    pub fn into_inner(self) -> (R, CursorBuf) {
+        (self.reader, self.buf)
+    }
+}
+
+impl<R: io::BufRead> Iterator for LineIter<R> {
+    type Item = Result<Bytes, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.parser.parse(&mut self.buf) {
+                Ok(Async::Ready(line)) => return Some(Ok(line)),
+                Ok(Async::NotReady) => { }
+                Err(_) => {
+                    self.done = true;
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData, "line too long"
+                    )))
+                }
+            }
+            match fill(&mut self.reader, &mut self.buf) {
+                Ok(0) => {
+                    self.done = true;
+                    return self.parser.finish(&mut self.buf).map(Ok)
+                }
+                Ok(_) => { }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err))
+                }
+            }
+        }
+        None
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use futures::stream;
+    use super::*;
+
+    #[test]
+    fn line_stream() {
+        let chunks = vec![&b"a\r"[..], &b"\nbc\n"[..], &b"d\r"[..]];
+        let mut lines = LineStream::new(
+            stream::iter_ok::<_, TokenError>(chunks)
+        );
+        lines.set_ending(LineEnding::Any);
+        let res: Vec<Bytes> = lines.wait().map(Result::unwrap).collect();
+        assert_eq!(res, vec![Bytes::from(&b"a"[..]), Bytes::from(&b"bc"[..]),
+                             Bytes::from(&b"d"[..])]);
+    }
+
+    #[test]
+    fn line_iter() {
+        let data = io::BufReader::with_capacity(2, &b"a\r\nbcdef\r\n"[..]);
+        let mut lines = LineIter::new(data);
+        lines.set_max_len(5);
+        assert_eq!(lines.next().unwrap().unwrap(), &b"a"[..]);
+        assert_eq!(lines.next().unwrap().unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        assert!(lines.next().is_none());
+
+        let mut lines = LineIter::new(&b"a\r\nb"[..]);
+        assert_eq!(lines.next().unwrap().unwrap(), &b"a"[..]);
+        assert_eq!(lines.next().unwrap().unwrap(), &b"b"[..]);
+        assert!(lines.next().is_none());
+    }
+}
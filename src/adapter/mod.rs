@@ -0,0 +1,12 @@
+//! Adapters.
+//!
+//! The parsers in this crate operate on a buffer of data received so far.
+//! The modules herein provide the glue that drives these parsers over
+//! actual sources of data, filling the buffer as needed.
+
+pub mod crlf;
+pub mod frame;
+#[cfg(feature = "std-future")] pub mod future;
+pub mod lines;
+pub mod read;
+pub mod slice;
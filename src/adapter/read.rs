@@ -0,0 +1,311 @@
+//! Parsing from synchronous readers.
+//!
+//! This module contains [`RuleIter`], an iterator that repeatedly applies
+//! a parser to data read from an `io::BufRead`. It is the simplest way to
+//! use the parsers in this crate in synchronous programs such as filters
+//! reading from standard input.
+//!
+//! By default, the parser operates on a [`CursorBuf`] so that rewinding
+//! after a failed group of rules is cheap. If the iterator is created via
+//! `RuleIter::with_hints()`, the parser operates on a [`Hinted`] buffer
+//! and the iterator uses the hints on how much data is needed to read
+//! that much before trying the parser again. Any other buffer can be
+//! used via `RuleIter::with_buffer()`.
+//!
+//! [`RuleIter`]: struct.RuleIter.html
+//! [`CursorBuf`]: ../../parse/cursor/struct.CursorBuf.html
+//! [`Hinted`]: ../../parse/hint/struct.Hinted.html
+
+use std::io;
+use bytes::BytesMut;
+use futures::{Async, Poll};
+use ::parse::cursor::CursorBuf;
+use ::parse::hint::Hinted;
+
+
+//------------ RuleIter ------------------------------------------------------
+
+/// An iterator over the items parsed from a reader.
+///
+/// The iterator applies the parser to its buffer. If the parser returns
+/// non-ready, more data is read from the reader and the parser tried
+/// again. Parsed items and errors are returned by the iterator.
+///
+/// The error type of the parser must be convertible from `io::Error` so
+/// that errors while reading can be returned, too. After any error, the
+/// iterator stops.
+///
+/// When the reader reaches its end, the iterator stops if the buffer is
+/// empty. Otherwise there is an incomplete item left and the iterator
+/// returns a final error of kind `io::ErrorKind::UnexpectedEof`.
+pub struct RuleIter<R, P, B = CursorBuf> {
+    /// The reader to read data from.
+    reader: R,
+
+    /// The buffer the parser operates on.
+    buf: B,
+
+    /// The parser.
+    parse: P,
+
+    /// Whether we are done.
+    done: bool,
+}
+
+impl<R: io::BufRead, P> RuleIter<R, P> {
+    /// Creates a new iterator reading from `reader` and parsing via `parse`.
+    pub fn new(reader: R, parse: P) -> Self {
+        Self::with_buffer(reader, CursorBuf::new(), parse)
+    }
+}
+
+impl<R: io::BufRead, P> RuleIter<R, P, Hinted<CursorBuf>> {
+    /// Creates a new iterator using hints on how much data is needed.
+    ///
+    /// When the parser returns non-ready and has reported how many octets
+    /// it needs, the iterator reads at least that many before trying the
+    /// parser again.
+    pub fn with_hints(reader: R, parse: P) -> Self {
+        Self::with_buffer(reader, Hinted::new(CursorBuf::new()), parse)
+    }
+}
+
+impl<R, P, B> RuleIter<R, P, B> {
+    /// Creates a new iterator parsing from `buf` before reading more.
+    ///
+    /// This allows using a different type of buffer and starting with
+    /// some data already present.
+    pub fn with_buffer(reader: R, buf: B, parse: P) -> Self {
+        RuleIter {
+            reader,
+            buf,
+            parse,
+            done: false
+        }
+    }
+
+    /// Returns a reference to the reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a reference to the data read but not yet parsed.
+    pub fn buffer(&self) -> &B {
+        &self.buf
+    }
+
+    /// Trades the iterator for the reader and the unparsed data.
+    pub fn into_inner(self) -> (R, B) {
+        (self.reader, self.buf)
+    }
+}
+
+impl<R, P, B, T, E> Iterator for RuleIter<R, P, B>
+                    where R: io::BufRead,
+                          P: FnMut(&mut B) -> Poll<T, E>,
+                          B: ReadBuffer,
+                          E: From<io::Error> {
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match (self.parse)(&mut self.buf) {
+                Ok(Async::Ready(item)) => return Some(Ok(item)),
+                Ok(Async::NotReady) => { }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err))
+                }
+            }
+            match fill_needed(&mut self.reader, &mut self.buf) {
+                Ok(0) => {
+                    self.done = true;
+                    if !self.buf.is_empty() {
+                        return Some(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "incomplete data at end of input"
+                        ).into()))
+                    }
+                }
+                Ok(_) => { }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err.into()))
+                }
+            }
+        }
+        None
+    }
+}
+
+
+//------------ ReadBuffer ----------------------------------------------------
+
+/// A buffer that data read from a reader can be added to.
+pub trait ReadBuffer {
+    /// Returns whether the buffer is empty.
+    fn is_empty(&self) -> bool;
+
+    /// Appends `data` to the buffer.
+    fn extend_from_slice(&mut self, data: &[u8]);
+
+    /// Returns the number of additional octets needed and forgets it.
+    ///
+    /// Buffers that don’t collect hints return `None`.
+    fn take_needed(&mut self) -> Option<usize> {
+        None
+    }
+}
+
+impl ReadBuffer for BytesMut {
+    fn is_empty(&self) -> bool {
+        BytesMut::is_empty(self)
+    }
+
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        BytesMut::extend_from_slice(self, data)
+    }
+}
+
+impl ReadBuffer for CursorBuf {
+    fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        CursorBuf::extend_from_slice(self, data)
+    }
+}
+
+impl<B: ReadBuffer> ReadBuffer for Hinted<B> {
+    fn is_empty(&self) -> bool {
+        self.get_ref().is_empty()
+    }
+
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        self.get_mut().extend_from_slice(data)
+    }
+
+    fn take_needed(&mut self) -> Option<usize> {
+        Hinted::take_needed(self)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Reads as much data from `reader` into `buf` as it needs.
+///
+/// Reads until the amount of data the buffer reports as needed has been
+/// read or at least once if it doesn’t know. Returns the number of octets
+/// read, which is zero at the end of the reader’s data.
+fn fill_needed<R, B>(reader: &mut R, buf: &mut B) -> Result<usize, io::Error>
+               where R: io::BufRead, B: ReadBuffer {
+    let needed = buf.take_needed().unwrap_or(1);
+    let mut total = 0;
+    while total < needed {
+        match fill(reader, buf)? {
+            0 => break,
+            len => total += len
+        }
+    }
+    Ok(total)
+}
+
+/// Reads more data from `reader` into `buf`.
+///
+/// Returns the number of octets read, which is zero at the end of the
+/// reader’s data.
+pub(crate) fn fill<R, B>(reader: &mut R, buf: &mut B)
+                         -> Result<usize, io::Error>
+                   where R: io::BufRead, B: ReadBuffer {
+    loop {
+        let len = match reader.fill_buf() {
+            Ok(data) => {
+                buf.extend_from_slice(data);
+                data.len()
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                continue
+            }
+            Err(err) => return Err(err)
+        };
+        reader.consume(len);
+        return Ok(len)
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use bytes::{Bytes, BytesMut};
+    use futures::Poll;
+    use std::cell::Cell;
+    use ::core::{parse_line, parse_octets_exact};
+    use ::parse::buffer::Buffer;
+    use super::*;
+
+    #[derive(Debug)]
+    enum Error {
+        Parse,
+        Io(io::ErrorKind),
+    }
+
+    impl From<io::Error> for Error {
+        fn from(err: io::Error) -> Self {
+            Error::Io(err.kind())
+        }
+    }
+
+    fn line<B: Buffer>(buf: &mut B) -> Poll<Bytes, Error> {
+        parse_line(buf).map_err(|_| Error::Parse)
+    }
+
+    #[test]
+    fn lines() {
+        let data = io::BufReader::with_capacity(2, &b"a\r\nbc\r\n"[..]);
+        let res: Vec<Bytes> = RuleIter::new(data, line).map(|item| {
+            item.unwrap()
+        }).collect();
+        assert_eq!(res, vec![Bytes::from(&b"a\r\n"[..]),
+                             Bytes::from(&b"bc\r\n"[..])]);
+    }
+
+    #[test]
+    fn incomplete() {
+        let mut iter = RuleIter::new(&b"a\r\nbc"[..], line);
+        assert_eq!(iter.next().unwrap().unwrap(), &b"a\r\n"[..]);
+        match iter.next() {
+            Some(Err(Error::Io(io::ErrorKind::UnexpectedEof))) => { }
+            res => panic!("unexpected {:?}", res)
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn buffer() {
+        let mut iter = RuleIter::with_buffer(
+            &b"c\r\nd"[..], BytesMut::from(&b"ab\r\n"[..]), line
+        );
+        assert_eq!(iter.next().unwrap().unwrap(), &b"ab\r\n"[..]);
+        assert_eq!(iter.next().unwrap().unwrap(), &b"c\r\n"[..]);
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(&iter.buffer()[..], b"d");
+    }
+
+    #[test]
+    fn hints() {
+        let calls = Cell::new(0);
+        let data = io::BufReader::with_capacity(2, &b"0123456789ab"[..]);
+        let mut iter = RuleIter::with_hints(data, |buf: &mut Hinted<_>| {
+            calls.set(calls.get() + 1);
+            parse_octets_exact(buf, 10).map_err(|_| Error::Parse)
+        });
+        assert_eq!(iter.next().unwrap().unwrap(), &b"0123456789"[..]);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(iter.buffer().get_ref().as_slice(), b"");
+    }
+}
@@ -0,0 +1,205 @@
+//! Parsing large slices.
+//!
+//! This module allows applying a parser repeatedly to a large, immutable
+//! slice of data such as a memory-mapped file. Rather than copying the
+//! whole slice into a buffer, [`SliceIter`] feeds it to the parser in
+//! windows of limited size, so the same parsers used on network input can
+//! be used for batch processing. The windows are collected in a
+//! [`CursorBuf`].
+//!
+//! In order to process a slice in parallel, [`split_records()`] cuts it
+//! into a number of parts at record boundaries.
+//!
+//! [`SliceIter`]: struct.SliceIter.html
+//! [`split_records()`]: fn.split_records.html
+//! [`CursorBuf`]: ../../parse/cursor/struct.CursorBuf.html
+
+use std::cmp::{max, min};
+use futures::{Async, Poll};
+use ::parse::buffer::Buffer;
+use ::parse::cursor::CursorBuf;
+
+
+//------------ SliceIter -----------------------------------------------------
+
+/// An iterator applying a parser repeatedly to a slice.
+///
+/// The iterator copies the slice window by window into a buffer and
+/// applies the parser to that buffer. Each time the parser returns
+/// non-ready, another window is added. Each successfully parsed item is
+/// returned by the iterator.
+///
+/// If the parser returns an error, the iterator returns this error and
+/// stops. It also stops if the parser returns non-ready after the end of
+/// the slice has been reached. In this case, `remainder()` returns the
+/// part of the slice that hasn’t been parsed.
+///
+/// The parser must make progress on success, i.e., drain at least one
+/// octet from the buffer, or else the iterator will loop forever.
+pub struct SliceIter<'a, P> {
+    /// The complete input slice.
+    input: &'a [u8],
+
+    /// The number of octets of `input` already added to `buf`.
+    fed: usize,
+
+    /// The number of octets of `input` drained by the parser.
+    consumed: usize,
+
+    /// The size of a window.
+    window: usize,
+
+    /// The buffer the parser operates on.
+    buf: CursorBuf,
+
+    /// The parser.
+    parse: P,
+
+    /// Whether we are done.
+    done: bool,
+}
+
+impl<'a, P> SliceIter<'a, P> {
+    /// Creates a new iterator over `input` using `parse`.
+    ///
+    /// Data will be added to the parser’s buffer in chunks of `window`
+    /// octets.
+    ///
+    /// # Panic
+    ///
+    /// The function panics if `window` is zero.
+    pub fn new(input: &'a [u8], window: usize, parse: P) -> Self {
+        assert!(window > 0);
+        SliceIter {
+            input,
+            fed: 0,
+            consumed: 0,
+            window,
+            buf: CursorBuf::new(),
+            parse,
+            done: false
+        }
+    }
+
+    /// Returns the offset into the slice of the next item to be parsed.
+    pub fn offset(&self) -> usize {
+        self.consumed
+    }
+
+    /// Returns the part of the slice that hasn’t been parsed yet.
+    pub fn remainder(&self) -> &'a [u8] {
+        &self.input[self.consumed..]
+    }
+
+    /// Returns whether the entire slice has been added to the buffer.
+    pub fn is_exhausted(&self) -> bool {
+        self.fed == self.input.len()
+    }
+
+    /// Adds the next window to the buffer.
+    fn feed(&mut self) {
+        let end = min(self.fed + self.window, self.input.len());
+        self.buf.extend_from_slice(&self.input[self.fed..end]);
+        self.fed = end;
+    }
+}
+
+impl<'a, P, T, E> Iterator for SliceIter<'a, P>
+               where P: FnMut(&mut CursorBuf) -> Poll<T, E> {
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let len = self.buf.len();
+            match (self.parse)(&mut self.buf) {
+                Ok(Async::Ready(item)) => {
+                    self.consumed += len - self.buf.len();
+                    return Some(Ok(item))
+                }
+                Ok(Async::NotReady) => {
+                    if self.is_exhausted() {
+                        self.done = true
+                    }
+                    else {
+                        self.feed()
+                    }
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err))
+                }
+            }
+        }
+        None
+    }
+}
+
+
+//------------ split_records -------------------------------------------------
+
+/// Splits a slice into about `parts` parts at CRLF boundaries.
+///
+/// Each part but the last one ends right after a CRLF. Parts are of about
+/// equal size, though fewer parts than requested may be returned if the
+/// slice is short or contains few line breaks. The returned parts cover
+/// the entire slice.
+pub fn split_records(data: &[u8], parts: usize) -> Vec<&[u8]> {
+    let mut res = Vec::new();
+    let part_len = data.len() / max(parts, 1);
+    let mut start = 0;
+    while res.len() + 1 < parts {
+        let end = match find_crlf(&data[start..], part_len) {
+            Some(end) => start + end,
+            None => break
+        };
+        res.push(&data[start..end]);
+        start = end;
+    }
+    res.push(&data[start..]);
+    res
+}
+
+/// Returns the index after the first CRLF ending at or after `min_len`.
+fn find_crlf(data: &[u8], min_len: usize) -> Option<usize> {
+    let from = max(min_len, 2) - 2;
+    if from >= data.len() {
+        return None
+    }
+    data[from..].windows(2).position(|window| window == b"\r\n")
+                .map(|pos| from + pos + 2)
+                .and_then(|end| if end < data.len() { Some(end) }
+                                else { None })
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+    use ::core::parse_line;
+    use ::parse::token::TokenError;
+    use super::*;
+
+    #[test]
+    fn iter_lines() {
+        let data = b"one\r\ntwo\r\nthree\r\nfour";
+        let mut iter = SliceIter::new(&data[..], 3, parse_line);
+        assert_eq!(iter.next(), Some(Ok(Bytes::from(&b"one\r\n"[..]))));
+        assert_eq!(iter.offset(), 5);
+        let rest: Vec<Result<Bytes, TokenError>> = iter.by_ref().collect();
+        assert_eq!(rest, vec![Ok(Bytes::from(&b"two\r\n"[..])),
+                              Ok(Bytes::from(&b"three\r\n"[..]))]);
+        assert_eq!(iter.remainder(), b"four");
+    }
+
+    #[test]
+    fn split() {
+        let data = b"aaaa\r\nbb\r\ncccccc\r\nd\r\n";
+        let parts = split_records(data, 3);
+        assert_eq!(parts, vec![&b"aaaa\r\nbb\r\n"[..], &b"cccccc\r\n"[..],
+                               &b"d\r\n"[..]]);
+        assert_eq!(split_records(data, 1), vec![&data[..]]);
+        assert_eq!(split_records(b"abc", 4), vec![&b"abc"[..]]);
+    }
+}
@@ -0,0 +1,171 @@
+//! The Base64 encoding.
+//!
+//! This implements the Base64 encoding as defined in RFC 4648 with the
+//! standard alphabet and padding. For use in MIME bodies, the output can
+//! be wrapped into lines of 76 characters as demanded by RFC 2045.
+
+use bytes::BytesMut;
+
+
+//------------ Constants -----------------------------------------------------
+
+/// The maximum line length for encoded MIME bodies.
+pub const MIME_LINE_LEN: usize = 76;
+
+/// The Base64 alphabet.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+                              abcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+//------------ Base64Encoder -------------------------------------------------
+
+/// A streaming Base64 encoder.
+///
+/// The data is handed to `encode()` in chunks of arbitrary size. Since
+/// Base64 encodes groups of three octets, up to two octets are held back
+/// until the next chunk or until `finish()` adds the padding.
+///
+/// By default, the output is not wrapped. Use `set_wrap()` to wrap it
+/// into lines separated by CRLF.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Base64Encoder {
+    /// The octets held back.
+    pending: [u8; 3],
+
+    /// The number of octets held back.
+    pending_len: usize,
+
+    /// The maximum line length or `None` to not wrap at all.
+    wrap: Option<usize>,
+
+    /// The length of the current output line.
+    line_len: usize,
+}
+
+impl Base64Encoder {
+    /// Creates a new encoder that doesn’t wrap its output.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new encoder for MIME bodies.
+    ///
+    /// The output is wrapped at `MIME_LINE_LEN` characters.
+    pub fn mime() -> Self {
+        let mut res = Self::new();
+        res.set_wrap(Some(MIME_LINE_LEN));
+        res
+    }
+
+    /// Sets the maximum line length or `None` to not wrap the output.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if the line length is zero.
+    pub fn set_wrap(&mut self, wrap: Option<usize>) {
+        assert!(wrap != Some(0));
+        self.wrap = wrap
+    }
+
+    /// Encodes a chunk of data and appends it to `target`.
+    pub fn encode(&mut self, data: &[u8], target: &mut BytesMut) {
+        target.reserve(self.encoded_len(data.len()));
+        for &ch in data {
+            self.pending[self.pending_len] = ch;
+            self.pending_len += 1;
+            if self.pending_len == 3 {
+                let group = self.pending;
+                self.group(&group, target);
+                self.pending_len = 0;
+            }
+        }
+    }
+
+    /// Appends the octets held back and padding to `target`.
+    ///
+    /// No line break is added after the last line.
+    pub fn finish(mut self, target: &mut BytesMut) {
+        if self.pending_len > 0 {
+            let group = self.pending;
+            self.group(&group[..self.pending_len], target);
+        }
+    }
+
+    /// Returns an upper bound of the length of `len` encoded octets.
+    fn encoded_len(&self, len: usize) -> usize {
+        let res = (len + self.pending_len) / 3 * 4 + 4;
+        match self.wrap {
+            Some(wrap) => res + (res / wrap + 1) * 2,
+            None => res
+        }
+    }
+
+    /// Encodes a group of one to three octets.
+    fn group(&mut self, group: &[u8], target: &mut BytesMut) {
+        let b0 = group[0];
+        let b1 = group.get(1).cloned().unwrap_or(0);
+        let b2 = group.get(2).cloned().unwrap_or(0);
+        let chars = [
+            ALPHABET[(b0 >> 2) as usize],
+            ALPHABET[((b0 & 0x03) << 4 | b1 >> 4) as usize],
+            if group.len() > 1 {
+                ALPHABET[((b1 & 0x0F) << 2 | b2 >> 6) as usize]
+            } else { b'=' },
+            if group.len() > 2 { ALPHABET[(b2 & 0x3F) as usize] }
+            else { b'=' },
+        ];
+        for &ch in &chars {
+            if let Some(wrap) = self.wrap {
+                if self.line_len == wrap {
+                    target.extend_from_slice(b"\r\n");
+                    self.line_len = 0;
+                }
+            }
+            target.extend_from_slice(&[ch]);
+            self.line_len += 1;
+        }
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(mut encoder: Base64Encoder, chunks: &[&[u8]]) -> BytesMut {
+        let mut res = BytesMut::new();
+        for chunk in chunks {
+            encoder.encode(chunk, &mut res)
+        }
+        encoder.finish(&mut res);
+        res
+    }
+
+    #[test]
+    fn rfc4648_vectors() {
+        let vectors: &[(&[u8], &[u8])] = &[
+            (b"", b""), (b"f", b"Zg=="), (b"fo", b"Zm8="),
+            (b"foo", b"Zm9v"), (b"foob", b"Zm9vYg=="),
+            (b"fooba", b"Zm9vYmE="), (b"foobar", b"Zm9vYmFy"),
+        ];
+        for &(data, encoded) in vectors {
+            assert_eq!(&encode(Base64Encoder::new(), &[data])[..], encoded);
+        }
+        assert_eq!(&encode(Base64Encoder::new(), &[b"fo", b"o", b"ba"])[..],
+                   b"Zm9vYmE=");
+    }
+
+    #[test]
+    fn wrap() {
+        let mut encoder = Base64Encoder::new();
+        encoder.set_wrap(Some(4));
+        assert_eq!(&encode(encoder, &[b"foobar"])[..], b"Zm9v\r\nYmFy");
+
+        let res = encode(Base64Encoder::mime(), &[&[0u8; 100][..]]);
+        let lines: Vec<&[u8]> = res[..].split(|&ch| ch == b'\n').collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), MIME_LINE_LEN + 1);
+    }
+}
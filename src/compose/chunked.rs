@@ -0,0 +1,126 @@
+//! The chunked transfer coding.
+//!
+//! HTTP/1.1 can transfer a body of unknown length as a sequence of chunks,
+//! each preceded by a line with its size in hex digits. The body ends with
+//! a chunk of size zero that may be followed by trailer fields and then an
+//! empty line. See section 7.1 of RFC 9112 for the details.
+
+use bytes::BytesMut;
+use ::core::{test_vchar, test_wsp};
+use ::http::test_tchar;
+use super::{hexdigs, ComposeError};
+
+
+//------------ ChunkedEncoder ------------------------------------------------
+
+/// A streaming encoder for the chunked transfer coding.
+///
+/// The body is handed to `encode()` in pieces of arbitrary size. Each
+/// piece becomes one or more chunks of at most the maximum chunk size.
+/// Once done, `finish()` appends the last chunk and trailer fields.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkedEncoder {
+    /// The maximum size of a chunk.
+    max_chunk_size: usize,
+}
+
+impl ChunkedEncoder {
+    /// Creates a new encoder with a maximum chunk size of 64 KiB.
+    pub fn new() -> Self {
+        ChunkedEncoder {
+            max_chunk_size: 0x1_0000,
+        }
+    }
+
+    /// Returns the maximum size of a chunk.
+    pub fn max_chunk_size(&self) -> usize {
+        self.max_chunk_size
+    }
+
+    /// Sets the maximum size of a chunk.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if `size` is zero.
+    pub fn set_max_chunk_size(&mut self, size: usize) {
+        assert!(size > 0);
+        self.max_chunk_size = size
+    }
+
+    /// Encodes a piece of the body and appends it to `target`.
+    ///
+    /// An empty piece produces nothing since a chunk of size zero would end
+    /// the body.
+    pub fn encode(&self, data: &[u8], target: &mut BytesMut) {
+        for chunk in data.chunks(self.max_chunk_size) {
+            target.reserve(chunk.len() + 20);
+            hexdigs(target, chunk.len() as u64);
+            target.extend_from_slice(b"\r\n");
+            target.extend_from_slice(chunk);
+            target.extend_from_slice(b"\r\n");
+        }
+    }
+
+    /// Appends the last chunk and the trailer fields to `target`.
+    ///
+    /// Each trailer field is given as a pair of name and value. Returns an
+    /// error and leaves `target` untouched if a name isn’t a token or a
+    /// value contains anything but printable characters and white space.
+    pub fn finish(self, trailers: &[(&[u8], &[u8])], target: &mut BytesMut)
+                  -> Result<(), ComposeError> {
+        for &(name, value) in trailers {
+            if name.is_empty() || !name.iter().all(|&ch| test_tchar(ch))
+                || !value.iter().all(|&ch| test_vchar(ch) || test_wsp(ch))
+            {
+                return Err(ComposeError::Invalid)
+            }
+        }
+        target.extend_from_slice(b"0\r\n");
+        for &(name, value) in trailers {
+            target.extend_from_slice(name);
+            target.extend_from_slice(b": ");
+            target.extend_from_slice(value);
+            target.extend_from_slice(b"\r\n");
+        }
+        target.extend_from_slice(b"\r\n");
+        Ok(())
+    }
+}
+
+impl Default for ChunkedEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let mut encoder = ChunkedEncoder::new();
+        encoder.set_max_chunk_size(16);
+        let mut target = BytesMut::new();
+        encoder.encode(b"Hello, ", &mut target);
+        encoder.encode(b"", &mut target);
+        encoder.encode(b"world! How are you today?", &mut target);
+        encoder.finish(&[(b"Expires", b"never")], &mut target).unwrap();
+        assert_eq!(&target[..],
+                   &b"7\r\nHello, \r\n\
+                      10\r\nworld! How are y\r\n\
+                      9\r\nou today?\r\n\
+                      0\r\nExpires: never\r\n\r\n"[..]);
+    }
+
+    #[test]
+    fn invalid_trailer() {
+        let mut target = BytesMut::new();
+        assert_eq!(ChunkedEncoder::new().finish(&[(b"Ex pires", b"x")],
+                                                &mut target),
+                   Err(ComposeError::Invalid));
+        assert!(target.is_empty());
+    }
+}
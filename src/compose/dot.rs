@@ -0,0 +1,89 @@
+//! Dot-stuffing.
+//!
+//! SMTP and a few other protocols transfer a message body as a sequence of
+//! lines terminated by a line consisting of a single dot. In order to keep
+//! a line of the message that happens to start with a dot from being taken
+//! for the end, such a line gets an additional dot prepended. This is
+//! called dot-stuffing.
+
+use bytes::BytesMut;
+
+
+//------------ DotEncoder ----------------------------------------------------
+
+/// A streaming dot-stuffing encoder.
+///
+/// The message body is handed to `encode()` in chunks of arbitrary size.
+/// Once done, `finish()` appends the terminating line, preceded by a CRLF
+/// if the body didn’t end in one.
+#[derive(Clone, Copy, Debug)]
+pub struct DotEncoder {
+    /// Whether the next octet starts a line.
+    line_start: bool,
+
+    /// Whether the last octet was a CR.
+    last_cr: bool,
+}
+
+impl DotEncoder {
+    /// Creates a new encoder.
+    pub fn new() -> Self {
+        DotEncoder {
+            line_start: true,
+            last_cr: false,
+        }
+    }
+
+    /// Encodes a chunk of the body and appends it to `target`.
+    pub fn encode(&mut self, data: &[u8], target: &mut BytesMut) {
+        target.reserve(data.len() + 1);
+        for &ch in data {
+            if self.line_start && ch == b'.' {
+                target.extend_from_slice(b".")
+            }
+            target.extend_from_slice(&[ch]);
+            self.line_start = self.last_cr && ch == b'\n';
+            self.last_cr = ch == b'\r';
+        }
+    }
+
+    /// Appends the end of the body to `target`.
+    pub fn finish(self, target: &mut BytesMut) {
+        if !self.line_start {
+            target.extend_from_slice(b"\r\n")
+        }
+        target.extend_from_slice(b".\r\n")
+    }
+}
+
+impl Default for DotEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(chunks: &[&[u8]]) -> BytesMut {
+        let mut encoder = DotEncoder::new();
+        let mut res = BytesMut::new();
+        for chunk in chunks {
+            encoder.encode(chunk, &mut res)
+        }
+        encoder.finish(&mut res);
+        res
+    }
+
+    #[test]
+    fn encode_chunks() {
+        assert_eq!(&encode(&[b".a\r\n..b\r", b"\n.", b"\r\nc.\r\n"])[..],
+                   &b"..a\r\n...b\r\n..\r\nc.\r\n.\r\n"[..]);
+        assert_eq!(&encode(&[b"abc"])[..], &b"abc\r\n.\r\n"[..]);
+        assert_eq!(&encode(&[])[..], &b".\r\n"[..]);
+    }
+}
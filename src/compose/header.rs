@@ -0,0 +1,149 @@
+//! Composing header fields.
+//!
+//! Header fields of the Internet Message Format defined in RFC 5322 and
+//! everything derived from it, such as MIME, should have lines of at most
+//! 78 octets and must not have lines longer than 998 octets, both without
+//! the CRLF. Longer values have to be folded by inserting a CRLF before
+//! white space within the value.
+
+use bytes::BytesMut;
+use ::core::{test_vchar, test_wsp};
+use super::ComposeError;
+
+
+//------------ Constants -----------------------------------------------------
+
+/// The line length header fields should stay within.
+pub const SOFT_LINE_LEN: usize = 78;
+
+/// The line length header fields must stay within.
+pub const HARD_LINE_LEN: usize = 998;
+
+
+//------------ header --------------------------------------------------------
+
+/// Returns whether `ch` is allowed in a field name.
+///
+/// This is `ftext` of RFC 5322, i.e., any printable character but colon.
+pub fn test_ftext(ch: u8) -> bool {
+    test_vchar(ch) && ch != b':'
+}
+
+/// Appends a header field, folding it if necessary.
+///
+/// Writes `name ": " value CRLF`. Lines are folded before white space so
+/// that they stay within `SOFT_LINE_LEN` octets if possible. A line never
+/// consists of white space only.
+///
+/// Returns `ComposeError::Invalid` if the name is empty or contains
+/// anything but `ftext` or if the value contains anything but printable
+/// characters and white space. Returns `ComposeError::Unfoldable` if there
+/// is no way to fold the field so that all lines stay within
+/// `HARD_LINE_LEN` octets. In both cases, `target` is left untouched.
+pub fn header(target: &mut BytesMut, name: &[u8], value: &[u8])
+              -> Result<(), ComposeError> {
+    if name.is_empty() || !name.iter().all(|&ch| test_ftext(ch))
+        || !value.iter().all(|&ch| test_vchar(ch) || test_wsp(ch))
+    {
+        return Err(ComposeError::Invalid)
+    }
+
+    let mut res = Vec::with_capacity(name.len() + value.len() + 4);
+    res.extend_from_slice(name);
+    res.extend_from_slice(b": ");
+    let mut line_len = res.len();
+    for segment in segments(value) {
+        // A trailing segment of only white space stays on the line since
+        // it would become a line of white space only otherwise.
+        if line_len + segment.len() > SOFT_LINE_LEN && line_len > 0
+            && test_wsp(segment[0])
+            && !segment.iter().all(|&ch| test_wsp(ch))
+        {
+            res.extend_from_slice(b"\r\n");
+            line_len = 0;
+        }
+        res.extend_from_slice(segment);
+        line_len += segment.len();
+        if line_len > HARD_LINE_LEN {
+            return Err(ComposeError::Unfoldable)
+        }
+    }
+    res.extend_from_slice(b"\r\n");
+    target.extend_from_slice(&res);
+    Ok(())
+}
+
+/// Splits a value into the segments between folding points.
+///
+/// Each segment but the first starts with white space and contains at
+/// least one other character unless it is at the very end.
+fn segments(value: &[u8]) -> Vec<&[u8]> {
+    let mut res = Vec::new();
+    let mut start = 0;
+    for pos in 1..value.len() {
+        if test_wsp(value[pos]) && !test_wsp(value[pos - 1]) {
+            res.push(&value[start..pos]);
+            start = pos;
+        }
+    }
+    if start < value.len() {
+        res.push(&value[start..]);
+    }
+    res
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn short() {
+        let mut target = BytesMut::new();
+        header(&mut target, b"Subject", b"Hello  world").unwrap();
+        assert_eq!(&target[..], b"Subject: Hello  world\r\n");
+    }
+
+    #[test]
+    fn folded() {
+        let value = "word ".repeat(30);
+        let value = value.trim_end();
+        let mut target = BytesMut::new();
+        header(&mut target, b"Subject", value.as_bytes()).unwrap();
+        let lines: Vec<&[u8]> = target[..target.len() - 2].split(|&ch| {
+            ch == b'\n'
+        }).collect();
+        assert_eq!(lines.len(), 3);
+        for line in &lines {
+            assert!(line.len() <= SOFT_LINE_LEN + 1);
+        }
+        assert!(lines[1].starts_with(b" word"));
+        let unfolded: Vec<u8> = target.iter().cloned().filter(|&ch| {
+            ch != b'\r' && ch != b'\n'
+        }).collect();
+        assert_eq!(unfolded, format!("Subject: {}", value).as_bytes());
+
+        let mut value = b"x".to_vec();
+        value.extend_from_slice(&[b' '; 80]);
+        let mut target = BytesMut::new();
+        header(&mut target, b"Subject", &value).unwrap();
+        assert_eq!(&target[..11], b"Subject: x ");
+        assert_eq!(target.len(), 9 + value.len() + 2);
+        assert!(!target[..target.len() - 2].contains(&b'\n'));
+    }
+
+    #[test]
+    fn errors() {
+        let mut target = BytesMut::new();
+        assert_eq!(header(&mut target, b"Sub ject", b"x"),
+                   Err(ComposeError::Invalid));
+        assert_eq!(header(&mut target, b"Subject", b"x\r\ny"),
+                   Err(ComposeError::Invalid));
+        let long = vec![b'x'; 1000];
+        assert_eq!(header(&mut target, b"Subject", &long),
+                   Err(ComposeError::Unfoldable));
+        assert!(target.is_empty());
+    }
+}
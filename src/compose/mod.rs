@@ -0,0 +1,226 @@
+//! Composing data.
+//!
+//! While the `parse` module and friends are concerned with reading data,
+//! this module helps with producing it. The functions herein append a
+//! token to a `BytesMut` and make sure that the token actually matches
+//! its rule. If the value given to a function can’t be represented, the
+//! function returns an error and leaves the target untouched.
+
+pub mod base64;
+pub mod chunked;
+pub mod dot;
+pub mod header;
+pub mod qp;
+
+use std::fmt;
+use bytes::BytesMut;
+use ::core::{test_alpha, test_digit, test_vchar, test_wsp};
+use ::octets::{OctetSet, URI_PCHAR, URI_SUB_DELIMS, URI_UNRESERVED};
+
+
+//------------ Literals and Line Endings -------------------------------------
+
+/// Appends a literal.
+pub fn literal(target: &mut BytesMut, lit: &[u8]) {
+    target.extend_from_slice(lit)
+}
+
+/// Appends a CRLF.
+pub fn crlf(target: &mut BytesMut) {
+    target.extend_from_slice(b"\r\n")
+}
+
+
+//------------ Integers ------------------------------------------------------
+
+/// Appends an integer as `1*DIGIT` without leading zeros.
+pub fn digits(target: &mut BytesMut, value: u64) {
+    target.extend_from_slice(value.to_string().as_bytes())
+}
+
+/// Appends an integer as exactly `n` digits, padding with zeros.
+///
+/// Returns an error if the integer needs more than `n` digits.
+pub fn digits_exact(target: &mut BytesMut, value: u64, n: usize)
+                    -> Result<(), ComposeError> {
+    let value = format!("{:01$}", value, n);
+    if value.len() != n {
+        return Err(ComposeError::Invalid)
+    }
+    target.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// Appends an integer as `1*HEXDIG` using upper case letters.
+///
+/// Upper case is what the HEXDIG rule of RFC 5234 strictly asks for.
+pub fn hexdigs(target: &mut BytesMut, value: u64) {
+    target.extend_from_slice(format!("{:X}", value).as_bytes())
+}
+
+/// Appends an integer as `1*HEXDIG` using lower case letters.
+pub fn lower_hexdigs(target: &mut BytesMut, value: u64) {
+    target.extend_from_slice(format!("{:x}", value).as_bytes())
+}
+
+
+//------------ Quoted Strings ------------------------------------------------
+
+/// Appends a quoted string.
+///
+/// The content is enclosed in double quotes. Double quotes and
+/// backslashes within it are escaped with a backslash. Since a quoted
+/// string can only contain printable characters and white space, returns
+/// an error if `content` contains anything else.
+pub fn quoted_string(target: &mut BytesMut, content: &[u8])
+                     -> Result<(), ComposeError> {
+    if !content.iter().all(|&ch| test_vchar(ch) || test_wsp(ch)) {
+        return Err(ComposeError::Invalid)
+    }
+    target.reserve(content.len() + 2);
+    target.extend_from_slice(b"\"");
+    for &ch in content {
+        if ch == b'"' || ch == b'\\' {
+            target.extend_from_slice(b"\\")
+        }
+        target.extend_from_slice(&[ch])
+    }
+    target.extend_from_slice(b"\"");
+    Ok(())
+}
+
+
+//------------ Atoms ---------------------------------------------------------
+
+/// Returns whether `ch` is `atext` as defined by RFC 5322.
+pub fn test_atext(ch: u8) -> bool {
+    test_alpha(ch) || test_digit(ch) || b"!#$%&'*+-/=?^_`{|}~".contains(&ch)
+}
+
+/// Appends a dot-atom.
+///
+/// This is `1*atext *("." 1*atext)`. Returns an error if `text` is empty,
+/// contains anything but `atext` and dots, or starts or ends with a dot or
+/// has two consecutive dots.
+pub fn dot_atom(target: &mut BytesMut, text: &[u8])
+                -> Result<(), ComposeError> {
+    if text.split(|&ch| ch == b'.').any(|atom| {
+        atom.is_empty() || !atom.iter().all(|&ch| test_atext(ch))
+    }) {
+        return Err(ComposeError::Invalid)
+    }
+    target.extend_from_slice(text);
+    Ok(())
+}
+
+
+//------------ Percent-Encoding ----------------------------------------------
+
+/// The octets left alone in a path segment of a URI.
+pub const PCT_PATH_SEGMENT: OctetSet = URI_PCHAR;
+
+/// The octets left alone in the query of a URI.
+pub const PCT_QUERY: OctetSet = URI_PCHAR.union(OctetSet::from_bytes(b"/?"));
+
+/// The octets left alone in the fragment of a URI.
+pub const PCT_FRAGMENT: OctetSet = PCT_QUERY;
+
+/// The octets left alone in the userinfo of a URI.
+pub const PCT_USERINFO: OctetSet = URI_UNRESERVED.union(URI_SUB_DELIMS)
+                                        .union(OctetSet::from_bytes(b":"));
+
+/// Appends `input` percent-encoding all octets not in `safe`.
+///
+/// Encoded octets are written as `%` followed by two upper case hex
+/// digits as recommended by RFC 3986. The percent sign itself is always
+/// encoded, even if it is in `safe`.
+pub fn pct_encode(target: &mut BytesMut, input: &[u8], safe: &OctetSet) {
+    target.reserve(input.len());
+    for &ch in input {
+        if ch != b'%' && safe.contains(ch) {
+            target.extend_from_slice(&[ch])
+        }
+        else {
+            target.extend_from_slice(format!("%{:02X}", ch).as_bytes())
+        }
+    }
+}
+
+
+//------------ Display Helpers -----------------------------------------------
+
+/// Writes octets to a formatter.
+///
+/// Since a formatter only takes text, octets that aren’t valid UTF-8 are
+/// replaced with U+FFFD. For the parsed values of the protocol modules,
+/// this only affects free text such as reason phrases.
+pub(crate) fn write_octets(f: &mut fmt::Formatter, octets: &[u8])
+                           -> fmt::Result {
+    f.write_str(&String::from_utf8_lossy(octets))
+}
+
+
+//============ Errors ========================================================
+
+/// An error happened while composing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ComposeError {
+    /// The value can’t be represented by the rule.
+    Invalid,
+
+    /// A header field can’t be folded to stay within the line length.
+    Unfoldable,
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compose<F>(op: F) -> Result<BytesMut, ComposeError>
+               where F: FnOnce(&mut BytesMut) -> Result<(), ComposeError> {
+        let mut res = BytesMut::new();
+        op(&mut res).map(|_| res)
+    }
+
+    #[test]
+    fn integers() {
+        let mut target = BytesMut::new();
+        digits(&mut target, 1024);
+        literal(&mut target, b" ");
+        hexdigs(&mut target, 0xbeef);
+        literal(&mut target, b" ");
+        lower_hexdigs(&mut target, 0xbeef);
+        crlf(&mut target);
+        assert_eq!(&target[..], b"1024 BEEF beef\r\n");
+
+        assert_eq!(&compose(|t| digits_exact(t, 7, 3)).unwrap()[..], b"007");
+        assert_eq!(compose(|t| digits_exact(t, 1000, 3)),
+                   Err(ComposeError::Invalid));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(&compose(|t| quoted_string(t, b"a \"b\\")).unwrap()[..],
+                   b"\"a \\\"b\\\\\"");
+        assert_eq!(compose(|t| quoted_string(t, b"a\r\n")),
+                   Err(ComposeError::Invalid));
+        assert_eq!(&compose(|t| dot_atom(t, b"foo.bar")).unwrap()[..],
+                   b"foo.bar");
+        assert!(compose(|t| dot_atom(t, b"foo..bar")).is_err());
+        assert!(compose(|t| dot_atom(t, b".foo")).is_err());
+        assert!(compose(|t| dot_atom(t, b"")).is_err());
+        assert!(compose(|t| dot_atom(t, b"a b")).is_err());
+    }
+
+    #[test]
+    fn pct() {
+        let mut target = BytesMut::new();
+        pct_encode(&mut target, b"a b/c?d%", &PCT_PATH_SEGMENT);
+        literal(&mut target, b"?");
+        pct_encode(&mut target, b"a b/c?d#", &PCT_QUERY);
+        assert_eq!(&target[..], &b"a%20b%2Fc%3Fd%25?a%20b/c?d%23"[..]);
+    }
+}
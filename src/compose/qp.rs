@@ -0,0 +1,202 @@
+//! The Quoted-Printable encoding.
+//!
+//! This implements the Quoted-Printable content transfer encoding defined
+//! in section 6.7 of RFC 2045. Printable characters other than `=` are
+//! left as they are, everything else is encoded as `=` followed by two
+//! hex digits. White space is only encoded at the end of a line.
+//!
+//! In text mode, CRLF in the data is a line break and is kept. In binary
+//! mode, CR and LF are encoded like any other octet.
+
+use bytes::BytesMut;
+use super::base64::MIME_LINE_LEN;
+
+
+//------------ QpEncoder -----------------------------------------------------
+
+/// A streaming Quoted-Printable encoder.
+///
+/// The data is handed to `encode()` in chunks of arbitrary size. Since
+/// white space needs to be encoded if a line break follows, it is held
+/// back until the next octet is known. So is a CR in text mode. Call
+/// `finish()` once done to write out what was held back.
+///
+/// By default, the encoder is in text mode and inserts soft line breaks
+/// so that lines are at most 76 characters long.
+#[derive(Clone, Copy, Debug)]
+pub struct QpEncoder {
+    /// The maximum line length or `None` to not wrap at all.
+    wrap: Option<usize>,
+
+    /// Whether CR and LF are encoded, too.
+    binary: bool,
+
+    /// The length of the current output line.
+    line_len: usize,
+
+    /// A white space octet held back.
+    pending_wsp: Option<u8>,
+
+    /// Whether a CR was held back.
+    pending_cr: bool,
+}
+
+impl QpEncoder {
+    /// Creates a new encoder in text mode wrapping at 76 characters.
+    pub fn new() -> Self {
+        QpEncoder {
+            wrap: Some(MIME_LINE_LEN),
+            binary: false,
+            line_len: 0,
+            pending_wsp: None,
+            pending_cr: false,
+        }
+    }
+
+    /// Sets the maximum line length or `None` to not wrap the output.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if the line length is less than four since
+    /// otherwise an encoded octet and a soft line break won’t fit.
+    pub fn set_wrap(&mut self, wrap: Option<usize>) {
+        assert!(wrap.map(|wrap| wrap >= 4).unwrap_or(true));
+        self.wrap = wrap
+    }
+
+    /// Sets whether to encode binary data.
+    pub fn set_binary(&mut self, binary: bool) {
+        self.binary = binary
+    }
+
+    /// Encodes a chunk of data and appends it to `target`.
+    pub fn encode(&mut self, data: &[u8], target: &mut BytesMut) {
+        target.reserve(data.len() + data.len() / 8);
+        for &ch in data {
+            self.octet(ch, target)
+        }
+    }
+
+    /// Appends what was held back to `target`.
+    ///
+    /// No line break is added after the last line.
+    pub fn finish(mut self, target: &mut BytesMut) {
+        if self.pending_cr {
+            self.flush_wsp(false, target);
+            self.emit_encoded(b'\r', target);
+        }
+        else {
+            self.flush_wsp(true, target);
+        }
+    }
+
+    /// Encodes a single octet.
+    fn octet(&mut self, ch: u8, target: &mut BytesMut) {
+        if self.pending_cr {
+            self.pending_cr = false;
+            if ch == b'\n' {
+                self.flush_wsp(true, target);
+                target.extend_from_slice(b"\r\n");
+                self.line_len = 0;
+                return
+            }
+            self.flush_wsp(false, target);
+            self.emit_encoded(b'\r', target);
+        }
+        match ch {
+            b'\r' if !self.binary => {
+                self.pending_cr = true;
+            }
+            b' ' | b'\t' => {
+                self.flush_wsp(false, target);
+                self.pending_wsp = Some(ch);
+            }
+            33..=60 | 62..=126 => {
+                self.flush_wsp(false, target);
+                self.emit(&[ch], target);
+            }
+            _ => {
+                self.flush_wsp(false, target);
+                self.emit_encoded(ch, target);
+            }
+        }
+    }
+
+    /// Writes out held back white space, encoding it if `at_end`.
+    fn flush_wsp(&mut self, at_end: bool, target: &mut BytesMut) {
+        if let Some(ch) = self.pending_wsp.take() {
+            if at_end {
+                self.emit_encoded(ch, target)
+            }
+            else {
+                self.emit(&[ch], target)
+            }
+        }
+    }
+
+    /// Writes out an encoded octet.
+    fn emit_encoded(&mut self, ch: u8, target: &mut BytesMut) {
+        self.emit(format!("={:02X}", ch).as_bytes(), target)
+    }
+
+    /// Writes out an encoded piece, adding a soft line break if needed.
+    fn emit(&mut self, piece: &[u8], target: &mut BytesMut) {
+        if let Some(wrap) = self.wrap {
+            // Leave room for the equals sign of a soft line break.
+            if self.line_len + piece.len() > wrap - 1 {
+                target.extend_from_slice(b"=\r\n");
+                self.line_len = 0;
+            }
+        }
+        target.extend_from_slice(piece);
+        self.line_len += piece.len();
+    }
+}
+
+impl Default for QpEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(mut encoder: QpEncoder, chunks: &[&[u8]]) -> BytesMut {
+        let mut res = BytesMut::new();
+        for chunk in chunks {
+            encoder.encode(chunk, &mut res)
+        }
+        encoder.finish(&mut res);
+        res
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(&encode(QpEncoder::new(),
+                           &[b"a=b \r", b"\nc\td \xff\rx "])[..],
+                   &b"a=3Db=20\r\nc\td =FF=0Dx=20"[..]);
+    }
+
+    #[test]
+    fn binary() {
+        let mut encoder = QpEncoder::new();
+        encoder.set_binary(true);
+        assert_eq!(&encode(encoder, &[b"a \r\n"])[..], &b"a =0D=0A"[..]);
+    }
+
+    #[test]
+    fn wrap() {
+        let res = encode(QpEncoder::new(), &[&[b'x'; 100][..]]);
+        assert_eq!(&res[..78], &[&[b'x'; 75][..], b"=\r\n"].concat()[..]);
+        assert_eq!(&res[78..], &[b'x'; 25][..]);
+
+        let mut encoder = QpEncoder::new();
+        encoder.set_wrap(None);
+        assert_eq!(encode(encoder, &[&[b'x'; 100][..]]).len(), 100);
+    }
+}
@@ -2,23 +2,66 @@
 //!
 //! These are defined in RFC 5234, appendix B.1.
 
-use bytes::{Bytes, BytesMut};
+use std::time::Duration;
+use bytes::Bytes;
 use futures::{Async, Poll};
+use ::parse::buffer::Buffer;
 use ::parse::token;
 use ::parse::token::{TokenError, Token};
 
 
+//------------ Conversion Helpers --------------------------------------------
+
+macro_rules! uint_from_digits {
+    ( $digits:expr, $uint:ty, $radix:expr ) => {{
+        let mut res = 0 as $uint;
+        for item in $digits {
+            let x = (*item as char).to_digit($radix).unwrap() as $uint;
+            res = match res.checked_mul($radix) {
+                Some(x) => x,
+                None => return Err(TokenError::Invalid)
+            };
+            res = match res.checked_add(x) {
+                Some(x) => x,
+                None => return Err(TokenError::Invalid)
+            };
+        }
+        Ok(res)
+    }}
+}
+
+macro_rules! convert_uint {
+    ( $token_name:ident, $uint:ty, $parsef:ident($arg:ident: $argty:ty),
+      $radix:expr) => {
+        pub fn $token_name<B: Buffer>(buf: &mut B, $arg: $argty)
+                                       -> Poll<$uint, TokenError> {
+            token::convert(buf, |token| $parsef(token, $arg), |digits| {
+                uint_from_digits!(digits?, $uint, $radix)
+            })
+        }
+    };
+    ( $token_name:ident, $uint:ty, $parsef:expr, $radix:expr) => {
+        pub fn $token_name<B: Buffer>(buf: &mut B)
+                                       -> Poll<$uint, TokenError> {
+            token::convert(buf, $parsef, |digits| {
+                uint_from_digits!(digits?, $uint, $radix)
+            })
+        }
+    };
+}
+
+
 //------------ ALPHA ---------------------------------------------------------
 
 pub fn test_alpha(ch: u8) -> bool {
-    (ch >= 0x41 && ch <= 0x5A) || (ch >= 0x61 && ch <= 0x7A)
+    (0x41..=0x5A).contains(&ch) || (0x61..=0x7A).contains(&ch)
 }
 
-pub fn alpha(token: &mut Token) -> Poll<(), TokenError> {
+pub fn alpha<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_alpha)
 }
 
-pub fn alphas(token: &mut Token) -> Poll<(), TokenError> {
+pub fn alphas<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_alpha)
 }
 
@@ -29,27 +72,32 @@ pub fn test_bit(ch: u8) -> bool {
     ch == b'0' || ch == b'1'
 }
 
-pub fn bit(token: &mut Token) -> Poll<(), TokenError> {
+pub fn bit<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_bit)
 }
 
-pub fn bits(token: &mut Token) -> Poll<(), TokenError> {
-    token::cats(token, test_bit)
+pub fn bits<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::digit_cats(token, test_bit)
 }
 
+convert_uint!(u8_bits, u8, bits, 2);
+convert_uint!(u16_bits, u16, bits, 2);
+convert_uint!(u32_bits, u32, bits, 2);
+convert_uint!(u64_bits, u64, bits, 2);
+
 
 
 //------------ NULL ----------------------------------------------------------
 
 pub fn test_char(ch: u8) -> bool {
-    ch > 0 && ch < 0x80
+    (0x01..0x80).contains(&ch)
 }
 
-pub fn char(token: &mut Token) -> Poll<(), TokenError> {
+pub fn char<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_char)
 }
 
-pub fn chars(token: &mut Token) -> Poll<(), TokenError> {
+pub fn chars<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_char)
 }
 
@@ -60,55 +108,274 @@ pub fn test_cr(ch: u8) -> bool {
     ch == 0x0D
 }
 
-pub fn cr(token: &mut Token) -> Poll<(), TokenError> {
+pub fn cr<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_cr)
 }
 
 
 //------------ CRLF and lines terminated by CRLF -----------------------------
 
-pub fn crlf(token: &mut Token) -> Poll<(), TokenError> {
-    try_ready!(token.expect(test_cr, || TokenError));
-    try_ready!(token.expect(test_lf, || TokenError));
-    Ok(Async::Ready(()))
+/// The line endings accepted when parsing lines.
+///
+/// The standards demand CRLF but many peers send a bare LF or, more
+/// rarely, a bare CR instead.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    /// Only CRLF ends a line.
+    #[default]
+    Crlf,
+
+    /// Either CRLF or a bare LF ends a line.
+    Lf,
+
+    /// Any of CRLF, a bare LF, or a bare CR ends a line.
+    ///
+    /// Since a CR may be followed by an LF, a line ending in CR can only
+    /// be recognized once the next octet has arrived.
+    Any,
 }
 
-pub fn skip_crlf(buf: &mut BytesMut) -> Poll<(), TokenError> {
-    token::skip(buf, crlf)
-}
+impl LineEnding {
+    /// Returns the length of a line ending at the start of `data`.
+    ///
+    /// This assumes that `data` is complete. Returns zero if there is no
+    /// line ending.
+    fn len_in(self, data: &[u8]) -> usize {
+        match (data.first(), data.get(1)) {
+            (Some(b'\r'), Some(b'\n')) => 2,
+            (Some(b'\r'), _) if self == LineEnding::Any => 1,
+            (Some(b'\n'), _) if self != LineEnding::Crlf => 1,
+            _ => 0
+        }
+    }
 
-pub fn line(token: &mut Token) -> Poll<(), TokenError> {
-    let mut pos = None;
-    for (i, slice) in token.remaining().windows(2).enumerate() {
-        if slice == b"\r\n" {
-            pos = Some(i);
-            break;
+    /// Returns the length of a line ending `offset` octets into `token`.
+    ///
+    /// Returns `Some(None)` if there is no line ending at this point and
+    /// `None` if this can’t be decided yet.
+    fn len_at<B: Buffer>(self, token: &Token<B>, offset: usize)
+                         -> Option<Option<usize>> {
+        let peek = |offset| match token.peek(offset) {
+            None if token.is_final() => Some(None),
+            ch => ch.map(Some)
+        };
+        match peek(offset)? {
+            None => Some(None),
+            Some(b'\r') => {
+                match peek(offset + 1)? {
+                    Some(b'\n') => Some(Some(2)),
+                    _ if self == LineEnding::Any => Some(Some(1)),
+                    _ => Some(None)
+                }
+            }
+            Some(b'\n') if self != LineEnding::Crlf => Some(Some(1)),
+            Some(_) => Some(None)
         }
     }
-    match pos {
-        Some(pos) => {
-            token.advance(pos + 2);
+}
+
+pub fn crlf<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    crlf_with(token, LineEnding::Crlf)
+}
+
+/// Advances over a line ending accepted by `ending`.
+pub fn crlf_with<B: Buffer>(token: &mut Token<B>, ending: LineEnding)
+                            -> Poll<(), TokenError> {
+    match ending.len_at(token, 0) {
+        Some(Some(len)) => {
+            token.advance(len);
             Ok(Async::Ready(()))
         }
+        Some(None) => Err(TokenError::Invalid),
         None => Ok(Async::NotReady)
     }
 }
 
-pub fn parse_line(buf: &mut BytesMut) -> Poll<Bytes, TokenError> {
+pub fn skip_crlf<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
+    token::skip(buf, crlf)
+}
+
+pub fn skip_crlf_with<B: Buffer>(buf: &mut B, ending: LineEnding)
+                                 -> Poll<(), TokenError> {
+    token::skip(buf, |token| crlf_with(token, ending))
+}
+
+pub fn line<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    line_with(token, LineEnding::Crlf)
+}
+
+/// Advances over a line including the line ending accepted by `ending`.
+pub fn line_with<B: Buffer>(token: &mut Token<B>, ending: LineEnding)
+                            -> Poll<(), TokenError> {
+    let (pos, len) = try_ready!(find_line_end(token, ending));
+    token.advance(pos + len);
+    Ok(Async::Ready(()))
+}
+
+pub fn line_limited<B: Buffer>(token: &mut Token<B>, max_len: usize)
+                               -> Poll<(), TokenError> {
+    line_limited_with(token, LineEnding::Crlf, max_len)
+}
+
+/// Advances over a line of at most `max_len` octets.
+///
+/// The length includes the line ending. If the line is longer, returns
+/// `TokenError::LineTooLong`. This happens as soon as there are more than
+/// `max_len` octets without a line ending rather than once the line
+/// ending finally arrives.
+pub fn line_limited_with<B: Buffer>(token: &mut Token<B>,
+                                    ending: LineEnding, max_len: usize)
+                                    -> Poll<(), TokenError> {
+    match find_line_end(token, ending)? {
+        Async::Ready((pos, len)) if pos + len <= max_len => {
+            token.advance(pos + len);
+            Ok(Async::Ready(()))
+        }
+        Async::Ready(_) => Err(TokenError::LineTooLong),
+        Async::NotReady if token.remaining() > max_len => {
+            Err(TokenError::LineTooLong)
+        }
+        Async::NotReady => Ok(Async::NotReady)
+    }
+}
+
+pub fn line_content<B: Buffer>(token: &mut Token<B>)
+                               -> Poll<(), TokenError> {
+    line_content_with(token, LineEnding::Crlf)
+}
+
+/// Advances over the content of a line but not its line ending.
+///
+/// Only returns ready once the line ending accepted by `ending` has been
+/// seen. The token ends right before it.
+pub fn line_content_with<B: Buffer>(token: &mut Token<B>,
+                                    ending: LineEnding)
+                                    -> Poll<(), TokenError> {
+    let (pos, _) = try_ready!(find_line_end(token, ending));
+    token.advance(pos);
+    Ok(Async::Ready(()))
+}
+
+/// Finds the first line ending in the token.
+///
+/// Returns the position and length of the line ending.
+fn find_line_end<B: Buffer>(token: &Token<B>, ending: LineEnding)
+                            -> Poll<(usize, usize), TokenError> {
+    for pos in 0..token.remaining() {
+        match ending.len_at(token, pos) {
+            Some(Some(len)) => return Ok(Async::Ready((pos, len))),
+            Some(None) => { }
+            None => break
+        }
+    }
+    Ok(Async::NotReady)
+}
+
+pub fn parse_line<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
     token::parse(buf, line)
 }
 
+pub fn parse_line_with<B: Buffer>(buf: &mut B, ending: LineEnding)
+                                  -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| line_with(token, ending))
+}
+
+pub fn parse_line_limited<B: Buffer>(buf: &mut B, max_len: usize)
+                                     -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| line_limited(token, max_len))
+}
+
+pub fn parse_line_limited_with<B: Buffer>(buf: &mut B, ending: LineEnding,
+                                          max_len: usize)
+                                          -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| line_limited_with(token, ending, max_len))
+}
+
+/// Advances over a line and all its continuation lines.
+///
+/// A continuation line is a line that starts with white space. Lines
+/// are folded this way in Internet Message Format headers and older
+/// versions of HTTP. Since the next line has to be seen to know whether
+/// it is a continuation, only returns ready once the first octet after
+/// the logical line is available.
+///
+/// The token includes all line endings, including the final one.
+pub fn folded_line_with<B: Buffer>(token: &mut Token<B>, ending: LineEnding)
+                                   -> Poll<(), TokenError> {
+    loop {
+        let (pos, len) = try_ready!(find_line_end(token, ending));
+        token.advance(pos + len);
+        if token.at_end() || !test_wsp(try_ready!(token.first())) {
+            return Ok(Async::Ready(()))
+        }
+    }
+}
+
+/// Parses a logical line with its continuation lines.
+///
+/// Returns the line exactly as it is including all line endings.
+pub fn parse_folded_line<B: Buffer>(buf: &mut B, ending: LineEnding)
+                                    -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| folded_line_with(token, ending))
+}
+
+/// Parses a logical line and unfolds it.
+///
+/// Each fold, i.e., a line ending and the white space that follows it, is
+/// replaced with a single space. The final line ending is dropped.
+pub fn parse_unfolded_line<B: Buffer>(buf: &mut B, ending: LineEnding)
+                                      -> Poll<Bytes, TokenError> {
+    let line = try_ready!(parse_folded_line(buf, ending));
+    let mut res = Vec::with_capacity(line.len());
+    let mut pos = 0;
+    while pos < line.len() {
+        let len = ending.len_in(&line[pos..]);
+        if len == 0 {
+            res.push(line[pos]);
+            pos += 1;
+            continue
+        }
+        pos += len;
+        if pos == line.len() {
+            break
+        }
+        while pos < line.len() && test_wsp(line[pos]) {
+            pos += 1
+        }
+        res.push(b' ');
+    }
+    Ok(Async::Ready(res.into()))
+}
+
+/// Parses a line and returns its content without the line ending.
+///
+/// The line ending is advanced over as well.
+pub fn parse_line_content<B: Buffer>(buf: &mut B)
+                                     -> Poll<Bytes, TokenError> {
+    parse_line_content_with(buf, LineEnding::Crlf)
+}
+
+pub fn parse_line_content_with<B: Buffer>(buf: &mut B, ending: LineEnding)
+                                          -> Poll<Bytes, TokenError> {
+    let res = try_ready!(token::parse(buf, |token| {
+        line_content_with(token, ending)
+    }));
+    // The line ending has been seen so this is ready.
+    try_ready!(skip_crlf_with(buf, ending));
+    Ok(Async::Ready(res))
+}
+
 //------------ CTL -----------------------------------------------------------
 
 pub fn test_ctl(ch: u8) -> bool {
     ch < 0x20 || ch == 0x7F
 }
 
-pub fn ctl(token: &mut Token) -> Poll<(), TokenError> {
+pub fn ctl<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_ctl)
 }
 
-pub fn ctls(token: &mut Token) -> Poll<(), TokenError> {
+pub fn ctls<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_ctl)
 }
 
@@ -116,32 +383,99 @@ pub fn ctls(token: &mut Token) -> Poll<(), TokenError> {
 //------------ DIGIT ---------------------------------------------------------
 
 pub fn test_digit(ch: u8) -> bool {
-    ch >= 0x30 && ch <= 0x39
+    (0x30..=0x39).contains(&ch)
 }
 
-pub fn digit(token: &mut Token) -> Poll<(), TokenError> {
+pub fn digit<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_digit)
 }
 
-pub fn digits(token: &mut Token) -> Poll<(), TokenError> {
-    token::cats(token, test_digit)
+pub fn digits<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::digit_cats(token, test_digit)
 }
 
-macro_rules! convert_uint {
-    ( $token_name:ident, $uint:ty, $parsef:expr, $radix:expr) => {
-        pub fn $token_name(buf: &mut BytesMut) -> Poll<$uint, TokenError> {
-            token::convert(buf, $parsef, |digits| {
+convert_uint!(u8_digits, u8, digits, 10);
+convert_uint!(u16_digits, u16, digits, 10);
+convert_uint!(u32_digits, u32, digits, 10);
+convert_uint!(u64_digits, u64, digits, 10);
+convert_uint!(u128_digits, u128, digits, 10);
+
+/// Advances over exactly `n` digits.
+///
+/// This is the ABNF `<n>DIGIT` commonly used for dates and times.
+pub fn digits_exact<B: Buffer>(token: &mut Token<B>, n: usize)
+                               -> Poll<(), TokenError> {
+    token::repeat(token, n, Some(n), digit)
+}
+
+convert_uint!(u8_digits_exact, u8, digits_exact(n: usize), 10);
+convert_uint!(u16_digits_exact, u16, digits_exact(n: usize), 10);
+convert_uint!(u32_digits_exact, u32, digits_exact(n: usize), 10);
+convert_uint!(u64_digits_exact, u64, digits_exact(n: usize), 10);
+
+/// Advances over digits without leading zeros.
+///
+/// A single zero is fine but a zero followed by more digits is an error
+/// rather than the end of the token, so that `007` is rejected.
+pub fn strict_digits<B: Buffer>(token: &mut Token<B>)
+                                -> Poll<(), TokenError> {
+    if try_ready!(token.first()) != b'0' {
+        return digits(token)
+    }
+    match token.peek(1) {
+        None if !token.is_final() => Ok(Async::NotReady),
+        Some(ch) if test_digit(ch) => Err(TokenError::Invalid),
+        _ => {
+            token.advance(1);
+            Ok(Async::Ready(()))
+        }
+    }
+}
+
+convert_uint!(u8_digits_strict, u8, strict_digits, 10);
+convert_uint!(u16_digits_strict, u16, strict_digits, 10);
+convert_uint!(u32_digits_strict, u32, strict_digits, 10);
+convert_uint!(u64_digits_strict, u64, strict_digits, 10);
+convert_uint!(u128_digits_strict, u128, strict_digits, 10);
+
+/// Advances over an optional sign followed by at least one digit.
+///
+/// If `plus` is `false`, only a minus sign is allowed.
+fn signed_digits<B: Buffer>(token: &mut Token<B>, plus: bool)
+                            -> Poll<(), TokenError> {
+    match try_ready!(token.first()) {
+        b'-' => token.advance(1),
+        b'+' if plus => token.advance(1),
+        _ => { }
+    }
+    digits(token)
+}
+
+macro_rules! convert_int {
+    ( $token_name:ident, $int:ty, $plus:expr ) => {
+        pub fn $token_name<B: Buffer>(buf: &mut B)
+                                       -> Poll<$int, TokenError> {
+            token::convert(buf, |token| signed_digits(token, $plus), |digits| {
                 let digits = digits?;
-                let mut res = 0 as $uint;
+                let (negative, digits) = match digits.split_first() {
+                    Some((&b'-', rest)) => (true, rest),
+                    Some((&b'+', rest)) => (false, rest),
+                    _ => (false, digits)
+                };
+                let mut res = 0 as $int;
                 for item in digits {
-                    let x = (*item as char).to_digit($radix).unwrap() as $uint;
-                    res = match res.checked_mul($radix) {
+                    let x = (*item as char).to_digit(10).unwrap() as $int;
+                    res = match res.checked_mul(10) {
                         Some(x) => x,
-                        None => return Err(TokenError)
+                        None => return Err(TokenError::Invalid)
                     };
-                    res = match res.checked_add(x) {
+                    // Accumulate negative values directly so that the
+                    // minimum value doesn’t overflow.
+                    let next = if negative { res.checked_sub(x) }
+                               else { res.checked_add(x) };
+                    res = match next {
                         Some(x) => x,
-                        None => return Err(TokenError)
+                        None => return Err(TokenError::Invalid)
                     };
                 }
                 Ok(res)
@@ -150,10 +484,146 @@ macro_rules! convert_uint {
     }
 }
 
-convert_uint!(u8_digits, u8, digits, 10);
-convert_uint!(u16_digits, u16, digits, 10);
-convert_uint!(u32_digits, u32, digits, 10);
-convert_uint!(u64_digits, u64, digits, 10);
+convert_int!(i8_digits, i8, true);
+convert_int!(i16_digits, i16, true);
+convert_int!(i32_digits, i32, true);
+convert_int!(i64_digits, i64, true);
+convert_int!(i128_digits, i128, true);
+convert_int!(i8_digits_no_plus, i8, false);
+convert_int!(i16_digits_no_plus, i16, false);
+convert_int!(i32_digits_no_plus, i32, false);
+convert_int!(i64_digits_no_plus, i64, false);
+convert_int!(i128_digits_no_plus, i128, false);
+
+rule!(sign = "-" / "+");
+
+rule!(
+    /// Advances over a decimal number with optional sign and fraction.
+    ///
+    /// This is `[sign] 1*DIGIT ["." 1*DIGIT]`.
+    pub decimal = [sign] 1* digit [(".") 1* digit]
+);
+
+rule!(
+    /// Advances over a floating point number.
+    ///
+    /// This is `[sign] 1*DIGIT ["." 1*DIGIT] [("e" / "E") [sign] 1*DIGIT]`.
+    pub float = decimal [("e") [sign] 1* digit]
+);
+
+/// Converts a token of ASCII digits and friends into a float.
+fn convert_float(digits: Result<&[u8], TokenError>)
+                 -> Result<f64, TokenError> {
+    // The token rules only allow ASCII, so this won’t fail.
+    let digits = ::std::str::from_utf8(digits?).unwrap();
+    digits.parse().map_err(|_| TokenError::Invalid)
+}
+
+pub fn f64_decimal<B: Buffer>(buf: &mut B) -> Poll<f64, TokenError> {
+    token::convert(buf, decimal, convert_float)
+}
+
+pub fn f64_float<B: Buffer>(buf: &mut B) -> Poll<f64, TokenError> {
+    token::convert(buf, float, convert_float)
+}
+
+/// Advances over a fixed-point number.
+///
+/// This is `1*<int_digits>DIGIT ["." *<frac_digits>DIGIT]`. Runs of digits
+/// longer than allowed are an error.
+///
+/// # Panic
+///
+/// The function panics if `int_digits` is zero.
+pub fn fixed_point<B: Buffer>(token: &mut Token<B>, int_digits: usize,
+                              frac_digits: usize) -> Poll<(), TokenError> {
+    assert!(int_digits > 0);
+    try_ready!(radix_digits(token, 10, int_digits));
+    if !try_ready!(token.advance_if(|ch| ch == b'.')) {
+        return Ok(Async::Ready(()))
+    }
+    for _ in 0..frac_digits {
+        if !try_ready!(token.advance_if(test_digit)) {
+            return Ok(Async::Ready(()))
+        }
+    }
+    if token.at_end() {
+        return Ok(Async::Ready(()))
+    }
+    match try_ready!(token.first()) {
+        ch if test_digit(ch) => Err(TokenError::Invalid),
+        _ => Ok(Async::Ready(()))
+    }
+}
+
+/// Converts a fixed-point number into an integer scaled by its fraction.
+///
+/// The result is the number multiplied by ten to the `frac_digits`.
+fn convert_fixed_point(digits: &[u8], frac_digits: usize)
+                       -> Result<u64, TokenError> {
+    let mut res = 0u64;
+    let mut frac = None;
+    for &ch in digits {
+        if ch == b'.' {
+            frac = Some(0);
+            continue
+        }
+        res = res.checked_mul(10).and_then(|res| {
+            res.checked_add(u64::from(ch - b'0'))
+        }).ok_or(TokenError::Invalid)?;
+        if let Some(ref mut frac) = frac {
+            *frac += 1
+        }
+    }
+    for _ in frac.unwrap_or(0)..frac_digits {
+        res = res.checked_mul(10).ok_or(TokenError::Invalid)?;
+    }
+    Ok(res)
+}
+
+/// Parses a fixed-point number into an integer.
+///
+/// The number is described by `fixed_point()`. It is returned multiplied
+/// by ten to the `frac_digits`, so `1.5` with three fractional digits
+/// becomes 1500. This avoids any rounding issues of floating point.
+pub fn u64_fixed_point<B: Buffer>(buf: &mut B, int_digits: usize,
+                                  frac_digits: usize)
+                                  -> Poll<u64, TokenError> {
+    token::convert(buf, |token| fixed_point(token, int_digits, frac_digits),
+                   |digits| convert_fixed_point(digits?, frac_digits))
+}
+
+/// Parses a quality value as used by HTTP into thousandths.
+///
+/// This is a fixed-point number between `0` and `1.000` with up to three
+/// fractional digits. It is returned as an integer between 0 and 1000.
+pub fn u16_qvalue<B: Buffer>(buf: &mut B) -> Poll<u16, TokenError> {
+    token::convert(buf, |token| fixed_point(token, 1, 3), |digits| {
+        match convert_fixed_point(digits?, 3)? {
+            value if value <= 1000 => Ok(value as u16),
+            _ => Err(TokenError::Invalid)
+        }
+    })
+}
+
+/// Parses a number of seconds with an optional fraction into a duration.
+///
+/// This is `1*DIGIT ["." *9DIGIT]`, i.e., the fraction can have at most
+/// nanosecond precision.
+pub fn duration_seconds<B: Buffer>(buf: &mut B)
+                                   -> Poll<Duration, TokenError> {
+    token::convert(buf, |token| fixed_point(token, 20, 9), |digits| {
+        let digits = digits?;
+        let (secs, nanos) = match digits.iter().position(|&ch| ch == b'.') {
+            Some(pos) => (&digits[..pos], &digits[pos + 1..]),
+            None => (digits, &b""[..])
+        };
+        let scale = 10u32.pow(9 - nanos.len() as u32);
+        let secs = uint_from_digits!(secs, u64, 10)?;
+        let nanos: u32 = uint_from_digits!(nanos, u32, 10)?;
+        Ok(Duration::new(secs, nanos * scale))
+    })
+}
 
 
 //------------ DQUOTE --------------------------------------------------------
@@ -162,11 +632,11 @@ pub fn test_dquote(ch: u8) -> bool {
     ch == b'"'
 }
 
-pub fn dquote(token: &mut Token) -> Poll<(), TokenError> {
+pub fn dquote<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_dquote)
 }
 
-pub fn skip_dquote(buf: &mut BytesMut) -> Poll<(), TokenError> {
+pub fn skip_dquote<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
     token::skip(buf, dquote)
 }
 
@@ -174,22 +644,94 @@ pub fn skip_dquote(buf: &mut BytesMut) -> Poll<(), TokenError> {
 //------------ HEXDIG --------------------------------------------------------
 
 pub fn test_hexdig(ch: u8) -> bool {
-    (ch >= 0x30 && ch <= 0x39) || (ch >= 0x41 && ch <= 0x46)
-        || (ch >= 0x61 && ch <= 0x66)
+    (0x30..=0x39).contains(&ch) || (0x41..=0x46).contains(&ch)
+        || (0x61..=0x66).contains(&ch)
 }
 
-pub fn hexdig(token: &mut Token) -> Poll<(), TokenError> {
+pub fn hexdig<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_hexdig)
 }
 
-pub fn hexdigs(token: &mut Token) -> Poll<(), TokenError> {
-    token::cats(token, test_hexdig)
+pub fn hexdigs<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::digit_cats(token, test_hexdig)
 }
 
 convert_uint!(u8_hexdigs, u8, hexdigs, 16);
 convert_uint!(u16_hexdigs, u16, hexdigs, 16);
 convert_uint!(u32_hexdigs, u32, hexdigs, 16);
 convert_uint!(u64_hexdigs, u64, hexdigs, 16);
+convert_uint!(u128_hexdigs, u128, hexdigs, 16);
+
+/// Parses a run of hex digits and decodes it into the octets it encodes.
+///
+/// Each pair of hex digits encodes one octet, most significant nibble
+/// first. A run of odd length is an error.
+pub fn parse_hex_bytes<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::convert(buf, hexdigs, |digits| {
+        let digits = digits?;
+        if digits.len() % 2 != 0 {
+            return Err(TokenError::Invalid)
+        }
+        Ok(digits.chunks(2).map(|pair| {
+            let hi = (pair[0] as char).to_digit(16).unwrap() as u8;
+            let lo = (pair[1] as char).to_digit(16).unwrap() as u8;
+            hi << 4 | lo
+        }).collect::<Vec<u8>>().into())
+    })
+}
+
+
+//------------ Digits in Any Radix -------------------------------------------
+
+/// Advances over between one and `max_digits` digits in base `radix`.
+///
+/// Digits beyond nine are the letters `a` to `z` in either case. If the
+/// run of digits is longer than `max_digits`, returns an error.
+///
+/// # Panic
+///
+/// The function panics if `radix` is not between 2 and 36.
+pub fn radix_digits<B: Buffer>(token: &mut Token<B>, radix: u32,
+                               max_digits: usize) -> Poll<(), TokenError> {
+    assert!((2..=36).contains(&radix));
+    let test = |ch: u8| (ch as char).is_digit(radix);
+    try_ready!(token::cat(token, test));
+    for _ in 1..max_digits {
+        if !try_ready!(token.advance_if(test)) {
+            return Ok(Async::Ready(()))
+        }
+    }
+    match token.peek(0) {
+        None if !token.is_final() => Ok(Async::NotReady),
+        Some(ch) if test(ch) => Err(TokenError::Invalid),
+        _ => Ok(Async::Ready(()))
+    }
+}
+
+/// Parses an unsigned integer in base `radix` of at most `max_digits`.
+///
+/// # Panic
+///
+/// The function panics if `radix` is not between 2 and 36.
+pub fn uint_radix<B: Buffer>(buf: &mut B, radix: u32, max_digits: usize)
+                             -> Poll<u64, TokenError> {
+    token::convert(buf, |token| radix_digits(token, radix, max_digits),
+                   |digits| {
+        let mut res = 0u64;
+        for item in digits? {
+            let x = u64::from((*item as char).to_digit(radix).unwrap());
+            res = match res.checked_mul(u64::from(radix)) {
+                Some(res) => res,
+                None => return Err(TokenError::Invalid)
+            };
+            res = match res.checked_add(x) {
+                Some(res) => res,
+                None => return Err(TokenError::Invalid)
+            };
+        }
+        Ok(res)
+    })
+}
 
 
 //------------ HTAB ----------------------------------------------------------
@@ -198,7 +740,7 @@ pub fn test_htab(ch: u8) -> bool {
     ch == 0x09
 }
 
-pub fn htab(token: &mut Token) -> Poll<(), TokenError> {
+pub fn htab<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_htab)
 }
 
@@ -209,26 +751,103 @@ pub fn test_lf(ch: u8) -> bool {
     ch == 0x0A
 }
 
-pub fn lf(token: &mut Token) -> Poll<(), TokenError> {
+pub fn lf<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_lf)
 }
 
 
 //------------ LWSP ----------------------------------------------------------
 
-pub fn lwsp(token: &mut Token) -> Poll<(), TokenError> {
-        loop {
-            if try_result!(wsp(token)).is_err()
-                    || try_result!(crlf(token)).is_err() {
-                return Ok(Async::Ready(()))
+/// Advances over linear white space.
+///
+/// This is `*(WSP / CRLF WSP)`. A CRLF is only advanced over if it is
+/// followed by white space, so the function needs to see up to two octets
+/// past a CRLF before it can decide.
+pub fn lwsp<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    lwsp_with(token, false)
+}
+
+/// Advances over linear white space rejecting bare CR or LF.
+///
+/// This behaves like `lwsp()` but returns an error if it encounters a CR
+/// not followed by LF or an LF not preceded by CR rather than leaving
+/// them for whatever comes next. Such bare line endings were allowed by
+/// some obsolete syntax.
+pub fn lwsp_strict<B: Buffer>(token: &mut Token<B>)
+                              -> Poll<(), TokenError> {
+    lwsp_with(token, true)
+}
+
+fn lwsp_with<B: Buffer>(token: &mut Token<B>, strict: bool)
+                        -> Poll<(), TokenError> {
+    loop {
+        if token.at_end() {
+            return Ok(Async::Ready(()))
+        }
+        match try_ready!(token.first()) {
+            ch if test_wsp(ch) => token.advance(1),
+            b'\r' => {
+                match (token.peek(1), token.peek(2)) {
+                    (None, _) if token.is_final() && strict => {
+                        return Err(TokenError::Invalid)
+                    }
+                    (Some(b'\n'), None) | (None, _) if token.is_final() => {
+                        return Ok(Async::Ready(()))
+                    }
+                    (None, _) | (Some(b'\n'), None) => {
+                        return Ok(Async::NotReady)
+                    }
+                    (Some(b'\n'), Some(ch)) if test_wsp(ch) => {
+                        token.advance(3)
+                    }
+                    (Some(b'\n'), Some(_)) => return Ok(Async::Ready(())),
+                    (Some(_), _) if strict => {
+                        return Err(TokenError::Invalid)
+                    }
+                    (Some(_), _) => return Ok(Async::Ready(()))
+                }
             }
+            b'\n' if strict => return Err(TokenError::Invalid),
+            _ => return Ok(Async::Ready(()))
         }
+    }
 }
 
-pub fn skip_lwsp(buf: &mut BytesMut) -> Poll<(), TokenError> {
+pub fn skip_lwsp<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
     token::skip(buf, lwsp)
 }
 
+pub fn skip_lwsp_strict<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
+    token::skip(buf, lwsp_strict)
+}
+
+
+//------------ OCTET ---------------------------------------------------------
+
+pub fn test_octet(_ch: u8) -> bool {
+    true
+}
+
+pub fn octet_any<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::cat(token, test_octet)
+}
+
+/// Advances over exactly `n` octets of any value.
+pub fn octets_exact<B: Buffer>(token: &mut Token<B>, n: usize)
+                               -> Poll<(), TokenError> {
+    if token.remaining() < n {
+        token.need(n);
+        return Ok(Async::NotReady)
+    }
+    token.advance(n);
+    Ok(Async::Ready(()))
+}
+
+pub fn parse_octets_exact<B: Buffer>(buf: &mut B, n: usize)
+                                     -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| octets_exact(token, n))
+}
+
 
 //------------ SP ------------------------------------------------------------
 
@@ -236,11 +855,11 @@ pub fn test_sp(ch: u8) -> bool {
     ch == 0x20
 }
 
-pub fn sp(token: &mut Token) -> Poll<(), TokenError> {
+pub fn sp<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_sp)
 }
 
-pub fn sps(token: &mut Token) -> Poll<(), TokenError> {
+pub fn sps<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_sp)
 }
 
@@ -248,14 +867,14 @@ pub fn sps(token: &mut Token) -> Poll<(), TokenError> {
 //------------ VCHAR ---------------------------------------------------------
 
 pub fn test_vchar(ch: u8) -> bool {
-    ch >= 0x21 && ch <= 0x7E
+    (0x21..=0x7E).contains(&ch)
 }
 
-pub fn vchar(token: &mut Token) -> Poll<(), TokenError> {
+pub fn vchar<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_vchar)
 }
 
-pub fn vchars(token: &mut Token) -> Poll<(), TokenError> {
+pub fn vchars<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_vchar)
 }
 
@@ -266,23 +885,23 @@ pub fn test_wsp(ch: u8) -> bool {
     ch == 0x20 || ch == 0x09
 }
 
-pub fn wsp(token: &mut Token) -> Poll<(), TokenError> {
+pub fn wsp<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cat(token, test_wsp)
 }
 
-pub fn wsps(token: &mut Token) -> Poll<(), TokenError> {
+pub fn wsps<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
     token::cats(token, test_wsp)
 }
 
-pub fn opt_wsps(token: &mut Token) -> Poll<bool, TokenError> {
+pub fn opt_wsps<B: Buffer>(token: &mut Token<B>) -> Poll<bool, TokenError> {
     token::opt_cats(token, test_wsp)
 }
 
-pub fn skip_wsps(buf: &mut BytesMut) -> Poll<(), TokenError> {
+pub fn skip_wsps<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
     token::skip(buf, wsps)
 }
 
-pub fn skip_opt_wsps(buf: &mut BytesMut) -> Poll<bool, TokenError> {
+pub fn skip_opt_wsps<B: Buffer>(buf: &mut B) -> Poll<bool, TokenError> {
     token::skip_opt(buf, wsps)
 }
 
@@ -295,14 +914,14 @@ mod test {
     use bytes::BytesMut;
     use super::*;
 
-    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(Vec::from(slice)) }
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
 
     #[test]
     fn test_u8_digits() {
         for i in 0u8..255 {
             println!("--- {}", i);
             assert_eq!(u8_digits(&mut BytesMut::from(format!("{} ", i)
-                                                      .into_bytes())),
+                                                      .as_str())),
                        Ok(Async::Ready(i)));
         }
         assert!(u8_digits(&mut buf(b"256 ")).is_err());
@@ -315,13 +934,265 @@ mod test {
     fn test_u16_hexdigs() {
         for i in 0u16..0xFFFF {
             assert_eq!(u16_hexdigs(&mut BytesMut::from(format!("{:x} ", i)
-                                                        .into_bytes())),
+                                                        .as_str())),
                        Ok(Async::Ready(i)));
             assert_eq!(u16_hexdigs(&mut BytesMut::from(format!("{:X} ", i)
-                                                        .into_bytes())),
+                                                        .as_str())),
                        Ok(Async::Ready(i)));
         }
         assert!(u16_hexdigs(&mut buf(b"70256 ")).is_err());
         assert!(u16_hexdigs(&mut buf(b" ")).is_err());
     }
+
+    #[test]
+    fn test_i8_digits() {
+        for i in -128i8..127 {
+            assert_eq!(i8_digits(&mut BytesMut::from(format!("{} ", i)
+                                                      .as_str())),
+                       Ok(Async::Ready(i)));
+        }
+        assert_eq!(i8_digits(&mut buf(b"+12 ")), Ok(Async::Ready(12)));
+        assert_eq!(i8_digits(&mut buf(b"-")), Ok(Async::NotReady));
+        assert!(i8_digits(&mut buf(b"-129 ")).is_err());
+        assert!(i8_digits(&mut buf(b"128 ")).is_err());
+        assert!(i8_digits(&mut buf(b"- ")).is_err());
+        assert!(i8_digits_no_plus(&mut buf(b"+12 ")).is_err());
+        assert_eq!(i8_digits_no_plus(&mut buf(b"-12 ")),
+                   Ok(Async::Ready(-12)));
+    }
+
+    #[test]
+    fn test_digits_exact() {
+        let mut data = buf(b"20161015");
+        assert_eq!(u16_digits_exact(&mut data, 4), Ok(Async::Ready(2016)));
+        assert_eq!(u8_digits_exact(&mut data, 2), Ok(Async::Ready(10)));
+        assert_eq!(u8_digits_exact(&mut data, 2), Ok(Async::Ready(15)));
+        assert_eq!(u8_digits_exact(&mut buf(b"1"), 2), Ok(Async::NotReady));
+        assert!(u8_digits_exact(&mut buf(b"1:"), 2).is_err());
+        assert!(u8_digits_exact(&mut buf(b"256"), 3).is_err());
+    }
+
+    #[test]
+    fn test_digits_strict() {
+        assert_eq!(u8_digits_strict(&mut buf(b"0 ")), Ok(Async::Ready(0)));
+        assert_eq!(u8_digits_strict(&mut buf(b"70 ")), Ok(Async::Ready(70)));
+        assert_eq!(u8_digits_strict(&mut buf(b"0")), Ok(Async::NotReady));
+        assert!(u8_digits_strict(&mut buf(b"007 ")).is_err());
+        assert_eq!(u8_digits(&mut buf(b"007 ")), Ok(Async::Ready(7)));
+    }
+
+    #[test]
+    fn test_parse_hex_bytes() {
+        assert_eq!(parse_hex_bytes(&mut buf(b"00fF7a ")),
+                   Ok(Async::Ready(Bytes::from(&b"\x00\xff\x7a"[..]))));
+        assert_eq!(parse_hex_bytes(&mut buf(b"00ff")), Ok(Async::NotReady));
+        assert!(parse_hex_bytes(&mut buf(b"00f ")).is_err());
+        assert!(parse_hex_bytes(&mut buf(b" ")).is_err());
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(u8_bits(&mut buf(b"101 ")), Ok(Async::Ready(5)));
+        assert_eq!(u8_bits(&mut buf(b"11111111 ")), Ok(Async::Ready(255)));
+        assert!(u8_bits(&mut buf(b"100000000 ")).is_err());
+        assert_eq!(u16_bits(&mut buf(b"100000000 ")), Ok(Async::Ready(256)));
+        assert!(u8_bits(&mut buf(b"2 ")).is_err());
+    }
+
+    #[test]
+    fn test_uint_radix() {
+        assert_eq!(uint_radix(&mut buf(b"zz "), 36, 4),
+                   Ok(Async::Ready(1295)));
+        assert_eq!(uint_radix(&mut buf(b"777 "), 8, 3), Ok(Async::Ready(511)));
+        assert_eq!(uint_radix(&mut buf(b"777"), 8, 3), Ok(Async::NotReady));
+        assert!(uint_radix(&mut buf(b"7777 "), 8, 3).is_err());
+        assert!(uint_radix(&mut buf(b"8 "), 8, 3).is_err());
+        assert!(uint_radix(&mut buf(b"10000000000000000 "), 16, 20).is_err());
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        assert_eq!(u64_fixed_point(&mut buf(b"12.5 "), 3, 2),
+                   Ok(Async::Ready(1250)));
+        assert_eq!(u64_fixed_point(&mut buf(b"12. "), 3, 2),
+                   Ok(Async::Ready(1200)));
+        assert_eq!(u64_fixed_point(&mut buf(b"12 "), 3, 0),
+                   Ok(Async::Ready(12)));
+        assert_eq!(u64_fixed_point(&mut buf(b"12.5"), 3, 2),
+                   Ok(Async::NotReady));
+        assert!(u64_fixed_point(&mut buf(b"1234 "), 3, 2).is_err());
+        assert!(u64_fixed_point(&mut buf(b"1.234 "), 3, 2).is_err());
+
+        assert_eq!(u16_qvalue(&mut buf(b"0.8;")), Ok(Async::Ready(800)));
+        assert_eq!(u16_qvalue(&mut buf(b"1.000,")), Ok(Async::Ready(1000)));
+        assert_eq!(u16_qvalue(&mut buf(b"0.125 ")), Ok(Async::Ready(125)));
+        assert_eq!(u16_qvalue(&mut buf(b"0 ")), Ok(Async::Ready(0)));
+        assert!(u16_qvalue(&mut buf(b"1.001 ")).is_err());
+        assert!(u16_qvalue(&mut buf(b"2 ")).is_err());
+    }
+
+    #[test]
+    fn test_duration_seconds() {
+        assert_eq!(duration_seconds(&mut buf(b"120 ")),
+                   Ok(Async::Ready(Duration::from_secs(120))));
+        assert_eq!(duration_seconds(&mut buf(b"1.5 ")),
+                   Ok(Async::Ready(Duration::from_millis(1500))));
+        assert_eq!(duration_seconds(&mut buf(b"0.000000001 ")),
+                   Ok(Async::Ready(Duration::new(0, 1))));
+        assert_eq!(duration_seconds(&mut buf(b"1.5")), Ok(Async::NotReady));
+        assert!(duration_seconds(&mut buf(b"0.0000000001 ")).is_err());
+        assert!(duration_seconds(&mut buf(b"99999999999999999999 ")).is_err());
+    }
+
+    #[test]
+    fn test_octets_exact() {
+        let mut data = buf(b"\r\n\x00\xffx");
+        assert_eq!(parse_octets_exact(&mut data, 4),
+                   Ok(Async::Ready(Bytes::from(&b"\r\n\x00\xff"[..]))));
+        assert_eq!(&data[..], b"x");
+        assert_eq!(parse_octets_exact(&mut data, 2), Ok(Async::NotReady));
+        assert_eq!(parse_octets_exact(&mut data, 0),
+                   Ok(Async::Ready(Bytes::new())));
+    }
+
+    #[test]
+    fn test_lwsp() {
+        let mut data = buf(b" \t\r\n x");
+        assert_eq!(skip_lwsp(&mut data), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"x");
+        let mut data = buf(b" \r\nx");
+        assert_eq!(skip_lwsp(&mut data), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"\r\nx");
+        assert_eq!(skip_lwsp(&mut buf(b" \r\n")), Ok(Async::NotReady));
+        assert_eq!(skip_lwsp(&mut buf(b" \r")), Ok(Async::NotReady));
+
+        let mut data = buf(b" \nx");
+        assert_eq!(skip_lwsp(&mut data), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"\nx");
+        assert!(skip_lwsp_strict(&mut buf(b" \nx")).is_err());
+        assert!(skip_lwsp_strict(&mut buf(b" \rx")).is_err());
+        assert_eq!(skip_lwsp_strict(&mut buf(b" \r\n\tx")),
+                   Ok(Async::Ready(())));
+    }
+
+    #[test]
+    fn test_line_with() {
+        let mut data = buf(b"a\nb\r\nc\rd\r");
+        assert_eq!(parse_line_with(&mut data, LineEnding::Crlf),
+                   Ok(Async::Ready(Bytes::from(&b"a\nb\r\n"[..]))));
+        assert_eq!(parse_line_with(&mut data, LineEnding::Lf),
+                   Ok(Async::NotReady));
+        assert_eq!(parse_line_with(&mut data, LineEnding::Any),
+                   Ok(Async::Ready(Bytes::from(&b"c\r"[..]))));
+        assert_eq!(parse_line_with(&mut data, LineEnding::Any),
+                   Ok(Async::NotReady));
+
+        let mut data = buf(b"a\nb");
+        assert_eq!(parse_line_with(&mut data, LineEnding::Lf),
+                   Ok(Async::Ready(Bytes::from(&b"a\n"[..]))));
+
+        assert!(skip_crlf_with(&mut buf(b"\nx"), LineEnding::Crlf).is_err());
+        assert_eq!(skip_crlf_with(&mut buf(b"\nx"), LineEnding::Lf),
+                   Ok(Async::Ready(())));
+        assert!(skip_crlf_with(&mut buf(b"\rx"), LineEnding::Lf).is_err());
+        assert_eq!(skip_crlf_with(&mut buf(b"\rx"), LineEnding::Any),
+                   Ok(Async::Ready(())));
+        assert_eq!(skip_crlf_with(&mut buf(b"\r"), LineEnding::Any),
+                   Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn test_line_content() {
+        let mut data = buf(b"foo\r\n\r\nbar\nbaz\r");
+        assert_eq!(parse_line_content(&mut data),
+                   Ok(Async::Ready(Bytes::from(&b"foo"[..]))));
+        assert_eq!(parse_line_content(&mut data),
+                   Ok(Async::Ready(Bytes::new())));
+        assert_eq!(parse_line_content(&mut data), Ok(Async::NotReady));
+        assert_eq!(parse_line_content_with(&mut data, LineEnding::Lf),
+                   Ok(Async::Ready(Bytes::from(&b"bar"[..]))));
+        assert_eq!(parse_line_content_with(&mut data, LineEnding::Any),
+                   Ok(Async::NotReady));
+        assert_eq!(&data[..], b"baz\r");
+    }
+
+    #[test]
+    fn test_line_limited() {
+        let mut data = buf(b"abc\r\nabcd\r\n");
+        assert_eq!(parse_line_limited(&mut data, 5),
+                   Ok(Async::Ready(Bytes::from(&b"abc\r\n"[..]))));
+        assert_eq!(parse_line_limited(&mut data, 5),
+                   Err(TokenError::LineTooLong));
+        assert_eq!(parse_line_limited(&mut buf(b"abcde"), 5),
+                   Ok(Async::NotReady));
+        assert_eq!(parse_line_limited(&mut buf(b"abcdef"), 5),
+                   Err(TokenError::LineTooLong));
+        assert_eq!(parse_line_limited_with(&mut buf(b"abcd\n"),
+                                           LineEnding::Lf, 5),
+                   Ok(Async::Ready(Bytes::from(&b"abcd\n"[..]))));
+    }
+
+    #[test]
+    fn test_folded_line() {
+        let data = b"Subject: foo\r\n  bar\r\n\tbaz\r\nTo: x\r\n";
+        let mut folded = buf(data);
+        assert_eq!(parse_folded_line(&mut folded, LineEnding::Crlf),
+                   Ok(Async::Ready(Bytes::from(
+                       &b"Subject: foo\r\n  bar\r\n\tbaz\r\n"[..]
+                   ))));
+        assert_eq!(&folded[..], b"To: x\r\n");
+        assert_eq!(parse_folded_line(&mut folded, LineEnding::Crlf),
+                   Ok(Async::NotReady));
+
+        let mut unfolded = buf(data);
+        assert_eq!(parse_unfolded_line(&mut unfolded, LineEnding::Crlf),
+                   Ok(Async::Ready(Bytes::from(
+                       &b"Subject: foo bar baz"[..]
+                   ))));
+        assert_eq!(parse_unfolded_line(&mut buf(b"a\n b\nc"),
+                                       LineEnding::Lf),
+                   Ok(Async::Ready(Bytes::from(&b"a b"[..]))));
+    }
+
+    #[test]
+    fn test_f64_float() {
+        assert_eq!(f64_float(&mut buf(b"12 ")), Ok(Async::Ready(12.)));
+        assert_eq!(f64_float(&mut buf(b"-1.5e3 ")), Ok(Async::Ready(-1500.)));
+        assert_eq!(f64_float(&mut buf(b"+0.25E-2 ")),
+                   Ok(Async::Ready(0.0025)));
+        assert_eq!(f64_float(&mut buf(b"1.5")), Ok(Async::NotReady));
+        assert_eq!(f64_float(&mut buf(b"1.5e")), Ok(Async::NotReady));
+        let mut data = buf(b"1.x");
+        assert_eq!(f64_float(&mut data), Ok(Async::Ready(1.)));
+        assert_eq!(&data[..], b".x");
+        assert!(f64_float(&mut buf(b".5 ")).is_err());
+        assert!(f64_float(&mut buf(b"- ")).is_err());
+
+        let mut data = buf(b"1.5e3 ");
+        assert_eq!(f64_decimal(&mut data), Ok(Async::Ready(1.5)));
+        assert_eq!(&data[..], b"e3 ");
+    }
+
+    #[test]
+    fn test_128() {
+        let max = format!("{} ", u128::MAX);
+        assert_eq!(u128_digits(&mut BytesMut::from(max.as_bytes())),
+                   Ok(Async::Ready(u128::MAX)));
+        assert!(u128_digits(
+            &mut buf(b"340282366920938463463374607431768211456 ")
+        ).is_err());
+        assert_eq!(
+            u128_hexdigs(&mut buf(b"fFfFfFfFfFfFfFfFfFfFfFfFfFfFfFfF ")),
+            Ok(Async::Ready(u128::MAX))
+        );
+        assert!(u128_hexdigs(
+            &mut buf(b"100000000000000000000000000000000 ")
+        ).is_err());
+        let min = format!("{} ", i128::MIN);
+        assert_eq!(i128_digits(&mut BytesMut::from(min.as_bytes())),
+                   Ok(Async::Ready(i128::MIN)));
+        assert!(i128_digits(
+            &mut buf(b"-170141183460469231731687303715884105729 ")
+        ).is_err());
+    }
 }
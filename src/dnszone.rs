@@ -0,0 +1,767 @@
+//! DNS zone files.
+//!
+//! This module parses the master file format of section 5 of RFC 1035,
+//! also known as presentation format. A zone file is a sequence of
+//! entries, each normally occupying one line. Parentheses allow an entry
+//! to continue over several lines, semicolons start comments that last
+//! until the end of the line.
+//!
+//! Entries are either control entries, i.e., the `$ORIGIN`, `$TTL`, and
+//! `$INCLUDE` directives, or resource records. The record data of the
+//! common record types A, AAAA, CNAME, MX, and TXT is parsed into typed
+//! values. For all other types, the items of the record data are returned
+//! as they appear.
+//!
+//! Domain names are returned in presentation format as they appear in
+//! the file. In particular, relative names are not completed and escape
+//! sequences are left in place. Character strings, on the other hand,
+//! are returned with their escape sequences decoded.
+//!
+//! Both LF and CRLF are accepted as line endings. Every entry, including
+//! the last one, must end in a line ending.
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use bytes::Bytes;
+use futures::{Async, Poll};
+use ::compose::write_octets;
+use ::core::{test_digit, u16_digits};
+use ::ipaddr::{parse_ipv4_addr, parse_ipv6_addr};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::{Token, TokenError};
+#[cfg(feature = "arbitrary")] use arbitrary::Arbitrary;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+
+//------------ Entry ---------------------------------------------------------
+
+/// An entry of a zone file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Entry {
+    /// An `$ORIGIN` directive with the new origin.
+    Origin(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+
+    /// A `$TTL` directive with the new default TTL.
+    Ttl(u32),
+
+    /// An `$INCLUDE` directive.
+    Include {
+        /// The name of the file to include.
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        file: Bytes,
+
+        /// The origin for the included file if given.
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_opt_bytes))]
+        origin: Option<Bytes>,
+    },
+
+    /// A resource record.
+    Record(Record),
+}
+
+/// A resource record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record {
+    /// The owner of the record.
+    pub owner: Owner,
+
+    /// The TTL of the record if given.
+    pub ttl: Option<u32>,
+
+    /// The class of the record if given.
+    pub class: Option<Class>,
+
+    /// The record data.
+    pub data: RecordData,
+}
+
+/// The owner of a resource record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Owner {
+    /// The given domain name.
+    Name(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+
+    /// The current origin, written as `@`.
+    Origin,
+
+    /// The owner of the previous record, indicated by leading white space.
+    Previous,
+}
+
+/// The class of a resource record.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Class {
+    In,
+    Cs,
+    Ch,
+    Hs,
+}
+
+/// The data of a resource record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+    Mx {
+        preference: u16,
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        exchange: Bytes,
+    },
+
+    /// The character strings of a TXT record.
+    Txt(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes_vec))]
+        Vec<Bytes>
+    ),
+
+    /// The data of some other record type.
+    ///
+    /// The record type is given in upper case. The items of the data are
+    /// given as they appear, quoted strings including their quotes.
+    Other {
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        rtype: Bytes,
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes_vec))]
+        data: Vec<Bytes>,
+    },
+}
+
+
+//------------ Formatting Entries --------------------------------------------
+
+impl fmt::Display for Entry {
+    /// Formats the entry without the line ending after it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Entry::Origin(ref name) => {
+                f.write_str("$ORIGIN ")?;
+                write_octets(f, name)
+            }
+            Entry::Ttl(ttl) => write!(f, "$TTL {}", ttl),
+            Entry::Include { ref file, ref origin } => {
+                f.write_str("$INCLUDE ")?;
+                write_char_string(f, file)?;
+                if let Some(ref origin) = *origin {
+                    f.write_str(" ")?;
+                    write_octets(f, origin)?;
+                }
+                Ok(())
+            }
+            Entry::Record(ref record) => record.fmt(f)
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    /// Formats the record without the line ending after it.
+    ///
+    /// A record with the previous owner starts with a space.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.owner {
+            Owner::Name(ref name) => write_octets(f, name)?,
+            Owner::Origin => f.write_str("@")?,
+            Owner::Previous => { }
+        }
+        if let Some(ttl) = self.ttl {
+            write!(f, " {}", ttl)?;
+        }
+        if let Some(class) = self.class {
+            write!(f, " {}", class)?;
+        }
+        match self.data {
+            RecordData::A(addr) => write!(f, " A {}", addr),
+            RecordData::Aaaa(addr) => write!(f, " AAAA {}", addr),
+            RecordData::Cname(ref name) => {
+                f.write_str(" CNAME ")?;
+                write_octets(f, name)
+            }
+            RecordData::Mx { preference, ref exchange } => {
+                write!(f, " MX {} ", preference)?;
+                write_octets(f, exchange)
+            }
+            RecordData::Txt(ref strings) => {
+                f.write_str(" TXT")?;
+                for string in strings {
+                    f.write_str(" ")?;
+                    write_char_string(f, string)?;
+                }
+                Ok(())
+            }
+            RecordData::Other { ref rtype, ref data } => {
+                f.write_str(" ")?;
+                write_octets(f, rtype)?;
+                for item in data {
+                    f.write_str(" ")?;
+                    write_octets(f, item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Class::In => "IN",
+            Class::Cs => "CS",
+            Class::Ch => "CH",
+            Class::Hs => "HS",
+        })
+    }
+}
+
+/// Writes a character string as a quoted string.
+///
+/// Double quotes and backslashes are escaped with a backslash, all other
+/// octets outside of printable ASCII with a decimal escape sequence.
+fn write_char_string(f: &mut fmt::Formatter, string: &[u8]) -> fmt::Result {
+    f.write_str("\"")?;
+    for &ch in string {
+        match ch {
+            b'"' | b'\\' => write!(f, "\\{}", char::from(ch))?,
+            0x20..=0x7E => write!(f, "{}", char::from(ch))?,
+            _ => write!(f, "\\{:03}", ch)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+
+//------------ Parsing Entries -----------------------------------------------
+
+/// Parses an entry including the line ending after it.
+///
+/// Returns `None` if the line was empty or contained only a comment.
+//
+//  entry = [ directive / record ] [ ";" comment ] line-ending
+pub fn parse_entry<B: Buffer>(buf: &mut B)
+                              -> Poll<Option<Entry>, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let indented = try_ready!(token::skip_opt(buf, |token| {
+            token::cats(token, test_blank)
+        }));
+        match buf.get(0) {
+            None => return Ok(Async::NotReady),
+            Some(b';') | Some(b'\r') | Some(b'\n') => {
+                try_ready!(skip_entry_end(buf, &mut false));
+                return Ok(Async::Ready(None))
+            }
+            Some(b'$') if !indented => {
+                return directive(buf).map(|res| res.map(Some))
+            }
+            _ => { }
+        }
+        let mut parens = false;
+        let owner = if indented {
+            Owner::Previous
+        }
+        else {
+            let name = try_ready!(parse_word(buf));
+            try_ready!(skip_separator(buf, &mut parens));
+            if &name[..] == b"@" { Owner::Origin } else { Owner::Name(name) }
+        };
+        let record = try_ready!(record(buf, owner, &mut parens));
+        try_ready!(skip_entry_end(buf, &mut parens));
+        Ok(Async::Ready(Some(Entry::Record(record))))
+    })
+}
+
+//  directive = "$ORIGIN" blank domain-name
+//            / "$TTL" blank ttl
+//            / "$INCLUDE" blank file-name [ blank domain-name ]
+fn directive<B: Buffer>(buf: &mut B) -> Poll<Entry, TokenError> {
+    let mut parens = false;
+    let name = try_ready!(parse_word(buf));
+    try_ready!(skip_separator(buf, &mut parens));
+    let res = match &name.to_ascii_uppercase()[..] {
+        b"$ORIGIN" => Entry::Origin(try_ready!(parse_word(buf))),
+        b"$TTL" => Entry::Ttl(try_ready!(parse_ttl(buf))),
+        b"$INCLUDE" => {
+            let file = try_ready!(parse_char_string(buf));
+            try_ready!(skip_blanks(buf, &mut parens));
+            let origin = if at_entry_end(buf) {
+                None
+            }
+            else {
+                Some(try_ready!(parse_word(buf)))
+            };
+            Entry::Include { file, origin }
+        }
+        _ => return Err(TokenError::Invalid)
+    };
+    try_ready!(skip_entry_end(buf, &mut parens));
+    Ok(Async::Ready(res))
+}
+
+//  record = owner blank [ ttl blank ] [ class blank ] type blank rdata
+//         / owner blank [ class blank ] [ ttl blank ] type blank rdata
+fn record<B: Buffer>(buf: &mut B, owner: Owner, parens: &mut bool)
+                     -> Poll<Record, TokenError> {
+    let mut ttl = None;
+    let mut class = None;
+    let rtype = loop {
+        let word = try_ready!(parse_word(buf));
+        try_ready!(skip_separator(buf, parens));
+        if ttl.is_none() && word.iter().all(|&ch| test_digit(ch)) {
+            ttl = Some(ttl_from_digits(&word)?);
+            continue
+        }
+        if class.is_none() {
+            if let Some(value) = class_from_word(&word) {
+                class = Some(value);
+                continue
+            }
+        }
+        break word.to_ascii_uppercase()
+    };
+    let data = match &rtype[..] {
+        b"A" => RecordData::A(try_ready!(parse_ipv4_addr(buf))),
+        b"AAAA" => RecordData::Aaaa(try_ready!(parse_ipv6_addr(buf))),
+        b"CNAME" => RecordData::Cname(try_ready!(parse_word(buf))),
+        b"MX" => {
+            let preference = try_ready!(u16_digits(buf));
+            try_ready!(skip_separator(buf, parens));
+            let exchange = try_ready!(parse_word(buf));
+            RecordData::Mx { preference, exchange }
+        }
+        b"TXT" => {
+            let mut strings = Vec::new();
+            loop {
+                strings.push(try_ready!(parse_char_string(buf)));
+                try_ready!(skip_blanks(buf, parens));
+                if at_entry_end(buf) {
+                    break
+                }
+            }
+            RecordData::Txt(strings)
+        }
+        _ => {
+            let mut data = Vec::new();
+            loop {
+                data.push(try_ready!(alt!(buf, parse_word, parse_quoted)));
+                try_ready!(skip_blanks(buf, parens));
+                if at_entry_end(buf) {
+                    break
+                }
+            }
+            RecordData::Other { rtype: rtype.into(), data }
+        }
+    };
+    Ok(Async::Ready(Record { owner, ttl, class, data }))
+}
+
+/// Returns the class for a word if it is one.
+fn class_from_word(word: &[u8]) -> Option<Class> {
+    match &word.to_ascii_uppercase()[..] {
+        b"IN" => Some(Class::In),
+        b"CS" => Some(Class::Cs),
+        b"CH" => Some(Class::Ch),
+        b"HS" => Some(Class::Hs),
+        _ => None
+    }
+}
+
+/// Parses a TTL.
+///
+/// The TTL is a decimal number of seconds that fits into 32 bits.
+fn parse_ttl<B: Buffer>(buf: &mut B) -> Poll<u32, TokenError> {
+    token::convert(buf, word, |res| ttl_from_digits(res?))
+}
+
+fn ttl_from_digits(digits: &[u8]) -> Result<u32, TokenError> {
+    digits.iter().try_fold(0u32, |res, &ch| {
+        if !test_digit(ch) {
+            return Err(TokenError::Invalid)
+        }
+        res.checked_mul(10).and_then(|res| {
+            res.checked_add(u32::from(ch - b'0'))
+        }).ok_or(TokenError::Invalid)
+    })
+}
+
+
+//------------ Separators ----------------------------------------------------
+
+/// Returns whether `ch` is blank, i.e., a space or a tab.
+pub fn test_blank(ch: u8) -> bool {
+    ch == b' ' || ch == b'\t'
+}
+
+/// Returns whether `ch` ends a word.
+pub fn test_delimiter(ch: u8) -> bool {
+    test_blank(ch) || b"\r\n;()\"".contains(&ch)
+}
+
+/// Skips over blanks between the items of an entry.
+///
+/// Within parentheses, line endings and comments are blank, too. The
+/// parentheses themselves are skipped and `parens` is updated to reflect
+/// whether we are within them. Returns whether anything was skipped.
+fn skip_blanks<B: Buffer>(buf: &mut B, parens: &mut bool)
+                          -> Poll<bool, TokenError> {
+    let mut skipped = false;
+    try_ready!(token::skip(buf, |token| {
+        loop {
+            if token.at_end() {
+                return Ok(Async::Ready(()))
+            }
+            match try_ready!(token.first()) {
+                b' ' | b'\t' => token.advance(1),
+                b'(' if !*parens => {
+                    *parens = true;
+                    token.advance(1)
+                }
+                b')' if *parens => {
+                    *parens = false;
+                    token.advance(1)
+                }
+                b'\r' | b'\n' if *parens => token.advance(1),
+                b';' if *parens => {
+                    try_ready!(comment(token));
+                }
+                b'(' | b')' => return Err(TokenError::Invalid),
+                _ => return Ok(Async::Ready(()))
+            }
+            skipped = true;
+        }
+    }));
+    Ok(Async::Ready(skipped))
+}
+
+/// Skips over the blanks separating two items of an entry.
+///
+/// Returns an error if there aren’t any.
+fn skip_separator<B: Buffer>(buf: &mut B, parens: &mut bool)
+                             -> Poll<(), TokenError> {
+    if try_ready!(skip_blanks(buf, parens)) {
+        Ok(Async::Ready(()))
+    }
+    else {
+        Err(TokenError::Invalid)
+    }
+}
+
+/// Returns whether the buffer starts with the end of an entry.
+fn at_entry_end<B: Buffer>(buf: &B) -> bool {
+    matches!(buf.get(0), Some(b';') | Some(b'\r') | Some(b'\n'))
+}
+
+/// Skips over the end of an entry including the line ending.
+///
+/// Returns an error if there is something else or the entry is still
+/// within parentheses.
+fn skip_entry_end<B: Buffer>(buf: &mut B, parens: &mut bool)
+                             -> Poll<(), TokenError> {
+    try_ready!(skip_blanks(buf, parens));
+    if *parens {
+        return Err(TokenError::Invalid)
+    }
+    token::skip(buf, |token| {
+        if try_ready!(token.first()) == b';' {
+            comment(token)
+        }
+        else {
+            line_ending(token)
+        }
+    })
+}
+
+/// Advances over a comment including the line ending.
+fn comment<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::octet(token, b';'));
+    loop {
+        match try_ready!(token.first()) {
+            b'\r' | b'\n' => return line_ending(token),
+            _ => token.advance(1)
+        }
+    }
+}
+
+/// Advances over either LF or CRLF.
+fn line_ending<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::opt_octet::<_, TokenError>(token, b'\r'));
+    token::octet(token, b'\n')
+}
+
+
+//------------ Words and Character Strings -----------------------------------
+
+/// Advances over a word.
+///
+/// A word is a non-empty sequence of octets other than delimiters.
+/// Delimiters can be included through escape sequences which are either
+/// a backslash followed by three decimal digits or by any other octet.
+pub fn word<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    let mut empty = true;
+    loop {
+        if token.at_end() && !empty {
+            return Ok(Async::Ready(()))
+        }
+        match try_ready!(token.first()) {
+            b'\\' => try_ready!(escape(token)),
+            ch if test_delimiter(ch) => {
+                return if empty { Err(TokenError::Invalid) }
+                       else { Ok(Async::Ready(())) }
+            }
+            _ => token.advance(1)
+        }
+        empty = false
+    }
+}
+
+/// Parses a word and returns it as is.
+pub fn parse_word<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::parse(buf, word)
+}
+
+/// Advances over a quoted string including the quotes.
+///
+/// Quoted strings can contain delimiters except for line endings.
+pub fn quoted<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::octet(token, b'"'));
+    loop {
+        match try_ready!(token.first()) {
+            b'"' => {
+                token.advance(1);
+                return Ok(Async::Ready(()))
+            }
+            b'\\' => try_ready!(escape(token)),
+            b'\r' | b'\n' => return Err(TokenError::Invalid),
+            _ => token.advance(1)
+        }
+    }
+}
+
+/// Parses a quoted string and returns it as is, including the quotes.
+pub fn parse_quoted<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::parse(buf, quoted)
+}
+
+/// Parses a character string.
+///
+/// A character string is either a word or a quoted string. Returns the
+/// content with escape sequences decoded. Returns an error if the
+/// content is longer than the 255 octets allowed by the wire format.
+pub fn parse_char_string<B: Buffer>(buf: &mut B)
+                                    -> Poll<Bytes, TokenError> {
+    token::convert(buf, |token| {
+        if try_ready!(token.first()) == b'"' { quoted(token) }
+        else { word(token) }
+    }, |res| {
+        let mut raw = res?;
+        if raw.first() == Some(&b'"') {
+            raw = &raw[1..raw.len() - 1]
+        }
+        let decoded = unescape(raw)?;
+        if decoded.len() > 255 {
+            return Err(TokenError::Invalid)
+        }
+        Ok(decoded.into())
+    })
+}
+
+/// Advances over an escape sequence.
+fn escape<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::octet(token, b'\\'));
+    if !test_digit(try_ready!(token.first())) {
+        token.advance(1);
+        return Ok(Async::Ready(()))
+    }
+    for _ in 0..3 {
+        try_ready!(token::cat(token, test_digit));
+    }
+    Ok(Async::Ready(()))
+}
+
+/// Decodes the escape sequences in `raw`.
+///
+/// Returns an error if a decimal escape sequence is larger than 255.
+fn unescape(raw: &[u8]) -> Result<Vec<u8>, TokenError> {
+    let mut res = Vec::with_capacity(raw.len());
+    let mut iter = raw.iter();
+    while let Some(&ch) = iter.next() {
+        if ch != b'\\' {
+            res.push(ch);
+            continue
+        }
+        let first = match iter.next() {
+            Some(&first) => first,
+            None => return Err(TokenError::Invalid)
+        };
+        if !test_digit(first) {
+            res.push(first);
+            continue
+        }
+        let mut value = u32::from(first - b'0');
+        for _ in 0..2 {
+            match iter.next() {
+                Some(&ch) if test_digit(ch) => {
+                    value = value * 10 + u32::from(ch - b'0')
+                }
+                _ => return Err(TokenError::Invalid)
+            }
+        }
+        if value > 255 {
+            return Err(TokenError::Invalid)
+        }
+        res.push(value as u8)
+    }
+    Ok(res)
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::test_support::{assert_canonical, assert_roundtrip,
+                         compose_display};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    fn bytes(slice: &[u8]) -> Bytes { Bytes::copy_from_slice(slice) }
+
+    fn parse_all(data: &[u8]) -> Result<Vec<Entry>, TokenError> {
+        let mut data = buf(data);
+        let mut res = Vec::new();
+        while !data.is_empty() {
+            match parse_entry(&mut data)? {
+                Async::Ready(Some(entry)) => res.push(entry),
+                Async::Ready(None) => { }
+                Async::NotReady => return Err(TokenError::Invalid)
+            }
+        }
+        Ok(res)
+    }
+
+    fn record(owner: Owner, ttl: Option<u32>, class: Option<Class>,
+              data: RecordData) -> Entry {
+        Entry::Record(Record { owner, ttl, class, data })
+    }
+
+    #[test]
+    fn zone() {
+        let zone = b"$ORIGIN example.com.\n\
+                     $TTL 3600 ; one hour\n\
+                     \n\
+                     @ IN SOA ns.example.com. hostmaster (\r\n\
+                     \t2024010101 ; serial\r\n\
+                     \t7200 3600 1209600 3600 )\r\n\
+                     ; a comment\n\
+                     www 300 IN A 192.0.2.1\n\
+                     \tIN 300 AAAA 2001:db8::1\n\
+                     mail MX 10 mx\\.1.example.com.\n\
+                     txt TXT \"v=spf1 -all\" a\\032b \"\\\"q\\\"\"\n\
+                     alias CNAME www\n\
+                     $INCLUDE \"sub zone.txt\" sub\n";
+        assert_eq!(parse_all(zone), Ok(vec![
+            Entry::Origin(bytes(b"example.com.")),
+            Entry::Ttl(3600),
+            record(Owner::Origin, None, Some(Class::In), RecordData::Other {
+                rtype: bytes(b"SOA"),
+                data: vec![
+                    bytes(b"ns.example.com."), bytes(b"hostmaster"),
+                    bytes(b"2024010101"), bytes(b"7200"), bytes(b"3600"),
+                    bytes(b"1209600"), bytes(b"3600")
+                ]
+            }),
+            record(Owner::Name(bytes(b"www")), Some(300), Some(Class::In),
+                   RecordData::A(Ipv4Addr::new(192, 0, 2, 1))),
+            record(Owner::Previous, Some(300), Some(Class::In),
+                   RecordData::Aaaa(Ipv6Addr::new(0x2001, 0xdb8, 0, 0,
+                                                  0, 0, 0, 1))),
+            record(Owner::Name(bytes(b"mail")), None, None, RecordData::Mx {
+                preference: 10, exchange: bytes(b"mx\\.1.example.com.")
+            }),
+            record(Owner::Name(bytes(b"txt")), None, None, RecordData::Txt(
+                vec![bytes(b"v=spf1 -all"), bytes(b"a b"), bytes(b"\"q\"")]
+            )),
+            record(Owner::Name(bytes(b"alias")), None, None,
+                   RecordData::Cname(bytes(b"www"))),
+            Entry::Include {
+                file: bytes(b"sub zone.txt"), origin: Some(bytes(b"sub"))
+            },
+        ]));
+    }
+
+    #[test]
+    fn incomplete_and_invalid() {
+        let mut data = buf(b"www A 192.0.2.1 ; no line ending");
+        assert_eq!(parse_entry(&mut data), Ok(Async::NotReady));
+        assert_eq!(data.len(), 32);
+        let mut data = buf(b"www A ( 192.0.2.1\n");
+        assert_eq!(parse_entry(&mut data), Ok(Async::NotReady));
+        assert_eq!(parse_entry(&mut buf(b"www A 192.0.2.1x\n")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_entry(&mut buf(b"www A 192.0.2.1 )\n")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_entry(&mut buf(b"www TXT \"a\\256\"\n")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_entry(&mut buf(b"$TTL 4294967296\n")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_entry(&mut buf(b"$FOO bar\n")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn display() {
+        fn entry(value: &Option<Entry>, target: &mut BytesMut) {
+            compose_display(value.as_ref().unwrap(), target);
+            target.extend_from_slice(b"\n")
+        }
+
+        for sample in &[
+            &b"$ORIGIN example.com.\n"[..],
+            b"$TTL 3600\n",
+            b"$INCLUDE \"sub zone.txt\"\n",
+            b"$INCLUDE \"sub.txt\" sub\n",
+            b"@ IN SOA ns.example.com. hostmaster 1 7200 3600 1209600 3600\n",
+            b"www 300 IN A 192.0.2.1\n",
+            b" 300 IN AAAA 2001:db8::1\n",
+            b"mail MX 10 mx\\.1.example.com.\n",
+            b"txt TXT \"v=spf1 -all\" \"a\\\"b\\\\c\" \"\\255\"\n",
+            b" CH CNAME www\n",
+        ] {
+            assert_canonical(sample, parse_entry, entry, b"\n");
+        }
+        assert_roundtrip(
+            &Some(record(Owner::Name(bytes(b"txt")), None, None,
+                         RecordData::Txt(vec![bytes(b""),
+                                              bytes(b"\x00\t\x7f")]))),
+            entry, parse_entry, b"\n"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Entry points for fuzzing.
+//!
+//! Each function in this module takes arbitrary data and runs one of the
+//! parsers of this crate over it as often as it makes progress. The
+//! buffer is wrapped with the default [`Limits`] so that malformed input
+//! can’t make a parser run away. The functions never panic unless a
+//! parser does – which is exactly what a fuzzer is looking for.
+//!
+//! The `fuzz` directory of the repository contains targets for
+//! `cargo fuzz` that use these entry points.
+//!
+//! With the `arbitrary` feature, the result types of the protocol modules
+//! implement `arbitrary::Arbitrary`, so that structure-aware fuzzers can
+//! produce values of them, for instance to test code composing them.
+//!
+//! [`Limits`]: ../parse/limits/struct.Limits.html
+
+#[cfg(feature = "arbitrary")] use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "arbitrary")] use bytes::Bytes;
+use bytes::BytesMut;
+use futures::{Async, Poll};
+use ::core::{self, LineEnding};
+use ::{dnszone, http, ipaddr, mime, rtsp, websocket};
+use ::grammar::RuleList;
+use ::grammar::interp::Interpreter;
+use ::parse::buffer::Buffer;
+use ::parse::limits::{Limited, Limits};
+
+
+//------------ Entry Points --------------------------------------------------
+
+/// Parses IPv4 addresses.
+pub fn ipv4_addr(data: &[u8]) {
+    parse_all(data, ipaddr::parse_ipv4_addr)
+}
+
+/// Parses IPv6 addresses.
+pub fn ipv6_addr(data: &[u8]) {
+    parse_all(data, ipaddr::parse_ipv6_addr)
+}
+
+/// Parses lines with any line ending and a maximum length of 1000.
+pub fn lines(data: &[u8]) {
+    parse_all(data, |buf| {
+        core::parse_line_limited_with(buf, LineEnding::Any, 1000)
+    })
+}
+
+/// Parses folded header lines.
+pub fn folded_lines(data: &[u8]) {
+    parse_all(data, |buf| core::parse_unfolded_line(buf, LineEnding::Lf))
+}
+
+/// Parses the various number formats.
+pub fn numbers(data: &[u8]) {
+    parse_all(data, core::u64_digits);
+    parse_all(data, core::i64_digits);
+    parse_all(data, core::u128_hexdigs);
+    parse_all(data, core::f64_float);
+    parse_all(data, core::u16_qvalue);
+    parse_all(data, core::duration_seconds);
+    parse_all(data, core::parse_hex_bytes);
+    parse_all(data, |buf| core::uint_radix(buf, 36, 13));
+}
+
+/// Parses the HTTP header fields and values.
+pub fn http(data: &[u8]) {
+    parse_all(data, http::parse_field_section);
+    parse_all(data, http::parse_field_line);
+    parse_all(data, http::parse_token_or_quoted);
+    parse_all(data, http::parse_http_date_time);
+    parse_all(data, http::parse_retry_after);
+}
+
+/// Parses the MIME header field values.
+pub fn mime(data: &[u8]) {
+    parse_all(data, mime::parse_mime_version);
+    parse_all(data, mime::parse_content_transfer_encoding);
+    parse_all(data, mime::parse_parameters);
+}
+
+/// Parses RTSP start lines and header field values.
+pub fn rtsp(data: &[u8]) {
+    parse_all(data, rtsp::parse_request_line);
+    parse_all(data, rtsp::parse_status_line);
+    parse_all(data, rtsp::parse_cseq);
+    parse_all(data, rtsp::parse_transports);
+    parse_all(data, rtsp::parse_host);
+    parse_all(data, rtsp::parse_session);
+}
+
+/// Parses the WebSocket header field values.
+pub fn websocket(data: &[u8]) {
+    parse_all(data, websocket::parse_key);
+    parse_all(data, websocket::parse_protocols);
+    parse_all(data, websocket::parse_extensions);
+}
+
+/// Parses the entries of a DNS zone file.
+pub fn dnszone(data: &[u8]) {
+    parse_all(data, dnszone::parse_entry);
+}
+
+/// Reads a grammar and matches data against its rules.
+///
+/// The data is split at the first NUL octet. The part before it is read
+/// as a rule list. If that succeeds, the part after it is matched against
+/// each rule with the interpreter. Memoization is enabled so that the
+/// time taken stays polynomial.
+pub fn grammar(data: &[u8]) {
+    let (grammar, input) = match data.iter().position(|&ch| ch == 0) {
+        Some(pos) => (&data[..pos], &data[pos + 1..]),
+        None => (data, &b""[..])
+    };
+    let rules = match RuleList::from_slice(grammar) {
+        Ok(rules) => rules,
+        Err(_) => return
+    };
+    let mut interp = Interpreter::new(&rules);
+    interp.set_memoize(true);
+    for rule in &rules {
+        parse_all(input, |buf| interp.parse(&rule.name, buf));
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Applies `parser` to `data` for as long as it makes progress.
+pub fn parse_all<P, T, E>(data: &[u8], mut parser: P)
+                 where P: FnMut(&mut Limited<BytesMut>) -> Poll<T, E> {
+    let mut buf = Limited::new(BytesMut::from(data), Limits::new());
+    loop {
+        let len = buf.len();
+        match parser(&mut buf) {
+            Ok(Async::Ready(_)) if buf.len() < len => { }
+            _ => break
+        }
+    }
+}
+
+
+//------------ Arbitrary Values ----------------------------------------------
+//
+//  Bytes doesn’t implement Arbitrary, so the derives for the result types
+//  of the protocol modules use these functions for fields containing it.
+
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_bytes(u: &mut Unstructured)
+                              -> arbitrary::Result<Bytes> {
+    Vec::<u8>::arbitrary(u).map(Bytes::from)
+}
+
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_opt_bytes(u: &mut Unstructured)
+                                  -> arbitrary::Result<Option<Bytes>> {
+    Option::<Vec<u8>>::arbitrary(u).map(|res| res.map(Bytes::from))
+}
+
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_bytes_vec(u: &mut Unstructured)
+                                  -> arbitrary::Result<Vec<Bytes>> {
+    u.arbitrary_iter::<Vec<u8>>()?.map(|res| res.map(Bytes::from)).collect()
+}
+
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_params(u: &mut Unstructured)
+                               -> arbitrary::Result<Vec<(Bytes,
+                                                         Option<Bytes>)>> {
+    u.arbitrary_iter::<(Vec<u8>, Option<Vec<u8>>)>()?.map(|res| {
+        res.map(|(name, value)| (name.into(), value.map(Bytes::from)))
+    }).collect()
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn entry_points() {
+        let data = b"12 ::ffff:1.2.3.4 0.5\r\n bar\r\nfe80::1 \xff\r1";
+        for pos in 0..data.len() {
+            let data = &data[pos..];
+            ipv4_addr(data);
+            ipv6_addr(data);
+            lines(data);
+            folded_lines(data);
+            numbers(data);
+        }
+    }
+
+    #[test]
+    fn protocols() {
+        let data = b"RTSP/1.0 200 OK\r\nHost: x; a=\"b\"\r\n\r\n\
+                     Sun, 06 Nov 1994 08:49:37 GMT\r\n\
+                     RTP/AVP;unicast;client_port=1-2, foo\r\n\
+                     www 300 IN A 192.0.2.1\n1.0 (x)\r\n";
+        for pos in 0..data.len() {
+            let data = &data[pos..];
+            http(data);
+            mime(data);
+            rtsp(data);
+            websocket(data);
+            dnszone(data);
+        }
+    }
+
+    #[test]
+    fn grammars() {
+        grammar(b"a = 1*b [c]\nb = \"x\" / a\nc = <prose>\0xxa");
+        grammar(b"a = a\0a");
+        grammar(b"a = (\0a");
+        grammar(b"");
+    }
+}
@@ -0,0 +1,404 @@
+//! Checking grammars for mistakes.
+//!
+//! Grammars extracted from documents frequently contain mistakes that
+//! only show once data is matched against them. The function
+//! [`validate()`] finds three kinds of them up front: references to rules
+//! that aren’t defined anywhere, rules that can’t be reached from the
+//! rule a parser starts with, and left recursion.
+//!
+//! A rule is left-recursive if it can reference itself, directly or via
+//! other rules, before anything has been matched. Since the interpreter
+//! descends into references right away, it would never return from such
+//! a rule. Left recursion is found taking into account that some
+//! elements may match the empty string and thus don’t consume anything.
+//!
+//! The core rules of RFC 5234 are considered defined since the
+//! interpreter provides them.
+//!
+//! [`validate()`]: fn.validate.html
+
+use std::cmp;
+use std::collections::HashMap;
+use super::{Alternation, Element, Repetition, Rule, RuleList};
+use super::interp::is_core_rule;
+use super::visit::Visitor;
+
+
+//------------ validate ------------------------------------------------------
+
+/// Checks a rule list for mistakes.
+///
+/// If `start` is given, all rules that can’t be reached from the rule of
+/// that name are reported. Returns the list of problems found in the
+/// order of the rules they concern, first undefined references, then
+/// unreachable rules, and finally left recursion. If everything is fine,
+/// the list is empty.
+pub fn validate(rules: &RuleList, start: Option<&str>) -> Vec<Diagnostic> {
+    let analysis = Analysis::new(rules);
+    let mut res = Vec::new();
+    analysis.undefined(&mut res);
+    if let Some(start) = start {
+        analysis.unreachable(start, &mut res);
+    }
+    analysis.left_recursion(&mut res);
+    res
+}
+
+
+//------------ Diagnostic ----------------------------------------------------
+
+/// A problem found in a grammar.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Diagnostic {
+    /// A rule references a rule that isn’t defined.
+    Undefined {
+        /// The name of the rule containing the reference.
+        rule: String,
+
+        /// The name of the undefined rule as written in the reference.
+        reference: String,
+    },
+
+    /// The start rule isn’t defined.
+    UndefinedStart(String),
+
+    /// The rule of the given name can’t be reached from the start rule.
+    Unreachable(String),
+
+    /// The given rules are left-recursive.
+    ///
+    /// The rules are listed in the order they appear in the rule list.
+    /// They all can reach each other without consuming input.
+    LeftRecursion(Vec<String>),
+}
+
+
+//------------ Analysis ------------------------------------------------------
+
+/// Information about a rule list.
+struct Analysis<'a> {
+    /// The rules in the order of the rule list.
+    rules: Vec<&'a Rule>,
+
+    /// The index of each rule by its lower-case name.
+    index: HashMap<String, usize>,
+
+    /// The indexes of the defined rules each rule references.
+    references: Vec<Vec<usize>>,
+
+    /// Whether each rule can match the empty string.
+    nullable: Vec<bool>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(list: &'a RuleList) -> Self {
+        let rules: Vec<_> = list.iter().collect();
+        let index = rules.iter().enumerate().map(|(i, rule)| {
+            (rule.name.to_ascii_lowercase(), i)
+        }).collect();
+        let mut res = Analysis {
+            rules,
+            index,
+            references: Vec::new(),
+            nullable: Vec::new(),
+        };
+        res.references = res.rules.iter().map(|rule| {
+            let mut names = Vec::new();
+            references(&rule.alternation, &mut names);
+            names.iter().filter_map(|name| res.get(name)).collect()
+        }).collect();
+        res.nullable = vec![false; res.rules.len()];
+        loop {
+            let mut changed = false;
+            for i in 0..res.rules.len() {
+                if !res.nullable[i]
+                    && res.alternation_nullable(&res.rules[i].alternation)
+                {
+                    res.nullable[i] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break
+            }
+        }
+        res
+    }
+
+    fn get(&self, name: &str) -> Option<usize> {
+        self.index.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    fn undefined(&self, res: &mut Vec<Diagnostic>) {
+        for rule in &self.rules {
+            let mut names = Vec::new();
+            references(&rule.alternation, &mut names);
+            let mut seen: Vec<&str> = Vec::new();
+            for name in &names {
+                if self.get(name).is_some() || is_core_rule(name)
+                    || seen.iter().any(|item| item.eq_ignore_ascii_case(name))
+                {
+                    continue
+                }
+                seen.push(name);
+                res.push(Diagnostic::Undefined {
+                    rule: rule.name.clone(),
+                    reference: name.clone()
+                })
+            }
+        }
+    }
+
+    fn unreachable(&self, start: &str, res: &mut Vec<Diagnostic>) {
+        let start = match self.get(start) {
+            Some(start) => start,
+            None => {
+                res.push(Diagnostic::UndefinedStart(start.into()));
+                return
+            }
+        };
+        let mut reached = vec![false; self.rules.len()];
+        let mut todo = vec![start];
+        reached[start] = true;
+        while let Some(item) = todo.pop() {
+            for &target in &self.references[item] {
+                if !reached[target] {
+                    reached[target] = true;
+                    todo.push(target);
+                }
+            }
+        }
+        for (rule, reached) in self.rules.iter().zip(reached) {
+            if !reached {
+                res.push(Diagnostic::Unreachable(rule.name.clone()))
+            }
+        }
+    }
+
+    fn left_recursion(&self, res: &mut Vec<Diagnostic>) {
+        let graph: Vec<_> = self.rules.iter().map(|rule| {
+            let mut targets = Vec::new();
+            self.left_alternation(&rule.alternation, &mut targets);
+            targets
+        }).collect();
+        let mut components = Components::new(&graph).find();
+        components.sort();
+        for component in components {
+            if component.len() > 1 || graph[component[0]].contains(
+                &component[0]
+            ) {
+                res.push(Diagnostic::LeftRecursion(
+                    component.iter().map(|&i| {
+                        self.rules[i].name.clone()
+                    }).collect()
+                ))
+            }
+        }
+    }
+
+    /// Adds the rules referenced before anything is consumed.
+    fn left_alternation(&self, alternation: &Alternation,
+                        res: &mut Vec<usize>) {
+        for concatenation in &alternation.concatenations {
+            for repetition in &concatenation.repetitions {
+                self.left_element(&repetition.element, res);
+                if !self.repetition_nullable(repetition) {
+                    break
+                }
+            }
+        }
+    }
+
+    fn left_element(&self, element: &Element, res: &mut Vec<usize>) {
+        match *element {
+            Element::Rulename(ref name) => {
+                if let Some(i) = self.get(name) {
+                    res.push(i)
+                }
+            }
+            Element::Group(ref alternation)
+                | Element::Optional(ref alternation) => {
+                self.left_alternation(alternation, res)
+            }
+            _ => { }
+        }
+    }
+
+    fn alternation_nullable(&self, alternation: &Alternation) -> bool {
+        alternation.concatenations.iter().any(|concatenation| {
+            concatenation.repetitions.iter().all(|repetition| {
+                self.repetition_nullable(repetition)
+            })
+        })
+    }
+
+    fn repetition_nullable(&self, repetition: &Repetition) -> bool {
+        repetition.min == 0 || self.element_nullable(&repetition.element)
+    }
+
+    fn element_nullable(&self, element: &Element) -> bool {
+        match *element {
+            Element::Rulename(ref name) => match self.get(name) {
+                Some(i) => self.nullable[i],
+                None => name.eq_ignore_ascii_case("LWSP")
+            },
+            Element::Group(ref alternation) => {
+                self.alternation_nullable(alternation)
+            }
+            Element::Optional(_) => true,
+            Element::CharVal(ref value) => value.value.is_empty(),
+            Element::NumVal(_) | Element::ProseVal(_) => false,
+        }
+    }
+}
+
+/// Adds the names of all rules referenced by an alternation.
+pub(super) fn references(alternation: &Alternation, res: &mut Vec<String>) {
+    struct Names<'a>(&'a mut Vec<String>);
+
+    impl<'a> Visitor for Names<'a> {
+        fn visit_rulename(&mut self, name: &str) {
+            self.0.push(name.into())
+        }
+    }
+
+    Names(res).visit_alternation(alternation)
+}
+
+
+//------------ Components ----------------------------------------------------
+
+/// Finds the strongly connected components of a graph.
+///
+/// This is Tarjan’s algorithm. The graph is given as the list of targets
+/// for each node.
+struct Components<'a> {
+    graph: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next: usize,
+    res: Vec<Vec<usize>>,
+}
+
+impl<'a> Components<'a> {
+    fn new(graph: &'a [Vec<usize>]) -> Self {
+        Components {
+            graph,
+            index: vec![None; graph.len()],
+            low: vec![0; graph.len()],
+            on_stack: vec![false; graph.len()],
+            stack: Vec::new(),
+            next: 0,
+            res: Vec::new(),
+        }
+    }
+
+    /// Returns the components, each sorted by node.
+    fn find(mut self) -> Vec<Vec<usize>> {
+        for node in 0..self.graph.len() {
+            if self.index[node].is_none() {
+                self.visit(node)
+            }
+        }
+        self.res
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.low[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        for &target in &self.graph[node] {
+            match self.index[target] {
+                None => {
+                    self.visit(target);
+                    self.low[node] = cmp::min(self.low[node],
+                                              self.low[target]);
+                }
+                Some(index) if self.on_stack[target] => {
+                    self.low[node] = cmp::min(self.low[node], index);
+                }
+                Some(_) => { }
+            }
+        }
+        if Some(self.low[node]) == self.index[node] {
+            let mut component = Vec::new();
+            loop {
+                let item = self.stack.pop().unwrap();
+                self.on_stack[item] = false;
+                component.push(item);
+                if item == node {
+                    break
+                }
+            }
+            component.sort();
+            self.res.push(component);
+        }
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(data: &[u8], start: Option<&str>) -> Vec<Diagnostic> {
+        validate(&RuleList::from_slice(data).unwrap(), start)
+    }
+
+    #[test]
+    fn undefined() {
+        assert_eq!(
+            check(b"a = b c B ALPHA\nb = (\"x\" / [d]) e\nc = a\n", None),
+            [
+                Diagnostic::Undefined {
+                    rule: "b".into(), reference: "d".into()
+                },
+                Diagnostic::Undefined {
+                    rule: "b".into(), reference: "e".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable() {
+        let grammar = b"a = b\nb = \"x\"\nc = b / a\nd = d \"x\"\n";
+        assert_eq!(check(grammar, Some("A")), [
+            Diagnostic::Unreachable("c".into()),
+            Diagnostic::Unreachable("d".into()),
+            Diagnostic::LeftRecursion(vec!["d".into()]),
+        ]);
+        assert_eq!(check(grammar, Some("c")), [
+            Diagnostic::Unreachable("d".into()),
+            Diagnostic::LeftRecursion(vec!["d".into()]),
+        ]);
+        assert_eq!(check(grammar, Some("x")), [
+            Diagnostic::UndefinedStart("x".into()),
+            Diagnostic::LeftRecursion(vec!["d".into()]),
+        ]);
+    }
+
+    #[test]
+    fn left_recursion() {
+        // Direct, indirect via a nullable prefix, and not left at all.
+        assert_eq!(
+            check(b"a = \"x\" / a \"x\"\n\
+                    b = c \"x\"\n\
+                    c = [d] *LWSP 0*1\"\" b\n\
+                    d = \"y\"\n\
+                    e = \"(\" e \")\" / \"\"\n\
+                    f = 1*e f\n", None),
+            [
+                Diagnostic::LeftRecursion(vec!["a".into()]),
+                Diagnostic::LeftRecursion(vec!["b".into(), "c".into()]),
+                Diagnostic::LeftRecursion(vec!["f".into()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Generating Rust code from a grammar.
+//!
+//! Instead of interpreting a grammar at run time, a [`Generator`] can turn
+//! it into Rust source code for the rules, typically from a build script.
+//! For every rule, the code contains a token rule function of the same
+//! kind as those defined by the `rule!()` macro or found in the `core`
+//! module:
+//!
+//! ```ignore
+//! pub fn name<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError>
+//! ```
+//!
+//! The name of the function is the rule name in lower case with hyphens
+//! replaced by underscores. If the result is a Rust keyword, an
+//! underscore is appended.
+//!
+//! The functions match data exactly like the [interpreter] would. In
+//! particular, references to core rules not defined by the grammar are
+//! translated into calls to the functions of the `core` module. Prose
+//! values never match. References to rules not defined at all are
+//! translated into calls to functions of the same name in the module the
+//! code is placed in. This provides a way to supply hand-written rules.
+//!
+//! All paths in the generated code are absolute and start with the path
+//! of this crate which can be changed from the default `::abnf` via
+//! `Generator::set_crate_path()`. The code should be placed into a module
+//! of its own, for instance in a build script:
+//!
+//! ```ignore
+//! // build.rs
+//! let rules = RuleList::from_slice(&fs::read("smtp.abnf")?)?;
+//! let out = Path::new(&env::var("OUT_DIR")?).join("smtp.rs");
+//! fs::write(out, codegen::generate(&rules))?;
+//! ```
+//!
+//! and then in the crate:
+//!
+//! ```ignore
+//! mod smtp {
+//!     include!(concat!(env!("OUT_DIR"), "/smtp.rs"));
+//! }
+//! ```
+//!
+//! [`Generator`]: struct.Generator.html
+//! [interpreter]: ../interp/index.html
+
+use super::{Alternation, CharVal, Concatenation, Element, NumKind, NumVal,
+            Repetition, Rule, RuleList};
+use super::interp::is_core_rule;
+
+
+//------------ generate ------------------------------------------------------
+
+/// Generates the code for a rule list with the default settings.
+pub fn generate(rules: &RuleList) -> String {
+    Generator::new().generate(rules)
+}
+
+
+//------------ Generator -----------------------------------------------------
+
+/// A generator of Rust code for grammars.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    /// The path to this crate.
+    crate_path: String,
+
+    /// Whether to add `parse_` and `skip_` functions for each rule.
+    wrappers: bool,
+}
+
+impl Generator {
+    /// Creates a new generator with the default settings.
+    pub fn new() -> Self {
+        Generator { crate_path: "::abnf".into(), wrappers: false }
+    }
+
+    /// Sets the path through which the generated code refers to this crate.
+    ///
+    /// The default is `::abnf`.
+    pub fn set_crate_path(&mut self, path: &str) {
+        self.crate_path = path.into()
+    }
+
+    /// Sets whether to add buffer functions for each rule.
+    ///
+    /// If enabled, the code contains two more functions for each rule,
+    /// following the naming of the functions in `parse::token`:
+    ///
+    /// ```ignore
+    /// pub fn parse_name<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError>
+    /// pub fn skip_name<B: Buffer>(buf: &mut B) -> Poll<(), TokenError>
+    /// ```
+    ///
+    /// These are disabled by default.
+    pub fn set_wrappers(&mut self, enable: bool) {
+        self.wrappers = enable
+    }
+
+    /// Generates the code for all rules of a rule list.
+    pub fn generate(&self, rules: &RuleList) -> String {
+        let mut res = String::from(
+            "// Generated from an ABNF grammar. Do not edit.\n"
+        );
+        for rule in rules {
+            res.push('\n');
+            Writer { gen: self, rules }.function(rule, &mut res);
+        }
+        res
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+//------------ Writer --------------------------------------------------------
+
+/// The generator at work on a rule list.
+struct Writer<'a> {
+    gen: &'a Generator,
+    rules: &'a RuleList,
+}
+
+impl<'a> Writer<'a> {
+    fn function(&self, rule: &Rule, target: &mut String) {
+        let c = &self.gen.crate_path;
+        target.push_str(&format!("//  {}\n", rule));
+        target.push_str(&format!(
+            "pub fn {}<B: {}::parse::buffer::Buffer>(\n    \
+                 token: &mut {}::parse::token::Token<B>\n\
+             ) -> {}::Poll<(), {}::parse::token::TokenError> {{\n",
+            ident(&rule.name), c, c, c, c
+        ));
+        for line in self.alternation(&rule.alternation) {
+            if !line.is_empty() {
+                target.push_str("    ");
+                target.push_str(&line);
+            }
+            target.push('\n');
+        }
+        target.push_str("}\n");
+        if self.gen.wrappers {
+            let bytes = format!("{}::prelude::Bytes", c);
+            self.wrapper(rule, "parse", &bytes, target);
+            self.wrapper(rule, "skip", "()", target);
+        }
+    }
+
+    /// Writes a buffer function calling `parse::token::op()`.
+    fn wrapper(&self, rule: &Rule, op: &str, value: &str,
+               target: &mut String) {
+        let c = &self.gen.crate_path;
+        target.push_str(&format!(
+            "\n\
+             pub fn {}_{}<B: {}::parse::buffer::Buffer>(\n    \
+                 buf: &mut B\n\
+             ) -> {}::Poll<{}, {}::parse::token::TokenError> {{\n    \
+                 {}::parse::token::{}(buf, self::{})\n\
+             }}\n",
+            op, rule.name.to_ascii_lowercase().replace('-', "_"), c, c,
+            value, c, c, op, ident(&rule.name)
+        ));
+    }
+
+    /// Returns the statements matching an alternation.
+    fn alternation(&self, alternation: &Alternation) -> Vec<String> {
+        if alternation.concatenations.len() == 1 {
+            return self.concatenation(&alternation.concatenations[0])
+        }
+        let mut res = Vec::new();
+        for concatenation in &alternation.concatenations {
+            let body = match self.single(concatenation) {
+                Some(Code::Fail) => continue,
+                Some(body) => body,
+                None => Code::Lines(self.concatenation(concatenation))
+            };
+            res.extend(self.proceed(
+                self.group(body),
+                &format!("Err({}) => {{ }}", self.invalid())
+            ));
+        }
+        res.push(format!("Err({})", self.invalid()));
+        res
+    }
+
+    /// Returns the statements matching a concatenation.
+    fn concatenation(&self, concatenation: &Concatenation) -> Vec<String> {
+        let mut res = Vec::new();
+        let (last, init) = concatenation.repetitions.split_last().unwrap();
+        for repetition in init {
+            res.extend(self.proceed(
+                self.repetition(repetition),
+                &format!("Ok({}::Async::Ready(())) => {{ }}",
+                         self.gen.crate_path)
+            ));
+        }
+        res.extend(self.repetition(last).lines(&self.gen.crate_path));
+        res
+    }
+
+    /// Returns a match continuing after `arm` and returning otherwise.
+    fn proceed(&self, expr: Code, arm: &str) -> Vec<String> {
+        let mut expr = expr.lines(&self.gen.crate_path);
+        expr[0] = format!("match {}", expr[0]);
+        expr.last_mut().unwrap().push_str(" {");
+        expr.push(format!("    {}", arm));
+        expr.push("    res => return res".into());
+        expr.push("}".into());
+        expr
+    }
+
+    /// Returns the element of a concatenation that is only that element.
+    fn single(&self, concatenation: &Concatenation) -> Option<Code> {
+        match concatenation.repetitions.as_slice() {
+            [repetition] => Some(self.repetition(repetition)),
+            _ => None
+        }
+    }
+
+    fn repetition(&self, repetition: &Repetition) -> Code {
+        let element = self.element(&repetition.element);
+        if repetition.min == 1 && repetition.max == Some(1) {
+            return element
+        }
+        let head = format!(
+            "{}::parse::token::repeat(token, {}, {}, ",
+            self.gen.crate_path, repetition.min,
+            match repetition.max {
+                Some(max) => format!("Some({})", max),
+                None => "None".into()
+            }
+        );
+        self.apply(head, element)
+    }
+
+    fn element(&self, element: &Element) -> Code {
+        let c = &self.gen.crate_path;
+        match *element {
+            Element::Rulename(ref name) => {
+                if self.rules.get(name).is_none() && is_core_rule(name) {
+                    let name = name.to_ascii_lowercase();
+                    Code::Call(format!(
+                        "{}::core::{}", c,
+                        if name == "octet" { "octet_any" } else { &name }
+                    ))
+                }
+                else {
+                    Code::Call(format!("self::{}", ident(name)))
+                }
+            }
+            Element::Group(ref alternation) => {
+                match alternation.concatenations.as_slice() {
+                    [concatenation] => {
+                        self.single(concatenation).unwrap_or_else(|| {
+                            self.group(Code::Lines(
+                                self.concatenation(concatenation)
+                            ))
+                        })
+                    }
+                    _ => {
+                        self.group(Code::Lines(self.alternation(alternation)))
+                    }
+                }
+            }
+            Element::Optional(ref alternation) => {
+                let body = match alternation.concatenations.as_slice() {
+                    [concatenation] => self.single(concatenation),
+                    _ => None
+                }.unwrap_or_else(|| {
+                    Code::Lines(self.alternation(alternation))
+                });
+                let mut res = self.group(body).lines(c);
+                res[0] = format!("match {}", res[0]);
+                res.last_mut().unwrap().push_str(" {");
+                res.push(format!("    Err({}) => {{", self.invalid()));
+                res.push(format!("        Ok({}::Async::Ready(()))", c));
+                res.push("    }".into());
+                res.push("    res => res".into());
+                res.push("}".into());
+                Code::Lines(res)
+            }
+            Element::CharVal(ref value) => self.char_val(value),
+            Element::NumVal(ref value) => self.num_val(value),
+            Element::ProseVal(_) => Code::Fail,
+        }
+    }
+
+    fn char_val(&self, value: &CharVal) -> Code {
+        Code::Lines(vec![format!(
+            "{}::parse::token::{}(token, b\"{}\")",
+            self.gen.crate_path,
+            if value.case_sensitive { "literal_exact" } else { "literal" },
+            value.value.replace('\\', "\\\\")
+        )])
+    }
+
+    fn num_val(&self, value: &NumVal) -> Code {
+        let c = &self.gen.crate_path;
+        match value.kind {
+            NumKind::Range(first, _) if first > 0xFF => Code::Fail,
+            NumKind::Range(0, last) if last >= 0xFF => {
+                Code::Call(format!("{}::core::octet_any", c))
+            }
+            NumKind::Range(first, last) => {
+                Code::Lines(vec![format!(
+                    "{}::parse::token::cat(token, |ch| \
+                     (0x{:02X}..=0x{:02X}).contains(&ch))",
+                    c, first, if last > 0xFF { 0xFF } else { last }
+                )])
+            }
+            NumKind::Concat(ref values) => {
+                if values.iter().any(|&value| value > 0xFF) {
+                    return Code::Fail
+                }
+                Code::Lines(vec![format!(
+                    "{}::parse::token::literal_exact(token, b\"{}\")",
+                    c,
+                    values.iter().map(|value| {
+                        format!("\\x{:02X}", value)
+                    }).collect::<String>()
+                )])
+            }
+        }
+    }
+
+    /// Returns code applying `body` in a group.
+    fn group(&self, body: Code) -> Code {
+        let head = format!("{}::parse::token::group(token, ",
+                           self.gen.crate_path);
+        self.apply(head, body)
+    }
+
+    /// Returns code calling a combinator with `body` as its closure.
+    ///
+    /// The `head` is the call up to and including the comma before the
+    /// closure.
+    fn apply(&self, head: String, body: Code) -> Code {
+        match body {
+            Code::Call(path) => Code::Lines(vec![
+                format!("{}{})", head, path)
+            ]),
+            Code::Fail => Code::Lines(vec![
+                format!("{}|_| Err({}))", head, self.invalid())
+            ]),
+            Code::Lines(lines) => {
+                let mut res = vec![format!("{}|token| {{", head)];
+                res.extend(lines.into_iter().map(indent));
+                res.push("})".into());
+                Code::Lines(res)
+            }
+        }
+    }
+
+    fn invalid(&self) -> String {
+        format!("{}::parse::token::TokenError::Invalid", self.gen.crate_path)
+    }
+}
+
+
+//------------ Code ----------------------------------------------------------
+
+/// A piece of code producing a `Poll<(), TokenError>` from `token`.
+enum Code {
+    /// The path to a function taking the token.
+    Call(String),
+
+    /// An expression that never matches.
+    Fail,
+
+    /// Lines of code, the last of which produces the result.
+    Lines(Vec<String>),
+}
+
+impl Code {
+    fn lines(self, crate_path: &str) -> Vec<String> {
+        match self {
+            Code::Call(path) => vec![format!("{}(token)", path)],
+            Code::Fail => vec![format!(
+                "Err({}::parse::token::TokenError::Invalid)", crate_path
+            )],
+            Code::Lines(lines) => lines,
+        }
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Indents a line by one level.
+fn indent(line: String) -> String {
+    if line.is_empty() {
+        line
+    }
+    else {
+        format!("    {}", line)
+    }
+}
+
+/// Returns the identifier of the function for a rule name.
+pub fn ident(name: &str) -> String {
+    let mut res = name.to_ascii_lowercase().replace('-', "_");
+    if KEYWORDS.contains(&res.as_str()) {
+        res.push('_')
+    }
+    res
+}
+
+/// The Rust keywords, including reserved ones, that can be rule names.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "typeof", "unsafe", "unsized", "use", "virtual", "where",
+    "while", "yield",
+];
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn idents() {
+        assert_eq!(ident("Rule-Name"), "rule_name");
+        assert_eq!(ident("TYPE"), "type_");
+        assert_eq!(ident("token"), "token");
+    }
+
+    #[test]
+    fn crate_path() {
+        let rules = RuleList::from_slice(b"a = *2DIGIT [b]\n").unwrap();
+        let mut gen = Generator::new();
+        gen.set_crate_path("abnf");
+        let code = gen.generate(&rules);
+        assert!(code.contains(
+            "abnf::parse::token::repeat(token, 0, Some(2), abnf::core::digit)"
+        ));
+        assert!(code.contains("abnf::parse::token::group(token, self::b)"));
+        assert!(!code.contains("::abnf"));
+        assert!(!code.contains("parse_a"));
+
+        gen.set_wrappers(true);
+        assert!(gen.generate(&rules).contains(
+            "pub fn parse_a<B: abnf::parse::buffer::Buffer>(\n    \
+                 buf: &mut B\n\
+             ) -> abnf::Poll<abnf::prelude::Bytes, \
+                             abnf::parse::token::TokenError> {\n    \
+                 abnf::parse::token::parse(buf, self::a)\n\
+             }\n"
+        ));
+    }
+}
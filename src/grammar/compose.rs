@@ -0,0 +1,544 @@
+//! Composing data from a grammar.
+//!
+//! A [`Composer`] is the inverse of the [`Interpreter`]. Given a tree of
+//! [`Value`]s for a rule, it produces octets that match the rule. This
+//! makes it possible to write data for a protocol described by a grammar
+//! without writing any code for its rules.
+//!
+//! A value names the rule it is for and provides either the octets for
+//! the rule or the values for the rules referenced by it. Octets are used
+//! as they are after checking via the interpreter that they match the
+//! rule. Values for the referenced rules are composed first and then
+//! slotted into the rule’s definition: the composer looks for a way
+//! through the definition in which the references consume exactly the
+//! given values in order.
+//!
+//! Everything else in the definition is filled in by the composer.
+//! Quoted strings are written as they appear in the grammar, numeric
+//! values as the octets they stand for. Optional parts are left out and
+//! repetitions repeated only as often as necessary unless values for
+//! rules inside them are given. References to rules without a value are
+//! replaced by the shortest derivation of the rule. Ranges with more than
+//! one value as well as prose values don’t determine the data, so parts
+//! of the grammar containing them can only be produced via octets given
+//! for a rule. Should there be no way through a definition that fits the
+//! values, composing fails with `ComposeError::Unrepresentable`.
+//!
+//! The core rules of RFC 5234 are provided unless the grammar defines
+//! rules of the same name.
+//!
+//! The interpreter takes the first alternative that matches and repeats
+//! elements as often as possible. As with the data produced by the
+//! [`Generator`], data composed for an ambiguous grammar may therefore not
+//! be parsed back into the same tree.
+//!
+//! [`Composer`]: struct.Composer.html
+//! [`Value`]: struct.Value.html
+//! [`Interpreter`]: ../interp/struct.Interpreter.html
+//! [`Generator`]: ../random/struct.Generator.html
+
+use std::collections::{BTreeMap, HashMap};
+use bytes::{Bytes, BytesMut};
+use ::Async;
+use ::parse::complete::Complete;
+use super::{Alternation, Concatenation, Element, NumKind, Repetition, Rule,
+            RuleList, CORE_RULES};
+use super::interp::{Interpreter, Node};
+
+
+//------------ Value ---------------------------------------------------------
+
+/// A value for a rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Value {
+    /// The name of the rule.
+    pub rule: String,
+
+    /// The content of the value.
+    pub content: Content,
+}
+
+/// The content of a value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Content {
+    /// The octets of the rule.
+    Octets(Bytes),
+
+    /// The values of the rules referenced by the rule in order.
+    Children(Vec<Value>),
+}
+
+impl Value {
+    /// Creates a value providing the octets for the rule `rule`.
+    pub fn octets<T: Into<Bytes>>(rule: &str, octets: T) -> Self {
+        Value { rule: rule.into(), content: Content::Octets(octets.into()) }
+    }
+
+    /// Creates a value from the values of referenced rules.
+    pub fn children(rule: &str, children: Vec<Value>) -> Self {
+        Value { rule: rule.into(), content: Content::Children(children) }
+    }
+
+    /// Creates a value from a node of a parse tree.
+    ///
+    /// Nodes without children become values with the octets they cover
+    /// in `data`, all other nodes values with the values of their
+    /// children.
+    pub fn from_node(node: &Node, data: &[u8]) -> Self {
+        if node.children.is_empty() {
+            let octets = &data[node.start..node.end];
+            Value::octets(&node.rule, Bytes::copy_from_slice(octets))
+        }
+        else {
+            Value::children(
+                &node.rule,
+                node.children.iter().map(|child| {
+                    Value::from_node(child, data)
+                }).collect()
+            )
+        }
+    }
+}
+
+
+//------------ Partials ------------------------------------------------------
+
+/// The ways through a part of a rule.
+///
+/// Keys are the number of values consumed, values the octets composed
+/// so far. Only the first way found for each number of values is kept.
+type Partials = BTreeMap<usize, BytesMut>;
+
+
+//------------ Composer ------------------------------------------------------
+
+/// Composes data for the rules of a rule list.
+pub struct Composer<'a> {
+    /// The rules of the grammar.
+    rules: &'a RuleList,
+
+    /// The core rules.
+    core: RuleList,
+
+    /// The interpreter checking octets given for rules.
+    interp: Interpreter<'a, Complete<BytesMut>>,
+
+    /// The shortest derivation of each rule.
+    ///
+    /// Keys are the lower-case names. Rules that can’t be derived without
+    /// values are missing.
+    defaults: HashMap<String, Bytes>,
+
+    /// The maximum depth of values.
+    max_depth: usize,
+}
+
+impl<'a> Composer<'a> {
+    /// Creates a new composer for the given rules.
+    pub fn new(rules: &'a RuleList) -> Self {
+        let mut res = Composer {
+            rules,
+            core: RuleList::from_slice(CORE_RULES).unwrap(),
+            interp: Interpreter::new(rules),
+            defaults: HashMap::new(),
+            max_depth: 128,
+        };
+        res.defaults = res.find_defaults();
+        res
+    }
+
+    /// Sets the maximum depth of values.
+    ///
+    /// Composing a value whose tree is deeper fails with
+    /// `ComposeError::LimitExceeded`. The default is 128.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth
+    }
+
+    /// Returns the interpreter used for checking octets.
+    ///
+    /// Resolvers for prose values and rules can be registered with it so
+    /// that octets for rules containing them can be checked.
+    pub fn interpreter_mut(&mut self)
+                           -> &mut Interpreter<'a, Complete<BytesMut>> {
+        &mut self.interp
+    }
+
+    /// Composes the data for a value.
+    pub fn compose(&self, value: &Value) -> Result<Bytes, ComposeError> {
+        self.value(value, 0)
+    }
+
+    /// Composes the data for `value` and appends it to `target`.
+    pub fn compose_into(&self, value: &Value, target: &mut BytesMut)
+                        -> Result<(), ComposeError> {
+        target.extend_from_slice(&self.compose(value)?);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.get(name).or_else(|| self.core.get(name))
+    }
+
+    fn value(&self, value: &Value, depth: usize)
+             -> Result<Bytes, ComposeError> {
+        if depth >= self.max_depth {
+            return Err(ComposeError::LimitExceeded)
+        }
+        match value.content {
+            Content::Octets(ref octets) => {
+                self.check(&value.rule, octets)?;
+                Ok(octets.clone())
+            }
+            Content::Children(ref children) => {
+                let rule = match self.get(&value.rule) {
+                    Some(rule) => rule,
+                    None => {
+                        return Err(ComposeError::Undefined(
+                            value.rule.clone()
+                        ))
+                    }
+                };
+                let parts = children.iter().map(|child| {
+                    Ok((child.rule.as_str(), self.value(child, depth + 1)?))
+                }).collect::<Result<Vec<_>, _>>()?;
+                let mut start = Partials::new();
+                start.insert(0, BytesMut::new());
+                match self.alternation(&rule.alternation, &parts, start,
+                                       &self.defaults)
+                          .remove(&parts.len()) {
+                    Some(res) => Ok(res.freeze()),
+                    None => {
+                        Err(ComposeError::Unrepresentable(
+                            value.rule.clone()
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that `octets` are a match of the rule named `name`.
+    fn check(&self, name: &str, octets: &[u8]) -> Result<(), ComposeError> {
+        if !self.interp.contains(name) {
+            return Err(ComposeError::Undefined(name.into()))
+        }
+        let mut buf = Complete::new(BytesMut::from(octets));
+        match self.interp.skip(name, &mut buf) {
+            Ok(Async::Ready(())) if buf.get_ref().is_empty() => Ok(()),
+            _ => Err(ComposeError::Mismatch(name.into()))
+        }
+    }
+
+    /// Finds the shortest derivation of all rules.
+    ///
+    /// Each round adds the rules that can be derived from those found in
+    /// earlier rounds until nothing changes anymore.
+    fn find_defaults(&self) -> HashMap<String, Bytes> {
+        let rules: Vec<_> = self.rules.iter().chain(
+            self.core.iter().filter(|rule| !self.rules.contains(&rule.name))
+        ).collect();
+        let mut res = HashMap::new();
+        loop {
+            let mut found = Vec::new();
+            for rule in &rules {
+                let key = rule.name.to_ascii_lowercase();
+                if res.contains_key(&key) {
+                    continue
+                }
+                let mut start = Partials::new();
+                start.insert(0, BytesMut::new());
+                let mut ways = self.alternation(&rule.alternation, &[], start,
+                                                &res);
+                if let Some(data) = ways.remove(&0) {
+                    found.push((key, data.freeze()))
+                }
+            }
+            if found.is_empty() {
+                return res
+            }
+            res.extend(found)
+        }
+    }
+
+    fn alternation(&self, alternation: &Alternation, parts: &[(&str, Bytes)],
+                   start: Partials, defaults: &HashMap<String, Bytes>)
+                   -> Partials {
+        let mut res = Partials::new();
+        for concatenation in &alternation.concatenations {
+            let ways = self.concatenation(concatenation, parts,
+                                          start.clone(), defaults);
+            for (pos, data) in ways {
+                res.entry(pos).or_insert(data);
+            }
+        }
+        res
+    }
+
+    fn concatenation(&self, concatenation: &Concatenation,
+                     parts: &[(&str, Bytes)], start: Partials,
+                     defaults: &HashMap<String, Bytes>) -> Partials {
+        concatenation.repetitions.iter().fold(start, |ways, repetition| {
+            self.repetition(repetition, parts, ways, defaults)
+        })
+    }
+
+    fn repetition(&self, repetition: &Repetition, parts: &[(&str, Bytes)],
+                  start: Partials, defaults: &HashMap<String, Bytes>)
+                  -> Partials {
+        let mut res = Partials::new();
+        let mut ways = start;
+        let mut count = 0;
+        loop {
+            if count >= repetition.min {
+                for (&pos, data) in &ways {
+                    res.entry(pos).or_insert_with(|| data.clone());
+                }
+            }
+            if ways.is_empty() || repetition.max == Some(count) {
+                return res
+            }
+            let next = self.element(&repetition.element, parts, ways,
+                                    defaults);
+            // Once the minimum is reached, further repetitions are only
+            // of use if they consume more values.
+            if count >= repetition.min
+                    && next.keys().all(|pos| res.contains_key(pos)) {
+                return res
+            }
+            ways = next;
+            count += 1;
+        }
+    }
+
+    fn element(&self, element: &Element, parts: &[(&str, Bytes)],
+               start: Partials, defaults: &HashMap<String, Bytes>)
+               -> Partials {
+        match *element {
+            Element::Rulename(ref name) => {
+                let default = defaults.get(&name.to_ascii_lowercase());
+                let mut res = Partials::new();
+                for (pos, data) in start {
+                    if let Some(&(rule, ref octets)) = parts.get(pos) {
+                        if rule.eq_ignore_ascii_case(name) {
+                            res.entry(pos + 1).or_insert_with(|| {
+                                extend(&data, octets)
+                            });
+                        }
+                    }
+                    if let Some(default) = default {
+                        res.entry(pos).or_insert_with(|| {
+                            extend(&data, default)
+                        });
+                    }
+                }
+                res
+            }
+            Element::Group(ref alternation) => {
+                self.alternation(alternation, parts, start, defaults)
+            }
+            Element::Optional(ref alternation) => {
+                let mut res = self.alternation(alternation, parts,
+                                               start.clone(), defaults);
+                // Leaving the optional part out is the shorter way.
+                res.extend(start);
+                res
+            }
+            Element::CharVal(ref value) => {
+                terminal(start, value.value.as_bytes())
+            }
+            Element::NumVal(ref value) => {
+                let octets = match value.kind {
+                    NumKind::Range(first, last) if first == last => {
+                        vec![first]
+                    }
+                    NumKind::Range(..) => return Partials::new(),
+                    NumKind::Concat(ref values) => values.clone()
+                };
+                if octets.iter().any(|&value| value > 255) {
+                    return Partials::new()
+                }
+                let octets: Vec<u8> = octets.into_iter().map(|value| {
+                    value as u8
+                }).collect();
+                terminal(start, &octets)
+            }
+            Element::ProseVal(_) => Partials::new()
+        }
+    }
+}
+
+/// Appends `octets` to all ways.
+fn terminal(start: Partials, octets: &[u8]) -> Partials {
+    start.into_iter().map(|(pos, mut data)| {
+        data.extend_from_slice(octets);
+        (pos, data)
+    }).collect()
+}
+
+/// Returns a copy of `data` with `octets` appended.
+fn extend(data: &BytesMut, octets: &[u8]) -> BytesMut {
+    let mut res = data.clone();
+    res.extend_from_slice(octets);
+    res
+}
+
+
+//------------ ComposeError --------------------------------------------------
+
+/// An error happened while composing a value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ComposeError {
+    /// A rule of the given name isn’t defined.
+    Undefined(String),
+
+    /// The octets given for the rule of the given name don’t match it.
+    Mismatch(String),
+
+    /// The values given for the rule of the given name don’t fit it.
+    Unrepresentable(String),
+
+    /// The value is nested too deeply.
+    LimitExceeded,
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GRAMMAR: &[u8] = b"\
+request = method SP target [ SP version ] CRLF
+method  = \"GET\" / \"PUT\"
+target  = 1*( \"/\" segment ) / \"/\"
+segment = 1*ALPHA
+version = \"HTTP/\" DIGIT \".\" DIGIT
+list    = \"(\" [ item *( \",\" item ) ] \")\"
+item    = 1*DIGIT / list
+";
+
+    fn rules() -> RuleList {
+        RuleList::from_slice(GRAMMAR).unwrap()
+    }
+
+    #[test]
+    fn children() {
+        let rules = rules();
+        let composer = Composer::new(&rules);
+        assert_eq!(
+            composer.compose(&Value::children("request", vec![
+                Value::octets("method", &b"PUT"[..]),
+                Value::children("target", vec![
+                    Value::octets("segment", &b"foo"[..]),
+                    Value::octets("segment", &b"bar"[..]),
+                ]),
+            ])),
+            Ok(Bytes::from_static(b"PUT /foo/bar\r\n"))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("request", vec![
+                Value::children("method", vec![]),
+                Value::children("target", vec![]),
+                Value::octets("version", &b"HTTP/1.1"[..]),
+            ])),
+            Ok(Bytes::from_static(b"GET / HTTP/1.1\r\n"))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("list", vec![
+                Value::octets("item", &b"1"[..]),
+                Value::children("item", vec![
+                    Value::children("list", vec![]),
+                ]),
+                Value::octets("ITEM", &b"23"[..]),
+            ])),
+            Ok(Bytes::from_static(b"(1,(),23)"))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("version", vec![
+                Value::octets("DIGIT", &b"2"[..]),
+                Value::octets("DIGIT", &b"0"[..]),
+            ])),
+            Ok(Bytes::from_static(b"HTTP/2.0"))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let rules = rules();
+        let composer = Composer::new(&rules);
+        assert_eq!(
+            composer.compose(&Value::octets("method", &b"POST"[..])),
+            Err(ComposeError::Mismatch("method".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::octets("segment", &b"foo/"[..])),
+            Err(ComposeError::Mismatch("segment".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("foo", vec![])),
+            Err(ComposeError::Undefined("foo".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::octets("foo", &b"foo"[..])),
+            Err(ComposeError::Undefined("foo".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("request", vec![
+                Value::children("target", vec![]),
+                Value::children("method", vec![]),
+            ])),
+            Err(ComposeError::Unrepresentable("request".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("target", vec![
+                Value::octets("method", &b"GET"[..]),
+            ])),
+            Err(ComposeError::Unrepresentable("target".into()))
+        );
+
+        // Ranges can only be produced from octets.
+        assert_eq!(
+            composer.compose(&Value::children("segment", vec![])),
+            Err(ComposeError::Unrepresentable("segment".into()))
+        );
+        assert_eq!(
+            composer.compose(&Value::children("version", vec![
+                Value::octets("DIGIT", &b"2"[..]),
+            ])),
+            Err(ComposeError::Unrepresentable("version".into()))
+        );
+
+        let mut composer = Composer::new(&rules);
+        composer.set_max_depth(2);
+        assert_eq!(
+            composer.compose(&Value::children("list", vec![
+                Value::children("item", vec![
+                    Value::children("list", vec![]),
+                ]),
+            ])),
+            Err(ComposeError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let rules = rules();
+        let composer = Composer::new(&rules);
+        let interp = Interpreter::new(&rules);
+        for data in &[&b"GET /\r\n"[..], b"PUT /a/bc HTTP/1.0\r\n",
+                      b"(1,(2,()),345)"] {
+            let name = if data[0] == b'(' { "list" } else { "request" };
+            let mut buf = Complete::new(BytesMut::from(*data));
+            let (_, node) = match interp.parse_tree(name, &mut buf) {
+                Ok(Async::Ready(res)) => res,
+                res => panic!("parsing {:?} resulted in {:?}", data, res)
+            };
+            assert_eq!(
+                composer.compose(&Value::from_node(&node, data)).unwrap(),
+                Bytes::copy_from_slice(data)
+            );
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Writing grammars in other notations.
+//!
+//! Grammars specified in ABNF sometimes need to be fed to tools that
+//! expect a different notation. This module converts a rule list into
+//! the EBNF used by W3C specifications such as XML via [`ebnf()`] and
+//! into a parsing expression grammar via [`peg()`].
+//!
+//! Neither notation knows case-insensitive strings or counted
+//! repetitions. Quoted strings containing letters are therefore written
+//! as a sequence of character classes for each letter, e.g., `[Aa]`, and
+//! a repetition `2*3x` becomes `x x x?`. Core rules of RFC 5234 that are
+//! referenced but not defined by the rule list are added at the end.
+//!
+//! The two notations differ in how they treat alternatives. Like ABNF,
+//! EBNF describes a language: any alternative may match. Parsing
+//! expressions instead are tried in order and the first one that matches
+//! wins, while repetitions are greedy. This is exactly the strategy of
+//! the [`Interpreter`], so the PEG accepts the same data as the
+//! interpreter. Rules relying on backtracking, like `*ALPHA "x"`, are
+//! converted as they are and won’t match in the PEG either.
+//!
+//! EBNF describes characters, so numeric values are kept as they are.
+//! The PEG describes octets just like the interpreter: values above 255
+//! never match and ranges are clamped to 255. Since rule names in a PEG
+//! can’t contain hyphens, these are replaced with underscores.
+//!
+//! Neither notation has a counterpart for prose values. They are written
+//! as a character class that never matches, followed by the prose as a
+//! comment in EBNF and as an empty character class in the PEG.
+//!
+//! [`ebnf()`]: fn.ebnf.html
+//! [`peg()`]: fn.peg.html
+//! [`Interpreter`]: ../interp/struct.Interpreter.html
+
+use std::collections::HashSet;
+use super::{Alternation, CharVal, Concatenation, Element, NumKind, NumVal,
+            Repetition, Rule, RuleList, CORE_RULES};
+use super::check::references;
+
+
+//------------ ebnf and peg --------------------------------------------------
+
+/// Writes a rule list in the EBNF notation of W3C specifications.
+///
+/// Each rule is written on a line of its own ending in a bare LF.
+pub fn ebnf(rules: &RuleList) -> String {
+    convert(rules, Notation::Ebnf)
+}
+
+/// Writes a rule list as a parsing expression grammar.
+///
+/// The grammar uses the syntax from Bryan Ford’s original paper. Each
+/// rule is written on a line of its own ending in a bare LF.
+pub fn peg(rules: &RuleList) -> String {
+    convert(rules, Notation::Peg)
+}
+
+/// Writes a rule list and the core rules it needs in a notation.
+fn convert(rules: &RuleList, notation: Notation) -> String {
+    let mut res = String::new();
+    for rule in rules {
+        notation.rule(rule, &mut res)
+    }
+    let core = used_core_rules(rules);
+    if !core.is_empty() {
+        res.push_str(notation.core_comment());
+        for rule in &core {
+            notation.rule(rule, &mut res)
+        }
+    }
+    res
+}
+
+/// Returns the core rules referenced but not defined by a rule list.
+///
+/// This includes the core rules referenced by those core rules. The
+/// rules are returned in the order of RFC 5234.
+fn used_core_rules(rules: &RuleList) -> Vec<Rule> {
+    let core = RuleList::from_slice(CORE_RULES).unwrap();
+    let mut names = Vec::new();
+    for rule in rules {
+        references(&rule.alternation, &mut names)
+    }
+    let mut used = HashSet::new();
+    while let Some(name) = names.pop() {
+        if rules.contains(&name) {
+            continue
+        }
+        if let Some(rule) = core.get(&name) {
+            if used.insert(rule.name.clone()) {
+                references(&rule.alternation, &mut names)
+            }
+        }
+    }
+    core.into_vec().into_iter().filter(|rule| {
+        used.contains(&rule.name)
+    }).collect()
+}
+
+
+//------------ Notation ------------------------------------------------------
+
+/// The notation to convert into.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Notation {
+    Ebnf,
+    Peg,
+}
+
+impl Notation {
+    /// Writes a rule on a line of its own.
+    fn rule(self, rule: &Rule, target: &mut String) {
+        let (name, define) = match self {
+            Notation::Ebnf => (rule.name.clone(), "::="),
+            Notation::Peg => (peg_name(&rule.name), "<-"),
+        };
+        target.push_str(&format!(
+            "{} {} {}\n", name, define, self.alternation(&rule.alternation)
+        ));
+    }
+
+    /// Returns the comment introducing the core rules.
+    fn core_comment(self) -> &'static str {
+        match self {
+            Notation::Ebnf => "/* Core rules of RFC 5234 */\n",
+            Notation::Peg => "# Core rules of RFC 5234\n",
+        }
+    }
+
+    fn alternation(self, alternation: &Alternation) -> String {
+        let separator = match self {
+            Notation::Ebnf => " | ",
+            Notation::Peg => " / ",
+        };
+        alternation.concatenations.iter().map(|concatenation| {
+            self.concatenation(concatenation)
+        }).collect::<Vec<_>>().join(separator)
+    }
+
+    fn concatenation(self, concatenation: &Concatenation) -> String {
+        concatenation.repetitions.iter().map(|repetition| {
+            self.repetition(repetition)
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    fn repetition(self, repetition: &Repetition) -> String {
+        let (element, atomic) = self.element(&repetition.element);
+        if repetition.min == 1 && repetition.max == Some(1) {
+            return element
+        }
+        if repetition.max == Some(0) {
+            return "\"\"".into()
+        }
+        let element = if atomic { element } else { format!("({})", element) };
+        let mut parts = Vec::new();
+        match repetition.max {
+            None => {
+                if repetition.min == 0 {
+                    parts.push(format!("{}*", element))
+                }
+                else {
+                    for _ in 1..repetition.min {
+                        parts.push(element.clone())
+                    }
+                    parts.push(format!("{}+", element))
+                }
+            }
+            Some(max) => {
+                for _ in 0..repetition.min {
+                    parts.push(element.clone())
+                }
+                for _ in repetition.min..max {
+                    parts.push(format!("{}?", element))
+                }
+            }
+        }
+        parts.join(" ")
+    }
+
+    /// Returns an element and whether it can take a suffix as it is.
+    fn element(self, element: &Element) -> (String, bool) {
+        match *element {
+            Element::Rulename(ref name) => match self {
+                Notation::Ebnf => (name.clone(), true),
+                Notation::Peg => (peg_name(name), true),
+            },
+            Element::Group(ref alternation) => {
+                (format!("({})", self.alternation(alternation)), true)
+            }
+            Element::Optional(ref alternation) => {
+                (format!("({})?", self.alternation(alternation)), true)
+            }
+            Element::CharVal(ref value) => self.char_val(value),
+            Element::NumVal(ref value) => self.num_val(value),
+            Element::ProseVal(ref text) => match self {
+                Notation::Ebnf => {
+                    (format!("[^#x0-#x10FFFF] /* {} */", text), false)
+                }
+                Notation::Peg => ("[]".into(), true),
+            },
+        }
+    }
+
+    fn char_val(self, value: &CharVal) -> (String, bool) {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        for ch in value.value.chars() {
+            if value.case_sensitive || !ch.is_ascii_alphabetic() {
+                literal.push(ch);
+                continue
+            }
+            if !literal.is_empty() {
+                parts.push(self.literal(&literal));
+                literal.clear();
+            }
+            parts.push(format!(
+                "[{}{}]", ch.to_ascii_uppercase(), ch.to_ascii_lowercase()
+            ));
+        }
+        if !literal.is_empty() || parts.is_empty() {
+            parts.push(self.literal(&literal));
+        }
+        (parts.join(" "), parts.len() == 1)
+    }
+
+    fn literal(self, value: &str) -> String {
+        match self {
+            Notation::Ebnf => format!("\"{}\"", value),
+            Notation::Peg => {
+                format!("\"{}\"", value.bytes().map(|ch| {
+                    peg_octet(ch, false)
+                }).collect::<String>())
+            }
+        }
+    }
+
+    fn num_val(self, value: &NumVal) -> (String, bool) {
+        match (self, &value.kind) {
+            (Notation::Ebnf, &NumKind::Range(first, last)) => {
+                (format!("[#x{:X}-#x{:X}]", first, last), true)
+            }
+            (Notation::Ebnf, NumKind::Concat(values)) => {
+                let parts = values.iter().map(|value| {
+                    format!("#x{:X}", value)
+                }).collect::<Vec<_>>();
+                (parts.join(" "), parts.len() == 1)
+            }
+            (Notation::Peg, &NumKind::Range(first, last)) => {
+                if first > 255 {
+                    ("[]".into(), true)
+                }
+                else if first == 0 && last >= 255 {
+                    (".".into(), true)
+                }
+                else {
+                    (format!("[{}-{}]", peg_octet(first as u8, true),
+                             peg_octet(last.min(255) as u8, true)), true)
+                }
+            }
+            (Notation::Peg, NumKind::Concat(values)) => {
+                if values.iter().any(|&value| value > 255) {
+                    return ("[]".into(), true)
+                }
+                let literal = values.iter().map(|&value| {
+                    peg_octet(value as u8, false)
+                }).collect::<String>();
+                (format!("\"{}\"", literal), true)
+            }
+        }
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Returns a rule name as a PEG identifier.
+fn peg_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+/// Returns an octet for use in a PEG literal or character class.
+///
+/// Printable ASCII characters without a special meaning in a literal or,
+/// if `class` is `true`, a character class are kept. All others are
+/// written as octal escapes.
+fn peg_octet(ch: u8, class: bool) -> String {
+    let special = match ch {
+        b'\\' => true,
+        b'"' => !class,
+        b'[' | b']' | b'-' => class,
+        0x20..=0x7E => false,
+        _ => true
+    };
+    if special {
+        format!("\\{:03o}", ch)
+    }
+    else {
+        char::from(ch).to_string()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GRAMMAR: &[u8] = b"\
+greeting = (%s\"HELO\" / \"ehlo\") SP domain [SP] CRLF
+domain = label *(\".\" label)
+label = 1*3(ALPHA / %x30-39 / \"-\") 2*label-char
+label-char = %d0-300 / %x100 / %x5C.22 / <other> / 0*0\"a1\"
+";
+
+    #[test]
+    fn ebnf() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        assert_eq!(
+            super::ebnf(&rules),
+            "greeting ::= (\"HELO\" | [Ee] [Hh] [Ll] [Oo]) SP domain (SP)? \
+                          CRLF\n\
+             domain ::= label (\".\" label)*\n\
+             label ::= (ALPHA | [#x30-#x39] | \"-\") \
+                       (ALPHA | [#x30-#x39] | \"-\")? \
+                       (ALPHA | [#x30-#x39] | \"-\")? \
+                       label-char label-char+\n\
+             label-char ::= [#x0-#x12C] | #x100 | #x5C #x22 \
+                            | [^#x0-#x10FFFF] /* other */ | \"\"\n\
+             /* Core rules of RFC 5234 */\n\
+             ALPHA ::= [#x41-#x5A] | [#x61-#x7A]\n\
+             CR ::= #xD\n\
+             CRLF ::= CR LF\n\
+             LF ::= #xA\n\
+             SP ::= #x20\n"
+        );
+    }
+
+    #[test]
+    fn peg() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        assert_eq!(
+            super::peg(&rules),
+            "greeting <- (\"HELO\" / [Ee] [Hh] [Ll] [Oo]) SP domain (SP)? \
+                          CRLF\n\
+             domain <- label (\".\" label)*\n\
+             label <- (ALPHA / [0-9] / \"-\") \
+                      (ALPHA / [0-9] / \"-\")? \
+                      (ALPHA / [0-9] / \"-\")? \
+                      label_char label_char+\n\
+             label_char <- . / [] / \"\\134\\042\" / [] / \"\"\n\
+             # Core rules of RFC 5234\n\
+             ALPHA <- [A-Z] / [a-z]\n\
+             CR <- \"\\015\"\n\
+             CRLF <- CR LF\n\
+             LF <- \"\\012\"\n\
+             SP <- \" \"\n"
+        );
+    }
+
+    #[test]
+    fn char_val() {
+        let value = CharVal { value: "a1b".into(), case_sensitive: false };
+        assert_eq!(Notation::Ebnf.char_val(&value),
+                   ("[Aa] \"1\" [Bb]".into(), false));
+        let value = CharVal { value: "a1".into(), case_sensitive: true };
+        assert_eq!(Notation::Peg.char_val(&value), ("\"a1\"".into(), true));
+        let value = CharVal { value: "".into(), case_sensitive: false };
+        assert_eq!(Notation::Ebnf.char_val(&value), ("\"\"".into(), true));
+    }
+}
@@ -0,0 +1,559 @@
+//! Compiling rules into deterministic finite automata.
+//!
+//! Rules that only consist of terminals – directly or through references
+//! to other such rules – describe a regular language. A [`Dfa`] compiled
+//! from such a rule matches it by looking up each octet in a transition
+//! table instead of walking the grammar or calling closures. This makes it
+//! a good fit for hot token rules such as a sequence of `atext` or the
+//! HTTP `token`.
+//!
+//! Rules are compiled via a nondeterministic automaton and the subset
+//! construction. Rules referencing themselves, directly or indirectly,
+//! can’t be compiled, nor can prose values. References are resolved
+//! against the rule list first and then the core rules.
+//!
+//! A DFA matches the longest prefix of the data that conforms to the rule.
+//! This differs from the interpreter which takes the first alternative
+//! that matches and repeats elements as often as possible without ever
+//! reconsidering. For instance, the rule `*ALPHA "x"` never matches in
+//! the interpreter since the repetition already consumes the final `x`,
+//! while the DFA matches `"abx"`. For the typical rules DFAs are useful
+//! for, both are the same.
+//!
+//! As in the interpreter, numeric values above 255 never match, and the
+//! upper end of a range is limited to 255.
+//!
+//! [`Dfa`]: struct.Dfa.html
+
+use std::collections::HashMap;
+use bytes::Bytes;
+use ::{Async, Poll};
+use ::parse::buffer::Buffer;
+use ::parse::token::{self, Token, TokenError};
+use super::{Alternation, Element, NumKind, Repetition, RuleList,
+            CORE_RULES};
+
+
+//------------ Dfa -----------------------------------------------------------
+
+/// A deterministic finite automaton matching a rule.
+#[derive(Clone, Debug)]
+pub struct Dfa {
+    /// The transitions.
+    ///
+    /// For each state, there are 256 entries with the next state for each
+    /// octet value. State 0 is the start state.
+    table: Vec<usize>,
+
+    /// Whether each state is accepting.
+    accepting: Vec<bool>,
+
+    /// Whether each state has transitions that stay in the automaton.
+    exits: Vec<bool>,
+}
+
+/// The value in the transition table for leaving the automaton.
+const DEAD: usize = usize::MAX;
+
+/// The maximum number of states of the nondeterministic automaton.
+const MAX_NFA_STATES: usize = 65536;
+
+/// The maximum number of states of a DFA.
+const MAX_DFA_STATES: usize = 4096;
+
+impl Dfa {
+    /// Compiles the rule `name` of a rule list.
+    pub fn compile(rules: &RuleList, name: &str) -> Result<Self, DfaError> {
+        let core = RuleList::from_slice(CORE_RULES).unwrap();
+        let mut nfa = Nfa::new(rules, &core);
+        let (start, end) = nfa.rulename(name)?;
+        nfa.accept = end;
+        nfa.into_dfa(start)
+    }
+
+    /// Returns the number of states of the automaton.
+    pub fn len(&self) -> usize {
+        self.accepting.len()
+    }
+
+    /// Returns whether the automaton has no states.
+    ///
+    /// This is never the case as there always is a start state.
+    pub fn is_empty(&self) -> bool {
+        self.accepting.is_empty()
+    }
+
+    /// Returns the length of the longest prefix of `data` matching.
+    ///
+    /// The data is considered complete. Returns `None` if not even an
+    /// empty prefix matches.
+    pub fn longest_match(&self, data: &[u8]) -> Option<usize> {
+        let mut res = if self.accepting[0] { Some(0) } else { None };
+        let mut state = 0;
+        for (i, &ch) in data.iter().enumerate() {
+            state = self.table[state * 256 + ch as usize];
+            if state == DEAD {
+                break
+            }
+            if self.accepting[state] {
+                res = Some(i + 1)
+            }
+        }
+        res
+    }
+
+    /// Matches the rule as a token rule.
+    ///
+    /// Returns non-ready if the end of the buffer is reached before the
+    /// automaton knows that the match can’t get any longer. This is known
+    /// at the end of final input and in a state that has no transitions
+    /// staying in the automaton.
+    pub fn token<B: Buffer>(&self, token: &mut Token<B>)
+                            -> Poll<(), TokenError> {
+        let mut res = if self.accepting[0] { Some(0) } else { None };
+        let mut state = 0;
+        let mut len = 0;
+        while self.exits[state] {
+            let ch = match token.peek(len) {
+                Some(ch) => ch,
+                None if token.is_final() => break,
+                None => {
+                    token.need(len + 1);
+                    return Ok(Async::NotReady)
+                }
+            };
+            state = self.table[state * 256 + ch as usize];
+            if state == DEAD {
+                break
+            }
+            len += 1;
+            if self.accepting[state] {
+                res = Some(len)
+            }
+        }
+        match res {
+            Some(len) => {
+                token.advance(len);
+                Ok(Async::Ready(()))
+            }
+            None => Err(TokenError::Invalid)
+        }
+    }
+
+    /// Parses the rule from the beginning of a buffer.
+    pub fn parse<B: Buffer>(&self, buf: &mut B) -> Poll<Bytes, TokenError> {
+        token::parse(buf, |token| self.token(token))
+    }
+
+    /// Skips over the rule at the beginning of a buffer.
+    pub fn skip<B: Buffer>(&self, buf: &mut B) -> Poll<(), TokenError> {
+        token::skip(buf, |token| self.token(token))
+    }
+}
+
+
+//------------ Nfa -----------------------------------------------------------
+
+/// A nondeterministic automaton under construction.
+///
+/// Each part of the grammar is translated into a fragment with a start
+/// and an end state. The end state has no transitions until it is
+/// connected to the next fragment.
+struct Nfa<'a> {
+    rules: &'a RuleList,
+    core: &'a RuleList,
+
+    /// The states.
+    states: Vec<State>,
+
+    /// The accepting state.
+    accept: usize,
+
+    /// The lower-case names of the rules currently being translated.
+    stack: Vec<String>,
+}
+
+/// A state of the nondeterministic automaton.
+#[derive(Default)]
+struct State {
+    /// The states reachable without consuming an octet.
+    empty: Vec<usize>,
+
+    /// The octets consumed for moving to the second element.
+    octets: Option<(OctetSet, usize)>,
+}
+
+/// A fragment of the automaton given by its start and end states.
+type Fragment = (usize, usize);
+
+impl<'a> Nfa<'a> {
+    fn new(rules: &'a RuleList, core: &'a RuleList) -> Self {
+        Nfa {
+            rules,
+            core,
+            states: Vec::new(),
+            accept: 0,
+            stack: Vec::new(),
+        }
+    }
+
+    fn state(&mut self) -> Result<usize, DfaError> {
+        if self.states.len() == MAX_NFA_STATES {
+            return Err(DfaError::TooLarge)
+        }
+        self.states.push(State::default());
+        Ok(self.states.len() - 1)
+    }
+
+    fn connect(&mut self, from: usize, to: usize) {
+        self.states[from].empty.push(to)
+    }
+
+    fn octets(&mut self, set: OctetSet) -> Result<Fragment, DfaError> {
+        let start = self.state()?;
+        let end = self.state()?;
+        self.states[start].octets = Some((set, end));
+        Ok((start, end))
+    }
+
+    fn rulename(&mut self, name: &str) -> Result<Fragment, DfaError> {
+        let key = name.to_ascii_lowercase();
+        if self.stack.contains(&key) {
+            return Err(DfaError::Recursive(name.into()))
+        }
+        let (rules, core) = (self.rules, self.core);
+        let rule = match rules.get(name).or_else(|| core.get(name)) {
+            Some(rule) => rule,
+            None => return Err(DfaError::Undefined(name.into()))
+        };
+        self.stack.push(key);
+        let res = self.alternation(&rule.alternation);
+        self.stack.pop();
+        res
+    }
+
+    fn alternation(&mut self, alternation: &Alternation)
+                   -> Result<Fragment, DfaError> {
+        let start = self.state()?;
+        let end = self.state()?;
+        for concatenation in &alternation.concatenations {
+            let mut last = start;
+            for repetition in &concatenation.repetitions {
+                let (item_start, item_end) = self.repetition(repetition)?;
+                self.connect(last, item_start);
+                last = item_end;
+            }
+            self.connect(last, end);
+        }
+        Ok((start, end))
+    }
+
+    fn repetition(&mut self, repetition: &Repetition)
+                  -> Result<Fragment, DfaError> {
+        let start = self.state()?;
+        let end = self.state()?;
+        let mut last = start;
+        for _ in 0..repetition.min {
+            let (item_start, item_end) = self.element(&repetition.element)?;
+            self.connect(last, item_start);
+            last = item_end;
+        }
+        match repetition.max {
+            None => {
+                let (item_start, item_end) = self.element(
+                    &repetition.element
+                )?;
+                self.connect(last, item_start);
+                self.connect(item_end, last);
+            }
+            Some(max) => {
+                for _ in repetition.min..max {
+                    let (item_start, item_end) = self.element(
+                        &repetition.element
+                    )?;
+                    self.connect(last, item_start);
+                    self.connect(last, end);
+                    last = item_end;
+                }
+            }
+        }
+        self.connect(last, end);
+        Ok((start, end))
+    }
+
+    fn element(&mut self, element: &Element) -> Result<Fragment, DfaError> {
+        match *element {
+            Element::Rulename(ref name) => self.rulename(name),
+            Element::Group(ref alternation) => self.alternation(alternation),
+            Element::Optional(ref alternation) => {
+                let (start, end) = self.alternation(alternation)?;
+                self.connect(start, end);
+                Ok((start, end))
+            }
+            Element::CharVal(ref value) => {
+                let sets = value.value.bytes().map(|ch| {
+                    let mut set = OctetSet::new();
+                    set.insert(ch);
+                    if !value.case_sensitive {
+                        set.insert(ch.to_ascii_lowercase());
+                        set.insert(ch.to_ascii_uppercase());
+                    }
+                    set
+                }).collect();
+                self.sequence(sets)
+            }
+            Element::NumVal(ref value) => match value.kind {
+                NumKind::Range(first, last) => {
+                    let mut set = OctetSet::new();
+                    for ch in first..=last.min(0xFF) {
+                        set.insert(ch as u8)
+                    }
+                    self.octets(set)
+                }
+                NumKind::Concat(ref values) => {
+                    let sets = values.iter().map(|&value| {
+                        let mut set = OctetSet::new();
+                        if value <= 0xFF {
+                            set.insert(value as u8)
+                        }
+                        set
+                    }).collect();
+                    self.sequence(sets)
+                }
+            },
+            Element::ProseVal(ref text) => {
+                Err(DfaError::Prose(text.clone()))
+            }
+        }
+    }
+
+    /// Returns a fragment consuming one octet from each set in order.
+    fn sequence(&mut self, sets: Vec<OctetSet>)
+                -> Result<Fragment, DfaError> {
+        let start = self.state()?;
+        let mut last = start;
+        for set in sets {
+            let (item_start, item_end) = self.octets(set)?;
+            self.connect(last, item_start);
+            last = item_end;
+        }
+        Ok((start, last))
+    }
+
+    /// Adds the states reachable from `state` without consuming octets.
+    fn closure(&self, state: usize, set: &mut Vec<bool>) {
+        if set[state] {
+            return
+        }
+        set[state] = true;
+        for &target in &self.states[state].empty {
+            self.closure(target, set)
+        }
+    }
+
+    /// Performs the subset construction.
+    fn into_dfa(self, start: usize) -> Result<Dfa, DfaError> {
+        let mut first = vec![false; self.states.len()];
+        self.closure(start, &mut first);
+        let first = members(&first);
+
+        let mut index = HashMap::new();
+        index.insert(first.clone(), 0);
+        let mut subsets = vec![first];
+        let mut res = Dfa {
+            table: Vec::new(), accepting: Vec::new(), exits: Vec::new()
+        };
+        let mut next = 0;
+        while next < subsets.len() {
+            let subset = subsets[next].clone();
+            res.accepting.push(subset.contains(&self.accept));
+            res.exits.push(false);
+            for ch in 0..256 {
+                let mut target = vec![false; self.states.len()];
+                for &state in &subset {
+                    if let Some((ref set, to)) = self.states[state].octets {
+                        if set.contains(ch as u8) {
+                            self.closure(to, &mut target)
+                        }
+                    }
+                }
+                let target = members(&target);
+                if target.is_empty() {
+                    res.table.push(DEAD);
+                    continue
+                }
+                res.exits[next] = true;
+                let id = match index.get(&target) {
+                    Some(&id) => id,
+                    None => {
+                        if subsets.len() == MAX_DFA_STATES {
+                            return Err(DfaError::TooLarge)
+                        }
+                        index.insert(target.clone(), subsets.len());
+                        subsets.push(target);
+                        subsets.len() - 1
+                    }
+                };
+                res.table.push(id);
+            }
+            next += 1;
+        }
+        Ok(res)
+    }
+}
+
+/// Returns the indexes of all set flags.
+fn members(set: &[bool]) -> Vec<usize> {
+    set.iter().enumerate().filter(|&(_, &member)| member)
+       .map(|(i, _)| i).collect()
+}
+
+
+//------------ OctetSet ------------------------------------------------------
+
+/// A set of octets.
+#[derive(Clone, Default)]
+struct OctetSet([u64; 4]);
+
+impl OctetSet {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(&mut self, ch: u8) {
+        self.0[(ch >> 6) as usize] |= 1 << (ch & 0x3F)
+    }
+
+    fn contains(&self, ch: u8) -> bool {
+        self.0[(ch >> 6) as usize] & (1 << (ch & 0x3F)) != 0
+    }
+}
+
+
+//------------ DfaError ------------------------------------------------------
+
+/// An error happened while compiling a rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DfaError {
+    /// A rule of the given name isn’t defined.
+    Undefined(String),
+
+    /// The rule of the given name references itself.
+    Recursive(String),
+
+    /// The rule contains the given prose value.
+    Prose(String),
+
+    /// The automaton would be too large.
+    TooLarge,
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::BytesMut;
+    use ::parse::sync;
+
+    fn compile(grammar: &[u8], name: &str) -> Dfa {
+        Dfa::compile(&RuleList::from_slice(grammar).unwrap(), name).unwrap()
+    }
+
+    fn parse_final(dfa: &Dfa, data: &[u8])
+                   -> Result<(Bytes, usize), TokenError> {
+        sync::parse(data, |buf: &mut sync::Input| dfa.parse(buf))
+    }
+
+    #[test]
+    fn longest_match() {
+        let dfa = compile(
+            b"token = 1*tchar\n\
+              tchar = \"!\" / %x23-27 / \"*\" / \"+\" / \"-\" / \".\"\n\
+              \x20     / %x5E-60 / \"|\" / \"~\" / DIGIT / ALPHA\n",
+            "token"
+        );
+        assert_eq!(dfa.longest_match(b"Content-Type: x"), Some(12));
+        assert_eq!(dfa.longest_match(b": x"), None);
+        assert_eq!(dfa.longest_match(b""), None);
+
+        let dfa = compile(b"a = *ALPHA %s\"x\" [\"yz\"]\n", "a");
+        assert_eq!(dfa.longest_match(b"abxY"), Some(3));
+        assert_eq!(dfa.longest_match(b"abxYz"), Some(5));
+        assert_eq!(dfa.longest_match(b"abXYz"), None);
+
+        let dfa = compile(b"a = 2*3%x30-39 / %d97.98 / %x100\n", "a");
+        assert_eq!(dfa.longest_match(b"12345"), Some(3));
+        assert_eq!(dfa.longest_match(b"1"), None);
+        assert_eq!(dfa.longest_match(b"abc"), Some(2));
+        assert_eq!(dfa.longest_match(b"AB"), None);
+
+        let dfa = compile(b"a = *\"x\"\n", "a");
+        assert_eq!(dfa.longest_match(b"y"), Some(0));
+    }
+
+    #[test]
+    fn token() {
+        let dfa = compile(b"a = 1*DIGIT \".\" 1*DIGIT\n", "a");
+        let mut buf = BytesMut::from(&b"12.34 "[..]);
+        assert_eq!(dfa.parse(&mut buf).unwrap(),
+                   Async::Ready(Bytes::from_static(b"12.34")));
+        assert_eq!(&buf[..], b" ");
+
+        let mut buf = BytesMut::from(&b"12.34"[..]);
+        assert_eq!(dfa.skip(&mut buf), Ok(Async::NotReady));
+        assert_eq!(&buf[..], b"12.34");
+
+        let mut buf = BytesMut::from(&b"12. "[..]);
+        assert_eq!(dfa.skip(&mut buf), Err(TokenError::Invalid));
+        assert_eq!(&buf[..], b"12. ");
+    }
+
+    #[test]
+    fn final_input() {
+        let dfa = compile(b"tok = 1*ALPHA\n", "tok");
+        assert_eq!(parse_final(&dfa, b"abc"),
+                   Ok((Bytes::from_static(b"abc"), 3)));
+        assert_eq!(parse_final(&dfa, b""),
+                   Err(TokenError::Invalid));
+
+        let dfa = compile(b"a = 1*DIGIT \".\" 1*DIGIT\n", "a");
+        assert_eq!(parse_final(&dfa, b"12."),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn dead_end() {
+        let dfa = compile(b"lit = \"abc\"\n", "lit");
+        let mut buf = BytesMut::from(&b"abc"[..]);
+        assert_eq!(dfa.parse(&mut buf),
+                   Ok(Async::Ready(Bytes::from_static(b"abc"))));
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(&b"ab"[..]);
+        assert_eq!(dfa.parse(&mut buf), Ok(Async::NotReady));
+
+        let dfa = compile(b"a = \"x\" [\"y\"]\n", "a");
+        let mut buf = BytesMut::from(&b"xy"[..]);
+        assert_eq!(dfa.skip(&mut buf), Ok(Async::Ready(())));
+        let mut buf = BytesMut::from(&b"x"[..]);
+        assert_eq!(dfa.skip(&mut buf), Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn errors() {
+        let rules = RuleList::from_slice(
+            b"a = \"x\" b\nb = [a]\nc = d\nd = <prose>\ne = 300*300(f)\n\
+              f = 200\"x\"\n"
+        ).unwrap();
+        assert_eq!(Dfa::compile(&rules, "a").unwrap_err(),
+                   DfaError::Recursive("a".into()));
+        assert_eq!(Dfa::compile(&rules, "x").unwrap_err(),
+                   DfaError::Undefined("x".into()));
+        assert_eq!(Dfa::compile(&rules, "c").unwrap_err(),
+                   DfaError::Prose("prose".into()));
+        assert_eq!(Dfa::compile(&rules, "e").unwrap_err(),
+                   DfaError::TooLarge);
+    }
+}
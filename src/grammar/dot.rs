@@ -0,0 +1,130 @@
+//! Writing grammars as Graphviz graphs.
+//!
+//! The function [`dot()`] turns a rule list into a directed graph in the
+//! DOT language of Graphviz. Each rule becomes a node with an edge to
+//! every rule it references. Rendered, for instance via `dot -Tsvg`, this
+//! gives an overview of how the rules of a grammar depend on each other
+//! for use in documentation.
+//!
+//! Rules defined in the rule list are drawn as boxes with their ABNF
+//! definition as the tooltip. References to core rules are drawn as
+//! ellipses, references to rules that aren’t defined anywhere as red
+//! ellipses.
+//!
+//! [`dot()`]: fn.dot.html
+
+use std::collections::HashSet;
+use super::RuleList;
+use super::check::references;
+use super::interp::is_core_rule;
+
+
+//------------ dot -----------------------------------------------------------
+
+/// Writes a rule list as a graph in the DOT language.
+///
+/// Nodes and edges appear in the order the rules are defined in. Lines
+/// end in a bare LF.
+pub fn dot(rules: &RuleList) -> String {
+    let mut res = String::from("digraph grammar {\n    node [shape=box];\n");
+    let mut edges = String::new();
+    let mut external = Vec::<String>::new();
+    for rule in rules {
+        res.push_str(&format!(
+            "    {} [tooltip={}];\n",
+            quote(&rule.name), quote(&rule.to_string())
+        ));
+        let mut names = Vec::new();
+        references(&rule.alternation, &mut names);
+        let mut seen = HashSet::new();
+        for name in names {
+            if !seen.insert(name.to_ascii_lowercase()) {
+                continue
+            }
+            let target = match rules.get(&name) {
+                Some(target) => target.name.clone(),
+                None => {
+                    if !external.iter().any(|item| {
+                        item.eq_ignore_ascii_case(&name)
+                    }) {
+                        external.push(name.clone())
+                    }
+                    name
+                }
+            };
+            edges.push_str(&format!(
+                "    {} -> {};\n", quote(&rule.name), quote(&target)
+            ));
+        }
+    }
+    for name in external {
+        if is_core_rule(&name) {
+            res.push_str(&format!("    {} [shape=ellipse];\n", quote(&name)))
+        }
+        else {
+            res.push_str(&format!(
+                "    {} [shape=ellipse, color=red];\n", quote(&name)
+            ))
+        }
+    }
+    res.push_str(&edges);
+    res.push_str("}\n");
+    res
+}
+
+/// Returns a string as a quoted DOT identifier.
+fn quote(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for ch in s.chars() {
+        if ch == '"' || ch == '\\' {
+            res.push('\\')
+        }
+        res.push(ch)
+    }
+    res.push('"');
+    res
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dot() {
+        let rules = RuleList::from_slice(
+            b"greeting = \"HELO\" SP domain [SP Domain] CRLF\n\
+              domain = label *(\".\" label)\n\
+              label = 1*ALPHA / other\n"
+        ).unwrap();
+        assert_eq!(
+            super::dot(&rules),
+            "digraph grammar {\n\
+             \x20   node [shape=box];\n\
+             \x20   \"greeting\" [tooltip=\"greeting = \\\"HELO\\\" SP \
+                    domain [SP Domain] CRLF\"];\n\
+             \x20   \"domain\" [tooltip=\"domain = label *(\\\".\\\" \
+                    label)\"];\n\
+             \x20   \"label\" [tooltip=\"label = 1*ALPHA / other\"];\n\
+             \x20   \"SP\" [shape=ellipse];\n\
+             \x20   \"CRLF\" [shape=ellipse];\n\
+             \x20   \"ALPHA\" [shape=ellipse];\n\
+             \x20   \"other\" [shape=ellipse, color=red];\n\
+             \x20   \"greeting\" -> \"SP\";\n\
+             \x20   \"greeting\" -> \"domain\";\n\
+             \x20   \"greeting\" -> \"CRLF\";\n\
+             \x20   \"domain\" -> \"label\";\n\
+             \x20   \"label\" -> \"ALPHA\";\n\
+             \x20   \"label\" -> \"other\";\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(super::quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}
@@ -0,0 +1,255 @@
+//! Writing grammars as ABNF.
+//!
+//! The types of the grammar AST implement `Display`, producing ABNF on a
+//! single line. The function [`format()`] writes an entire rule list in
+//! a normalized form suitable for comparing different revisions of a
+//! grammar:
+//!
+//! * Rules appear in the order they were defined in with all
+//!   incremental alternatives merged into the rule.
+//! * The equals signs of all rules are aligned.
+//! * Elements are separated by a single space, alternatives by a slash
+//!   surrounded by spaces.
+//! * Repetitions are written in their shortest form, quoted strings
+//!   without the redundant `%i` prefix, and hexadecimal values in upper
+//!   case.
+//! * Comments are dropped.
+//!
+//! Rules that don’t fit into the line width are folded. Each alternative
+//! is placed on a line of its own, starting with the slash aligned below
+//! the equals sign. Alternatives that still don’t fit are broken between
+//! their elements.
+//!
+//! [`format()`]: fn.format.html
+
+use std::fmt;
+use super::{Alternation, Base, CharVal, Concatenation, Element, NumKind,
+            NumVal, Repetition, Rule, RuleList};
+
+
+//------------ format --------------------------------------------------------
+
+/// Writes a rule list as normalized ABNF.
+///
+/// Rules are folded if they are longer than `width` characters. Lines
+/// end in a bare LF.
+pub fn format(rules: &RuleList, width: usize) -> String {
+    let name_width = rules.iter().map(|rule| rule.name.len()).max()
+                          .unwrap_or(0);
+    let mut res = String::new();
+    for rule in rules {
+        format_rule(rule, name_width, width, &mut res)
+    }
+    res
+}
+
+/// Writes a single rule with its name padded to `name_width`.
+fn format_rule(rule: &Rule, name_width: usize, width: usize,
+               target: &mut String) {
+    let prefix = format!("{:<width$} = ", rule.name, width = name_width);
+    let line = format!("{}{}", prefix, rule.alternation);
+    if line.len() <= width {
+        target.push_str(&line);
+        target.push('\n');
+        return
+    }
+    let indent = prefix.len() - 2;
+    for (i, concatenation) in rule.alternation.concatenations.iter()
+                                  .enumerate() {
+        let start = if i == 0 {
+            prefix.clone()
+        }
+        else {
+            format!("{:indent$}/ ", "", indent = indent)
+        };
+        format_concatenation(concatenation, start, width, target);
+    }
+}
+
+/// Writes an alternative starting with `line`, folding it if necessary.
+fn format_concatenation(concatenation: &Concatenation, mut line: String,
+                        width: usize, target: &mut String) {
+    let indent = line.len();
+    for (i, repetition) in concatenation.repetitions.iter().enumerate() {
+        let item = repetition.to_string();
+        if i > 0 {
+            if line.len() + 1 + item.len() > width {
+                target.push_str(&line);
+                target.push('\n');
+                line = format!("{:indent$}", "", indent = indent);
+            }
+            else {
+                line.push(' ');
+            }
+        }
+        line.push_str(&item);
+    }
+    target.push_str(&line);
+    target.push('\n');
+}
+
+
+//------------ Display Implementations ---------------------------------------
+
+impl fmt::Display for RuleList {
+    /// Writes the rule list via `format()` with a width of 72.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format(self, 72))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.alternation)
+    }
+}
+
+impl fmt::Display for Alternation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, concatenation) in self.concatenations.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" / ")?;
+            }
+            write!(f, "{}", concatenation)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Concatenation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, repetition) in self.repetitions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", repetition)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Repetition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.min, self.max) {
+            (1, Some(1)) => { }
+            (min, Some(max)) if min == max => write!(f, "{}", min)?,
+            (min, max) => {
+                if min > 0 {
+                    write!(f, "{}", min)?;
+                }
+                f.write_str("*")?;
+                if let Some(max) = max {
+                    write!(f, "{}", max)?;
+                }
+            }
+        }
+        write!(f, "{}", self.element)
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Element::Rulename(ref name) => f.write_str(name),
+            Element::Group(ref alternation) => {
+                write!(f, "({})", alternation)
+            }
+            Element::Optional(ref alternation) => {
+                write!(f, "[{}]", alternation)
+            }
+            Element::CharVal(ref value) => write!(f, "{}", value),
+            Element::NumVal(ref value) => write!(f, "{}", value),
+            Element::ProseVal(ref text) => write!(f, "<{}>", text),
+        }
+    }
+}
+
+impl fmt::Display for CharVal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.case_sensitive {
+            f.write_str("%s")?;
+        }
+        write!(f, "\"{}\"", self.value)
+    }
+}
+
+impl fmt::Display for NumVal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_value = |f: &mut fmt::Formatter, value: u32| {
+            match self.base {
+                Base::Bin => write!(f, "{:b}", value),
+                Base::Dec => write!(f, "{}", value),
+                Base::Hex => write!(f, "{:02X}", value),
+            }
+        };
+        f.write_str(match self.base {
+            Base::Bin => "%b",
+            Base::Dec => "%d",
+            Base::Hex => "%x",
+        })?;
+        match self.kind {
+            NumKind::Range(first, last) => {
+                write_value(f, first)?;
+                f.write_str("-")?;
+                write_value(f, last)
+            }
+            NumKind::Concat(ref values) => {
+                for (i, &value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    write_value(f, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let rules = RuleList::from_slice(
+            b"greeting=  ( %i\"HELO\"/%s\"EHLO\" )   1*1SP domain\n\
+              domain   = label  0*(\".\" label) ; comment\n\
+              label    = 1*63( ALPHA/DIGIT/\"-\" ) [ <tail> ]\n\
+              nl       = %d13.10 / %x0a / %B1010\n\
+              hex      = %x0-1f\n\
+              domain   =/ \"[\" 2*2%x30-39 \"]\"\n"
+        ).unwrap();
+        assert_eq!(
+            format(&rules, 72),
+            "greeting = (\"HELO\" / %s\"EHLO\") SP domain\n\
+             domain   = label *(\".\" label) / \"[\" 2%x30-39 \"]\"\n\
+             label    = 1*63(ALPHA / DIGIT / \"-\") [<tail>]\n\
+             nl       = %d13.10 / %x0A / %b1010\n\
+             hex      = %x00-1F\n"
+        );
+        assert_eq!(RuleList::from_slice(rules.to_string().as_bytes()),
+                   Ok(rules));
+    }
+
+    #[test]
+    fn fold() {
+        let rules = RuleList::from_slice(
+            b"a = \"one\" \"two\" / \"three\" \"four\" \"five\" \"six\" / x\n\
+              b = \"short\"\n"
+        ).unwrap();
+        let text = format(&rules, 20);
+        assert_eq!(
+            text,
+            "a = \"one\" \"two\"\n  \
+               / \"three\" \"four\"\n    \
+                 \"five\" \"six\"\n  \
+               / x\n\
+             b = \"short\"\n"
+        );
+        assert_eq!(RuleList::from_slice(text.as_bytes()), Ok(rules));
+    }
+}
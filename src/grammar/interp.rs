@@ -0,0 +1,956 @@
+//! Applying a grammar to data.
+//!
+//! An [`Interpreter`] matches data against the rules of a [`RuleList`]
+//! without the need to write any code for the rules. It works on tokens
+//! just like the token functions of this crate and can therefore parse
+//! data that arrives piece by piece.
+//!
+//! Since ABNF only describes which sequences of octets are valid but not
+//! how to find them, the interpreter follows the same strategy as the
+//! hand-written parsers in this crate: alternatives are tried in order
+//! and the first one that matches wins. Repetitions are greedy and never
+//! give back what they have matched. This works for the vast majority of
+//! grammars found in RFCs but not for all of ABNF. For instance, the rule
+//! `*ALPHA "x"` will never match anything since the repetition swallows
+//! the final `x`.
+//!
+//! Alternations whose alternatives all start with terminals that can’t
+//! match the same first octet are treated specially. When creating the
+//! interpreter, a dispatch table is built for them that tells which
+//! alternative can match for each first octet. Only that alternative is
+//! tried then instead of trying each one in turn. The result is the same
+//! either way, only faster.
+//!
+//! Terminals are matched as follows. Quoted strings match the octets of
+//! the string ignoring ASCII case unless they are case-sensitive. Numeric
+//! values are compared to single octets. Values above 255 can never
+//! match. Ranges, however, are clamped to 255, so that a range reaching
+//! into Unicode like `%x80-10FFFF` accepts the octets of UTF-8 encoded
+//! characters without checking the encoding.
+//!
+//! References to the core rules of RFC 5234 are resolved to the
+//! respective functions of the [`core`] module unless the rule list
+//! contains rules of the same name. References to any other rule not in
+//! the rule list fail.
+//!
+//! Since alternatives are tried one after another, the same rule may be
+//! matched at the same position over and over again. For some grammars
+//! and data this takes exponential time. If this is a concern, the
+//! interpreter can remember the outcome of each rule at each position of
+//! the token – a technique known as packrat parsing – via
+//! `set_memoize()`. This costs memory for each rule and position tried.
+//!
+//! Besides only matching data, the interpreter can build a parse tree
+//! via `parse_tree()`. It consists of a [`Node`] for each rule matched,
+//! including core rules and rules replaced by a resolver, with the
+//! position of the match in the data and the nodes of the rules
+//! referenced by it. This makes it possible to inspect the structure of
+//! data without writing code for each rule.
+//!
+//! Prose values can’t be matched by the interpreter at all. Instead, a
+//! resolver – a closure advancing a token over the terminal – can be
+//! registered for the text of a prose value via `resolve_prose()`. Since
+//! prose often describes an entire rule, a resolver can also be
+//! registered for a rule name via `resolve_rule()`. It then replaces the
+//! rule’s definition. Prose values without a resolver never match.
+//! Parsers for buffers kept in a [`RuleRegistry`] can be used for rules
+//! of the same name via `resolve_registry()`.
+//!
+//! [`Interpreter`]: struct.Interpreter.html
+//! [`Node`]: struct.Node.html
+//! [`RuleList`]: ../struct.RuleList.html
+//! [`core`]: ../../core/index.html
+//! [`RuleRegistry`]: ../../parse/registry/struct.RuleRegistry.html
+
+use std::{fmt, mem};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use bytes::{Bytes, BytesMut};
+use futures::{Async, Poll};
+use ::core;
+use ::parse::token;
+use ::parse::buffer::Buffer;
+use ::parse::registry::RuleRegistry;
+use ::parse::token::{Token, TokenError};
+use super::{Alternation, CharVal, Concatenation, Element, NumKind, NumVal,
+            Repetition, Rule, RuleList};
+use super::visit::{walk_alternation, Visitor};
+
+
+//------------ Resolver ------------------------------------------------------
+
+/// A closure advancing a token over a terminal.
+pub type Resolver<'a, B> = Box<dyn Fn(&mut Token<B>) -> Poll<(), TokenError>
+                                   + 'a>;
+
+
+//------------ Memo ----------------------------------------------------------
+
+/// The outcome of rules by lower-case rule name and start position.
+///
+/// The value is the end position and, if a tree is being built, the child
+/// nodes if the rule matched or `None` if it didn’t.
+type Memo = HashMap<(String, usize), Option<(usize, Vec<Node>)>>;
+
+
+//------------ Interpreter ---------------------------------------------------
+
+/// Matches data in buffers of type `B` against the rules of a rule list.
+pub struct Interpreter<'a, B = BytesMut> {
+    /// The rules by their lower-case names.
+    rules: HashMap<String, &'a Rule>,
+
+    /// The resolvers for prose values by their text.
+    prose: HashMap<String, Resolver<'a, B>>,
+
+    /// The resolvers replacing rules by the lower-case rule names.
+    overrides: HashMap<String, Resolver<'a, B>>,
+
+    /// The maximum number of nested rule references.
+    max_depth: usize,
+
+    /// The memo table if memoization is enabled.
+    memo: Option<RefCell<Memo>>,
+
+    /// The nodes of the rule currently matched if building a tree.
+    nodes: RefCell<Option<Vec<Node>>>,
+
+    /// The dispatch tables by the address of their alternation.
+    dispatch: HashMap<*const Alternation, DispatchTable>,
+}
+
+impl<'a, B: Buffer> Interpreter<'a, B> {
+    /// Creates a new interpreter for the given rules.
+    pub fn new(rules: &'a RuleList) -> Self {
+        Interpreter {
+            rules: rules.iter().map(|rule| {
+                (rule.name.to_ascii_lowercase(), rule)
+            }).collect(),
+            prose: HashMap::new(),
+            overrides: HashMap::new(),
+            max_depth: 128,
+            memo: None,
+            nodes: RefCell::new(None),
+            dispatch: DispatchTables::collect(rules),
+        }
+    }
+
+    /// Sets the maximum number of nested rule references.
+    ///
+    /// If matching a rule would need to descend deeper, it fails with
+    /// `TokenError::LimitExceeded`. This protects against running out of
+    /// stack on left-recursive rules or deeply nested data. The default
+    /// is 128.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth
+    }
+
+    /// Sets whether to remember the outcome of rules.
+    ///
+    /// If enabled, the interpreter remembers for each rule and position
+    /// whether and how far the rule matched during one call to `rule()`,
+    /// `parse()`, or `skip()`. Only the rules of the rule list are
+    /// remembered, neither core rules nor resolvers. Memoization is
+    /// disabled by default.
+    pub fn set_memoize(&mut self, enable: bool) {
+        self.memo = if enable { Some(RefCell::default()) } else { None }
+    }
+
+    /// Registers a resolver for prose values with the text `prose`.
+    ///
+    /// The text is given without the angle brackets and has to match
+    /// exactly. A resolver previously registered for the same text is
+    /// replaced.
+    pub fn resolve_prose<F>(&mut self, prose: &str, resolver: F)
+                         where F: Fn(&mut Token<B>) -> Poll<(), TokenError>
+                                  + 'a {
+        self.prose.insert(prose.into(), Box::new(resolver));
+    }
+
+    /// Registers a resolver replacing the rule named `name`.
+    ///
+    /// The resolver is used instead of the rule’s definition in the rule
+    /// list or the core rule of that name. The rule doesn’t need to be
+    /// defined at all. A resolver previously registered for the same name
+    /// is replaced.
+    pub fn resolve_rule<F>(&mut self, name: &str, resolver: F)
+                        where F: Fn(&mut Token<B>) -> Poll<(), TokenError>
+                                 + 'a {
+        self.overrides.insert(name.to_ascii_lowercase(), Box::new(resolver));
+    }
+
+    /// Registers the parsers of a registry as resolvers for rules.
+    ///
+    /// Each parser currently registered in `registry` replaces the rule
+    /// of the same name as if registered via `resolve_rule()`. Since the
+    /// parsers work on buffers rather than tokens, they are run on a copy
+    /// of the remaining data. Their results are dropped.
+    pub fn resolve_registry<T>(
+        &mut self,
+        registry: &'a RefCell<RuleRegistry<BytesMut, T, TokenError>>
+    ) {
+        let names: Vec<_> = registry.borrow().names().map(|name| {
+            String::from_utf8_lossy(name).into_owned()
+        }).collect();
+        for name in names {
+            let key = name.clone();
+            self.resolve_rule(&key, move |token| {
+                buffered(token, |buf| {
+                    match registry.borrow_mut().parse(&name, buf) {
+                        Some(res) => res,
+                        None => Err(TokenError::Invalid)
+                    }
+                })
+            })
+        }
+    }
+
+    /// Returns whether the interpreter knows a rule of the given name.
+    ///
+    /// This includes the core rules and rules replaced by a resolver.
+    pub fn contains(&self, name: &str) -> bool {
+        let key = name.to_ascii_lowercase();
+        self.rules.contains_key(&key) || self.overrides.contains_key(&key)
+            || is_core_rule(name)
+    }
+
+    /// Advances a token over a match of the rule named `name`.
+    pub fn rule(&self, name: &str, token: &mut Token<B>)
+                -> Poll<(), TokenError> {
+        if let Some(ref memo) = self.memo {
+            memo.borrow_mut().clear()
+        }
+        self.rulename(name, token, 0)
+    }
+
+    /// Parses a match of the rule named `name` from a buffer.
+    pub fn parse(&self, name: &str, buf: &mut B) -> Poll<Bytes, TokenError> {
+        token::parse(buf, |token| self.rule(name, token))
+    }
+
+    /// Skips over a match of the rule named `name` in a buffer.
+    pub fn skip(&self, name: &str, buf: &mut B) -> Poll<(), TokenError> {
+        token::skip(buf, |token| self.rule(name, token))
+    }
+
+    /// Parses a match of the rule named `name` and builds its parse tree.
+    ///
+    /// Returns the matched data and the node for the rule. The positions
+    /// of all nodes of the tree are relative to the start of the data.
+    pub fn parse_tree(&self, name: &str, buf: &mut B)
+                      -> Poll<(Bytes, Node), TokenError> {
+        *self.nodes.borrow_mut() = Some(Vec::new());
+        let res = token::parse(buf, |token| self.rule(name, token));
+        let nodes = self.nodes.borrow_mut().take();
+        let data = try_ready!(res);
+        let root = nodes.and_then(|mut nodes| nodes.pop())
+                        .expect("missing root node");
+        Ok(Async::Ready((data, root)))
+    }
+
+    fn rulename(&self, name: &str, token: &mut Token<B>, depth: usize)
+                -> Poll<(), TokenError> {
+        if depth >= self.max_depth {
+            return Err(TokenError::LimitExceeded)
+        }
+        let parent = self.nodes.borrow_mut().as_mut().map(mem::take);
+        let parent = match parent {
+            Some(parent) => parent,
+            None => return self.resolve(name, token, depth)
+        };
+        let start = token.checkpoint();
+        let res = self.resolve(name, token, depth);
+        let mut nodes = self.nodes.borrow_mut();
+        let nodes = nodes.as_mut().expect("missing nodes");
+        let children = mem::replace(nodes, parent);
+        if let Ok(Async::Ready(())) = res {
+            nodes.push(Node {
+                rule: name.into(),
+                start,
+                end: token.checkpoint(),
+                children,
+            })
+        }
+        res
+    }
+
+    fn resolve(&self, name: &str, token: &mut Token<B>, depth: usize)
+               -> Poll<(), TokenError> {
+        let key = name.to_ascii_lowercase();
+        if let Some(resolver) = self.overrides.get(&key) {
+            return resolver(token)
+        }
+        if let Some(rule) = self.rules.get(&key) {
+            let memo = match self.memo {
+                Some(ref memo) => memo,
+                None => {
+                    return self.alternation(&rule.alternation, token,
+                                            depth + 1)
+                }
+            };
+            let key = (key, token.checkpoint());
+            let known = memo.borrow().get(&key).cloned();
+            return match known {
+                Some(Some((end, children))) => {
+                    token.advance(end - key.1);
+                    if let Some(ref mut nodes) = *self.nodes.borrow_mut() {
+                        *nodes = children
+                    }
+                    Ok(Async::Ready(()))
+                }
+                Some(None) => Err(TokenError::Invalid),
+                None => {
+                    let res = self.alternation(&rule.alternation, token,
+                                               depth + 1);
+                    match res {
+                        Ok(Async::Ready(())) => {
+                            let end = token.checkpoint();
+                            let children = self.nodes.borrow().as_ref()
+                                               .cloned().unwrap_or_default();
+                            memo.borrow_mut().insert(
+                                key, Some((end, children))
+                            );
+                        }
+                        Err(TokenError::Invalid) => {
+                            memo.borrow_mut().insert(key, None);
+                        }
+                        _ => { }
+                    }
+                    res
+                }
+            }
+        }
+        match core_rule(name) {
+            Some(rule) => rule(token),
+            None => Err(TokenError::Invalid)
+        }
+    }
+
+    fn alternation(&self, alternation: &Alternation,
+                              token: &mut Token<B>, depth: usize)
+                              -> Poll<(), TokenError> {
+        if let Some(table) = self.dispatch.get(&(alternation as *const _)) {
+            let index = match token.peek(0) {
+                Some(ch) => table[usize::from(ch)],
+                None if token.is_final() => return Err(TokenError::Invalid),
+                None => {
+                    token.need(1);
+                    return Ok(Async::NotReady)
+                }
+            };
+            let concatenation = match index {
+                Some(index) => &alternation.concatenations[index],
+                None => return Err(TokenError::Invalid)
+            };
+            let mark = self.mark();
+            let res = token::group(token, |token| {
+                self.concatenation(concatenation, token, depth)
+            });
+            if let Err(TokenError::Invalid) = res {
+                self.reset(mark)
+            }
+            return res
+        }
+        let count = alternation.concatenations.len();
+        for (index, concatenation) in
+                alternation.concatenations.iter().enumerate() {
+            let mark = self.mark();
+            let res = token::group(token, |token| {
+                self.concatenation(concatenation, token, depth)
+            });
+            if let Ok(Async::NotReady) = res {
+                // A single octet may decide against this alternative.
+                if index + 1 < count {
+                    let count = token.remaining() + 1;
+                    token.need(count)
+                }
+                return Ok(Async::NotReady)
+            }
+            match try_result!(res) {
+                Ok(()) => return Ok(Async::Ready(())),
+                Err(TokenError::Invalid) => self.reset(mark),
+                Err(err) => return Err(err)
+            }
+        }
+        Err(TokenError::Invalid)
+    }
+
+    fn concatenation(&self, concatenation: &Concatenation,
+                                token: &mut Token<B>, depth: usize)
+                                -> Poll<(), TokenError> {
+        for repetition in &concatenation.repetitions {
+            try_ready!(self.repetition(repetition, token, depth))
+        }
+        Ok(Async::Ready(()))
+    }
+
+    fn repetition(&self, repetition: &Repetition,
+                             token: &mut Token<B>, depth: usize)
+                             -> Poll<(), TokenError> {
+        if repetition.min == 1 && repetition.max == Some(1) {
+            return self.element(&repetition.element, token, depth)
+        }
+        token::repeat(token, repetition.min, repetition.max, |token| {
+            self.element(&repetition.element, token, depth)
+        })
+    }
+
+    fn element(&self, element: &Element, token: &mut Token<B>,
+                          depth: usize) -> Poll<(), TokenError> {
+        match *element {
+            Element::Rulename(ref name) => self.rulename(name, token, depth),
+            Element::Group(ref alternation) => {
+                self.alternation(alternation, token, depth)
+            }
+            Element::Optional(ref alternation) => {
+                let mark = self.mark();
+                match try_result!(token::group(token, |token| {
+                    self.alternation(alternation, token, depth)
+                })) {
+                    Ok(()) => Ok(Async::Ready(())),
+                    Err(TokenError::Invalid) => {
+                        self.reset(mark);
+                        Ok(Async::Ready(()))
+                    }
+                    Err(err) => Err(err)
+                }
+            }
+            Element::CharVal(ref value) => char_val(value, token),
+            Element::NumVal(ref value) => num_val(value, token),
+            Element::ProseVal(ref text) => {
+                match self.prose.get(text) {
+                    Some(resolver) => resolver(token),
+                    None => Err(TokenError::Invalid)
+                }
+            }
+        }
+    }
+
+    /// Returns the number of nodes of the rule currently matched.
+    fn mark(&self) -> usize {
+        self.nodes.borrow().as_ref().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Drops the nodes added after `mark()` returned `mark`.
+    fn reset(&self, mark: usize) {
+        if let Some(ref mut nodes) = *self.nodes.borrow_mut() {
+            nodes.truncate(mark)
+        }
+    }
+}
+
+impl<'a, B> fmt::Debug for Interpreter<'a, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("rules", &self.rules.keys().collect::<Vec<_>>())
+            .field("prose", &self.prose.keys().collect::<Vec<_>>())
+            .field("overrides", &self.overrides.keys().collect::<Vec<_>>())
+            .field("max_depth", &self.max_depth)
+            .field("memoize", &self.memo.is_some())
+            .field("dispatch", &self.dispatch.len())
+            .finish()
+    }
+}
+
+
+//------------ Dispatch Tables -----------------------------------------------
+
+/// The index of the only alternative that can match each first octet.
+type DispatchTable = Box<[Option<usize>; 256]>;
+
+/// A visitor collecting the dispatch tables for a rule list.
+struct DispatchTables(HashMap<*const Alternation, DispatchTable>);
+
+impl DispatchTables {
+    fn collect(rules: &RuleList)
+               -> HashMap<*const Alternation, DispatchTable> {
+        let mut res = DispatchTables(HashMap::new());
+        res.visit_rule_list(rules);
+        res.0
+    }
+}
+
+impl Visitor for DispatchTables {
+    fn visit_alternation(&mut self, alternation: &Alternation) {
+        if let Some(table) = dispatch_table(alternation) {
+            self.0.insert(alternation, table);
+        }
+        walk_alternation(self, alternation)
+    }
+}
+
+/// Returns the dispatch table for an alternation if it can have one.
+///
+/// This is the case if there are at least two alternatives and each of
+/// them starts with a terminal that has to match and the sets of first
+/// octets of these terminals don’t overlap.
+fn dispatch_table(alternation: &Alternation) -> Option<DispatchTable> {
+    if alternation.concatenations.len() < 2 {
+        return None
+    }
+    let mut res = Box::new([None; 256]);
+    for (index, concatenation) in alternation.concatenations.iter()
+                                             .enumerate() {
+        let first = concatenation.repetitions.first()?;
+        if first.min == 0 {
+            return None
+        }
+        for ch in first_octets(&first.element)? {
+            if res[usize::from(ch)].replace(index).is_some() {
+                return None
+            }
+        }
+    }
+    Some(res)
+}
+
+/// Returns the octets a terminal can start with.
+///
+/// Returns `None` if the element isn’t a terminal or can match the empty
+/// string.
+fn first_octets(element: &Element) -> Option<Vec<u8>> {
+    match *element {
+        Element::CharVal(ref value) => {
+            let ch = *value.value.as_bytes().first()?;
+            if value.case_sensitive || !ch.is_ascii_alphabetic() {
+                Some(vec![ch])
+            }
+            else {
+                Some(vec![ch.to_ascii_lowercase(), ch.to_ascii_uppercase()])
+            }
+        }
+        Element::NumVal(ref value) => match value.kind {
+            NumKind::Range(first, last) => {
+                Some((first..=last.min(255)).map(|ch| ch as u8).collect())
+            }
+            NumKind::Concat(ref values) => {
+                let first = *values.first()?;
+                if first > 255 {
+                    Some(Vec::new())
+                }
+                else {
+                    Some(vec![first as u8])
+                }
+            }
+        },
+        _ => None
+    }
+}
+
+
+//------------ Node ----------------------------------------------------------
+
+/// A rule matched while building a parse tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node {
+    /// The name of the rule as given by the reference to it.
+    pub rule: String,
+
+    /// The position of the first octet of the match.
+    pub start: usize,
+
+    /// The position after the last octet of the match.
+    pub end: usize,
+
+    /// The nodes of the rules referenced by the rule in order.
+    pub children: Vec<Node>,
+}
+
+
+//------------ Terminals -----------------------------------------------------
+
+/// Advances a token over a quoted string.
+pub fn char_val<B: Buffer>(value: &CharVal, token: &mut Token<B>)
+                           -> Poll<(), TokenError> {
+    if value.case_sensitive {
+        token::literal_exact(token, value.value.as_bytes())
+    }
+    else {
+        token::literal(token, value.value.as_bytes())
+    }
+}
+
+/// Advances a token over a terminal given by numeric values.
+pub fn num_val<B: Buffer>(value: &NumVal, token: &mut Token<B>)
+                          -> Poll<(), TokenError> {
+    match value.kind {
+        NumKind::Range(first, last) => {
+            token::cat(token, |ch| {
+                first <= u32::from(ch) && u32::from(ch) <= last
+            })
+        }
+        NumKind::Concat(ref values) => {
+            for (i, &value) in values.iter().enumerate() {
+                match token.peek(i) {
+                    None if token.is_final() => {
+                        return Err(TokenError::Invalid)
+                    }
+                    None => {
+                        token.need(values.len());
+                        return Ok(Async::NotReady)
+                    }
+                    Some(ch) => {
+                        if u32::from(ch) != value {
+                            return Err(TokenError::Invalid)
+                        }
+                    }
+                }
+            }
+            token.advance(values.len());
+            Ok(Async::Ready(()))
+        }
+    }
+}
+
+
+//------------ Buffered Parsers ----------------------------------------------
+
+/// Advances a token over what a parser for buffers consumes.
+///
+/// The parser is given a copy of the data remaining after the token.
+fn buffered<B, P, T>(token: &mut Token<B>, parser: P) -> Poll<(), TokenError>
+            where B: Buffer,
+                  P: FnOnce(&mut BytesMut) -> Poll<T, TokenError> {
+    let remaining = token.remaining();
+    let mut buf: BytesMut = (0..remaining).filter_map(|i| {
+        token.peek(i)
+    }).collect();
+    match parser(&mut buf)? {
+        Async::Ready(_) => {
+            token.advance(remaining - buf.len());
+            Ok(Async::Ready(()))
+        }
+        Async::NotReady => {
+            token.need(remaining + 1);
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+
+//------------ Core Rules ----------------------------------------------------
+
+/// Returns whether `name` is the name of one of the core rules.
+///
+/// The core rules are those defined in appendix B.1 of RFC 5234.
+pub fn is_core_rule(name: &str) -> bool {
+    core_rule::<BytesMut>(name).is_some()
+}
+
+/// A function advancing a token over a rule.
+type RuleFn<B> = fn(&mut Token<B>) -> Poll<(), TokenError>;
+
+/// Returns the function for the core rule named `name` if there is one.
+fn core_rule<B: Buffer>(name: &str) -> Option<RuleFn<B>> {
+    Some(match name.to_ascii_uppercase().as_str() {
+        "ALPHA" => core::alpha,
+        "BIT" => core::bit,
+        "CHAR" => core::char,
+        "CR" => core::cr,
+        "CRLF" => core::crlf,
+        "CTL" => core::ctl,
+        "DIGIT" => core::digit,
+        "DQUOTE" => core::dquote,
+        "HEXDIG" => core::hexdig,
+        "HTAB" => core::htab,
+        "LF" => core::lf,
+        "LWSP" => core::lwsp,
+        "OCTET" => core::octet_any,
+        "SP" => core::sp,
+        "VCHAR" => core::vchar,
+        "WSP" => core::wsp,
+        _ => return None
+    })
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut {
+        BytesMut::from(slice)
+    }
+
+    #[test]
+    fn terminals() {
+        let rules = RuleList::from_slice(
+            b"upper = %x41-5A\n\
+              crlf2 = %d13.10.13.10\n\
+              utf8 = 1*%x80-10FFFF\n\
+              big = %x100\n\
+              hello = \"Hello\"\n\
+              exact = %s\"Hello\"\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+
+        assert_eq_ready!(interp.parse("upper", &mut buf(b"Qx")),
+                         Bytes::from_static(b"Q"));
+        assert_parse_err!(interp.parse("upper", &mut buf(b"q")));
+        assert_eq_ready!(interp.parse("crlf2", &mut buf(b"\r\n\r\nx")),
+                         Bytes::from_static(b"\r\n\r\n"));
+        assert_parse_err!(interp.parse("crlf2", &mut buf(b"\r\n\n")));
+        assert_not_ready!(interp.parse("crlf2", &mut buf(b"\r\n")));
+        assert_eq_ready!(interp.parse("utf8", &mut buf("äö!".as_bytes())),
+                         Bytes::from("äö"));
+        assert_parse_err!(interp.parse("big", &mut buf(b"\x00\xff")));
+        assert_eq_ready!(interp.parse("hello", &mut buf(b"hELLO")),
+                         Bytes::from_static(b"hELLO"));
+        assert_eq_ready!(interp.parse("exact", &mut buf(b"Hello")),
+                         Bytes::from_static(b"Hello"));
+        assert_parse_err!(interp.parse("exact", &mut buf(b"hello")));
+    }
+
+    #[test]
+    fn rules() {
+        let rules = RuleList::from_slice(
+            b"greeting = (\"HELO\" / \"EHLO\") SP domain [SP] CRLF\n\
+              domain   = label *(\".\" label)\n\
+              label    = 1*63(ALPHA / DIGIT / \"-\")\n\
+              prose    = <something>\n\
+              missing  = nowhere\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+        assert!(interp.contains("Domain"));
+        assert!(interp.contains("crlf"));
+        assert!(!interp.contains("nowhere"));
+
+        let mut data = buf(b"EHLO mail.example.com\r\nQUIT");
+        assert_eq_ready!(interp.skip("GREETING", &mut data), ());
+        assert_eq!(&data[..], b"QUIT");
+        assert_eq_ready!(interp.skip("greeting",
+                                     &mut buf(b"helo a \r\n")), ());
+        assert_parse_err!(interp.skip("greeting",
+                                      &mut buf(b"HELO a..b\r\n")));
+        assert_not_ready!(interp.skip("greeting", &mut buf(b"HELO a.b")));
+        assert_parse_err!(interp.skip("prose", &mut buf(b"something")));
+        assert_parse_err!(interp.skip("missing", &mut buf(b"x")));
+        assert_parse_err!(interp.skip("nowhere", &mut buf(b"x")));
+    }
+
+    #[test]
+    fn depth() {
+        let rules = RuleList::from_slice(
+            b"nested = \"(\" [nested] \")\"\n\
+              left = left \"x\" / \"x\"\n"
+        ).unwrap();
+        let mut interp = Interpreter::new(&rules);
+        assert_eq_ready!(interp.skip("nested", &mut buf(b"((()))")), ());
+        assert_parse_err!(interp.skip("left", &mut buf(b"xx")),
+                          TokenError::LimitExceeded);
+        interp.set_max_depth(2);
+        assert_parse_err!(interp.skip("nested", &mut buf(b"((()))")),
+                          TokenError::LimitExceeded);
+    }
+
+    #[test]
+    fn resolvers() {
+        let rules = RuleList::from_slice(
+            b"address = \"[\" <IPv4 address> \"]\"\n\
+              host    = 1*ALPHA\n"
+        ).unwrap();
+        let mut interp = Interpreter::new(&rules);
+        assert!(!interp.contains("domain"));
+        assert_parse_err!(interp.skip("address", &mut buf(b"[1.2.3.4]")));
+
+        interp.resolve_prose("IPv4 address", |token| {
+            try_ready!(core::digits(token));
+            for _ in 0..3 {
+                try_ready!(token::octet(token, b'.'));
+                try_ready!(core::digits(token));
+            }
+            Ok(Async::Ready(()))
+        });
+        interp.resolve_rule("DOMAIN", |token| {
+            token::cats(token, core::test_alpha)
+        });
+        interp.resolve_rule("host", |token| token::literal(token, b"h"));
+        assert!(interp.contains("domain"));
+        assert_eq_ready!(interp.parse("address", &mut buf(b"[1.2.3.4]")),
+                         Bytes::from_static(b"[1.2.3.4]"));
+        assert_parse_err!(interp.skip("address", &mut buf(b"[1.2.3]")));
+        assert_eq_ready!(interp.parse("domain", &mut buf(b"ab.")),
+                         Bytes::from_static(b"ab"));
+        assert_eq_ready!(interp.parse("host", &mut buf(b"hhh")),
+                         Bytes::from_static(b"h"));
+    }
+
+    #[test]
+    fn registry() {
+        use ::ipaddr::{parse_ipv4_addr, parse_ipv6_addr};
+        use ::parse::parser::Parser;
+
+        let rules = RuleList::from_slice(
+            b"address-literal = \"[\" ( IPv4-address-literal /\n\
+              \x20   IPv6-address-literal ) \"]\"\n\
+              IPv4-address-literal = <see RFC 5321>\n\
+              IPv6-address-literal = \"IPv6:\" IPv6-addr\n"
+        ).unwrap();
+        let mut registry = RuleRegistry::new();
+        registry.register("ipv4-address-literal",
+                          parse_ipv4_addr.map(|_| ()));
+        registry.register("IPv6-addr", parse_ipv6_addr.map(|_| ()));
+        let registry = RefCell::new(registry);
+        let mut interp = Interpreter::new(&rules);
+        interp.resolve_registry(&registry);
+        assert!(interp.contains("ipv6-addr"));
+
+        assert_eq_ready!(interp.parse("address-literal",
+                                      &mut buf(b"[192.0.2.1]x")),
+                         Bytes::from_static(b"[192.0.2.1]"));
+        assert_eq_ready!(interp.parse("address-literal",
+                                      &mut buf(b"[IPv6:2001:db8::1]")),
+                         Bytes::from_static(b"[IPv6:2001:db8::1]"));
+        assert_parse_err!(interp.parse("address-literal",
+                                       &mut buf(b"[192.0.2]")));
+        assert_not_ready!(interp.parse("address-literal",
+                                       &mut buf(b"[192.0.2.1")));
+    }
+
+    #[test]
+    fn dispatch() {
+        let rules = RuleList::from_slice(
+            b"a = \"x\" 1*DIGIT / %x41-43 b / \"yz\" / %d10.13 / %x100\n\
+              b = \"b\" / \"B\" / DIGIT\n\
+              c = [\"x\"] / \"y\"\n\
+              d = \"x\" / %x70-7A\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+        let table = dispatch_table(&rules.get("a").unwrap().alternation)
+                        .unwrap();
+        assert_eq!(table[usize::from(b'X')], Some(0));
+        assert_eq!(table[usize::from(b'B')], Some(1));
+        assert_eq!(table[usize::from(b'y')], Some(2));
+        assert_eq!(table[10], Some(3));
+        assert_eq!(table[usize::from(b'D')], None);
+        assert!(dispatch_table(&rules.get("b").unwrap().alternation)
+                    .is_none());
+        assert!(dispatch_table(&rules.get("c").unwrap().alternation)
+                    .is_none());
+        assert!(dispatch_table(&rules.get("d").unwrap().alternation)
+                    .is_none());
+        assert_eq!(interp.dispatch.len(), 1);
+
+        assert_eq_ready!(interp.parse("a", &mut buf(b"X12.")),
+                         Bytes::from_static(b"X12"));
+        assert_eq_ready!(interp.parse("a", &mut buf(b"Bb.")),
+                         Bytes::from_static(b"Bb"));
+        assert_eq_ready!(interp.parse("a", &mut buf(b"\n\r")),
+                         Bytes::from_static(b"\n\r"));
+        assert_parse_err!(interp.parse("a", &mut buf(b"D")));
+        assert_parse_err!(interp.parse("a", &mut buf(b"yy")));
+        assert_not_ready!(interp.parse("a", &mut buf(b"")));
+        assert_not_ready!(interp.parse("a", &mut buf(b"y")));
+    }
+
+    #[test]
+    fn complete() {
+        use ::parse::complete::Complete;
+
+        fn input(slice: &[u8]) -> Complete<&[u8]> {
+            Complete::new(slice)
+        }
+
+        let rules = RuleList::from_slice(
+            b"alt = \"a\" [\"b\" / \"c\"]\n\
+              concat = \"a\" [%x62.63]\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+        assert_eq!(interp.dispatch.len(), 1);
+
+        assert_eq_ready!(interp.parse("alt", &mut input(b"a")),
+                         Bytes::from_static(b"a"));
+        assert_eq_ready!(interp.parse("alt", &mut input(b"ac")),
+                         Bytes::from_static(b"ac"));
+        assert_eq_ready!(interp.parse("concat", &mut input(b"a")),
+                         Bytes::from_static(b"a"));
+        assert_eq_ready!(interp.parse("concat", &mut input(b"ab")),
+                         Bytes::from_static(b"a"));
+        assert_eq_ready!(interp.parse("concat", &mut input(b"abc")),
+                         Bytes::from_static(b"abc"));
+        assert_parse_err!(interp.parse("concat", &mut input(b"")));
+    }
+
+    #[test]
+    fn hints() {
+        use ::parse::hint::Hinted;
+
+        let rules = RuleList::from_slice(
+            b"greeting = \"HELLOWORLD\" / \"HELLO, WORLD\"\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+        let mut data = Hinted::new(buf(b"HELLO"));
+        assert_not_ready!(interp.parse("greeting", &mut data));
+        assert_eq!(data.take_needed(), Some(1));
+        let mut data = Hinted::new(buf(b"HELLO,"));
+        assert_not_ready!(interp.parse("greeting", &mut data));
+        assert_eq!(data.take_needed(), Some(6));
+    }
+
+    #[test]
+    fn memoize() {
+        use std::cell::Cell;
+
+        // Each level tries `x` up to three times, so without memoization
+        // the number of visits to the leaf grows exponentially with the
+        // nesting depth.
+        let rules = RuleList::from_slice(
+            b"s = x \"a\" / x \"b\" / x\n\
+              x = \"(\" s \")\" / <leaf>\n"
+        ).unwrap();
+        let count = Cell::new(0);
+        let mut interp = Interpreter::new(&rules);
+        interp.resolve_prose("leaf", |token| {
+            count.set(count.get() + 1);
+            token::octet(token, b'z')
+        });
+        let data = b"((((((((z))))))))b.";
+
+        assert_eq_ready!(interp.parse("s", &mut buf(data)),
+                         Bytes::from_static(&data[..18]));
+        assert!(count.get() > 10000);
+
+        count.set(0);
+        interp.set_memoize(true);
+        assert_eq_ready!(interp.parse("s", &mut buf(data)),
+                         Bytes::from_static(&data[..18]));
+        assert_eq!(count.get(), 1);
+        assert_parse_err!(interp.parse("s", &mut buf(b"((z).")));
+        assert_not_ready!(interp.parse("s", &mut buf(b"((z))")));
+        assert_eq_ready!(interp.parse("s", &mut buf(b"((z))a")),
+                         Bytes::from_static(b"((z))a"));
+    }
+
+    #[test]
+    fn tree() {
+        fn node(rule: &str, start: usize, end: usize, children: Vec<Node>)
+                -> Node {
+            Node { rule: rule.into(), start, end, children }
+        }
+
+        let rules = RuleList::from_slice(
+            b"pair = key \"=\" key \";\" / key \"=\" value [\";\"]\n\
+              key = 1*ALPHA\n\
+              value = 1*DIGIT\n"
+        ).unwrap();
+        let mut interp = Interpreter::new(&rules);
+        let tree = node("pair", 0, 5, vec![
+            node("key", 0, 2, vec![
+                node("ALPHA", 0, 1, vec![]),
+                node("ALPHA", 1, 2, vec![]),
+            ]),
+            node("value", 3, 4, vec![node("DIGIT", 3, 4, vec![])]),
+        ]);
+        assert_eq_ready!(interp.parse_tree("pair", &mut buf(b"ab=1;\n")),
+                         (Bytes::from_static(b"ab=1;"), tree.clone()));
+        interp.set_memoize(true);
+        assert_eq_ready!(interp.parse_tree("pair", &mut buf(b"ab=1;\n")),
+                         (Bytes::from_static(b"ab=1;"), tree));
+        assert_parse_err!(interp.parse_tree("pair", &mut buf(b"ab=;")));
+        assert_not_ready!(interp.parse_tree("pair", &mut buf(b"ab")));
+        assert_eq_ready!(interp.parse("pair", &mut buf(b"ab=cd;")),
+                         Bytes::from_static(b"ab=cd;"));
+    }
+}
@@ -0,0 +1,598 @@
+//! ABNF grammars.
+//!
+//! While the rest of this crate helps with writing parsers for rules by
+//! hand, this module deals with ABNF itself. It can read an ABNF
+//! specification as given by section 4 of RFC 5234 – for instance the
+//! collected ABNF of an RFC – into a [`RuleList`]. The [`rfc`] module
+//! finds the grammar in the text of an RFC itself.
+//!
+//! A rule list is a sequence of [`Rule`]s, each consisting of a name and
+//! an [`Alternation`]. An alternation is a list of [`Concatenation`]s
+//! one of which has to match. A concatenation in turn is a sequence of
+//! [`Repetition`]s which have to match one after another. Finally, a
+//! repetition states how often its [`Element`] has to match. Elements
+//! are references to other rules, nested alternations, or terminals.
+//!
+//! The parsers for the individual parts of the syntax are available in
+//! the [`syntax`] module. The [`interp`] module allows matching data
+//! against the rules of a rule list and building a parse tree for the
+//! data, while the [`compose`] module does the reverse and produces data
+//! for a rule from a tree of values. Before doing so, [`validate()`] can
+//! check the rule list for common mistakes and [`optimize()`] rewrite it
+//! for faster matching.
+//! Alternatively, the [`codegen`] module turns a rule list into Rust
+//! code, and rules made of terminals only can be compiled into a
+//! [`Dfa`]. A [`Generator`] produces random data conforming to a rule,
+//! which the `strategy` module available with the `proptest` feature
+//! uses for property tests. Finally, [`format()`] writes a rule list
+//! back as normalized ABNF and [`dot()`] as a Graphviz graph of the
+//! references between its rules, while the [`convert`] module writes it
+//! as EBNF or as a parsing expression grammar. Tools of their own can
+//! walk the AST via the [`Visitor`] trait.
+//!
+//! [`RuleList`]: struct.RuleList.html
+//! [`Rule`]: struct.Rule.html
+//! [`Alternation`]: struct.Alternation.html
+//! [`Concatenation`]: struct.Concatenation.html
+//! [`Repetition`]: struct.Repetition.html
+//! [`Element`]: enum.Element.html
+//! [`syntax`]: syntax/index.html
+//! [`interp`]: interp/index.html
+//! [`compose`]: compose/index.html
+//! [`rfc`]: rfc/index.html
+//! [`codegen`]: codegen/index.html
+//! [`validate()`]: check/fn.validate.html
+//! [`optimize()`]: optimize/fn.optimize.html
+//! [`format()`]: format/fn.format.html
+//! [`dot()`]: dot/fn.dot.html
+//! [`convert`]: convert/index.html
+//! [`Dfa`]: dfa/struct.Dfa.html
+//! [`Generator`]: random/struct.Generator.html
+//! [`Visitor`]: visit/trait.Visitor.html
+
+pub mod check;
+pub mod codegen;
+pub mod compose;
+pub mod convert;
+pub mod dfa;
+pub mod dot;
+pub mod format;
+pub mod interp;
+pub mod optimize;
+pub mod random;
+#[cfg(feature = "proptest")] pub mod strategy;
+pub mod rfc;
+pub mod syntax;
+pub mod visit;
+
+pub use self::check::{validate, Diagnostic};
+pub use self::optimize::optimize;
+pub use self::random::Generator;
+pub use self::visit::Visitor;
+
+use std::{cmp, slice};
+use bytes::BytesMut;
+use futures::Async;
+
+
+//------------ RuleList ------------------------------------------------------
+
+/// A list of rules.
+///
+/// Rules are kept in the order they were defined in. Since rule names
+/// are case-insensitive, each name can only appear once irrespective of
+/// its case. Incremental alternatives added to a rule later on via `=/`
+/// are merged into the rule’s alternation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RuleList {
+    rules: Vec<Rule>,
+}
+
+impl RuleList {
+    /// Creates a new, empty rule list.
+    pub fn new() -> Self {
+        RuleList { rules: Vec::new() }
+    }
+
+    /// Reads a rule list from its ABNF representation.
+    ///
+    /// The data is expected to be complete. Lines may end in either CRLF
+    /// or a bare LF. A line ending after the last line may be missing.
+    pub fn from_slice(data: &[u8]) -> Result<Self, GrammarError> {
+        // The parsers need to see an octet past the end of a rule before
+        // they can be sure it has ended. Since all of the data is here,
+        // we can add an octet that can’t continue any rule.
+        let mut buf = BytesMut::with_capacity(data.len() + 2);
+        buf.extend_from_slice(data);
+        if !data.ends_with(b"\n") {
+            buf.extend_from_slice(b"\n")
+        }
+        buf.extend_from_slice(b"\0");
+        let len = buf.len();
+        let mut res = RuleList::new();
+        while &buf[..] != b"\0" {
+            match syntax::parse_rulelist_item(&mut buf) {
+                Ok(Async::Ready(Some(def))) => {
+                    if def.incremental {
+                        res.add_alternatives(def.rule)?
+                    }
+                    else {
+                        res.push(def.rule)?
+                    }
+                }
+                Ok(Async::Ready(None)) => { }
+                _ => {
+                    let offset = cmp::min(len - buf.len(), data.len());
+                    return Err(GrammarError::Syntax(line_at(data, offset)))
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Reads a rule list from the plain text of an RFC.
+    ///
+    /// The grammar is collected from the text via `rfc::extract()`. Line
+    /// numbers in syntax errors refer to the lines of the text.
+    pub fn from_rfc(data: &[u8]) -> Result<Self, GrammarError> {
+        Self::from_slice(&rfc::extract(data))
+    }
+
+    /// Appends a rule to the end of the list.
+    ///
+    /// Returns an error if a rule of the same name is already present.
+    pub fn push(&mut self, rule: Rule) -> Result<(), GrammarError> {
+        if self.contains(&rule.name) {
+            return Err(GrammarError::Duplicate(rule.name))
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    /// Adds the alternatives of `rule` to the existing rule of its name.
+    ///
+    /// This is what an incremental definition using `=/` does. The new
+    /// alternatives are appended to those already present. Returns an
+    /// error if there is no rule of that name yet.
+    pub fn add_alternatives(&mut self, rule: Rule)
+                            -> Result<(), GrammarError> {
+        match self.get_mut(&rule.name) {
+            Some(existing) => {
+                existing.alternation.concatenations.extend(
+                    rule.alternation.concatenations
+                );
+                Ok(())
+            }
+            None => Err(GrammarError::Undefined(rule.name))
+        }
+    }
+
+    /// Returns the number of rules in the list.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns whether the list contains a rule named `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns the rule named `name`.
+    ///
+    /// Rule names are compared ignoring case.
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns a mutable reference to the rule named `name`.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Rule> {
+        self.rules.iter_mut().find(|rule| {
+            rule.name.eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Returns an iterator over the rules.
+    pub fn iter(&self) -> slice::Iter<'_, Rule> {
+        self.rules.iter()
+    }
+
+    /// Trades the list for a vector of its rules.
+    pub fn into_vec(self) -> Vec<Rule> {
+        self.rules
+    }
+}
+
+impl<'a> IntoIterator for &'a RuleList {
+    type Item = &'a Rule;
+    type IntoIter = slice::Iter<'a, Rule>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+
+//------------ Rule ----------------------------------------------------------
+
+/// A single rule.
+//
+//  rule = rulename defined-as elements c-nl
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    /// The name of the rule as it was written.
+    pub name: String,
+
+    /// The definition of the rule.
+    pub alternation: Alternation,
+}
+
+
+//------------ Alternation ---------------------------------------------------
+
+/// A list of alternatives, one of which has to match.
+//
+//  alternation = concatenation *(*c-wsp "/" *c-wsp concatenation)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Alternation {
+    /// The alternatives in the order they were given.
+    pub concatenations: Vec<Concatenation>,
+}
+
+
+//------------ Concatenation -------------------------------------------------
+
+/// A sequence of elements that have to match one after another.
+//
+//  concatenation = repetition *(1*c-wsp repetition)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Concatenation {
+    /// The parts of the sequence.
+    pub repetitions: Vec<Repetition>,
+}
+
+
+//------------ Repetition ----------------------------------------------------
+
+/// An element repeated a number of times.
+///
+/// An element without a repeat prefix is represented as a repetition
+/// with both `min` and `max` being one.
+//
+//  repetition = [repeat] element
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Repetition {
+    /// The minimum number of occurrences.
+    pub min: usize,
+
+    /// The maximum number of occurrences if there is a limit.
+    pub max: Option<usize>,
+
+    /// The element to repeat.
+    pub element: Element,
+}
+
+
+//------------ Element -------------------------------------------------------
+
+/// An element of a rule.
+//
+//  element = rulename / group / option / char-val / num-val / prose-val
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Element {
+    /// A reference to another rule.
+    Rulename(String),
+
+    /// An alternation in parentheses.
+    Group(Alternation),
+
+    /// An alternation in square brackets that may be missing.
+    Optional(Alternation),
+
+    /// A quoted string.
+    CharVal(CharVal),
+
+    /// A terminal given by numeric values.
+    NumVal(NumVal),
+
+    /// A description in angle brackets without the brackets.
+    ProseVal(String),
+}
+
+
+//------------ CharVal -------------------------------------------------------
+
+/// A quoted string.
+///
+/// Strings match case-insensitively unless they are prefixed with `%s`
+/// as introduced by RFC 7405.
+//
+//  char-val = case-insensitive-string / case-sensitive-string
+//  case-insensitive-string = [ "%i" ] quoted-string
+//  case-sensitive-string = "%s" quoted-string
+//  quoted-string = DQUOTE *(%x20-21 / %x23-7E) DQUOTE
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CharVal {
+    /// The string without its quotes.
+    pub value: String,
+
+    /// Whether the string has to match exactly.
+    pub case_sensitive: bool,
+}
+
+
+//------------ NumVal --------------------------------------------------------
+
+/// A terminal given by numeric values.
+//
+//  num-val = "%" (bin-val / dec-val / hex-val)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NumVal {
+    /// The base the values were written in.
+    pub base: Base,
+
+    /// The values.
+    pub kind: NumKind,
+}
+
+/// The base of the values of a numeric terminal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Base {
+    /// Binary, prefixed by `%b`.
+    Bin,
+
+    /// Decimal, prefixed by `%d`.
+    Dec,
+
+    /// Hexadecimal, prefixed by `%x`.
+    Hex,
+}
+
+impl Base {
+    /// Returns the radix of the base.
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Bin => 2,
+            Base::Dec => 10,
+            Base::Hex => 16,
+        }
+    }
+}
+
+/// The values of a numeric terminal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NumKind {
+    /// A single value within the given inclusive range, e.g., `%x41-5A`.
+    Range(u32, u32),
+
+    /// A sequence of values, e.g., `%d13.10`.
+    ///
+    /// A single value, e.g., `%x20`, is a sequence of length one.
+    Concat(Vec<u32>),
+}
+
+
+//------------ GrammarError --------------------------------------------------
+
+/// An error happened while reading a grammar.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GrammarError {
+    /// The text didn’t follow the syntax of ABNF.
+    ///
+    /// The line given is the one where the offending rule starts. Line
+    /// numbers start at one.
+    Syntax(usize),
+
+    /// A rule of the given name was defined more than once.
+    Duplicate(String),
+
+    /// Alternatives were added to a rule that wasn’t defined yet.
+    Undefined(String),
+}
+
+/// Returns the line number of `offset` into `data`.
+fn line_at(data: &[u8], offset: usize) -> usize {
+    data[..offset].iter().filter(|&&ch| ch == b'\n').count() + 1
+}
+
+
+//------------ Core Rules ----------------------------------------------------
+
+/// The core rules of RFC 5234, appendix B.1.
+///
+/// This is for the parts of this module that need the core rules as a
+/// grammar rather than as the functions of the `core` module.
+const CORE_RULES: &[u8] = b"\
+ALPHA  = %x41-5A / %x61-7A
+BIT    = \"0\" / \"1\"
+CHAR   = %x01-7F
+CR     = %x0D
+CRLF   = CR LF
+CTL    = %x00-1F / %x7F
+DIGIT  = %x30-39
+DQUOTE = %x22
+HEXDIG = DIGIT / \"A\" / \"B\" / \"C\" / \"D\" / \"E\" / \"F\"
+HTAB   = %x09
+LF     = %x0A
+LWSP   = *(WSP / CRLF WSP)
+OCTET  = %x00-FF
+SP     = %x20
+VCHAR  = %x21-7E
+WSP    = SP / HTAB
+";
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rulename(name: &str) -> Repetition {
+        Repetition {
+            min: 1, max: Some(1),
+            element: Element::Rulename(name.into())
+        }
+    }
+
+    #[test]
+    fn from_slice() {
+        let list = RuleList::from_slice(
+            b"; A greeting.\r\n\
+              greeting = \"HELO\" 1*SP domain\r\n\
+              \r\n\
+              domain   = label\r\n\
+              \x20          *(\".\" label) ; dots\r\n\
+              label    = 1*63(ALPHA / DIGIT)"
+        ).unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(
+            list.get("GREETING").unwrap().alternation,
+            Alternation {
+                concatenations: vec![Concatenation {
+                    repetitions: vec![
+                        Repetition {
+                            min: 1, max: Some(1),
+                            element: Element::CharVal(CharVal {
+                                value: "HELO".into(),
+                                case_sensitive: false,
+                            })
+                        },
+                        Repetition {
+                            min: 1, max: None,
+                            element: Element::Rulename("SP".into())
+                        },
+                        rulename("domain"),
+                    ]
+                }]
+            }
+        );
+        assert_eq!(
+            list.get("domain").unwrap().alternation.concatenations[0]
+                .repetitions.len(),
+            2
+        );
+        assert_eq!(
+            list.get("label").unwrap().alternation.concatenations[0]
+                .repetitions[0],
+            Repetition {
+                min: 1, max: Some(63),
+                element: Element::Group(Alternation {
+                    concatenations: vec![
+                        Concatenation { repetitions: vec![rulename("ALPHA")] },
+                        Concatenation { repetitions: vec![rulename("DIGIT")] },
+                    ]
+                })
+            }
+        );
+        assert_eq!(
+            list.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(),
+            ["greeting", "domain", "label"]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(RuleList::from_slice(b"a = b\nc = (d\ne = f\n"),
+                   Err(GrammarError::Syntax(2)));
+        assert_eq!(RuleList::from_slice(b"a = b\n\nA = c\n"),
+                   Err(GrammarError::Duplicate("A".into())));
+        assert_eq!(RuleList::from_slice(b" a = b\n"),
+                   Err(GrammarError::Syntax(1)));
+        assert_eq!(RuleList::from_slice(b""), Ok(RuleList::new()));
+        assert_eq!(RuleList::from_slice(b"a = b\nc =/ d\n"),
+                   Err(GrammarError::Undefined("c".into())));
+    }
+
+    #[test]
+    fn incremental() {
+        // RFC 5234, section 3.3.
+        let list = RuleList::from_slice(
+            b"ruleset     =  alt1 / alt2\n\
+              other       =  alt6\n\
+              ruleset     =/ alt3\n\
+              RuleSet     =/ alt4 / alt5\n"
+        ).unwrap();
+        assert_eq!(
+            list, RuleList::from_slice(
+                b"ruleset = alt1 / alt2 / alt3 / alt4 / alt5\n\
+                  other = alt6\n"
+            ).unwrap()
+        );
+    }
+
+    #[test]
+    fn core_rules() {
+        // RFC 5234, appendix B.1.
+        let list = RuleList::from_slice(CORE_RULES).unwrap();
+        assert_eq!(list.len(), 16);
+        assert_eq!(
+            list.get("lwsp").unwrap().alternation.concatenations[0]
+                .repetitions[0].min,
+            0
+        );
+        assert_eq!(
+            list.get("HEXDIG").unwrap().alternation.concatenations.len(),
+            7
+        );
+    }
+
+    const CORE_RULES: &[u8] = b"\
+ALPHA          =  %x41-5A / %x61-7A   ; A-Z / a-z
+
+BIT            =  \"0\" / \"1\"
+
+CHAR           =  %x01-7F
+                       ; any 7-bit US-ASCII character,
+                       ;  excluding NUL
+
+CR             =  %x0D
+                       ; carriage return
+
+CRLF           =  CR LF
+                       ; Internet standard newline
+
+CTL            =  %x00-1F / %x7F
+                       ; controls
+
+DIGIT          =  %x30-39
+                       ; 0-9
+
+DQUOTE         =  %x22
+                       ; \" (Double Quote)
+
+HEXDIG         =  DIGIT / \"A\" / \"B\" / \"C\" / \"D\" / \"E\" / \"F\"
+
+HTAB           =  %x09
+                       ; horizontal tab
+
+LF             =  %x0A
+                       ; linefeed
+
+LWSP           =  *(WSP / CRLF WSP)
+                       ; Use of this linear-white-space rule
+                       ;  permits lines containing only white
+                       ;  space that are no longer legal in
+                       ;  mail headers and have caused
+                       ;  interoperability problems in other
+                       ;  contexts.
+                       ; Do not use when defining mail
+                       ;  headers and use with caution in
+                       ;  other contexts.
+
+OCTET          =  %x00-FF
+                       ; 8 bits of data
+
+SP             =  %x20
+
+VCHAR          =  %x21-7E
+                       ; visible (printing) characters
+
+WSP            =  SP / HTAB
+                       ; white space
+";
+}
@@ -0,0 +1,358 @@
+//! Rewriting grammars for faster matching.
+//!
+//! Grammars copied from RFCs are written for human readers. The
+//! interpreter, which tries alternatives in order, pays for this with a
+//! lot of backtracking. For instance, when matching
+//! `a = "<" addr ">" / "<" route addr ">"` against data of the second
+//! alternative, it first matches `"<"` and tries to match `addr` only to
+//! give up, rewind, and match `"<"` again.
+//!
+//! The function [`optimize()`] rewrites a rule list so that the
+//! interpreter accepts exactly the same data with less work. It applies
+//! two transformations:
+//!
+//! * References to rules that consist of a single terminal, such as
+//!   `DQUOTE = %x22`, are replaced by the terminal. This saves descending
+//!   into the rule and makes the terminals visible to the next step.
+//! * Consecutive alternatives starting with the same elements are left
+//!   factored: `x y / x z` becomes `x (y / z)`, so the common prefix is
+//!   only matched once. Since alternatives are tried in order, any
+//!   alternative following one that consists of the prefix only can
+//!   never be reached and is dropped: `x / x y` becomes `x`.
+//!
+//! Because alternatives are tried in order, it is only safe to factor
+//! alternatives that are next to each other. The order of alternatives
+//! is never changed.
+//!
+//! There are two things to be aware of. The rules whose references were
+//! replaced no longer appear in parse trees built from the optimized
+//! rule list and resolvers registered for them aren’t used for those
+//! references anymore. And, since the rewritten rule list is meant for
+//! the interpreter, it may describe a different language than the
+//! original one if that relied on backtracking into alternatives.
+//!
+//! [`optimize()`]: fn.optimize.html
+
+use std::cmp;
+use super::{Alternation, Concatenation, Element, Repetition, Rule,
+            RuleList};
+
+
+//------------ optimize ------------------------------------------------------
+
+/// Rewrites a rule list for faster matching.
+///
+/// Returns the rewritten rule list and the list of transformations that
+/// were applied in the order of the rules they concern.
+pub fn optimize(rules: &RuleList) -> (RuleList, Vec<Transformation>) {
+    let mut res = RuleList::new();
+    let mut report = Vec::new();
+    for rule in rules {
+        let mut optimizer = Optimizer {
+            rules,
+            rule: &rule.name,
+            report: &mut report,
+        };
+        let alternation = optimizer.alternation(&rule.alternation);
+        res.push(Rule { name: rule.name.clone(), alternation })
+           .expect("duplicate rule in rule list");
+    }
+    (res, report)
+}
+
+
+//------------ Transformation ------------------------------------------------
+
+/// A transformation applied to a rule by `optimize()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Transformation {
+    /// A reference was replaced by the terminal of the referenced rule.
+    Inlined {
+        /// The name of the rule containing the reference.
+        rule: String,
+
+        /// The name of the referenced rule as written in the reference.
+        reference: String,
+    },
+
+    /// Consecutive alternatives with a common prefix were factored.
+    Factored {
+        /// The name of the rule containing the alternatives.
+        rule: String,
+
+        /// The number of alternatives factored.
+        alternatives: usize,
+
+        /// The number of elements in the common prefix.
+        prefix: usize,
+    },
+
+    /// Alternatives that could never be reached were dropped.
+    Dropped {
+        /// The name of the rule containing the alternatives.
+        rule: String,
+
+        /// The number of alternatives dropped.
+        alternatives: usize,
+    },
+}
+
+
+//------------ Optimizer -----------------------------------------------------
+
+/// Optimizes the definition of a single rule.
+struct Optimizer<'a> {
+    /// The complete rule list.
+    rules: &'a RuleList,
+
+    /// The name of the rule being optimized.
+    rule: &'a str,
+
+    /// The transformations applied so far.
+    report: &'a mut Vec<Transformation>,
+}
+
+impl<'a> Optimizer<'a> {
+    fn alternation(&mut self, alternation: &Alternation) -> Alternation {
+        let concatenations = alternation.concatenations.iter().map(|item| {
+            self.concatenation(item)
+        }).collect();
+        self.factor(concatenations)
+    }
+
+    fn concatenation(&mut self, concatenation: &Concatenation)
+                     -> Concatenation {
+        Concatenation {
+            repetitions: concatenation.repetitions.iter().map(|item| {
+                Repetition {
+                    min: item.min,
+                    max: item.max,
+                    element: self.element(&item.element),
+                }
+            }).collect()
+        }
+    }
+
+    fn element(&mut self, element: &Element) -> Element {
+        match *element {
+            Element::Rulename(ref name) => match self.terminal(name) {
+                Some(element) => {
+                    self.report.push(Transformation::Inlined {
+                        rule: self.rule.into(),
+                        reference: name.clone(),
+                    });
+                    element
+                }
+                None => element.clone()
+            },
+            Element::Group(ref alternation) => {
+                Element::Group(self.alternation(alternation))
+            }
+            Element::Optional(ref alternation) => {
+                Element::Optional(self.alternation(alternation))
+            }
+            _ => element.clone()
+        }
+    }
+
+    /// Returns the terminal if the rule `name` consists of one.
+    fn terminal(&self, name: &str) -> Option<Element> {
+        let rule = self.rules.get(name)?;
+        let concatenation = match rule.alternation.concatenations.as_slice() {
+            [concatenation] => concatenation,
+            _ => return None
+        };
+        let repetition = match concatenation.repetitions.as_slice() {
+            [repetition] => repetition,
+            _ => return None
+        };
+        if repetition.min != 1 || repetition.max != Some(1) {
+            return None
+        }
+        match repetition.element {
+            Element::CharVal(_) | Element::NumVal(_) => {
+                Some(repetition.element.clone())
+            }
+            _ => None
+        }
+    }
+
+    /// Left-factors runs of alternatives with a common prefix.
+    fn factor(&mut self, concatenations: Vec<Concatenation>) -> Alternation {
+        let mut res = Vec::new();
+        let mut start = 0;
+        while start < concatenations.len() {
+            let first = &concatenations[start].repetitions;
+            let mut end = start + 1;
+            let mut prefix = first.len();
+            while let Some(next) = concatenations.get(end) {
+                let common = common_prefix(first, &next.repetitions);
+                if common == 0 {
+                    break
+                }
+                prefix = cmp::min(prefix, common);
+                end += 1;
+            }
+            if prefix == 0 || end == start + 1 {
+                res.push(concatenations[start].clone());
+                start += 1;
+                continue
+            }
+            let run = &concatenations[start..end];
+            self.report.push(Transformation::Factored {
+                rule: self.rule.into(),
+                alternatives: run.len(),
+                prefix,
+            });
+
+            // Only the alternatives up to the first one that consists of
+            // the prefix only can ever match.
+            let rest: Vec<_> = run.iter().map(|item| {
+                Concatenation {
+                    repetitions: item.repetitions[prefix..].into()
+                }
+            }).collect();
+            let reachable = rest.iter().position(|item| {
+                item.repetitions.is_empty()
+            });
+            if let Some(pos) = reachable {
+                if pos + 1 < rest.len() {
+                    self.report.push(Transformation::Dropped {
+                        rule: self.rule.into(),
+                        alternatives: rest.len() - pos - 1,
+                    });
+                }
+            }
+
+            let mut repetitions = first[..prefix].to_vec();
+            match reachable {
+                Some(0) => { }
+                Some(pos) => {
+                    let rest = rest[..pos].to_vec();
+                    repetitions.push(single(
+                        Element::Optional(self.factor(rest))
+                    ));
+                }
+                None => {
+                    repetitions.push(single(
+                        Element::Group(self.factor(rest))
+                    ));
+                }
+            }
+            res.push(Concatenation { repetitions });
+            start = end;
+        }
+        Alternation { concatenations: res }
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Returns the number of leading repetitions two sequences have in common.
+fn common_prefix(left: &[Repetition], right: &[Repetition]) -> usize {
+    left.iter().zip(right).take_while(|&(left, right)| {
+        left == right
+    }).count()
+}
+
+/// Returns a repetition matching an element exactly once.
+fn single(element: Element) -> Repetition {
+    Repetition { min: 1, max: Some(1), element }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::BytesMut;
+    use super::super::interp::Interpreter;
+
+    const GRAMMAR: &[u8] = b"\
+path = \"<\" addr \">\" / \"<\" route \":\" addr \">\" / \"<\" \">\"
+addr = word AT word
+route = AT word
+word = 1*ALPHA
+AT = \"@\"
+list = word / word COMMA word / word COMMA list / word
+COMMA = %x2C
+";
+
+    #[test]
+    fn optimize() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let (optimized, report) = super::optimize(&rules);
+        assert_eq!(
+            optimized,
+            RuleList::from_slice(
+                b"path = \"<\" (addr \">\" / route \":\" addr \">\" / \">\")\n\
+                  addr = word \"@\" word\n\
+                  route = \"@\" word\n\
+                  word = 1*ALPHA\n\
+                  AT = \"@\"\n\
+                  list = word\n\
+                  COMMA = %x2C\n"
+            ).unwrap()
+        );
+        assert_eq!(
+            report,
+            [
+                Transformation::Factored {
+                    rule: "path".into(), alternatives: 3, prefix: 1
+                },
+                Transformation::Inlined {
+                    rule: "addr".into(), reference: "AT".into()
+                },
+                Transformation::Inlined {
+                    rule: "route".into(), reference: "AT".into()
+                },
+                Transformation::Inlined {
+                    rule: "list".into(), reference: "COMMA".into()
+                },
+                Transformation::Inlined {
+                    rule: "list".into(), reference: "COMMA".into()
+                },
+                Transformation::Factored {
+                    rule: "list".into(), alternatives: 4, prefix: 1
+                },
+                Transformation::Dropped {
+                    rule: "list".into(), alternatives: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let rules = RuleList::from_slice(
+            b"a = \"x\" \"y\" \"z\" / \"x\" \"y\" / \"x\" \"w\" / \"v\"\n"
+        ).unwrap();
+        let (optimized, _) = super::optimize(&rules);
+        assert_eq!(
+            optimized,
+            RuleList::from_slice(
+                b"a = \"x\" (\"y\" [\"z\"] / \"w\") / \"v\"\n"
+            ).unwrap()
+        );
+    }
+
+    #[test]
+    fn same_matches() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let (optimized, _) = super::optimize(&rules);
+        let before = Interpreter::new(&rules);
+        let after = Interpreter::new(&optimized);
+        for (name, data) in &[
+            ("path", &b"<a@b>."[..]), ("path", b"<@r:a@b>."),
+            ("path", b"<>."), ("path", b"<a>."), ("path", b"<@r>."),
+            ("list", b"a,b."), ("list", b"a."),
+        ] {
+            assert_eq!(
+                before.parse(name, &mut BytesMut::from(*data)),
+                after.parse(name, &mut BytesMut::from(*data))
+            );
+        }
+    }
+}
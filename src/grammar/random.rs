@@ -0,0 +1,412 @@
+//! Generating random data from a grammar.
+//!
+//! A [`Generator`] produces random octet sequences that conform to a rule
+//! of a grammar. This is useful for fuzzing a parser with input that is
+//! valid yet unusual.
+//!
+//! Each choice in the grammar is made at random: which alternative to
+//! take, how often to repeat an element, whether to include an optional
+//! part, the value of a range, and the case of each letter of a case-
+//! insensitive string. Repetitions without an upper bound are repeated at
+//! most a configurable number of times beyond their minimum.
+//!
+//! Since grammars are frequently recursive, the generator keeps track of
+//! how deeply it has descended into rule references. Beyond a configurable
+//! depth, it only makes choices that lead to the end quickest.
+//!
+//! The core rules of RFC 5234 are provided unless the grammar defines
+//! rules of the same name. Values above 255 as well as prose values can’t
+//! be generated. Parts of a grammar that can only be produced through
+//! them are avoided, as are references to undefined rules.
+//!
+//! Note that the interpreter takes the first alternative that matches and
+//! repeats elements as often as possible. Data produced by the generator
+//! for an ambiguous grammar may therefore not be accepted by it.
+//!
+//! [`Generator`]: struct.Generator.html
+
+use std::cell::Cell;
+use std::cmp;
+use std::collections::HashMap;
+use super::{Alternation, CharVal, Concatenation, Element, NumKind, NumVal,
+            Repetition, Rule, RuleList, CORE_RULES};
+
+
+//------------ Generator -----------------------------------------------------
+
+/// A generator of random data conforming to a grammar.
+#[derive(Debug)]
+pub struct Generator<'a> {
+    /// The rules of the grammar.
+    rules: &'a RuleList,
+
+    /// The core rules.
+    core: RuleList,
+
+    /// The minimum depth of the derivation of each rule.
+    ///
+    /// Keys are the lower-case names. Rules that can’t be generated are
+    /// missing.
+    heights: HashMap<String, usize>,
+
+    /// The random number generator.
+    rng: Rng,
+
+    /// The number of extra repetitions for unbounded repetitions.
+    max_repeat: usize,
+
+    /// The depth of rule references beyond which to take the shortest way.
+    max_depth: usize,
+}
+
+impl<'a> Generator<'a> {
+    /// Creates a new generator for a rule list.
+    ///
+    /// The same `seed` always leads to the same sequence of data.
+    pub fn new(rules: &'a RuleList, seed: u64) -> Self {
+        let mut res = Generator {
+            rules,
+            core: RuleList::from_slice(CORE_RULES).unwrap(),
+            heights: HashMap::new(),
+            rng: Rng::new(seed),
+            max_repeat: 8,
+            max_depth: 16,
+        };
+        res.heights = res.find_heights();
+        res
+    }
+
+    /// Sets how often unbounded repetitions are repeated at most.
+    ///
+    /// A repetition is repeated at most this many times beyond its
+    /// minimum, even if its maximum is larger. The default is 8.
+    pub fn set_max_repeat(&mut self, max_repeat: usize) {
+        self.max_repeat = max_repeat
+    }
+
+    /// Sets the depth of rule references to make random choices up to.
+    ///
+    /// Beyond this depth, the generator takes the quickest way to finish.
+    /// The default is 16.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth
+    }
+
+    /// Returns whether data can be generated for the rule `name`.
+    pub fn can_generate(&self, name: &str) -> bool {
+        self.heights.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Generates data for the rule `name`.
+    ///
+    /// Returns `None` if the rule isn’t defined or can’t be generated.
+    pub fn generate(&self, name: &str) -> Option<Vec<u8>> {
+        if !self.can_generate(name) {
+            return None
+        }
+        let mut res = Vec::new();
+        self.alternation(&self.get(name)?.alternation, 0, &mut res);
+        Some(res)
+    }
+
+    fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.get(name).or_else(|| self.core.get(name))
+    }
+
+    fn alternation(&self, alternation: &Alternation, depth: usize,
+                   target: &mut Vec<u8>) {
+        let heights = alternation.concatenations.iter().filter_map(|item| {
+            self.concatenation_height(item).map(|height| (item, height))
+        });
+        let choices: Vec<_> = if depth < self.max_depth {
+            heights.map(|(item, _)| item).collect()
+        }
+        else {
+            let heights: Vec<_> = heights.collect();
+            let min = heights.iter().map(|&(_, height)| height).min();
+            heights.into_iter().filter(|&(_, height)| Some(height) == min)
+                   .map(|(item, _)| item).collect()
+        };
+        if choices.is_empty() {
+            return
+        }
+        let choice = choices[self.rng.below(choices.len())];
+        for repetition in &choice.repetitions {
+            self.repetition(repetition, depth, target)
+        }
+    }
+
+    fn repetition(&self, repetition: &Repetition, depth: usize,
+                  target: &mut Vec<u8>) {
+        if self.element_height(&repetition.element).is_none() {
+            return
+        }
+        let count = if depth < self.max_depth {
+            let max = cmp::min(
+                repetition.max.unwrap_or(usize::MAX),
+                repetition.min.saturating_add(self.max_repeat)
+            );
+            self.rng.between(repetition.min, max)
+        }
+        else {
+            repetition.min
+        };
+        for _ in 0..count {
+            self.element(&repetition.element, depth, target)
+        }
+    }
+
+    fn element(&self, element: &Element, depth: usize,
+               target: &mut Vec<u8>) {
+        match *element {
+            Element::Rulename(ref name) => {
+                if let Some(rule) = self.get(name) {
+                    self.alternation(&rule.alternation, depth + 1, target)
+                }
+            }
+            Element::Group(ref alternation) => {
+                self.alternation(alternation, depth, target)
+            }
+            Element::Optional(ref alternation) => {
+                if depth < self.max_depth
+                    && self.alternation_height(alternation).is_some()
+                    && self.rng.below(2) == 1
+                {
+                    self.alternation(alternation, depth, target)
+                }
+            }
+            Element::CharVal(ref value) => self.char_val(value, target),
+            Element::NumVal(ref value) => self.num_val(value, target),
+            Element::ProseVal(_) => { }
+        }
+    }
+
+    fn char_val(&self, value: &CharVal, target: &mut Vec<u8>) {
+        for &ch in value.value.as_bytes() {
+            if !value.case_sensitive && self.rng.below(2) == 1 {
+                if ch.is_ascii_lowercase() {
+                    target.push(ch.to_ascii_uppercase());
+                    continue
+                }
+                else if ch.is_ascii_uppercase() {
+                    target.push(ch.to_ascii_lowercase());
+                    continue
+                }
+            }
+            target.push(ch)
+        }
+    }
+
+    fn num_val(&self, value: &NumVal, target: &mut Vec<u8>) {
+        match value.kind {
+            NumKind::Range(first, last) => {
+                let last = cmp::min(last, 0xFF);
+                if first <= last {
+                    target.push(self.rng.between(
+                        first as usize, last as usize
+                    ) as u8)
+                }
+            }
+            NumKind::Concat(ref values) => {
+                target.extend(values.iter().map(|&value| value as u8))
+            }
+        }
+    }
+}
+
+
+//--- Heights
+//
+// The height of a part of the grammar is the smallest number of rule
+// references nested within each other needed to generate it, or `None`
+// if it can’t be generated at all. Since taking the choices of minimal
+// height makes the height of referenced rules strictly smaller, doing so
+// beyond the maximum depth is guaranteed to end.
+
+impl<'a> Generator<'a> {
+    fn find_heights(&self) -> HashMap<String, usize> {
+        let rules: Vec<_> = self.rules.iter().chain(
+            self.core.iter().filter(|rule| !self.rules.contains(&rule.name))
+        ).collect();
+        let mut heights = HashMap::new();
+        loop {
+            let mut changed = false;
+            for rule in &rules {
+                let height = Heights(&heights).alternation(&rule.alternation);
+                let name = rule.name.to_ascii_lowercase();
+                if let Some(height) = height {
+                    if heights.get(&name).is_none_or(|&old| height < old) {
+                        heights.insert(name, height);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return heights
+            }
+        }
+    }
+
+    fn alternation_height(&self, alternation: &Alternation) -> Option<usize> {
+        Heights(&self.heights).alternation(alternation)
+    }
+
+    fn concatenation_height(&self, concatenation: &Concatenation)
+                            -> Option<usize> {
+        Heights(&self.heights).concatenation(concatenation)
+    }
+
+    fn element_height(&self, element: &Element) -> Option<usize> {
+        Heights(&self.heights).element(element)
+    }
+}
+
+/// Determines heights from the known heights of rules.
+struct Heights<'a>(&'a HashMap<String, usize>);
+
+impl<'a> Heights<'a> {
+    fn alternation(&self, alternation: &Alternation) -> Option<usize> {
+        alternation.concatenations.iter().filter_map(|item| {
+            self.concatenation(item)
+        }).min()
+    }
+
+    fn concatenation(&self, concatenation: &Concatenation) -> Option<usize> {
+        let mut res = 0;
+        for repetition in &concatenation.repetitions {
+            if repetition.min > 0 {
+                res = cmp::max(res, self.element(&repetition.element)?);
+            }
+        }
+        Some(res)
+    }
+
+    fn element(&self, element: &Element) -> Option<usize> {
+        match *element {
+            Element::Rulename(ref name) => {
+                self.0.get(&name.to_ascii_lowercase()).map(|&h| h + 1)
+            }
+            Element::Group(ref alternation) => self.alternation(alternation),
+            Element::Optional(_) | Element::CharVal(_) => Some(0),
+            Element::NumVal(ref value) => match value.kind {
+                NumKind::Range(first, _) if first > 0xFF => None,
+                NumKind::Concat(ref values)
+                    if values.iter().any(|&value| value > 0xFF) => None,
+                _ => Some(0),
+            },
+            Element::ProseVal(_) => None,
+        }
+    }
+}
+
+
+//------------ Rng -----------------------------------------------------------
+
+/// A simple pseudo-random number generator.
+///
+/// This is xorshift64*. It is by no means suitable for anything where
+/// security matters but good enough to pick choices from a grammar.
+#[derive(Debug)]
+struct Rng(Cell<u64>);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero.
+        Rng(Cell::new(seed ^ 0x9E37_79B9_7F4A_7C15 | 1))
+    }
+
+    fn next(&self) -> u64 {
+        let mut x = self.0.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0.set(x);
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number less than `n` which must not be zero.
+    fn below(&self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns a number between `min` and `max`, both included.
+    fn between(&self, min: usize, max: usize) -> usize {
+        match (max - min).checked_add(1) {
+            Some(n) => min + self.below(n),
+            None => self.next() as usize
+        }
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::BytesMut;
+    use ::Async;
+    use super::super::interp::Interpreter;
+
+    const GRAMMAR: &[u8] = b"\
+message = command *(SP arg) CRLF
+command = 1*ALPHA
+arg     = 1*DIGIT / DQUOTE *(%x20-21 / %x23-7E) DQUOTE
+list    = \"(\" [item *(\",\" item)] \")\"
+item    = list / 1*HEXDIG
+";
+
+    #[test]
+    fn generate() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let interp = Interpreter::new(&rules);
+        for seed in 0..100 {
+            for name in &["message", "list"] {
+                let data = Generator::new(&rules, seed).generate(name)
+                                                       .unwrap();
+                let mut buf = BytesMut::from(data.as_slice());
+                buf.extend_from_slice(b"\0");
+                assert_eq!(interp.skip(name, &mut buf), Ok(Async::Ready(())),
+                           "{:?}", String::from_utf8_lossy(&data));
+                assert_eq!(&buf[..], b"\0");
+            }
+        }
+    }
+
+    #[test]
+    fn seed() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let first = Generator::new(&rules, 7);
+        let second = Generator::new(&rules, 7);
+        for _ in 0..10 {
+            assert_eq!(first.generate("message"), second.generate("message"))
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let mut gen = Generator::new(&rules, 1);
+        gen.set_max_depth(0);
+        gen.set_max_repeat(0);
+        assert_eq!(gen.generate("list"), Some(b"()".to_vec()));
+        assert_eq!(gen.generate("message").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn impossible() {
+        let rules = RuleList::from_slice(
+            b"a = <prose> / b\nb = %x100 / c\nc = \"c\" c\n\
+              d = a / [a] %s\"d\" / x\n"
+        ).unwrap();
+        let gen = Generator::new(&rules, 0);
+        assert!(!gen.can_generate("a"));
+        assert!(!gen.can_generate("x"));
+        assert_eq!(gen.generate("a"), None);
+        assert_eq!(gen.generate("x"), None);
+        for _ in 0..10 {
+            assert_eq!(gen.generate("d"), Some(b"d".to_vec()));
+        }
+        assert_eq!(gen.generate("alpha").map(|data| data.len()), Some(1));
+    }
+}
@@ -0,0 +1,203 @@
+//! Extracting grammars from RFCs.
+//!
+//! The plain text versions of RFCs contain their grammar as indented
+//! fragments scattered between the prose. The function [`extract()`]
+//! finds these fragments and turns them into ABNF that can be given to
+//! `RuleList::from_slice()`. `RuleList::from_rfc()` does both in one go.
+//!
+//! A fragment starts with an indented line that begins with a rule name
+//! followed by `=` or `=/`. All following lines that are indented deeper
+//! than that line belong to the same rule, as do comment lines. A rule
+//! ends at the first line that doesn’t belong to it or at an empty line.
+//!
+//! The page breaks of the text are removed before. These consist of a
+//! footer line ending in `[Page n]`, a form feed, and a header line. A
+//! rule continues across a page break even though there are empty lines
+//! around it.
+//!
+//! Everything else is dropped. In order for line numbers in syntax errors
+//! to refer to the lines of the RFC, each dropped line is replaced by an
+//! empty line or, within a rule, a line with only a comment.
+//!
+//! [`extract()`]: fn.extract.html
+
+
+//------------ extract -------------------------------------------------------
+
+/// Extracts the ABNF from the plain text of an RFC.
+///
+/// Returns ABNF with the same number of lines as `data`, each ending in
+/// a bare LF.
+pub fn extract(data: &[u8]) -> Vec<u8> {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    let mut res = Vec::with_capacity(data.len());
+
+    // The indentation of the first line of the current rule.
+    let mut rule = None;
+
+    // Whether there were empty lines or a page break since the last line.
+    let mut empty = false;
+    let mut page_break = false;
+
+    // Whether the next non-empty line is a page header.
+    let mut header = false;
+
+    for line in data.split(|&ch| ch == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let indent = line.iter().take_while(|&&ch| {
+            ch == b' ' || ch == b'\t'
+        }).count();
+        let text = &line[indent..];
+        if line.contains(&b'\x0C') {
+            page_break = true;
+            header = true;
+        }
+        else if text.is_empty() {
+            empty = true;
+        }
+        else if header && indent == 0 {
+            header = false;
+        }
+        else if is_footer(text) {
+            page_break = true;
+        }
+        else {
+            header = false;
+            let continues = match rule {
+                Some(rule) => {
+                    (!empty || page_break)
+                        && (indent > rule || text.starts_with(b";"))
+                }
+                None => false
+            };
+            empty = false;
+            page_break = false;
+            if continues {
+                res.push(b' ');
+                res.extend_from_slice(text);
+                res.push(b'\n');
+                continue
+            }
+            if indent > 0 && is_rule_start(text) {
+                rule = Some(indent);
+                res.extend_from_slice(text);
+                res.push(b'\n');
+                continue
+            }
+            rule = None;
+        }
+        if rule.is_some() {
+            res.extend_from_slice(b" ;\n");
+        }
+        else {
+            res.push(b'\n');
+        }
+    }
+    res
+}
+
+/// Returns whether a line starts with a rule name and `=`.
+fn is_rule_start(text: &[u8]) -> bool {
+    if !text.first().is_some_and(u8::is_ascii_alphabetic) {
+        return false
+    }
+    let name = text.iter().take_while(|&&ch| {
+        ch.is_ascii_alphanumeric() || ch == b'-'
+    }).count();
+    let rest = &text[name..];
+    let space = rest.iter().take_while(|&&ch| {
+        ch == b' ' || ch == b'\t'
+    }).count();
+    rest[space..].starts_with(b"=")
+}
+
+/// Returns whether a line is a page footer.
+fn is_footer(text: &[u8]) -> bool {
+    let text = match text.strip_suffix(b"]") {
+        Some(text) => text,
+        None => return false
+    };
+    let digits = text.iter().rev().take_while(|ch| {
+        ch.is_ascii_digit()
+    }).count();
+    digits > 0 && text[..text.len() - digits].ends_with(b"[Page ")
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{GrammarError, RuleList};
+
+    const RFC: &[u8] = b"\
+Network Working Group                                          A. Author
+Request for Comments: 9999                                  January 2024
+
+
+1.  Syntax
+
+   The syntax of a greeting is as follows:
+
+      greeting   = \"HELO\" SP domain
+                   [SP comment] CRLF
+      ; the domain
+      domain     = label *(\".\"
+                   label)
+
+   where a label is:
+
+      label      = 1*(ALPHA / DIGIT /
+
+
+
+Author                       Informational                      [Page 1]
+\x0C
+RFC 9999                        Greetings                   January 2024
+
+
+                   \"-\")
+      comment    = *VCHAR
+
+   And that is all.
+";
+
+    #[test]
+    fn extract() {
+        let abnf = super::extract(RFC);
+        assert_eq!(
+            abnf.split(|&ch| ch == b'\n').count(),
+            RFC.split(|&ch| ch == b'\n').count()
+        );
+        assert_eq!(
+            RuleList::from_slice(&abnf).unwrap(),
+            RuleList::from_slice(
+                b"greeting = \"HELO\" SP domain [SP comment] CRLF\n\
+                  domain = label *(\".\" label)\n\
+                  label = 1*(ALPHA / DIGIT / \"-\")\n\
+                  comment = *VCHAR\n"
+            ).unwrap()
+        );
+        assert_eq!(RuleList::from_rfc(RFC), RuleList::from_slice(&abnf));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            RuleList::from_rfc(b"Text\n\n   a = b\n   b = (c\n\n"),
+            Err(GrammarError::Syntax(4))
+        );
+    }
+
+    #[test]
+    fn helpers() {
+        assert!(is_rule_start(b"a-1 =/ b"));
+        assert!(is_rule_start(b"a=b"));
+        assert!(!is_rule_start(b"where a = b"));
+        assert!(!is_rule_start(b"1a = b"));
+        assert!(is_footer(b"Author      Standards Track     [Page 12]"));
+        assert!(!is_footer(b"[Page ]"));
+        assert!(!is_footer(b"Page 12"));
+    }
+}
@@ -0,0 +1,246 @@
+//! Property testing with data generated from a grammar.
+//!
+//! This module is only available with the `proptest` feature. It provides
+//! a [`RuleStrategy`] for the `proptest` crate that produces data for a
+//! rule of a grammar through a [`Generator`]. A property test can then
+//! check that a hand-written parser accepts everything the grammar
+//! permits:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn accepts_greeting(data in strategy(&RULES, "greeting").unwrap()) {
+//!         let mut buf = CursorBuf::from(&data[..]);
+//!         prop_assert!(parse_greeting(&mut buf).is_ok());
+//!     }
+//! }
+//! ```
+//!
+//! Each test case is produced from a seed drawn from the test runner.
+//! When a case fails, it is simplified by generating data from the same
+//! seed with fewer extra repetitions and a smaller maximum depth of rule
+//! references, which leads to shorter data.
+//!
+//! [`RuleStrategy`]: struct.RuleStrategy.html
+//! [`Generator`]: ../random/struct.Generator.html
+
+use std::fmt;
+use std::sync::Arc;
+use proptest::prelude::Rng;
+use proptest::strategy::{NewTree, Strategy, ValueTree};
+use proptest::test_runner::TestRunner;
+use super::RuleList;
+use super::random::Generator;
+
+
+//------------ strategy ------------------------------------------------------
+
+/// Returns a strategy producing data for the rule `name` of a rule list.
+///
+/// Returns `None` if the rule isn’t defined or can’t be generated.
+pub fn strategy(rules: &RuleList, name: &str) -> Option<RuleStrategy> {
+    RuleStrategy::new(rules.clone(), name)
+}
+
+
+//------------ RuleStrategy --------------------------------------------------
+
+/// A strategy producing data for a rule.
+#[derive(Clone, Debug)]
+pub struct RuleStrategy {
+    rules: Arc<RuleList>,
+    name: String,
+    max_repeat: usize,
+    max_depth: usize,
+}
+
+impl RuleStrategy {
+    /// Creates a strategy for the rule `name` of a rule list.
+    ///
+    /// Returns `None` if the rule isn’t defined or can’t be generated.
+    pub fn new(rules: RuleList, name: &str) -> Option<Self> {
+        if !Generator::new(&rules, 0).can_generate(name) {
+            return None
+        }
+        Some(RuleStrategy {
+            rules: Arc::new(rules),
+            name: name.into(),
+            max_repeat: 8,
+            max_depth: 16,
+        })
+    }
+
+    /// Sets how often unbounded repetitions are repeated at most.
+    ///
+    /// See `Generator::set_max_repeat()` for details. The default is 8.
+    pub fn set_max_repeat(&mut self, max_repeat: usize) {
+        self.max_repeat = max_repeat
+    }
+
+    /// Sets the depth of rule references to make random choices up to.
+    ///
+    /// See `Generator::set_max_depth()` for details. The default is 16.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth
+    }
+}
+
+impl Strategy for RuleStrategy {
+    type Tree = RuleValueTree;
+    type Value = Vec<u8>;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let mut res = RuleValueTree {
+            strategy: self.clone(),
+            seed: runner.rng().next_u64(),
+            current: Vec::new(),
+            previous: None,
+            repeat_done: false,
+            depth_done: false,
+        };
+        res.generate();
+        Ok(res)
+    }
+}
+
+
+//------------ RuleValueTree -------------------------------------------------
+
+/// The value tree of a `RuleStrategy`.
+///
+/// Simplifying halves the maximum number of extra repetitions until it
+/// reaches zero or the test stops failing, then does the same with the
+/// maximum depth.
+pub struct RuleValueTree {
+    /// The strategy with the current limits.
+    strategy: RuleStrategy,
+
+    /// The seed for the generator.
+    seed: u64,
+
+    /// The current value.
+    current: Vec<u8>,
+
+    /// The limits before the last simplification.
+    previous: Option<(usize, usize)>,
+
+    /// Whether simplifying the number of repetitions is exhausted.
+    repeat_done: bool,
+
+    /// Whether simplifying the depth is exhausted.
+    depth_done: bool,
+}
+
+impl RuleValueTree {
+    fn generate(&mut self) {
+        let mut gen = Generator::new(&self.strategy.rules, self.seed);
+        gen.set_max_repeat(self.strategy.max_repeat);
+        gen.set_max_depth(self.strategy.max_depth);
+        self.current = gen.generate(&self.strategy.name).unwrap_or_default();
+    }
+}
+
+impl ValueTree for RuleValueTree {
+    type Value = Vec<u8>;
+
+    fn current(&self) -> Vec<u8> {
+        self.current.clone()
+    }
+
+    fn simplify(&mut self) -> bool {
+        let limits = (self.strategy.max_repeat, self.strategy.max_depth);
+        if !self.repeat_done && self.strategy.max_repeat > 0 {
+            self.strategy.max_repeat /= 2;
+        }
+        else if !self.depth_done && self.strategy.max_depth > 0 {
+            self.repeat_done = true;
+            self.strategy.max_depth /= 2;
+        }
+        else {
+            return false
+        }
+        self.previous = Some(limits);
+        self.generate();
+        true
+    }
+
+    fn complicate(&mut self) -> bool {
+        let (max_repeat, max_depth) = match self.previous.take() {
+            Some(limits) => limits,
+            None => return false
+        };
+        if self.strategy.max_repeat != max_repeat {
+            self.repeat_done = true;
+        }
+        else {
+            self.depth_done = true;
+        }
+        self.strategy.max_repeat = max_repeat;
+        self.strategy.max_depth = max_depth;
+        self.generate();
+        true
+    }
+}
+
+impl fmt::Debug for RuleValueTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RuleValueTree")
+            .field("seed", &self.seed)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::BytesMut;
+    use proptest::test_runner::TestCaseError;
+    use ::Async;
+    use super::super::interp::Interpreter;
+
+    const GRAMMAR: &[u8] = b"\
+list = \"(\" [item *(\",\" item)] \")\"
+item = list / 1*DIGIT
+";
+
+    #[test]
+    fn accepted() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let interp = Interpreter::new(&rules);
+        let strategy = strategy(&rules, "list").unwrap();
+        TestRunner::default().run(&strategy, |data| {
+            let mut buf = BytesMut::from(data.as_slice());
+            buf.extend_from_slice(b"\0");
+            if interp.skip("list", &mut buf) != Ok(Async::Ready(())) {
+                return Err(TestCaseError::fail("not accepted"))
+            }
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn shrink() {
+        let rules = RuleList::from_slice(GRAMMAR).unwrap();
+        let strategy = strategy(&rules, "list").unwrap();
+        let mut runner = TestRunner::deterministic();
+        let mut tree = strategy.new_tree(&mut runner).unwrap();
+        let first = tree.current();
+        assert!(tree.simplify());
+        assert!(tree.complicate());
+        assert_eq!(tree.current(), first);
+        assert!(!tree.complicate());
+        while tree.simplify() { }
+        assert_eq!(tree.current(), b"()");
+    }
+
+    #[test]
+    fn impossible() {
+        let rules = RuleList::from_slice(b"a = <prose>\n").unwrap();
+        assert!(strategy(&rules, "a").is_none());
+        assert!(strategy(&rules, "b").is_none());
+    }
+}
@@ -0,0 +1,553 @@
+//! Parsing ABNF.
+//!
+//! This module implements the ABNF of ABNF from section 4 of RFC 5234
+//! with the case-sensitive strings of RFC 7405.
+//! Like all parsers in this crate, the functions herein operate on a
+//! buffer that may not contain all data yet. In order to decide whether
+//! a rule continues on the next line, they need to see the first octet
+//! of that line.
+//!
+//! In deviation from the RFC, lines may end in a bare LF in addition to
+//! CRLF since that is how grammars copied from RFCs usually end up.
+
+use futures::{Async, Poll};
+use ::core::{LineEnding, crlf_with, test_alpha, test_digit, test_vchar,
+             test_wsp, u32_bits, u32_digits, u32_hexdigs};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::{Token, TokenError};
+use super::{Alternation, Base, CharVal, Concatenation, Element, NumKind,
+            NumVal, Repetition, Rule};
+
+
+//------------ Rules ---------------------------------------------------------
+
+/// Parses either a rule definition or a line without one.
+///
+/// Returns `None` if the line was empty or contained only a comment.
+//
+//  rulelist = 1*( rule / (*c-wsp c-nl) )
+pub fn parse_rulelist_item<B: Buffer>(buf: &mut B)
+                                      -> Poll<Option<Definition>, TokenError> {
+    match buf.get(0) {
+        None => Ok(Async::NotReady),
+        Some(ch) if test_alpha(ch) => {
+            parse_definition(buf).map(|res| res.map(Some))
+        }
+        Some(_) => {
+            try_ready!(token::skip(buf, |token| {
+                try_ready!(opt_c_wsps(token));
+                c_nl(token)
+            }));
+            Ok(Async::Ready(None))
+        }
+    }
+}
+
+/// Parses a rule definition including the line ending after it.
+//
+//  rule = rulename defined-as elements c-nl
+//  elements = alternation *c-wsp
+pub fn parse_definition<B: Buffer>(buf: &mut B)
+                                   -> Poll<Definition, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_rulename(buf));
+        let incremental = try_ready!(parse_defined_as(buf));
+        let alternation = try_ready!(parse_alternation(buf));
+        try_ready!(token::skip(buf, |token| {
+            try_ready!(opt_c_wsps(token));
+            c_nl(token)
+        }));
+        Ok(Async::Ready(Definition {
+            rule: Rule { name, alternation },
+            incremental
+        }))
+    })
+}
+
+/// Parses the operator between a rule name and its elements.
+///
+/// Returns whether the definition adds incremental alternatives.
+//
+//  defined-as = *c-wsp ("=" / "=/") *c-wsp
+fn parse_defined_as<B: Buffer>(buf: &mut B) -> Poll<bool, TokenError> {
+    let mut incremental = false;
+    try_ready!(token::skip(buf, |token| {
+        try_ready!(opt_c_wsps(token));
+        try_ready!(token::octet(token, b'='));
+        incremental = try_ready!(token.advance_if(|ch| ch == b'/'));
+        try_ready!(opt_c_wsps(token));
+        Ok(Async::Ready(()))
+    }));
+    Ok(Async::Ready(incremental))
+}
+
+
+//------------ Definition ----------------------------------------------------
+
+/// A rule definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Definition {
+    /// The rule being defined.
+    pub rule: Rule,
+
+    /// Whether the rule adds alternatives to an existing rule.
+    ///
+    /// This is the case if the definition used `=/` instead of `=`.
+    pub incremental: bool,
+}
+
+
+//------------ Rule Names ----------------------------------------------------
+
+/// Returns whether `ch` may appear in a rule name after the first octet.
+pub fn test_rulename(ch: u8) -> bool {
+    test_alpha(ch) || test_digit(ch) || ch == b'-'
+}
+
+/// Advances over a rule name.
+//
+//  rulename = ALPHA *(ALPHA / DIGIT / "-")
+pub fn rulename<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::cat(token, test_alpha));
+    try_ready!(token::opt_cats(token, test_rulename));
+    Ok(Async::Ready(()))
+}
+
+/// Parses a rule name.
+pub fn parse_rulename<B: Buffer>(buf: &mut B) -> Poll<String, TokenError> {
+    token::convert(buf, rulename, |res| res.map(ascii_string))
+}
+
+
+//------------ Comments and White Space --------------------------------------
+
+/// Advances over a comment or a line ending.
+//
+//  c-nl = comment / CRLF
+//  comment = ";" *(WSP / VCHAR) CRLF
+pub fn c_nl<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    if try_ready!(token.first()) == b';' {
+        token.advance(1);
+        try_ready!(token::opt_cats(token, |ch| {
+            test_wsp(ch) || test_vchar(ch)
+        }));
+    }
+    crlf_with(token, LineEnding::Lf)
+}
+
+/// Advances over white space or a line break followed by white space.
+//
+//  c-wsp = WSP / (c-nl WSP)
+pub fn c_wsp<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    if try_ready!(token.advance_if(test_wsp)) {
+        return Ok(Async::Ready(()))
+    }
+    token::group(token, |token| {
+        try_ready!(c_nl(token));
+        token::cat(token, test_wsp)
+    })
+}
+
+/// Advances over a possibly empty sequence of `c_wsp()`.
+///
+/// Returns whether the sequence was non-empty.
+pub fn opt_c_wsps<B: Buffer>(token: &mut Token<B>)
+                             -> Poll<bool, TokenError> {
+    let mut res = false;
+    loop {
+        match try_result!(c_wsp(token)) {
+            Ok(()) => res = true,
+            Err(TokenError::Invalid) => return Ok(Async::Ready(res)),
+            Err(err) => return Err(err)
+        }
+    }
+}
+
+/// Skips over a possibly empty sequence of `c_wsp()`.
+///
+/// Returns whether anything was skipped.
+pub fn skip_opt_c_wsps<B: Buffer>(buf: &mut B) -> Poll<bool, TokenError> {
+    let mut res = false;
+    try_ready!(token::skip(buf, |token| {
+        res = try_ready!(opt_c_wsps(token));
+        Ok(Async::Ready(()))
+    }));
+    Ok(Async::Ready(res))
+}
+
+
+//------------ Alternations and Concatenations -------------------------------
+
+/// Parses an alternation.
+//
+//  alternation = concatenation *(*c-wsp "/" *c-wsp concatenation)
+pub fn parse_alternation<B: Buffer>(buf: &mut B)
+                                    -> Poll<Alternation, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let mut concatenations = vec![try_ready!(parse_concatenation(buf))];
+        while let Some(item) = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_opt_c_wsps(buf));
+                try_ready!(token::skip_octet(buf, b'/'));
+                try_ready!(skip_opt_c_wsps(buf));
+                parse_concatenation(buf)
+            })
+        })) {
+            concatenations.push(item)
+        }
+        Ok(Async::Ready(Alternation { concatenations }))
+    })
+}
+
+/// Parses a concatenation.
+//
+//  concatenation = repetition *(1*c-wsp repetition)
+pub fn parse_concatenation<B: Buffer>(buf: &mut B)
+                                      -> Poll<Concatenation, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let mut repetitions = vec![try_ready!(parse_repetition(buf))];
+        while let Some(item) = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                if !try_ready!(skip_opt_c_wsps(buf)) {
+                    return Err(TokenError::Invalid)
+                }
+                parse_repetition(buf)
+            })
+        })) {
+            repetitions.push(item)
+        }
+        Ok(Async::Ready(Concatenation { repetitions }))
+    })
+}
+
+
+//------------ Repetitions ---------------------------------------------------
+
+/// Parses a repetition.
+//
+//  repetition = [repeat] element
+pub fn parse_repetition<B: Buffer>(buf: &mut B)
+                                   -> Poll<Repetition, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let (min, max) = try_ready!(parse_repeat(buf));
+        let element = try_ready!(parse_element(buf));
+        Ok(Async::Ready(Repetition { min, max, element }))
+    })
+}
+
+/// Parses the optional repeat prefix of a repetition.
+///
+/// Returns the minimum and maximum number of occurrences. If there is
+/// no prefix, both are one.
+//
+//  repeat = 1*DIGIT / (*DIGIT "*" *DIGIT)
+fn parse_repeat<B: Buffer>(buf: &mut B)
+                           -> Poll<(usize, Option<usize>), TokenError> {
+    let min = try_ready!(opt_count(buf));
+    if try_ready!(token::skip_opt_octet(buf, b'*')) {
+        let max = try_ready!(opt_count(buf));
+        let min = min.unwrap_or(0);
+        if max.map(|max| max < min).unwrap_or(false) {
+            return Err(TokenError::Invalid)
+        }
+        Ok(Async::Ready((min, max)))
+    }
+    else {
+        let count = min.unwrap_or(1);
+        Ok(Async::Ready((count, Some(count))))
+    }
+}
+
+fn opt_count<B: Buffer>(buf: &mut B) -> Poll<Option<usize>, TokenError> {
+    match buf.get(0) {
+        None => Ok(Async::NotReady),
+        Some(ch) if test_digit(ch) => {
+            u32_digits(buf).map(|res| res.map(|count| Some(count as usize)))
+        }
+        Some(_) => Ok(Async::Ready(None))
+    }
+}
+
+
+//------------ Elements ------------------------------------------------------
+
+/// Parses an element.
+//
+//  element = rulename / group / option / char-val / num-val / prose-val
+pub fn parse_element<B: Buffer>(buf: &mut B) -> Poll<Element, TokenError> {
+    match buf.get(0) {
+        None => Ok(Async::NotReady),
+        Some(b'(') => {
+            parse_group(buf, b'(', b')').map(|res| res.map(Element::Group))
+        }
+        Some(b'[') => {
+            parse_group(buf, b'[', b']').map(|res| {
+                res.map(Element::Optional)
+            })
+        }
+        Some(b'"') => parse_char_val(buf).map(|res| res.map(Element::CharVal)),
+        Some(b'%') => match buf.get(1) {
+            None => Ok(Async::NotReady),
+            Some(b'i') | Some(b'I') | Some(b's') | Some(b'S') => {
+                parse_char_val(buf).map(|res| res.map(Element::CharVal))
+            }
+            Some(_) => {
+                parse_num_val(buf).map(|res| res.map(Element::NumVal))
+            }
+        },
+        Some(b'<') => {
+            parse_prose_val(buf).map(|res| res.map(Element::ProseVal))
+        }
+        Some(ch) if test_alpha(ch) => {
+            parse_rulename(buf).map(|res| res.map(Element::Rulename))
+        }
+        Some(_) => Err(TokenError::Invalid)
+    }
+}
+
+/// Parses a group or an option.
+//
+//  group = "(" *c-wsp alternation *c-wsp ")"
+//  option = "[" *c-wsp alternation *c-wsp "]"
+fn parse_group<B: Buffer>(buf: &mut B, open: u8, close: u8)
+                          -> Poll<Alternation, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::skip_octet(buf, open));
+        try_ready!(skip_opt_c_wsps(buf));
+        let res = try_ready!(parse_alternation(buf));
+        try_ready!(skip_opt_c_wsps(buf));
+        try_ready!(token::skip_octet(buf, close));
+        Ok(Async::Ready(res))
+    })
+}
+
+
+//------------ Terminals -----------------------------------------------------
+
+/// Returns whether `ch` may appear in a quoted string.
+pub fn test_char_val(ch: u8) -> bool {
+    (0x20..=0x21).contains(&ch) || (0x23..=0x7E).contains(&ch)
+}
+
+/// Advances over a quoted string including its prefix.
+//
+//  char-val = case-insensitive-string / case-sensitive-string
+//  case-insensitive-string = [ "%i" ] quoted-string
+//  case-sensitive-string = "%s" quoted-string
+//  quoted-string = DQUOTE *(%x20-21 / %x23-7E) DQUOTE
+pub fn char_val<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    if try_ready!(token.first()) == b'%' {
+        token.advance(1);
+        try_ready!(token::cat(token, |ch| b"iIsS".contains(&ch)));
+    }
+    try_ready!(token::octet(token, b'"'));
+    try_ready!(token::opt_cats(token, test_char_val));
+    token::octet(token, b'"')
+}
+
+/// Parses a quoted string.
+pub fn parse_char_val<B: Buffer>(buf: &mut B) -> Poll<CharVal, TokenError> {
+    token::convert(buf, char_val, |res| {
+        let res = res?;
+        let (start, case_sensitive) = match res[0] {
+            b'%' => (3, res[1].eq_ignore_ascii_case(&b's')),
+            _ => (1, false)
+        };
+        Ok(CharVal {
+            value: ascii_string(&res[start..res.len() - 1]),
+            case_sensitive
+        })
+    })
+}
+
+/// Parses a terminal given by numeric values.
+//
+//  num-val = "%" (bin-val / dec-val / hex-val)
+//  bin-val = "b" 1*BIT [ 1*("." 1*BIT) / ("-" 1*BIT) ]
+//  dec-val = "d" 1*DIGIT [ 1*("." 1*DIGIT) / ("-" 1*DIGIT) ]
+//  hex-val = "x" 1*HEXDIG [ 1*("." 1*HEXDIG) / ("-" 1*HEXDIG) ]
+pub fn parse_num_val<B: Buffer>(buf: &mut B) -> Poll<NumVal, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::skip_octet(buf, b'%'));
+        let base = match buf.get(0) {
+            None => return Ok(Async::NotReady),
+            Some(b'b') | Some(b'B') => Base::Bin,
+            Some(b'd') | Some(b'D') => Base::Dec,
+            Some(b'x') | Some(b'X') => Base::Hex,
+            Some(_) => return Err(TokenError::Invalid)
+        };
+        buf.advance(1);
+        let first = try_ready!(parse_num(buf, base));
+        let kind = match buf.get(0) {
+            None => return Ok(Async::NotReady),
+            Some(b'-') => {
+                buf.advance(1);
+                let last = try_ready!(parse_num(buf, base));
+                if last < first {
+                    return Err(TokenError::Invalid)
+                }
+                NumKind::Range(first, last)
+            }
+            Some(_) => {
+                let mut values = vec![first];
+                while try_ready!(token::skip_opt_octet(buf, b'.')) {
+                    values.push(try_ready!(parse_num(buf, base)))
+                }
+                NumKind::Concat(values)
+            }
+        };
+        Ok(Async::Ready(NumVal { base, kind }))
+    })
+}
+
+fn parse_num<B: Buffer>(buf: &mut B, base: Base) -> Poll<u32, TokenError> {
+    match base {
+        Base::Bin => u32_bits(buf),
+        Base::Dec => u32_digits(buf),
+        Base::Hex => u32_hexdigs(buf),
+    }
+}
+
+/// Returns whether `ch` may appear in a prose description.
+pub fn test_prose_val(ch: u8) -> bool {
+    (0x20..=0x3D).contains(&ch) || (0x3F..=0x7E).contains(&ch)
+}
+
+/// Advances over a prose description.
+//
+//  prose-val = "<" *(%x20-3D / %x3F-7E) ">"
+pub fn prose_val<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    try_ready!(token::octet(token, b'<'));
+    try_ready!(token::opt_cats(token, test_prose_val));
+    token::octet(token, b'>')
+}
+
+/// Parses a prose description, returning it without the brackets.
+pub fn parse_prose_val<B: Buffer>(buf: &mut B) -> Poll<String, TokenError> {
+    token::convert(buf, prose_val, |res| {
+        let res = res?;
+        Ok(ascii_string(&res[1..res.len() - 1]))
+    })
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Converts octets already known to be ASCII into a string.
+fn ascii_string(data: &[u8]) -> String {
+    data.iter().map(|&ch| ch as char).collect()
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut {
+        BytesMut::from(slice)
+    }
+
+    fn char_val(value: &str, case_sensitive: bool) -> Element {
+        Element::CharVal(CharVal { value: value.into(), case_sensitive })
+    }
+
+    fn num_val(base: Base, kind: NumKind) -> Element {
+        Element::NumVal(NumVal { base, kind })
+    }
+
+    #[test]
+    fn elements() {
+        assert_eq_ready!(parse_element(&mut buf(b"foo-1 ")),
+                         Element::Rulename("foo-1".into()));
+        assert_eq_ready!(parse_element(&mut buf(b"\"a b\" ")),
+                         char_val("a b", false));
+        assert_eq_ready!(parse_element(&mut buf(b"%s\"aB\" ")),
+                         char_val("aB", true));
+        assert_eq_ready!(parse_element(&mut buf(b"%I\"aB\" ")),
+                         char_val("aB", false));
+        assert_eq_ready!(parse_element(&mut buf(b"<any text> ")),
+                         Element::ProseVal("any text".into()));
+        assert_eq_ready!(parse_element(&mut buf(b"%x41-5A ")),
+                         num_val(Base::Hex, NumKind::Range(0x41, 0x5A)));
+        assert_eq_ready!(parse_element(&mut buf(b"%d13.10 ")),
+                         num_val(Base::Dec, NumKind::Concat(vec![13, 10])));
+        assert_eq_ready!(parse_element(&mut buf(b"%B1010 ")),
+                         num_val(Base::Bin, NumKind::Concat(vec![10])));
+        assert_parse_err!(parse_element(&mut buf(b"%x5A-41 ")));
+        assert_parse_err!(parse_element(&mut buf(b"%q1 ")));
+        assert_parse_err!(parse_element(&mut buf(b"%s \"a\" ")));
+        assert_parse_err!(parse_element(&mut buf(b"\"a\tb\" ")));
+        assert_parse_err!(parse_element(&mut buf(b"/ ")));
+        assert_not_ready!(parse_element(&mut buf(b"foo")));
+        assert_not_ready!(parse_element(&mut buf(b"(a / b")));
+    }
+
+    #[test]
+    fn repetitions() {
+        fn repeat(data: &[u8]) -> (usize, Option<usize>) {
+            match parse_repetition(&mut buf(data)) {
+                Ok(Async::Ready(res)) => (res.min, res.max),
+                res => panic!("{:?}", res)
+            }
+        }
+
+        assert_eq!(repeat(b"a "), (1, Some(1)));
+        assert_eq!(repeat(b"3a "), (3, Some(3)));
+        assert_eq!(repeat(b"*a "), (0, None));
+        assert_eq!(repeat(b"1*a "), (1, None));
+        assert_eq!(repeat(b"*4a "), (0, Some(4)));
+        assert_eq!(repeat(b"2*4a "), (2, Some(4)));
+        assert_parse_err!(parse_repetition(&mut buf(b"4*2a ")));
+    }
+
+    #[test]
+    fn white_space() {
+        let mut data = buf(b"a\r\n   b ; comment\n c\nd = e\n");
+        let concat = assert_consumed!(data, 20,
+                                      parse_concatenation(&mut data));
+        assert_eq!(concat.unwrap(), Async::Ready(Concatenation {
+            repetitions: ["a", "b", "c"].iter().map(|name| Repetition {
+                min: 1, max: Some(1),
+                element: Element::Rulename((*name).into())
+            }).collect()
+        }));
+
+        let mut data = buf(b"( a\n  / b )\n");
+        let group = assert_consumed!(data, 11, parse_element(&mut data));
+        match group {
+            Ok(Async::Ready(Element::Group(alt))) => {
+                assert_eq!(alt.concatenations.len(), 2)
+            }
+            res => panic!("{:?}", res)
+        }
+
+        let mut data = buf(b"  ; just a comment\r\nfoo = bar\r\n");
+        let res = assert_consumed!(data, 20,
+                                   parse_rulelist_item(&mut data));
+        assert_eq_ready!(res, None);
+        assert_not_ready!(parse_rulelist_item(&mut data));
+    }
+
+    #[test]
+    fn definitions() {
+        fn definition(data: &[u8]) -> (String, bool, usize) {
+            match parse_definition(&mut buf(data)) {
+                Ok(Async::Ready(res)) => {
+                    (res.rule.name, res.incremental,
+                     res.rule.alternation.concatenations.len())
+                }
+                res => panic!("{:?}", res)
+            }
+        }
+
+        assert_eq!(definition(b"a = b / c\nd"), ("a".into(), false, 2));
+        assert_eq!(definition(b"a =/ b\nd"), ("a".into(), true, 1));
+        assert_eq!(definition(b"a\n =/\n b\nd"), ("a".into(), true, 1));
+        assert_parse_err!(parse_definition(&mut buf(b"a = / b\nd")));
+        assert_parse_err!(parse_definition(&mut buf(b"a / b\nd")));
+    }
+}
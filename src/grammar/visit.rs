@@ -0,0 +1,224 @@
+//! Walking the grammar AST.
+//!
+//! Tools that need to look at all the parts of a grammar can implement
+//! the [`Visitor`] trait instead of matching on the AST types themselves.
+//! The trait has a method for each kind of node. By default, the methods
+//! for inner nodes walk on to the children of the node by calling the
+//! `walk_*` function of the same name, while the methods for terminals do
+//! nothing. An implementation overrides the methods for the nodes it is
+//! interested in. If it still wants to reach the nodes below, it calls
+//! the walk function itself.
+//!
+//! For instance, a visitor collecting all the rule names referenced by a
+//! grammar only needs to implement one method:
+//!
+//! ```
+//! use abnf::grammar::{RuleList, Visitor};
+//!
+//! struct Names(Vec<String>);
+//!
+//! impl Visitor for Names {
+//!     fn visit_rulename(&mut self, name: &str) {
+//!         self.0.push(name.into())
+//!     }
+//! }
+//!
+//! let rules = RuleList::from_slice(b"a = b [c \"x\"]\n").unwrap();
+//! let mut names = Names(Vec::new());
+//! names.visit_rule_list(&rules);
+//! assert_eq!(names.0, ["b", "c"]);
+//! ```
+//!
+//! [`Visitor`]: trait.Visitor.html
+
+use super::{Alternation, CharVal, Concatenation, Element, NumVal,
+            Repetition, Rule, RuleList};
+
+
+//------------ Visitor -------------------------------------------------------
+
+/// A type visiting the nodes of a grammar.
+pub trait Visitor {
+    /// Visits all rules of a rule list.
+    fn visit_rule_list(&mut self, rules: &RuleList) {
+        walk_rule_list(self, rules)
+    }
+
+    /// Visits a rule.
+    fn visit_rule(&mut self, rule: &Rule) {
+        walk_rule(self, rule)
+    }
+
+    /// Visits an alternation.
+    fn visit_alternation(&mut self, alternation: &Alternation) {
+        walk_alternation(self, alternation)
+    }
+
+    /// Visits a concatenation.
+    fn visit_concatenation(&mut self, concatenation: &Concatenation) {
+        walk_concatenation(self, concatenation)
+    }
+
+    /// Visits a repetition.
+    fn visit_repetition(&mut self, repetition: &Repetition) {
+        walk_repetition(self, repetition)
+    }
+
+    /// Visits an element.
+    ///
+    /// The default implementation calls the method for the kind of
+    /// element.
+    fn visit_element(&mut self, element: &Element) {
+        walk_element(self, element)
+    }
+
+    /// Visits a group in parentheses.
+    fn visit_group(&mut self, alternation: &Alternation) {
+        self.visit_alternation(alternation)
+    }
+
+    /// Visits an optional group in square brackets.
+    fn visit_optional(&mut self, alternation: &Alternation) {
+        self.visit_alternation(alternation)
+    }
+
+    /// Visits a reference to a rule.
+    fn visit_rulename(&mut self, _name: &str) { }
+
+    /// Visits a quoted string.
+    fn visit_char_val(&mut self, _value: &CharVal) { }
+
+    /// Visits a numeric value.
+    fn visit_num_val(&mut self, _value: &NumVal) { }
+
+    /// Visits a prose value.
+    fn visit_prose_val(&mut self, _text: &str) { }
+}
+
+
+//------------ Walk Functions ------------------------------------------------
+
+/// Visits each rule of a rule list.
+pub fn walk_rule_list<V>(visitor: &mut V, rules: &RuleList)
+                      where V: Visitor + ?Sized {
+    for rule in rules {
+        visitor.visit_rule(rule)
+    }
+}
+
+/// Visits the alternation of a rule.
+pub fn walk_rule<V>(visitor: &mut V, rule: &Rule)
+                 where V: Visitor + ?Sized {
+    visitor.visit_alternation(&rule.alternation)
+}
+
+/// Visits each concatenation of an alternation.
+pub fn walk_alternation<V>(visitor: &mut V, alternation: &Alternation)
+                        where V: Visitor + ?Sized {
+    for concatenation in &alternation.concatenations {
+        visitor.visit_concatenation(concatenation)
+    }
+}
+
+/// Visits each repetition of a concatenation.
+pub fn walk_concatenation<V>(visitor: &mut V, concatenation: &Concatenation)
+                          where V: Visitor + ?Sized {
+    for repetition in &concatenation.repetitions {
+        visitor.visit_repetition(repetition)
+    }
+}
+
+/// Visits the element of a repetition.
+pub fn walk_repetition<V>(visitor: &mut V, repetition: &Repetition)
+                       where V: Visitor + ?Sized {
+    visitor.visit_element(&repetition.element)
+}
+
+/// Calls the visitor method for the kind of an element.
+pub fn walk_element<V>(visitor: &mut V, element: &Element)
+                    where V: Visitor + ?Sized {
+    match *element {
+        Element::Rulename(ref name) => visitor.visit_rulename(name),
+        Element::Group(ref alternation) => visitor.visit_group(alternation),
+        Element::Optional(ref alternation) => {
+            visitor.visit_optional(alternation)
+        }
+        Element::CharVal(ref value) => visitor.visit_char_val(value),
+        Element::NumVal(ref value) => visitor.visit_num_val(value),
+        Element::ProseVal(ref text) => visitor.visit_prose_val(text),
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Counts the nodes of each kind.
+    #[derive(Default)]
+    struct Count {
+        rules: usize,
+        alternations: usize,
+        concatenations: usize,
+        repetitions: usize,
+        terminals: usize,
+        optionals: usize,
+    }
+
+    impl Visitor for Count {
+        fn visit_rule(&mut self, rule: &Rule) {
+            self.rules += 1;
+            walk_rule(self, rule)
+        }
+
+        fn visit_alternation(&mut self, alternation: &Alternation) {
+            self.alternations += 1;
+            walk_alternation(self, alternation)
+        }
+
+        fn visit_concatenation(&mut self, concatenation: &Concatenation) {
+            self.concatenations += 1;
+            walk_concatenation(self, concatenation)
+        }
+
+        fn visit_repetition(&mut self, repetition: &Repetition) {
+            self.repetitions += 1;
+            walk_repetition(self, repetition)
+        }
+
+        fn visit_optional(&mut self, _alternation: &Alternation) {
+            // Don’t descend into optional parts.
+            self.optionals += 1;
+        }
+
+        fn visit_char_val(&mut self, _value: &CharVal) {
+            self.terminals += 1
+        }
+
+        fn visit_num_val(&mut self, _value: &NumVal) {
+            self.terminals += 1
+        }
+
+        fn visit_prose_val(&mut self, _text: &str) {
+            self.terminals += 1
+        }
+    }
+
+    #[test]
+    fn count() {
+        let rules = RuleList::from_slice(
+            b"a = b (\"x\" / %x30-39 c) [d \"y\"]\n\
+              b = <prose> / \"z\"\n"
+        ).unwrap();
+        let mut count = Count::default();
+        count.visit_rule_list(&rules);
+        assert_eq!(count.rules, 2);
+        assert_eq!(count.alternations, 3);
+        assert_eq!(count.concatenations, 5);
+        assert_eq!(count.repetitions, 8);
+        assert_eq!(count.terminals, 4);
+        assert_eq!(count.optionals, 1);
+    }
+}
@@ -0,0 +1,1271 @@
+//! Common HTTP rules.
+//!
+//! This module implements the generic rules RFC 9110 defines for use in
+//! header field values: tokens, quoted strings, the comma-separated
+//! lists denoted by the `#` operator, parameters, and dates. Protocols
+//! that reuse the HTTP header syntax can build their field parsers on top
+//! of them.
+//!
+//! From the HTTP/1.1 message syntax of RFC 9112, the module provides field
+//! lines and sections as well as a decoder for the chunked transfer
+//! coding.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::slice;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use futures::{Async, Poll};
+use ::compose::write_octets;
+use ::core::{digits, dquote, skip_crlf, skip_opt_wsps, test_alpha,
+             test_digit, test_vchar, test_wsp, u16_digits_exact,
+             u64_hexdigs, u8_digits_exact};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::{Keyword, Token, TokenError};
+#[cfg(feature = "arbitrary")] use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+
+//------------ token ---------------------------------------------------------
+
+/// Returns whether `ch` is `tchar` as defined by RFC 9110.
+pub fn test_tchar(ch: u8) -> bool {
+    test_alpha(ch) || test_digit(ch) || b"!#$%&'*+-.^_`|~".contains(&ch)
+}
+
+pub fn tchar<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::cat(token, test_tchar)
+}
+
+pub fn tchars<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::cats(token, test_tchar)
+}
+
+/// Parses a `token`.
+pub fn parse_token<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::parse(buf, tchars)
+}
+
+
+//------------ quoted-string -------------------------------------------------
+
+/// Returns whether `ch` is `qdtext` as defined by RFC 9110.
+///
+/// This includes `obs-text`, i.e., all octets from 0x80 onwards.
+pub fn test_qdtext(ch: u8) -> bool {
+    ch == b'\t' || ch == b' ' || ch == 0x21 || (0x23..=0x5B).contains(&ch)
+        || (0x5D..=0x7E).contains(&ch) || ch >= 0x80
+}
+
+/// Returns whether `ch` can follow the backslash of a `quoted-pair`.
+pub fn test_quoted_pair(ch: u8) -> bool {
+    ch == b'\t' || ch == b' ' || (0x21..=0x7E).contains(&ch) || ch >= 0x80
+}
+
+/// Advances over a `quoted-string` including the double quotes.
+pub fn quoted_string<B: Buffer>(token: &mut Token<B>)
+                                -> Poll<(), TokenError> {
+    token::group(token, |token| {
+        try_ready!(dquote(token));
+        loop {
+            match try_ready!(token.first()) {
+                b'"' => {
+                    token.advance(1);
+                    return Ok(Async::Ready(()))
+                }
+                b'\\' => {
+                    match token.peek(1) {
+                        Some(ch) if test_quoted_pair(ch) => token.advance(2),
+                        Some(_) => return Err(TokenError::Invalid),
+                        None => return Ok(Async::NotReady)
+                    }
+                }
+                ch if test_qdtext(ch) => token.advance(1),
+                _ => return Err(TokenError::Invalid)
+            }
+        }
+    })
+}
+
+/// Parses a `quoted-string` and returns its unescaped content.
+pub fn parse_quoted_string<B: Buffer>(buf: &mut B)
+                                      -> Poll<Bytes, TokenError> {
+    let quoted = try_ready!(token::parse(buf, quoted_string));
+    let quoted = &quoted[1..quoted.len() - 1];
+    if !quoted.contains(&b'\\') {
+        return Ok(Async::Ready(Bytes::copy_from_slice(quoted)))
+    }
+    let mut res = Vec::with_capacity(quoted.len());
+    let mut escaped = false;
+    for &ch in quoted {
+        if ch == b'\\' && !escaped {
+            escaped = true
+        }
+        else {
+            res.push(ch);
+            escaped = false
+        }
+    }
+    Ok(Async::Ready(res.into()))
+}
+
+/// Parses either a `token` or a `quoted-string`.
+///
+/// For a quoted string, returns the unescaped content.
+pub fn parse_token_or_quoted<B: Buffer>(buf: &mut B)
+                                        -> Poll<Bytes, TokenError> {
+    alt!(buf, parse_token, parse_quoted_string)
+}
+
+/// Writes a value as a `token` if possible or a `quoted-string` otherwise.
+///
+/// This is the inverse of `parse_token_or_quoted()`.
+pub(crate) fn write_token_or_quoted(f: &mut fmt::Formatter, value: &[u8])
+                                    -> fmt::Result {
+    if !value.is_empty() && value.iter().all(|&ch| test_tchar(ch)) {
+        return write_octets(f, value)
+    }
+    let mut quoted = Vec::with_capacity(value.len() + 2);
+    quoted.push(b'"');
+    for &ch in value {
+        if ch == b'"' || ch == b'\\' {
+            quoted.push(b'\\')
+        }
+        quoted.push(ch)
+    }
+    quoted.push(b'"');
+    write_octets(f, &quoted)
+}
+
+
+//------------ Lists ---------------------------------------------------------
+
+/// Parses a comma-separated list of elements.
+///
+/// This implements the `#element` and `1#element` rules of section 5.6.1
+/// of RFC 9110 with each element parsed by `element`. As the RFC demands
+/// from recipients, empty list elements are accepted and ignored. The
+/// list ends with the first element that isn’t followed by a comma.
+///
+/// Returns an error if there are fewer than `min` elements.
+pub fn parse_list<B, P, T, E>(buf: &mut B, min: usize, mut element: P)
+                              -> Poll<Vec<T>, E>
+                  where B: Buffer,
+                        P: FnMut(&mut B) -> Poll<T, E>,
+                        E: From<TokenError> {
+    rule::group_limited(buf, |buf| {
+        let mut res = Vec::new();
+        // *( "," OWS ) element
+        while try_ready!(token::skip_opt_octet(buf, b',')) {
+            try_ready!(skip_opt_wsps(buf));
+        }
+        if let Ok(item) = try_result!(rule::group_limited(buf, &mut element)) {
+            res.push(item)
+        }
+        // *( OWS "," [ OWS element ] )
+        while try_ready!(list_separator(buf)) {
+            try_ready!(skip_opt_wsps(buf));
+            if let Ok(item) = try_result!(rule::group_limited(buf, &mut element)) {
+                res.push(item)
+            }
+        }
+        if res.len() < min {
+            return Err(TokenError::Invalid.into())
+        }
+        Ok(Async::Ready(res))
+    })
+}
+
+/// Skips over `OWS ","` if present.
+fn list_separator<B: Buffer>(buf: &mut B) -> Poll<bool, TokenError> {
+    let res = rule::group_limited(buf, |buf| {
+        try_ready!(skip_opt_wsps(buf));
+        token::skip_octet(buf, b',')
+    });
+    match try_result!(res) {
+        Ok(()) => Ok(Async::Ready(true)),
+        Err(_) => Ok(Async::Ready(false))
+    }
+}
+
+
+//------------ Parameters ----------------------------------------------------
+
+/// Parses a list of parameters.
+///
+/// Parameters are separated by `item_sep` and consist of a name that is a
+/// `token`, optionally followed by `kv_sep` and a value parsed by `value`.
+/// Optional white space is allowed around both separators. This covers
+/// the parameters of media types and transfer codings with `b';'` and
+/// `b'='` as well as many similar constructs in other protocols.
+///
+/// The list may be empty. It ends before the first separator not followed
+/// by a valid parameter. A parameter whose value fails to parse ends the
+/// list before its separator, too.
+//
+//  param-list = [ param *( OWS item-sep OWS param ) ]
+//  param      = token [ OWS kv-sep OWS value ]
+pub fn param_list<B, V>(buf: &mut B, item_sep: u8, kv_sep: u8,
+                        mut value: V) -> Poll<ParamList, TokenError>
+                  where B: Buffer,
+                        V: FnMut(&mut B) -> Poll<Bytes, TokenError> {
+    let mut res = ParamList::new();
+    match try_ready!(rule::optional(buf, |buf| {
+        param(buf, kv_sep, &mut value)
+    })) {
+        Some(item) => res.params.push(item),
+        None => return Ok(Async::Ready(res))
+    }
+    while let Some(item) = try_ready!(rule::optional(buf, |buf| {
+        rule::group_limited(buf, |buf| {
+            try_ready!(skip_opt_wsps(buf));
+            try_ready!(token::skip_octet(buf, item_sep));
+            try_ready!(skip_opt_wsps(buf));
+            param(buf, kv_sep, &mut value)
+        })
+    })) {
+        res.params.push(item)
+    }
+    Ok(Async::Ready(res))
+}
+
+fn param<B, V>(buf: &mut B, kv_sep: u8, value: &mut V)
+               -> Poll<(Bytes, Option<Bytes>), TokenError>
+         where B: Buffer, V: FnMut(&mut B) -> Poll<Bytes, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_token(buf));
+        let sep = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_opt_wsps(buf));
+                token::skip_octet(buf, kv_sep)
+            })
+        }));
+        if sep.is_none() {
+            return Ok(Async::Ready((name, None)))
+        }
+        try_ready!(skip_opt_wsps(buf));
+        let value = try_ready!(value(buf));
+        Ok(Async::Ready((name, Some(value))))
+    })
+}
+
+
+//------------ ParamList -----------------------------------------------------
+
+/// An ordered list of parameters.
+///
+/// Each parameter has a name and an optional value. The same name may
+/// appear more than once. Names are compared case-insensitively.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParamList {
+    params: Vec<(Bytes, Option<Bytes>)>,
+}
+
+impl ParamList {
+    /// Creates a new, empty list.
+    pub fn new() -> Self {
+        ParamList { params: Vec::new() }
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns whether there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// Returns whether there is a parameter named `name`.
+    pub fn contains(&self, name: &[u8]) -> bool {
+        self.params.iter().any(|item| item.0.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of the first parameter named `name`.
+    ///
+    /// Returns `None` both if there is no such parameter and if the first
+    /// such parameter has no value.
+    pub fn get(&self, name: &[u8]) -> Option<&Bytes> {
+        self.params.iter().find(|item| {
+            item.0.eq_ignore_ascii_case(name)
+        }).and_then(|item| item.1.as_ref())
+    }
+
+    /// Returns the values of all parameters named `name` in order.
+    pub fn get_all<'a>(&'a self, name: &'a [u8])
+                       -> impl Iterator<Item = &'a Option<Bytes>> + 'a {
+        self.params.iter().filter(move |item| {
+            item.0.eq_ignore_ascii_case(name)
+        }).map(|item| &item.1)
+    }
+
+    /// Returns an iterator over all parameters in order.
+    pub fn iter(&self) -> slice::Iter<'_, (Bytes, Option<Bytes>)> {
+        self.params.iter()
+    }
+
+    /// Converts the list into a vector of names and values.
+    pub fn into_vec(self) -> Vec<(Bytes, Option<Bytes>)> {
+        self.params
+    }
+}
+
+impl<'a> IntoIterator for &'a ParamList {
+    type Item = &'a (Bytes, Option<Bytes>);
+    type IntoIter = slice::Iter<'a, (Bytes, Option<Bytes>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+
+//------------ HTTP-date -----------------------------------------------------
+
+/// Parses an `HTTP-date` into a system time.
+///
+/// All three formats of section 5.6.7 of RFC 9110 are accepted: the
+/// preferred IMF-fixdate as well as the obsolete RFC 850 and asctime
+/// formats. A two-digit year of the RFC 850 format is taken to be in
+/// the range from 1970 to 2069. The day name isn’t checked against the
+/// date.
+pub fn parse_http_date<B: Buffer>(buf: &mut B)
+                                  -> Poll<SystemTime, TokenError> {
+    parse_http_date_time(buf).map(|res| res.map(SystemTime::from))
+}
+
+/// Parses an `HTTP-date` into its date and time components.
+///
+/// This accepts the same formats as `parse_http_date()`.
+pub fn parse_http_date_time<B: Buffer>(buf: &mut B)
+                                       -> Poll<DateTime, TokenError> {
+    alt!(buf, imf_fixdate, rfc850_date, asctime_date)
+}
+
+//  IMF-fixdate  = day-name "," SP date1 SP time-of-day SP GMT
+//  date1        = day SP month SP year
+fn imf_fixdate<B: Buffer>(buf: &mut B) -> Poll<DateTime, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::parse_keyword(buf, &DAY_NAMES));
+        try_ready!(token::skip_literal(buf, b", "));
+        let day = try_ready!(u8_digits_exact(buf, 2));
+        try_ready!(token::skip_octet(buf, b' '));
+        let month = try_ready!(token::parse_keyword(buf, &MONTHS));
+        try_ready!(token::skip_octet(buf, b' '));
+        let year = try_ready!(u16_digits_exact(buf, 4));
+        try_ready!(token::skip_octet(buf, b' '));
+        let time = try_ready!(time_of_day(buf));
+        try_ready!(token::skip_literal(buf, b" GMT"));
+        DateTime::from_parts(year, month, day, time)
+    })
+}
+
+//  rfc850-date  = day-name-l "," SP date2 SP time-of-day SP GMT
+//  date2        = day "-" month "-" 2DIGIT
+fn rfc850_date<B: Buffer>(buf: &mut B) -> Poll<DateTime, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::parse_keyword(buf, &LONG_DAY_NAMES));
+        try_ready!(token::skip_literal(buf, b", "));
+        let day = try_ready!(u8_digits_exact(buf, 2));
+        try_ready!(token::skip_octet(buf, b'-'));
+        let month = try_ready!(token::parse_keyword(buf, &MONTHS));
+        try_ready!(token::skip_octet(buf, b'-'));
+        let year = u16::from(try_ready!(u8_digits_exact(buf, 2)));
+        let year = if year < 70 { 2000 + year } else { 1900 + year };
+        try_ready!(token::skip_octet(buf, b' '));
+        let time = try_ready!(time_of_day(buf));
+        try_ready!(token::skip_literal(buf, b" GMT"));
+        DateTime::from_parts(year, month, day, time)
+    })
+}
+
+//  asctime-date = day-name SP date3 SP time-of-day SP year
+//  date3        = month SP ( 2DIGIT / ( SP DIGIT ))
+fn asctime_date<B: Buffer>(buf: &mut B) -> Poll<DateTime, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::parse_keyword(buf, &DAY_NAMES));
+        try_ready!(token::skip_octet(buf, b' '));
+        let month = try_ready!(token::parse_keyword(buf, &MONTHS));
+        try_ready!(token::skip_octet(buf, b' '));
+        let day = if try_ready!(token::skip_opt_octet(buf, b' ')) {
+            try_ready!(u8_digits_exact(buf, 1))
+        }
+        else {
+            try_ready!(u8_digits_exact(buf, 2))
+        };
+        try_ready!(token::skip_octet(buf, b' '));
+        let time = try_ready!(time_of_day(buf));
+        try_ready!(token::skip_octet(buf, b' '));
+        let year = try_ready!(u16_digits_exact(buf, 4));
+        DateTime::from_parts(year, month, day, time)
+    })
+}
+
+//  time-of-day  = hour ":" minute ":" second
+fn time_of_day<B: Buffer>(buf: &mut B) -> Poll<(u8, u8, u8), TokenError> {
+    rule::group_limited(buf, |buf| {
+        let hour = try_ready!(u8_digits_exact(buf, 2));
+        try_ready!(token::skip_octet(buf, b':'));
+        let minute = try_ready!(u8_digits_exact(buf, 2));
+        try_ready!(token::skip_octet(buf, b':'));
+        let second = try_ready!(u8_digits_exact(buf, 2));
+        Ok(Async::Ready((hour, minute, second)))
+    })
+}
+
+
+//------------ DateTime ------------------------------------------------------
+
+/// A date and time in UTC as expressed by an `HTTP-date`.
+///
+/// The value is always a valid calendar date with a year between 0 and
+/// 9999. The second may be 60 for a leap second. Converting into a
+/// system time or timestamp treats it as the first second of the
+/// following minute.
+///
+/// With the `chrono` and `time` features, the value converts into
+/// `chrono::DateTime<FixedOffset>` and `time::OffsetDateTime`,
+/// respectively, both with an offset of zero. Only the former can
+/// represent a leap second.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl DateTime {
+    /// Creates a new value from its components.
+    ///
+    /// The month and day start at 1. Returns `None` if the components
+    /// don’t form a valid date and time or the year is past 9999.
+    pub fn new(year: u16, month: u8, day: u8,
+               hour: u8, minute: u8, second: u8) -> Option<Self> {
+        if year > 9999 || month == 0 || month > 12 || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23 || minute > 59 || second > 60
+        {
+            return None
+        }
+        Some(DateTime { year, month, day, hour, minute, second })
+    }
+
+    /// Creates a value from a Unix timestamp.
+    ///
+    /// Returns `None` if the timestamp is outside the years 0 to 9999.
+    pub fn from_unix_timestamp(secs: i64) -> Option<Self> {
+        let days = secs.div_euclid(86400);
+        let secs = secs.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        if !(0..=9999).contains(&year) {
+            return None
+        }
+        Some(DateTime {
+            year: year as u16, month, day,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        })
+    }
+
+    /// Creates a value from a system time.
+    ///
+    /// Fractions of a second are dropped. Returns `None` if the time is
+    /// outside the years 0 to 9999.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).ok()?,
+            Err(err) => {
+                let before = err.duration();
+                let secs = i64::try_from(before.as_secs()).ok()?;
+                if before.subsec_nanos() > 0 { -secs - 1 } else { -secs }
+            }
+        };
+        Self::from_unix_timestamp(secs)
+    }
+
+    /// Creates a value from parsed components or fails as invalid.
+    fn from_parts(year: u16, month: u8, day: u8, time: (u8, u8, u8))
+                  -> Poll<Self, TokenError> {
+        let (hour, minute, second) = time;
+        match Self::new(year, month, day, hour, minute, second) {
+            Some(res) => Ok(Async::Ready(res)),
+            None => Err(TokenError::Invalid)
+        }
+    }
+
+    /// Returns the year.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Returns the month starting at 1 for January.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Returns the day of the month starting at 1.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Returns the hour.
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// Returns the minute.
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// Returns the second.
+    ///
+    /// This is 60 for a leap second.
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Returns the day of the week starting at 1 for Monday.
+    pub fn weekday(&self) -> u8 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        // 1970-01-01 was a Thursday.
+        ((days + 3).rem_euclid(7) + 1) as u8
+    }
+
+    /// Returns the number of seconds since 1970-01-01T00:00:00Z.
+    pub fn unix_timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + i64::from(self.hour) * 3600 + i64::from(self.minute) * 60
+            + i64::from(self.second)
+    }
+}
+
+
+//--- Display
+
+impl fmt::Display for DateTime {
+    /// Formats the value as an IMF-fixdate.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |keywords: &[Keyword<u8>], value: u8| {
+            keywords.iter().find(|keyword| keyword.value == value)
+                    .map(|keyword| String::from_utf8_lossy(keyword.literal))
+                    .unwrap_or_default()
+        };
+        write!(f, "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+               name(&DAY_NAMES, self.weekday()), self.day,
+               name(&MONTHS, self.month), self.year,
+               self.hour, self.minute, self.second)
+    }
+}
+
+
+//--- From
+
+impl From<DateTime> for SystemTime {
+    fn from(date: DateTime) -> Self {
+        let secs = date.unix_timestamp();
+        if secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(secs as u64)
+        }
+        else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime> for chrono::DateTime<chrono::FixedOffset> {
+    fn from(date: DateTime) -> Self {
+        use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+        // The components are valid and in range for chrono, so unwrapping
+        // is fine. A leap second is represented as a second 59 with an
+        // extra second’s worth of nanoseconds.
+        let day = NaiveDate::from_ymd_opt(
+            i32::from(date.year), u32::from(date.month), u32::from(date.day)
+        ).unwrap();
+        let hour = u32::from(date.hour);
+        let minute = u32::from(date.minute);
+        let time = match date.second {
+            60 => NaiveTime::from_hms_nano_opt(hour, minute, 59,
+                                               1_000_000_000),
+            second => NaiveTime::from_hms_opt(hour, minute,
+                                              u32::from(second)),
+        }.unwrap();
+        NaiveDateTime::new(day, time).and_utc().fixed_offset()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<DateTime> for time::OffsetDateTime {
+    fn from(date: DateTime) -> Self {
+        // Years 0 to 9999 are within the default range of the time crate.
+        time::OffsetDateTime::from_unix_timestamp(date.unix_timestamp())
+            .unwrap()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for DateTime {
+    /// Produces a value within the years 0 to 9999.
+    ///
+    /// Leap seconds are never produced.
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        // 0000-01-01 00:00:00 and 9999-12-31 23:59:59.
+        let secs = u.int_in_range(-62_167_219_200..=253_402_300_799)?;
+        Ok(Self::from_unix_timestamp(secs).unwrap())
+    }
+}
+
+
+//------------ Calendar Math -------------------------------------------------
+
+/// Returns the number of days in a month.
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4)
+            && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// Returns the number of days since 1970-01-01 of a date.
+fn days_from_civil(year: u16, month: u8, day: u8) -> i64 {
+    // Years start in March so the leap day is at the end.
+    let year = if month <= 2 { i64::from(year) - 1 } else { i64::from(year) };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((i64::from(month) + 9) % 12) + 2) / 5
+                    + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100
+                   + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns year, month, and day of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    // This is the inverse of days_from_civil, again with years starting
+    // in March.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+                       - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era
+                    - (365 * year_of_era + year_of_era / 4
+                       - year_of_era / 100);
+    let month_of_year = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_of_year + 2) / 5 + 1) as u8;
+    let month = if month_of_year < 10 {
+        (month_of_year + 3) as u8
+    }
+    else {
+        (month_of_year - 9) as u8
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+
+//------------ Keywords ------------------------------------------------------
+
+/// Creates a case-sensitive keyword.
+const fn keyword(literal: &'static [u8], value: u8) -> Keyword<u8> {
+    Keyword { literal, value, case_sensitive: true }
+}
+
+const DAY_NAMES: [Keyword<u8>; 7] = [
+    keyword(b"Mon", 1), keyword(b"Tue", 2), keyword(b"Wed", 3),
+    keyword(b"Thu", 4), keyword(b"Fri", 5), keyword(b"Sat", 6),
+    keyword(b"Sun", 7),
+];
+
+const LONG_DAY_NAMES: [Keyword<u8>; 7] = [
+    keyword(b"Monday", 1), keyword(b"Tuesday", 2), keyword(b"Wednesday", 3),
+    keyword(b"Thursday", 4), keyword(b"Friday", 5), keyword(b"Saturday", 6),
+    keyword(b"Sunday", 7),
+];
+
+const MONTHS: [Keyword<u8>; 12] = [
+    keyword(b"Jan", 1), keyword(b"Feb", 2), keyword(b"Mar", 3),
+    keyword(b"Apr", 4), keyword(b"May", 5), keyword(b"Jun", 6),
+    keyword(b"Jul", 7), keyword(b"Aug", 8), keyword(b"Sep", 9),
+    keyword(b"Oct", 10), keyword(b"Nov", 11), keyword(b"Dec", 12),
+];
+
+
+//------------ delta-seconds and Retry-After ---------------------------------
+
+/// The value `delta-seconds` are clamped to.
+///
+/// This is the value section 1.2.2 of RFC 9111 recommends for values that
+/// overflow.
+pub const MAX_DELTA_SECONDS: u64 = 1 << 31;
+
+/// Parses `delta-seconds`.
+///
+/// Values larger than `MAX_DELTA_SECONDS` are clamped to it. The rule is
+/// also used for the `delay-seconds` of the `Retry-After` header field.
+pub fn parse_delta_seconds<B: Buffer>(buf: &mut B)
+                                      -> Poll<Duration, TokenError> {
+    token::convert(buf, digits, |res| {
+        let secs = res?.iter().fold(0u64, |secs, &ch| {
+            (secs * 10 + u64::from(ch - b'0')).min(MAX_DELTA_SECONDS)
+        });
+        Ok(Duration::from_secs(secs))
+    })
+}
+
+/// The value of the `Retry-After` header field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RetryAfter {
+    /// Retry after the given point in time.
+    Date(SystemTime),
+
+    /// Retry after the given delay.
+    Delay(Duration),
+}
+
+impl fmt::Display for RetryAfter {
+    /// Formats the value as an IMF-fixdate or `delay-seconds`.
+    ///
+    /// A date outside the years 0 to 9999 can’t be formatted and results
+    /// in an error. Fractions of seconds are dropped.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RetryAfter::Date(date) => {
+                match DateTime::from_system_time(date) {
+                    Some(date) => date.fmt(f),
+                    None => Err(fmt::Error)
+                }
+            }
+            RetryAfter::Delay(delay) => delay.as_secs().fmt(f)
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for RetryAfter {
+    /// Produces a date within the years 0 to 9999 or a delay of whole
+    /// seconds up to `MAX_DELTA_SECONDS`.
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        if bool::arbitrary(u)? {
+            Ok(RetryAfter::Date(DateTime::arbitrary(u)?.into()))
+        }
+        else {
+            let secs = u.int_in_range(0..=MAX_DELTA_SECONDS)?;
+            Ok(RetryAfter::Delay(Duration::from_secs(secs)))
+        }
+    }
+}
+
+/// Parses the value of the `Retry-After` header field.
+//
+//  Retry-After = HTTP-date / delay-seconds
+pub fn parse_retry_after<B: Buffer>(buf: &mut B)
+                                    -> Poll<RetryAfter, TokenError> {
+    if let Ok(date) = try_result!(parse_http_date(buf)) {
+        return Ok(Async::Ready(RetryAfter::Date(date)))
+    }
+    parse_delta_seconds(buf).map(|res| res.map(RetryAfter::Delay))
+}
+
+
+//------------ Field Lines ---------------------------------------------------
+
+/// Returns whether `ch` may appear in a field value.
+///
+/// This is `field-vchar` plus white space, i.e., all octets but controls
+/// other than horizontal tab.
+pub fn test_field_content(ch: u8) -> bool {
+    test_vchar(ch) || test_wsp(ch) || ch >= 0x80
+}
+
+/// Parses a field line including its CRLF.
+///
+/// Returns the field name and value. White space around the value is
+/// removed. Obsolete line folding isn’t supported and results in an
+/// error.
+//
+//  field-line = field-name ":" OWS field-value OWS CRLF
+pub fn parse_field_line<B: Buffer>(buf: &mut B)
+                                   -> Poll<(Bytes, Bytes), TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_token(buf));
+        try_ready!(token::skip_octet(buf, b':'));
+        try_ready!(skip_opt_wsps(buf));
+        let value = try_ready!(token::parse(buf, |token| {
+            token::opt_cats(token, test_field_content).map(|res| {
+                res.map(|_| ())
+            })
+        }));
+        try_ready!(skip_crlf(buf));
+        let len = value.iter().rposition(|&ch| !test_wsp(ch))
+                       .map(|pos| pos + 1).unwrap_or(0);
+        Ok(Async::Ready((name, value.slice(..len))))
+    })
+}
+
+/// Parses a field section including the empty line ending it.
+///
+/// This is used for both the header section and the trailer section of a
+/// message. Returns the field lines in order.
+//
+//  field-section = *( field-line ) CRLF
+pub fn parse_field_section<B: Buffer>(buf: &mut B)
+                                      -> Poll<Vec<(Bytes, Bytes)>,
+                                              TokenError> {
+    rule::group_limited(buf, |buf| {
+        let mut res = Vec::new();
+        loop {
+            if try_result!(skip_crlf(buf)).is_ok() {
+                return Ok(Async::Ready(res))
+            }
+            res.push(try_ready!(parse_field_line(buf)))
+        }
+    })
+}
+
+
+//------------ ChunkedDecoder ------------------------------------------------
+
+/// A streaming decoder for the chunked transfer coding.
+///
+/// The decoder is the counterpart of [`ChunkedEncoder`]. Each call to
+/// `decode()` returns the next piece of the body as soon as any of it is
+/// available, so pieces don’t necessarily correspond to chunks. Chunk
+/// extensions are skipped. After the last chunk, the trailer section is
+/// returned separately from the data.
+///
+/// [`ChunkedEncoder`]: ../compose/chunked/struct.ChunkedEncoder.html
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkedDecoder {
+    state: ChunkedState,
+}
+
+/// The part of the chunked body the decoder expects next.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ChunkedState {
+    /// The line with the chunk size.
+    Size,
+
+    /// Chunk data with the given number of octets left.
+    Data(u64),
+
+    /// The CRLF after the chunk data.
+    DataEnd,
+
+    /// The trailer section after the last chunk.
+    Trailers,
+
+    /// Nothing, the body is complete.
+    Done,
+}
+
+/// A piece of a chunked body.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ChunkedPart {
+    /// Some data of the body.
+    Data(Bytes),
+
+    /// The end of the body with the trailer fields.
+    End(Vec<(Bytes, Bytes)>),
+}
+
+impl ChunkedDecoder {
+    /// Creates a new decoder for a body.
+    pub fn new() -> Self {
+        ChunkedDecoder { state: ChunkedState::Size }
+    }
+
+    /// Returns whether the end of the body has been returned.
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkedState::Done
+    }
+
+    /// Decodes the next piece of the body from the beginning of `buf`.
+    ///
+    /// Returns non-ready if `buf` doesn’t contain enough data to produce
+    /// anything. Once `ChunkedPart::End` has been returned, the body is
+    /// complete and any further call returns an error.
+    pub fn decode<B: Buffer>(&mut self, buf: &mut B)
+                             -> Poll<ChunkedPart, TokenError> {
+        loop {
+            match self.state {
+                ChunkedState::Size => {
+                    let size = try_ready!(chunk_size_line(buf));
+                    self.state = if size == 0 { ChunkedState::Trailers }
+                                 else { ChunkedState::Data(size) };
+                }
+                ChunkedState::Data(left) => {
+                    if buf.is_empty() {
+                        return Ok(Async::NotReady)
+                    }
+                    let len = (buf.len() as u64).min(left);
+                    self.state = if len == left { ChunkedState::DataEnd }
+                                 else { ChunkedState::Data(left - len) };
+                    let data = buf.split_to(len as usize);
+                    return Ok(Async::Ready(ChunkedPart::Data(data)))
+                }
+                ChunkedState::DataEnd => {
+                    try_ready!(skip_crlf(buf));
+                    self.state = ChunkedState::Size;
+                }
+                ChunkedState::Trailers => {
+                    let trailers = try_ready!(parse_field_section(buf));
+                    self.state = ChunkedState::Done;
+                    return Ok(Async::Ready(ChunkedPart::End(trailers)))
+                }
+                ChunkedState::Done => return Err(TokenError::Invalid)
+            }
+        }
+    }
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//  chunk-size-line = chunk-size *( chunk-ext ) CRLF
+fn chunk_size_line<B: Buffer>(buf: &mut B) -> Poll<u64, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let size = try_ready!(u64_hexdigs(buf));
+        while try_ready!(rule::optional(buf, skip_chunk_ext)).is_some() { }
+        try_ready!(skip_crlf(buf));
+        Ok(Async::Ready(size))
+    })
+}
+
+//  chunk-ext = BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ]
+fn skip_chunk_ext<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(skip_opt_wsps(buf));
+        try_ready!(token::skip_octet(buf, b';'));
+        try_ready!(skip_opt_wsps(buf));
+        try_ready!(parse_token(buf));
+        try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_opt_wsps(buf));
+                try_ready!(token::skip_octet(buf, b'='));
+                try_ready!(skip_opt_wsps(buf));
+                parse_token_or_quoted(buf)
+            })
+        }));
+        Ok(Async::Ready(()))
+    })
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::test_support::{assert_canonical, assert_roundtrip,
+                         compose_display};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    #[test]
+    fn tokens() {
+        let mut data = buf(b"x-foo/1");
+        assert_eq!(parse_token(&mut data),
+                   Ok(Async::Ready(Bytes::from(&b"x-foo"[..]))));
+        assert_eq!(&data[..], b"/1");
+        assert_eq!(parse_token(&mut buf(b"(")), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        let mut data = buf(b"\"a \\\"b\\\\\";");
+        assert_eq!(parse_quoted_string(&mut data),
+                   Ok(Async::Ready(Bytes::from(&b"a \"b\\"[..]))));
+        assert_eq!(&data[..], b";");
+        assert_eq!(parse_quoted_string(&mut buf(b"\"ab\\")),
+                   Ok(Async::NotReady));
+        assert_eq!(parse_quoted_string(&mut buf(b"\"a\rb\"")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_token_or_quoted(&mut buf(b"\"x y\" ")),
+                   Ok(Async::Ready(Bytes::from(&b"x y"[..]))));
+    }
+
+    #[test]
+    fn http_dates() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        for date in &[&b"Sun, 06 Nov 1994 08:49:37 GMT"[..],
+                      &b"Sunday, 06-Nov-94 08:49:37 GMT"[..],
+                      &b"Sun Nov  6 08:49:37 1994"[..]] {
+            assert_eq!(parse_http_date(&mut buf(date)),
+                       Ok(Async::Ready(expected)));
+        }
+        assert_eq!(parse_http_date(&mut buf(b"Thu, 29 Feb 1968 00:00:00 GMT")),
+                   Ok(Async::Ready(UNIX_EPOCH
+                                   - Duration::from_secs(58_060_800))));
+        let mut data = buf(b"Thu, 29 Feb 2001 00:00:00 GMT");
+        assert_eq!(parse_http_date(&mut data), Err(TokenError::Invalid));
+        assert_eq!(data.len(), 29);
+        assert_eq!(parse_http_date(&mut buf(b"Sun, 06 Nov 1994 08:4")),
+                   Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn date_times() {
+        let date = DateTime::new(1994, 11, 6, 8, 49, 37).unwrap();
+        assert_eq!(parse_http_date_time(&mut buf(
+                       b"Sun, 06 Nov 1994 08:49:37 GMT"
+                   )),
+                   Ok(Async::Ready(date)));
+        assert_eq!(date.weekday(), 7);
+        assert_eq!(date.unix_timestamp(), 784_111_777);
+        assert_eq!(DateTime::from_unix_timestamp(784_111_777), Some(date));
+        assert_eq!(DateTime::from_system_time(SystemTime::from(date)),
+                   Some(date));
+
+        assert_eq!(DateTime::new(2000, 2, 29, 0, 0, 0).unwrap()
+                       .unix_timestamp(),
+                   951_782_400);
+        assert_eq!(DateTime::from_unix_timestamp(-1),
+                   DateTime::new(1969, 12, 31, 23, 59, 59));
+        assert_eq!(DateTime::from_system_time(
+                       UNIX_EPOCH - Duration::from_millis(500)
+                   ),
+                   DateTime::new(1969, 12, 31, 23, 59, 59));
+        assert_eq!(DateTime::from_unix_timestamp(-62_167_219_200),
+                   DateTime::new(0, 1, 1, 0, 0, 0));
+        assert_eq!(DateTime::from_unix_timestamp(-62_167_219_201), None);
+        assert_eq!(DateTime::from_unix_timestamp(253_402_300_800), None);
+        for days in (-719_528..2_932_897).step_by(89) {
+            let secs = days * 86400 + 3723;
+            assert_eq!(DateTime::from_unix_timestamp(secs).unwrap()
+                           .unix_timestamp(),
+                       secs);
+        }
+
+        assert!(DateTime::new(1900, 2, 29, 0, 0, 0).is_none());
+        assert!(DateTime::new(2024, 13, 1, 0, 0, 0).is_none());
+        assert!(DateTime::new(2024, 4, 31, 0, 0, 0).is_none());
+        assert!(DateTime::new(2024, 4, 30, 24, 0, 0).is_none());
+        assert!(DateTime::new(10000, 1, 1, 0, 0, 0).is_none());
+
+        let leap = DateTime::new(2016, 12, 31, 23, 59, 60).unwrap();
+        assert_eq!(leap.unix_timestamp(),
+                   DateTime::new(2017, 1, 1, 0, 0, 0).unwrap()
+                       .unix_timestamp());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_date_times() {
+        let data: Vec<u8> = (0..1024u32).map(|i| (i * 37 % 251) as u8)
+                                         .collect();
+        let mut u = Unstructured::new(&data);
+        for _ in 0..128 {
+            let date = DateTime::arbitrary(&mut u).unwrap();
+            assert_roundtrip(&date, compose_display, parse_http_date_time,
+                             b"\r\n");
+            let retry = RetryAfter::arbitrary(&mut u).unwrap();
+            assert_roundtrip(&retry, compose_display, parse_retry_after,
+                             b"\r\n");
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_date_times() {
+        use chrono::{FixedOffset, TimeZone, Timelike};
+
+        let date = DateTime::new(1994, 11, 6, 8, 49, 37).unwrap();
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(chrono::DateTime::<FixedOffset>::from(date),
+                   utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap());
+
+        let leap = chrono::DateTime::<FixedOffset>::from(
+            DateTime::new(2016, 12, 31, 23, 59, 60).unwrap()
+        );
+        assert_eq!(leap.second(), 59);
+        assert_eq!(leap.nanosecond(), 1_000_000_000);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_date_times() {
+        let date = DateTime::new(1994, 11, 6, 8, 49, 37).unwrap();
+        let res = time::OffsetDateTime::from(date);
+        assert_eq!(res.unix_timestamp(), 784_111_777);
+        assert_eq!(res.offset(), time::UtcOffset::UTC);
+        assert_eq!(
+            time::OffsetDateTime::from(DateTime::new(0, 1, 1, 0, 0, 0)
+                                           .unwrap()).year(),
+            0
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_canonical(b"Sun, 06 Nov 1994 08:49:37 GMT",
+                         parse_http_date_time, compose_display, b"\r\n");
+        assert_canonical(b"Mon, 01 Jan 0001 00:00:00 GMT",
+                         parse_http_date_time, compose_display, b"\r\n");
+        assert_roundtrip(&DateTime::new(2016, 12, 31, 23, 59, 60).unwrap(),
+                         compose_display, parse_http_date_time, b"\r\n");
+        assert_eq!(
+            DateTime::from_unix_timestamp(1_700_000_000).unwrap().to_string(),
+            "Tue, 14 Nov 2023 22:13:20 GMT"
+        );
+
+        assert_canonical(b"Fri, 31 Dec 1999 23:59:59 GMT",
+                         parse_retry_after, compose_display, b"\r\n");
+        assert_canonical(b"120", parse_retry_after, compose_display,
+                         b"\r\n");
+        assert_roundtrip(&RetryAfter::Delay(Duration::from_secs(0)),
+                         compose_display, parse_retry_after, b"\r\n");
+        assert_eq!(RetryAfter::Delay(Duration::from_millis(1500)).to_string(),
+                   "1");
+    }
+
+    #[test]
+    fn retry_after() {
+        assert_eq!(parse_retry_after(&mut buf(b"120\r\n")),
+                   Ok(Async::Ready(RetryAfter::Delay(
+                       Duration::from_secs(120)
+                   ))));
+        assert_eq!(parse_retry_after(&mut buf(b"99999999999999999999999\r\n")),
+                   Ok(Async::Ready(RetryAfter::Delay(
+                       Duration::from_secs(MAX_DELTA_SECONDS)
+                   ))));
+        let mut data = buf(b"Fri, 31 Dec 1999 23:59:59 GMT");
+        assert_eq!(parse_retry_after(&mut data),
+                   Ok(Async::Ready(RetryAfter::Date(
+                       UNIX_EPOCH + Duration::from_secs(946_684_799)
+                   ))));
+        assert_eq!(parse_retry_after(&mut buf(b"soon\r\n")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn lists() {
+        let mut data = buf(b", a ,, b,c ;");
+        assert_eq!(parse_list(&mut data, 1, parse_token),
+                   Ok(Async::Ready(vec![Bytes::from(&b"a"[..]),
+                                        Bytes::from(&b"b"[..]),
+                                        Bytes::from(&b"c"[..])])));
+        assert_eq!(&data[..], b" ;");
+
+        let mut data = buf(b";");
+        assert_eq!(parse_list(&mut data, 0, parse_token),
+                   Ok(Async::Ready(vec![])));
+        assert_eq!(parse_list(&mut data, 1, parse_token),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_list(&mut buf(b"a, b"), 1, parse_token),
+                   Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn field_lines() {
+        let mut data = buf(b"Expires: never \r\nX-Empty:\r\n\r\nbody");
+        assert_eq!(parse_field_section(&mut data), Ok(Async::Ready(vec![
+            (Bytes::from(&b"Expires"[..]), Bytes::from(&b"never"[..])),
+            (Bytes::from(&b"X-Empty"[..]), Bytes::new()),
+        ])));
+        assert_eq!(&data[..], b"body");
+        let mut data = buf(b"Expires: never\r\n");
+        assert_eq!(parse_field_section(&mut data), Ok(Async::NotReady));
+        assert_eq!(data.len(), 16);
+        assert_eq!(parse_field_line(&mut buf(b"Ex pires: never\r\n")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_field_section(&mut buf(b"A: b\r\n c\r\n\r\n")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn chunked() {
+        let mut body = BytesMut::new();
+        let mut encoder = ::compose::chunked::ChunkedEncoder::new();
+        encoder.set_max_chunk_size(4);
+        encoder.encode(b"Hello, world!", &mut body);
+        body.extend_from_slice(b"3 ; ext=\"x\";y\r\nabc\r\n");
+        encoder.finish(&[(b"Digest", b"sha-256=:x:")], &mut body).unwrap();
+        body.extend_from_slice(b"next");
+
+        // Feed octet by octet.
+        let mut decoder = ChunkedDecoder::new();
+        let mut data = BytesMut::new();
+        let mut content = Vec::new();
+        let mut trailers = None;
+        for &ch in body.iter() {
+            data.extend_from_slice(&[ch]);
+            if let Ok(Async::Ready(part)) = decoder.decode(&mut data) {
+                match part {
+                    ChunkedPart::Data(piece) => {
+                        content.extend_from_slice(&piece)
+                    }
+                    ChunkedPart::End(fields) => trailers = Some(fields)
+                }
+            }
+        }
+        assert_eq!(content, b"Hello, world!abc");
+        assert_eq!(trailers, Some(vec![(Bytes::from(&b"Digest"[..]),
+                                        Bytes::from(&b"sha-256=:x:"[..]))]));
+        assert!(decoder.is_done());
+        assert_eq!(&data[..], b"next");
+        assert_eq!(decoder.decode(&mut data), Err(TokenError::Invalid));
+
+        let mut data = buf(b"x\r\n");
+        assert_eq!(ChunkedDecoder::new().decode(&mut data),
+                   Err(TokenError::Invalid));
+        let mut data = buf(b"2\r\nabX\r\n");
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(decoder.decode(&mut data),
+                   Ok(Async::Ready(ChunkedPart::Data(b"ab"[..].into()))));
+        assert_eq!(decoder.decode(&mut data), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn param_lists() {
+        let mut data = buf(b"charset=\"utf-8\" ; q = 0.5;flag;\
+                             Charset=latin1; ;x");
+        let res = param_list(&mut data, b';', b'=', parse_token_or_quoted);
+        let res = match res {
+            Ok(Async::Ready(res)) => res,
+            res => panic!("{:?}", res)
+        };
+        assert_eq!(&data[..], b"; ;x");
+        assert_eq!(res.len(), 4);
+        assert_eq!(res.get(b"CHARSET"), Some(&Bytes::from(&b"utf-8"[..])));
+        assert_eq!(res.get_all(b"charset").cloned().collect::<Vec<_>>(),
+                   vec![Some(Bytes::from(&b"utf-8"[..])),
+                        Some(Bytes::from(&b"latin1"[..]))]);
+        assert_eq!(res.get(b"q"), Some(&Bytes::from(&b"0.5"[..])));
+        assert!(res.contains(b"flag"));
+        assert_eq!(res.get(b"flag"), None);
+        assert!(!res.contains(b"x"));
+        assert_eq!(res.iter().map(|item| &item.0[..]).collect::<Vec<_>>(),
+                   vec![&b"charset"[..], b"q", b"flag", b"Charset"]);
+
+        let mut data = buf(b"a=1, b=\"x\"\r\n");
+        let res = param_list(&mut data, b',', b'=', parse_token).unwrap();
+        assert_eq!(res, Async::Ready(ParamList {
+            params: vec![(Bytes::from(&b"a"[..]), Some(Bytes::from("1")))]
+        }));
+        assert_eq!(&data[..], b", b=\"x\"\r\n");
+
+        let mut data = buf(b"\r\n");
+        assert_eq!(param_list(&mut data, b';', b'=', parse_token),
+                   Ok(Async::Ready(ParamList::new())));
+        assert_eq!(param_list(&mut buf(b"a=1;b"), b';', b'=', parse_token),
+                   Ok(Async::NotReady));
+    }
+}
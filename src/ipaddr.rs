@@ -1,8 +1,8 @@
 
 use std::net::{Ipv4Addr, Ipv6Addr};
-use bytes::BytesMut;
 use futures::{Async, Poll};
 use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
 use ::parse::token::TokenError;
 use ::core::{u16_hexdigs, u8_digits};
  
@@ -10,17 +10,12 @@ use ::core::{u16_hexdigs, u8_digits};
 //------------ parse_ipv4addr ------------------------------------------------
 
 /// Parses an IPv4 address
-pub fn parse_ipv4_addr(buf: &mut BytesMut) -> Poll<Ipv4Addr, TokenError> {
-    rule::group(buf, |buf| {
-        let a = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let b = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let c = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let d = try_ready!(u8_digits(buf));
-        Ok(Async::Ready(Ipv4Addr::new(a, b, c, d)))
-    })
+pub fn parse_ipv4_addr<B: Buffer>(buf: &mut B) -> Poll<Ipv4Addr, TokenError> {
+    seq!(buf, u8_digits, token::skip_octet(b'.'), u8_digits,
+         token::skip_octet(b'.'), u8_digits, token::skip_octet(b'.'),
+         u8_digits).map(|res| res.map(|(a, _, b, _, c, _, d)| {
+        Ipv4Addr::new(a, b, c, d)
+    }))
 }
 
 
@@ -30,92 +25,64 @@ pub fn parse_ipv4_addr(buf: &mut BytesMut) -> Poll<Ipv4Addr, TokenError> {
 ///
 //  IPv6-addr      = IPv6-full / IPv6-comp / IPv6v4-full / IPv6v4-comp
 //
-pub fn parse_ipv6_addr(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
-    try_fail!(ipv6_full(buf));
-    try_fail!(ipv6_comp(buf));
-    try_fail!(ipv6v4_full(buf));
-    try_fail!(ipv6v4_comp(buf));
-    Err(TokenError)
+//  The alternatives with an embedded IPv4 address are tried first since
+//  IPv6-comp would otherwise match only a prefix of them.
+//
+pub fn parse_ipv6_addr<B: Buffer>(buf: &mut B) -> Poll<Ipv6Addr, TokenError> {
+    alt!(buf, ipv6v4_full, ipv6v4_comp, ipv6_full, ipv6_comp)
 }
 
 //  IPv6-full      = IPv6-hex 7(":" IPv6-hex)
-fn ipv6_full(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
-    rule::group(buf, |buf| {
-        let a = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let b = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let c = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let d = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let e = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let f = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let g = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let h = try_ready!(u16_hexdigs(buf));
-        Ok(Async::Ready(Ipv6Addr::new(a, b, c, d, e, f, g, h)))
-    })
+fn ipv6_full<B: Buffer>(buf: &mut B) -> Poll<Ipv6Addr, TokenError> {
+    seq!(buf, u16_hexdigs, token::skip_octet(b':'), u16_hexdigs,
+         token::skip_octet(b':'), u16_hexdigs, token::skip_octet(b':'),
+         u16_hexdigs, token::skip_octet(b':'), u16_hexdigs,
+         token::skip_octet(b':'), u16_hexdigs, token::skip_octet(b':'),
+         u16_hexdigs, token::skip_octet(b':'), u16_hexdigs)
+    .map(|res| res.map(|(a, _, b, _, c, _, d, _, e, _, f, _, g, _, h)| {
+        Ipv6Addr::new(a, b, c, d, e, f, g, h)
+    }))
 }
 
 // IPv6-comp      = [IPv6-hex *5(":" IPv6-hex)] "::"
 //                  [IPv6-hex *5(":" IPv6-hex)]
-fn ipv6_comp(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
-    rule::group(buf, |buf| {
+fn ipv6_comp<B: Buffer>(buf: &mut B) -> Poll<Ipv6Addr, TokenError> {
+    rule::group_limited(buf, |buf| {
         let (mut left, left_count) = try_ready!(ipv6_comp_left(buf, 6));
         let (right, right_count) = try_ready!(ipv6_comp_right(buf,
                                                               6 - left_count));
-        for i in 0..right_count {
-            left[8 - right_count + i] = right[i];
-        }
+        left[8 - right_count..].copy_from_slice(&right[..right_count]);
         Ok(Async::Ready(Ipv6Addr::new(left[0], left[1], left[2], left[3],
                                       left[4], left[5], left[6], left[7])))
     })
 }
 
 // IPv6v4-full    = IPv6-hex 5(":" IPv6-hex) ":" IPv4-address-literal
-fn ipv6v4_full(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
-    rule::group(buf, |buf| {
-        let a = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let b = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let c = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let d = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let e = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let f = try_ready!(u16_hexdigs(buf));
-        try_ready!(token::skip_octet(buf, b':'));
-        let g1 = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let g2 = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let h1 = try_ready!(u8_digits(buf));
-        try_ready!(token::skip_octet(buf, b'.'));
-        let h2 = try_ready!(u8_digits(buf));
-        Ok(Async::Ready(Ipv6Addr::new(a, b, c, d, e, f,
-                                      (g1 as u16) << 8 | (g2 as u16),
-                                      (h1 as u16) << 8 | (h2 as u16))))
-    })
+fn ipv6v4_full<B: Buffer>(buf: &mut B) -> Poll<Ipv6Addr, TokenError> {
+    seq!(buf, u16_hexdigs, token::skip_octet(b':'), u16_hexdigs,
+         token::skip_octet(b':'), u16_hexdigs, token::skip_octet(b':'),
+         u16_hexdigs, token::skip_octet(b':'), u16_hexdigs,
+         token::skip_octet(b':'), u16_hexdigs, token::skip_octet(b':'),
+         parse_ipv4_addr)
+    .map(|res| res.map(|(a, _, b, _, c, _, d, _, e, _, f, _, v4)| {
+        let v4 = v4.octets();
+        Ipv6Addr::new(a, b, c, d, e, f,
+                      (v4[0] as u16) << 8 | (v4[1] as u16),
+                      (v4[2] as u16) << 8 | (v4[3] as u16))
+    }))
 }
 
 // IPv6v4-comp    = [IPv6-hex *3(":" IPv6-hex)] "::"
 //                  [IPv6-hex *3(":" IPv6-hex) ":"]
 //                  IPv4-address-literal
-fn ipv6v4_comp(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
-    rule::group(buf, |buf| {
+fn ipv6v4_comp<B: Buffer>(buf: &mut B) -> Poll<Ipv6Addr, TokenError> {
+    rule::group_limited(buf, |buf| {
         let (mut left, left_count) = try_ready!(ipv6_comp_left(buf, 4));
-        let (right, right_count) = try_ready!(ipv6_comp_right(buf,
+        let (right, right_count) = try_ready!(ipv6v4_comp_right(buf,
                                                               4 - left_count));
         let v4 = try_ready!(parse_ipv4_addr(buf));
         let v4 = v4.octets();
-        for i in 0..right_count {
-            left[6 - right_count + 1] = right[i];
-        }
+        left[6 - right_count..6].copy_from_slice(&right[..right_count]);
         left[6] = (v4[0] as u16) << 8 | (v4[1] as u16);
         left[7] = (v4[2] as u16) << 8 | (v4[3] as u16);
         Ok(Async::Ready(Ipv6Addr::new(left[0], left[1], left[2], left[3],
@@ -126,8 +93,8 @@ fn ipv6v4_comp(buf: &mut BytesMut) -> Poll<Ipv6Addr, TokenError> {
 /// Parses the left hand side of a compressed IPv6 address.
 ///
 /// Returns the parsed components and the number of them.
-fn ipv6_comp_left(buf: &mut BytesMut, max: usize)
-                  -> Poll<([u16; 8], usize), TokenError> {
+fn ipv6_comp_left<B: Buffer>(buf: &mut B, max: usize)
+                             -> Poll<([u16; 8], usize), TokenError> {
     let mut res = [0u16, 0, 0, 0, 0, 0, 0, 0];
 
     // Minimum size is two: b"::" or b"0:"
@@ -135,34 +102,35 @@ fn ipv6_comp_left(buf: &mut BytesMut, max: usize)
 
     // We may start with two colons, in which case there is no left hand
     // side.
-    if buf[0] == b':' && buf[1] == b':' {
-        buf.split_to(2);
+    if buf.get(0) == Some(b':') && buf.get(1) == Some(b':') {
+        buf.advance(2);
         return Ok(Async::Ready((res, 0)));
     }
 
     // Up to six components that end in a colon and may end in a
     // double colon
-    for i in 0..max {
+    for (i, item) in res.iter_mut().enumerate().take(max) {
         let v = try_ready!(u16_hexdigs(buf));
         try_ready!(token::skip_octet(buf, b':'));
-        res[i] = v;
-        if buf.first() == Some(&b':') {
-            buf.split_to(1);
+        *item = v;
+        if buf.get(0) == Some(b':') {
+            buf.advance(1);
             return Ok(Async::Ready((res, i + 1)))
         }
     }
 
-    Ok(Async::Ready((res, max)))
+    // No double colon within `max` components.
+    Err(TokenError::Invalid)
 }
 
 /// Parses the right hand side of a compressed IPv6 address.
 ///
 /// Returns the parsed components and the number of them.
-fn ipv6_comp_right(buf: &mut BytesMut, max: usize)
-                   -> Poll<([u16; 8], usize), TokenError> {
+fn ipv6_comp_right<B: Buffer>(buf: &mut B, max: usize)
+                              -> Poll<([u16; 8], usize), TokenError> {
     let mut res = [0u16, 0, 0, 0, 0, 0, 0, 0];
 
-    for i in 0..max {
+    for (i, item) in res.iter_mut().enumerate().take(max) {
         match u16_hexdigs(buf) {
             Ok(Async::NotReady) => return Ok(Async::NotReady),
             Err(_) => {
@@ -170,11 +138,11 @@ fn ipv6_comp_right(buf: &mut BytesMut, max: usize)
                     return Ok(Async::Ready((res, 0)))
                 }
                 else {
-                    return Err(TokenError)
+                    return Err(TokenError::Invalid)
                 }
             }
             Ok(Async::Ready(v)) => {
-                res[i] = v;
+                *item = v;
             }
         }
         match token::skip_octet(buf, b':') {
@@ -189,6 +157,28 @@ fn ipv6_comp_right(buf: &mut BytesMut, max: usize)
     Ok(Async::Ready((res, max)))
 }
 
+/// Parses the right hand side of a compressed IPv6v4 address.
+///
+/// Unlike with `ipv6_comp_right()`, each component ends in a colon.
+/// Returns the parsed components and the number of them.
+fn ipv6v4_comp_right<B: Buffer>(buf: &mut B, max: usize)
+                                -> Poll<([u16; 8], usize), TokenError> {
+    let mut res = [0u16, 0, 0, 0, 0, 0, 0, 0];
+
+    for (i, item) in res.iter_mut().enumerate().take(max) {
+        let component = rule::group_limited(buf, |buf| -> Poll<u16, TokenError> {
+            let v = try_ready!(u16_hexdigs(buf));
+            try_ready!(token::skip_octet(buf, b':'));
+            Ok(Async::Ready(v))
+        });
+        match try_result!(component) {
+            Ok(v) => *item = v,
+            Err(_) => return Ok(Async::Ready((res, i)))
+        }
+    }
+    Ok(Async::Ready((res, max)))
+}
+
 
 //============ Test =========================================================
 
@@ -199,7 +189,7 @@ mod test {
     use bytes::BytesMut;
     use super::*;
 
-    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(Vec::from(slice)) }
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
 
     #[test]
     fn ipv4_good() {
@@ -239,5 +229,15 @@ mod test {
             parse_ipv6_addr(&mut buf(b":: ")),
             Ok(Async::Ready(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)))
         );
+        assert_eq!(
+            parse_ipv6_addr(&mut buf(b"0:0:0:0:0:FFFF:129.144.52.38 ")),
+            Ok(Async::Ready(Ipv6Addr::new(0, 0, 0, 0, 0, 0xFFFF,
+                                          0x8190, 0x3426)))
+        );
+        assert_eq!(
+            parse_ipv6_addr(&mut buf(b"::FFFF:129.144.52.38 ")),
+            Ok(Async::Ready(Ipv6Addr::new(0, 0, 0, 0, 0, 0xFFFF,
+                                          0x8190, 0x3426)))
+        );
     }
 }
@@ -1,11 +1,29 @@
+#[cfg(feature = "arbitrary")] extern crate arbitrary;
 extern crate bytes;
+#[cfg(feature = "chrono")] extern crate chrono;
 extern crate futures;
+#[cfg(feature = "proptest")] extern crate proptest;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "time")] extern crate time;
 
 #[macro_use] pub mod macros;
 
 /// Re-exported for use by the macros.
-pub use futures::Async;
+pub use futures::{Async, Poll};
 
+pub mod adapter;
+pub mod compose;
 pub mod core;
+pub mod dnszone;
+pub mod fuzz;
+pub mod grammar;
+pub mod http;
 pub mod ipaddr;
+pub mod mime;
+pub mod octets;
 pub mod parse;
+pub mod prelude;
+pub mod rtsp;
+#[cfg(feature = "proptest")] pub mod strategy;
+pub mod test_support;
+pub mod websocket;
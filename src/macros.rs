@@ -6,6 +6,56 @@ macro_rules! assert_eq_ready {
     }
 }
 
+/// Asserts that a `Poll` is non-ready.
+#[macro_export]
+macro_rules! assert_not_ready {
+    ($e:expr) => (match $e {
+        Ok($crate::Async::NotReady) => { }
+        res => panic!("expected non-ready, got {:?}", res)
+    })
+}
+
+/// Asserts that a `Poll` is an error, optionally matching a pattern.
+///
+/// Evaluates to the error.
+#[macro_export]
+macro_rules! assert_parse_err {
+    ($e:expr) => (match $e {
+        Err(err) => err,
+        res => panic!("expected an error, got {:?}", res)
+    });
+    ($e:expr, $kind:pat) => (match $e {
+        Err(err @ $kind) => err,
+        res => panic!("expected error {}, got {:?}", stringify!($kind), res)
+    })
+}
+
+/// Asserts that evaluating an expression consumes `n` octets of a buffer.
+///
+/// The macro takes a buffer, the number of octets, and an expression
+/// parsing from the buffer. It asserts that afterwards exactly what
+/// followed the first `n` octets is left in the buffer. On failure, both
+/// the expected and actual remaining data are shown as escaped strings.
+/// Evaluates to the value of the expression.
+#[macro_export]
+macro_rules! assert_consumed {
+    ($buf:expr, $n:expr, $e:expr) => {{
+        let before = $crate::test_support::contents(&$buf);
+        let res = $e;
+        let after = $crate::test_support::contents(&$buf);
+        let expected = before.get($n..).unwrap_or(&[]);
+        if after != expected {
+            panic!("expected {} octets consumed by {}\n\
+                    expected left: \"{}\"\n  \
+                    actual left: \"{}\"",
+                   $n, stringify!($e),
+                   $crate::test_support::escape(expected),
+                   $crate::test_support::escape(&after))
+        }
+        res
+    }}
+}
+
 
 /// A macro for extracting the successful type of a `Poll<T, E>`.
 ///
@@ -19,7 +69,7 @@ macro_rules! try_ready {
     ($e:expr) => (match $e {
         Ok($crate::Async::Ready(t)) => t,
         Ok($crate::Async::NotReady) => return Ok($crate::Async::NotReady),
-        Err(e) => return Err(From::from(e)),
+        Err(e) => return Err(::std::convert::From::from(e)),
     })
 }
 
@@ -64,3 +114,517 @@ macro_rules! try_opt {
     })
 }
 
+
+//------------ seq! and alt! -------------------------------------------------
+
+/// Parses a sequence of rules, returning a tuple of their results.
+///
+/// The first argument is the buffer to parse from. It is followed by a
+/// comma-separated list of parsing functions. These can be given either
+/// as a plain path to a function taking only the buffer, such as
+/// `u8_digits`, or as a call with further arguments that follow the buffer,
+/// such as `token::skip_octet(b'.')`. The functions are applied one after
+/// another and the macro evaluates to a `Poll` of the tuple of all their
+/// results. The whole sequence is wrapped in
+/// `parse::rule::group_limited()`, so the buffer is rewound if any of the
+/// rules fails or is undecided.
+///
+/// The error types of all the functions need to be convertible into the
+/// error type of the resulting `Poll`. Since this type can’t be inferred
+/// from the functions, it needs to be clear from the context, such as the
+/// return type of the function the macro is used in.
+///
+/// ```
+/// #[macro_use] extern crate abnf;
+/// # extern crate futures;
+/// use abnf::core::u8_digits;
+/// use abnf::parse::token::{self, TokenError};
+/// # use abnf::parse::cursor::CursorBuf;
+/// # use futures::{Async, Poll};
+///
+/// fn range(buf: &mut CursorBuf) -> Poll<(u8, u8), TokenError> {
+///     seq!(buf, u8_digits, token::skip_octet(b'-'), u8_digits)
+///         .map(|res| res.map(|(from, _, to)| (from, to)))
+/// }
+///
+/// # fn main() {
+/// assert_eq!(range(&mut CursorBuf::from(&b"12-3 "[..])),
+///            Ok(Async::Ready((12, 3))));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! seq {
+    ( $buf:expr, $( $($f:ident)::+ $(( $($arg:expr),* ))? ),+ $(,)? ) => {
+        $crate::parse::rule::group_limited($buf, |buf| {
+            Ok($crate::Async::Ready((
+                $(
+                    $crate::try_ready!($($f)::+ (buf $($(, $arg)*)?)),
+                )+
+            )))
+        })
+    }
+}
+
+/// Parses the first of a number of alternative rules.
+///
+/// The arguments are the same as for `seq!()`. The rules are tried in
+/// order, each wrapped in `parse::rule::group_limited()`, and the result
+/// of the first successful rule is returned. If a rule is undecided, the
+/// whole expression is undecided since a later alternative must only be
+/// tried once the earlier ones have failed definitely. Since a single
+/// further octet may already make an undecided rule fail, a need for one
+/// octet is reported to the buffer unless the rule was the last
+/// alternative. If all alternatives fail,
+/// their errors are combined via `parse::token::MergeError`. For
+/// `TokenError`, this reports a limit error of any alternative rather than
+/// `TokenError::Invalid`. All rules need to have the same success and error
+/// types.
+///
+/// ```
+/// #[macro_use] extern crate abnf;
+/// # extern crate futures;
+/// use abnf::ipaddr::{parse_ipv4_addr, parse_ipv6_addr};
+/// # use abnf::parse::cursor::CursorBuf;
+/// # use futures::{Async, Poll};
+/// # use std::net::{IpAddr, Ipv6Addr};
+/// # use abnf::parse::token::TokenError;
+///
+/// fn ip_addr(buf: &mut CursorBuf) -> Poll<IpAddr, TokenError> {
+///     alt!(buf, v4, v6)
+/// }
+/// # fn v4(buf: &mut CursorBuf) -> Poll<IpAddr, TokenError> {
+/// #     parse_ipv4_addr(buf).map(|res| res.map(IpAddr::V4))
+/// # }
+/// # fn v6(buf: &mut CursorBuf) -> Poll<IpAddr, TokenError> {
+/// #     parse_ipv6_addr(buf).map(|res| res.map(IpAddr::V6))
+/// # }
+///
+/// # fn main() {
+/// assert_eq!(ip_addr(&mut CursorBuf::from(&b"::1 "[..])),
+///            Ok(Async::Ready(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0,
+///                                                     0, 0, 0, 1)))));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! alt {
+    ( $buf:expr, $( $($f:ident)::+ $(( $($arg:expr),* ))? ),+ $(,)? ) => {
+        $crate::parse::rule::group_limited($buf, |buf| {
+            let mut _err = None;
+            let mut _left = [$( stringify!($($f)::+) ),+].len();
+            $(
+                _left -= 1;
+                let err = match $crate::parse::rule::group_limited(
+                    buf, |buf| $($f)::+ (buf $($(, $arg)*)?)
+                ) {
+                    Ok($crate::Async::Ready(t)) => {
+                        return Ok($crate::Async::Ready(t))
+                    }
+                    Ok($crate::Async::NotReady) => {
+                        if _left > 0 {
+                            $crate::parse::buffer::Buffer::report_needed(
+                                buf, 1
+                            )
+                        }
+                        return Ok($crate::Async::NotReady)
+                    }
+                    Err(err) => err
+                };
+                _err = Some(match _err.take() {
+                    Some(prev) => {
+                        $crate::parse::token::MergeError::merge(prev, err)
+                    }
+                    None => err
+                });
+            )+
+            Err(_err.unwrap())
+        })
+    }
+}
+
+
+//------------ repeat_abnf! --------------------------------------------------
+
+/// Parses a repetition written in ABNF notation.
+///
+/// The first argument is the buffer to parse from. It is followed by the
+/// repetition in the notation of [RFC 5234], i.e., `<a>*<b>element` with
+/// both `a` and `b` optional, or `<n>element` for exactly `n` elements.
+/// Since the Rust tokenizer would combine a number and a following name
+/// into one token, a space is necessary between the bounds and an element
+/// that is a name, e.g., `1*4 u16_hexdigs`.
+///
+/// The element is either the path to a parsing function, possibly with
+/// further arguments after the buffer like in `seq!()`, or a parenthesized
+/// sequence of such functions and string literals. The literals are
+/// skipped via `parse::token::skip_literal()`. The result of a sequence is
+/// the result of its single function or a tuple of the results if there
+/// are several.
+///
+/// The macro expands to the `Parser::repeat()` combinator applied to the
+/// buffer and thus evaluates to a `Poll` of a vec of the element results.
+/// The parsing functions all need to have the same error type. If string
+/// literals are used, this is `TokenError`.
+///
+/// ```
+/// #[macro_use] extern crate abnf;
+/// # extern crate futures;
+/// use abnf::core::u8_digits;
+/// # use abnf::parse::cursor::CursorBuf;
+/// # use futures::{Async, Poll};
+/// # use abnf::parse::token::TokenError;
+///
+/// //  list = number *( "," number )
+/// fn list(buf: &mut CursorBuf) -> Poll<Vec<u8>, TokenError> {
+///     let first = try_ready!(u8_digits(buf));
+///     let mut res = try_ready!(repeat_abnf!(buf, *( "," u8_digits )));
+///     res.insert(0, first);
+///     Ok(Async::Ready(res))
+/// }
+///
+/// # fn main() {
+/// assert_eq!(list(&mut CursorBuf::from(&b"1,2,3 "[..])),
+///            Ok(Async::Ready(vec![1, 2, 3])));
+/// # }
+/// ```
+///
+/// [RFC 5234]: https://tools.ietf.org/html/rfc5234
+#[macro_export]
+macro_rules! repeat_abnf {
+    ( $buf:expr, $min:literal * $max:literal $($elem:tt)+ ) => {
+        $crate::__repeat_abnf!($buf, $min..=$max, $($elem)+)
+    };
+    ( $buf:expr, $min:literal * $($elem:tt)+ ) => {
+        $crate::__repeat_abnf!($buf, $min.., $($elem)+)
+    };
+    ( $buf:expr, * $max:literal $($elem:tt)+ ) => {
+        $crate::__repeat_abnf!($buf, ..=$max, $($elem)+)
+    };
+    ( $buf:expr, * $($elem:tt)+ ) => {
+        $crate::__repeat_abnf!($buf, .., $($elem)+)
+    };
+    ( $buf:expr, $n:literal $($elem:tt)+ ) => {
+        $crate::__repeat_abnf!($buf, $n..=$n, $($elem)+)
+    };
+}
+
+/// Applies a repetition of an element to a buffer.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeat_abnf {
+    ( $buf:expr, $range:expr, ( $($inner:tt)+ ) ) => {
+        $crate::parse::parser::Parser::parse(
+            &mut $crate::parse::parser::Parser::repeat(
+                |buf: &mut _| $crate::parse::rule::group_limited(buf, |buf| {
+                    $crate::__repeat_abnf_seq!(buf; []; $($inner)+)
+                }),
+                $range
+            ),
+            $buf
+        )
+    };
+    ( $buf:expr, $range:expr, $($f:ident)::+ $(( $($arg:expr),* ))? ) => {
+        $crate::parse::parser::Parser::parse(
+            &mut $crate::parse::parser::Parser::repeat(
+                |buf: &mut _| $($f)::+ (buf $($(, $arg)*)?),
+                $range
+            ),
+            $buf
+        )
+    };
+}
+
+/// Expands the sequence inside a parenthesized repetition element.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeat_abnf_seq {
+    ( $buf:ident; [$($var:ident)*]; $lit:literal $($rest:tt)* ) => {{
+        $crate::__repeat_abnf_seq!(@try $crate::parse::token::skip_literal(
+            $buf, ::std::convert::AsRef::<[u8]>::as_ref($lit)
+        ));
+        $crate::__repeat_abnf_seq!($buf; [$($var)*]; $($rest)*)
+    }};
+    ( $buf:ident; [$($var:ident)*];
+      $($f:ident)::+ $(( $($arg:expr),* ))? $($rest:tt)* ) => {{
+        let item = $crate::__repeat_abnf_seq!(
+            @try $($f)::+ ($buf $($(, $arg)*)?)
+        );
+        $crate::__repeat_abnf_seq!($buf; [$($var)* item]; $($rest)*)
+    }};
+    ( $buf:ident; [$var:ident]; ) => {
+        Ok($crate::Async::Ready($var))
+    };
+    ( $buf:ident; [$($var:ident)*]; ) => {
+        Ok($crate::Async::Ready(($($var),*)))
+    };
+    ( @try $e:expr ) => {
+        match $e {
+            Ok($crate::Async::Ready(item)) => item,
+            Ok($crate::Async::NotReady) => {
+                return Ok($crate::Async::NotReady)
+            }
+            Err(err) => return Err(err)
+        }
+    };
+}
+
+//------------ rule! ---------------------------------------------------------
+
+/// Defines a token rule using near-ABNF syntax.
+///
+/// The macro defines a function with the given name that advances a token
+/// over whatever the ABNF-like definition matches. The function has the
+/// same signature as the token rules in the `core` module, i.e., it takes
+/// a `&mut Token<B>` and returns a `Poll<(), TokenError>`. Such functions
+/// can in turn be used as elements of other rules and can be applied to a
+/// buffer via `parse::token::parse()` or `parse::token::skip()`.
+///
+/// The definition supports the following ABNF constructs:
+///
+/// * names of other token rule functions, such as `alpha` or `crlf`,
+/// * string literals, which are matched case-insensitively,
+/// * concatenation by writing elements one after another,
+/// * alternatives separated by `/`,
+/// * grouping via `( … )` and optional sequences via `[ … ]`, and
+/// * repetition via `*element`, `<a>*element`, `*<b>element`, and
+///   `<a>*<b>element`.
+///
+/// Since the Rust tokenizer would combine a number and a directly
+/// following name into one token, you have to put a space between the
+/// repetition bounds and the element, e.g., `1*2 digit`. Specific
+/// repetition `<n>element` has to be written as `<n>*<n> element`. A string
+/// literal element of a repetition has to be put into parentheses. So does
+/// a string literal that is directly followed by a repetition since the
+/// macro would otherwise mistake it for the minimum of that repetition.
+///
+/// Alternatives are tried in order and the first one to match wins.
+///
+/// ```
+/// #[macro_use] extern crate abnf;
+/// # extern crate futures;
+/// use abnf::core::{alpha, digit, sp};
+/// # use abnf::parse::cursor::CursorBuf;
+/// # use futures::Async;
+/// # use abnf::parse::token;
+///
+/// rule!(version = "HTTP/" 1* digit "." 1* digit);
+/// rule!(pub request_line = 1* alpha sp 1* ("/" / alpha) sp version);
+///
+/// # fn main() {
+/// let mut buf = CursorBuf::from(&b"GET /foo HTTP/1.1\r\n"[..]);
+/// assert_eq!(token::parse(&mut buf, request_line),
+///            Ok(Async::Ready((&b"GET /foo HTTP/1.1"[..]).into())));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! rule {
+    ( $(#[$attr:meta])* pub $name:ident = $($rest:tt)+ ) => {
+        $(#[$attr])*
+        pub fn $name<B: $crate::parse::buffer::Buffer>(
+            token: &mut $crate::parse::token::Token<B>
+        ) -> $crate::Poll<(), $crate::parse::token::TokenError> {
+            $crate::__rule_alt!(token; [] []; $($rest)+)
+        }
+    };
+    ( $(#[$attr:meta])* $name:ident = $($rest:tt)+ ) => {
+        $(#[$attr])*
+        fn $name<B: $crate::parse::buffer::Buffer>(
+            token: &mut $crate::parse::token::Token<B>
+        ) -> $crate::Poll<(), $crate::parse::token::TokenError> {
+            $crate::__rule_alt!(token; [] []; $($rest)+)
+        }
+    };
+}
+
+/// Splits a rule definition into alternatives.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rule_alt {
+    ( $tok:ident; [$($cur:tt)*] [$($alt:tt)*]; / $($rest:tt)* ) => {
+        $crate::__rule_alt!($tok; [] [$($alt)* ($($cur)*)]; $($rest)*)
+    };
+    ( $tok:ident; [$($cur:tt)*] [$($alt:tt)*]; $next:tt $($rest:tt)* ) => {
+        $crate::__rule_alt!($tok; [$($cur)* $next] [$($alt)*]; $($rest)*)
+    };
+    ( $tok:ident; [$($cur:tt)*] [$( ($($alt:tt)*) )*]; ) => {
+        $crate::__rule_alt!(@emit $tok; $( ($($alt)*) )* ($($cur)*))
+    };
+    ( @emit $tok:ident; ($($only:tt)*) ) => {
+        $crate::parse::token::group($tok, |$tok| -> $crate::Poll<
+            (), $crate::parse::token::TokenError
+        > {
+            $crate::__rule_seq!($tok; $($only)*);
+            Ok($crate::Async::Ready(()))
+        })
+    };
+    ( @emit $tok:ident; ($($first:tt)*) $( ($($alt:tt)*) )+ ) => {
+        match $crate::parse::token::group($tok, |$tok| -> $crate::Poll<
+            (), $crate::parse::token::TokenError
+        > {
+            $crate::__rule_seq!($tok; $($first)*);
+            Ok($crate::Async::Ready(()))
+        }) {
+            Ok($crate::Async::NotReady) => {
+                let count = $tok.remaining() + 1;
+                $tok.need(count);
+                Ok($crate::Async::NotReady)
+            }
+            Err(_) => $crate::__rule_alt!(@emit $tok; $( ($($alt)*) )+),
+            res => res
+        }
+    };
+}
+
+/// Expands a concatenation of rule elements.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rule_seq {
+    ( $tok:ident; ) => { };
+    ( $tok:ident; $min:literal * $max:literal $elem:tt $($rest:tt)* ) => {
+        $crate::__rule_seq!(@repeat $tok; $min, Some($max), $elem);
+        $crate::__rule_seq!($tok; $($rest)*);
+    };
+    ( $tok:ident; $min:literal * $elem:tt $($rest:tt)* ) => {
+        $crate::__rule_seq!(@repeat $tok; $min, None, $elem);
+        $crate::__rule_seq!($tok; $($rest)*);
+    };
+    ( $tok:ident; * $max:literal $elem:tt $($rest:tt)* ) => {
+        $crate::__rule_seq!(@repeat $tok; 0, Some($max), $elem);
+        $crate::__rule_seq!($tok; $($rest)*);
+    };
+    ( $tok:ident; * $elem:tt $($rest:tt)* ) => {
+        $crate::__rule_seq!(@repeat $tok; 0, None, $elem);
+        $crate::__rule_seq!($tok; $($rest)*);
+    };
+    ( $tok:ident; $elem:tt $($rest:tt)* ) => {
+        $crate::try_ready!($crate::__rule_elem!($tok; $elem));
+        $crate::__rule_seq!($tok; $($rest)*);
+    };
+    ( @repeat $tok:ident; $min:expr, $max:expr, $elem:tt ) => {
+        $crate::try_ready!($crate::parse::token::repeat(
+            $tok, $min, $max, |$tok| $crate::__rule_elem!($tok; $elem)
+        ));
+    };
+}
+
+/// Expands a single rule element.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rule_elem {
+    ( $tok:ident; ($($inner:tt)*) ) => {
+        $crate::__rule_alt!($tok; [] []; $($inner)*)
+    };
+    ( $tok:ident; [$($inner:tt)*] ) => {
+        match $crate::__rule_alt!($tok; [] []; $($inner)*) {
+            Err(_) => Ok($crate::Async::Ready(())),
+            res => res
+        }
+    };
+    ( $tok:ident; $lit:literal ) => {
+        $crate::parse::token::literal(
+            $tok, ::std::convert::AsRef::<[u8]>::as_ref($lit)
+        )
+    };
+    ( $tok:ident; $rule:ident ) => {
+        $rule($tok)
+    };
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::{Async, Poll};
+    use ::core::{alpha, digit, hexdig, sp, u8_digits, u16_digits};
+    use ::parse::buffer::Buffer;
+    use ::parse::limits::{Limited, Limits};
+    use ::parse::token::{self, skip, TokenError};
+
+    rule!(number = ("0x" 1* hexdig) / 1*3 digit);
+    rule!(list = number *(*1 sp (",") *1 sp number) [";"]);
+    rule!(word = 2*2 alpha);
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    #[test]
+    fn rules() {
+        let mut data = buf(b"0xfF, 12 ,7;x");
+        assert_eq!(skip(&mut data, list), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"x");
+
+        let mut data = buf(b"1234");
+        assert_eq!(skip(&mut data, number), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"4");
+
+        assert_eq!(skip(&mut buf(b"12"), list), Ok(Async::NotReady));
+        assert_eq!(skip(&mut buf(b"ab"), word), Ok(Async::Ready(())));
+        assert_eq!(skip(&mut buf(b"a1"), word), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn assertions() {
+        assert_not_ready!(u8_digits(&mut buf(b"12")));
+        assert_eq!(assert_parse_err!(u8_digits(&mut buf(b"x"))),
+                   TokenError::Invalid);
+        assert_parse_err!(u8_digits(&mut buf(b"x")), TokenError::Invalid);
+        let mut data = buf(b"12 ");
+        assert_eq!(assert_consumed!(data, 2, u8_digits(&mut data)),
+                   Ok(Async::Ready(12)));
+    }
+
+    #[test]
+    #[should_panic(expected = "actual left: \"\\r\\n\"")]
+    fn assert_consumed_failure() {
+        let mut data = buf(b"12\r\n");
+        let _ = assert_consumed!(data, 4, u8_digits(&mut data));
+    }
+
+    #[test]
+    fn alt() {
+        fn none<B: Buffer>(buf: &mut B) -> Poll<u16, TokenError> {
+            token::skip_literal(buf, b"none").map(|res| res.map(|_| 0))
+        }
+
+        fn number_first(buf: &mut Limited<BytesMut>)
+                        -> Poll<u16, TokenError> {
+            alt!(buf, u16_digits, none)
+        }
+
+        fn number_last(buf: &mut Limited<BytesMut>)
+                       -> Poll<u16, TokenError> {
+            alt!(buf, none, u16_digits)
+        }
+
+        let mut limits = Limits::new();
+        limits.set_max_digits(2);
+        let mut data = Limited::new(buf(b"123 "), limits);
+        assert_eq!(number_first(&mut data), Err(TokenError::LimitExceeded));
+        assert_eq!(number_last(&mut data), Err(TokenError::LimitExceeded));
+        assert_eq!(&data.get_ref()[..], b"123 ");
+        let mut data = Limited::new(buf(b"x "), limits);
+        assert_eq!(number_first(&mut data), Err(TokenError::Invalid));
+        let mut data = Limited::new(buf(b"none "), limits);
+        assert_eq!(number_last(&mut data), Ok(Async::Ready(0)));
+    }
+
+    #[test]
+    fn repeat_abnf() {
+        let mut data = buf(b"1.2.3");
+        assert_eq!(repeat_abnf!(&mut data, 2 (u8_digits ".")),
+                   Ok(Async::Ready(vec![1, 2])));
+        assert_eq!(&data[..], b"3");
+
+        let mut data = buf(b"1=2;3=4;x");
+        assert_eq!(repeat_abnf!(&mut data, 1*(u8_digits "=" u8_digits ";")),
+                   Ok(Async::Ready(vec![(1, 2), (3, 4)])));
+        assert_eq!(&data[..], b"x");
+
+        let mut data = buf(b"xxxy");
+        assert_eq!(repeat_abnf!(&mut data, *2 token::skip_octet(b'x')),
+                   Ok(Async::Ready(vec![(), ()])));
+        assert_eq!(repeat_abnf!(&mut data, 2*3 token::skip_octet(b'x')),
+                   Err(TokenError::Invalid));
+        assert_eq!(&data[..], b"xy");
+    }
+}
@@ -0,0 +1,799 @@
+//! MIME header fields.
+//!
+//! This module implements the values of the header fields MIME adds to
+//! the Internet Message Format: `MIME-Version` and
+//! `Content-Transfer-Encoding` as defined in RFC 2045. Their values may
+//! contain comments and folding white space as defined in RFC 5322, so
+//! the module provides rules for those, too.
+//!
+//! It also implements the parameters that follow the value of header
+//! fields such as `Content-Type` and `Content-Disposition`, including the
+//! continuations and character set information of RFC 2231.
+
+use std::collections::HashMap;
+use std::fmt;
+use bytes::Bytes;
+use futures::{Async, Poll};
+use ::compose::write_octets;
+use ::core::{dquote, lwsp, test_ctl, u8_digits};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::{Token, TokenError};
+#[cfg(feature = "arbitrary")] use arbitrary::Arbitrary;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+
+//------------ CFWS ----------------------------------------------------------
+
+/// Returns whether `ch` is `ctext` as defined by RFC 5322.
+///
+/// This includes all octets from 0x80 onwards as allowed by RFC 6532.
+pub fn test_ctext(ch: u8) -> bool {
+    (33..=39).contains(&ch) || (42..=91).contains(&ch)
+        || (93..=126).contains(&ch) || ch >= 0x80
+}
+
+/// Advances over a `comment` including its parentheses.
+///
+/// Comments can be nested. Folding white space within a comment is
+/// advanced over as well.
+pub fn comment<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::group(token, |token| {
+        try_ready!(token::octet(token, b'('));
+        let mut depth = 1usize;
+        loop {
+            try_ready!(lwsp(token));
+            match try_ready!(token.first()) {
+                b'(' => {
+                    token.advance(1);
+                    depth += 1;
+                }
+                b')' => {
+                    token.advance(1);
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Async::Ready(()))
+                    }
+                }
+                b'\\' => {
+                    match token.peek(1) {
+                        Some(ch) if ch >= 0x20 && ch != 0x7F => {
+                            token.advance(2)
+                        }
+                        Some(_) => return Err(TokenError::Invalid),
+                        None => return Ok(Async::NotReady)
+                    }
+                }
+                ch if test_ctext(ch) => token.advance(1),
+                _ => return Err(TokenError::Invalid)
+            }
+        }
+    })
+}
+
+/// Advances over optional comments and folding white space.
+///
+/// This is `[CFWS]`. Like `lwsp()`, the function needs to see up to two
+/// octets past a CRLF before it can decide whether it ends the value.
+pub fn opt_cfws<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    loop {
+        try_ready!(lwsp(token));
+        if token.at_end() || try_ready!(token.first()) != b'(' {
+            return Ok(Async::Ready(()))
+        }
+        try_ready!(comment(token));
+    }
+}
+
+pub fn skip_opt_cfws<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
+    token::skip(buf, opt_cfws)
+}
+
+
+//------------ token ---------------------------------------------------------
+
+/// Returns whether `ch` is a `token` character as defined by RFC 2045.
+///
+/// These are all printable ASCII characters except for `tspecials`.
+pub fn test_token(ch: u8) -> bool {
+    ch > 0x20 && !test_ctl(ch) && ch < 0x80
+        && !b"()<>@,;:\\\"/[]?=".contains(&ch)
+}
+
+pub fn tokens<B: Buffer>(token: &mut Token<B>) -> Poll<(), TokenError> {
+    token::cats(token, test_token)
+}
+
+/// Parses a `token`.
+pub fn parse_token<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::parse(buf, tokens)
+}
+
+
+//------------ quoted-string -------------------------------------------------
+
+/// Returns whether `ch` is `qtext` as defined by RFC 5322.
+///
+/// This includes all octets from 0x80 onwards as allowed by RFC 6532.
+pub fn test_qtext(ch: u8) -> bool {
+    ch == 33 || (35..=91).contains(&ch) || (93..=126).contains(&ch)
+        || ch >= 0x80
+}
+
+/// Advances over a `quoted-string` including the double quotes.
+///
+/// Folding white space within the quotes is advanced over as well.
+pub fn quoted_string<B: Buffer>(token: &mut Token<B>)
+                                -> Poll<(), TokenError> {
+    token::group(token, |token| {
+        try_ready!(dquote(token));
+        loop {
+            try_ready!(lwsp(token));
+            match try_ready!(token.first()) {
+                b'"' => {
+                    token.advance(1);
+                    return Ok(Async::Ready(()))
+                }
+                b'\\' => {
+                    match token.peek(1) {
+                        Some(ch) if ch >= 0x20 && ch != 0x7F => {
+                            token.advance(2)
+                        }
+                        Some(_) => return Err(TokenError::Invalid),
+                        None => return Ok(Async::NotReady)
+                    }
+                }
+                ch if test_qtext(ch) => token.advance(1),
+                _ => return Err(TokenError::Invalid)
+            }
+        }
+    })
+}
+
+/// Parses a `quoted-string` and returns its content.
+///
+/// Quoted pairs are unescaped and folding is removed.
+pub fn parse_quoted_string<B: Buffer>(buf: &mut B)
+                                      -> Poll<Bytes, TokenError> {
+    let quoted = try_ready!(token::parse(buf, quoted_string));
+    let quoted = &quoted[1..quoted.len() - 1];
+    let mut res = Vec::with_capacity(quoted.len());
+    let mut escaped = false;
+    for &ch in quoted {
+        if escaped {
+            res.push(ch);
+            escaped = false
+        }
+        else if ch == b'\\' {
+            escaped = true
+        }
+        else if ch != b'\r' && ch != b'\n' {
+            res.push(ch)
+        }
+    }
+    Ok(Async::Ready(res.into()))
+}
+
+
+//------------ MIME-Version --------------------------------------------------
+
+/// Parses the value of the `MIME-Version` header field.
+///
+/// Returns the major and minor version. Only version 1.0 has ever been
+/// defined, but other values are returned rather than rejected. As
+/// section 4 of RFC 2045 demands, comments are allowed anywhere within
+/// the value.
+//
+//  version := [CFWS] 1*DIGIT [CFWS] "." [CFWS] 1*DIGIT [CFWS]
+pub fn parse_mime_version<B: Buffer>(buf: &mut B)
+                                     -> Poll<(u8, u8), TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(skip_opt_cfws(buf));
+        let major = try_ready!(u8_digits(buf));
+        try_ready!(skip_opt_cfws(buf));
+        try_ready!(token::skip_octet(buf, b'.'));
+        try_ready!(skip_opt_cfws(buf));
+        let minor = try_ready!(u8_digits(buf));
+        try_ready!(skip_opt_cfws(buf));
+        Ok(Async::Ready((major, minor)))
+    })
+}
+
+
+//------------ Content-Transfer-Encoding -------------------------------------
+
+/// The mechanism of the `Content-Transfer-Encoding` header field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ContentTransferEncoding {
+    /// `7bit`, short lines of US-ASCII data.
+    SevenBit,
+
+    /// `8bit`, short lines of octets.
+    EightBit,
+
+    /// `binary`, any sequence of octets.
+    Binary,
+
+    /// `quoted-printable`.
+    QuotedPrintable,
+
+    /// `base64`.
+    Base64,
+
+    /// Some other mechanism, i.e., an `ietf-token` or an `x-token`.
+    Other(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+}
+
+impl ContentTransferEncoding {
+    /// Returns whether the body is encoded.
+    ///
+    /// The identity mechanisms `7bit`, `8bit`, and `binary` only state
+    /// what the body contains while the others require decoding.
+    pub fn is_encoded(&self) -> bool {
+        !matches!(*self, ContentTransferEncoding::SevenBit
+                         | ContentTransferEncoding::EightBit
+                         | ContentTransferEncoding::Binary)
+    }
+}
+
+impl fmt::Display for ContentTransferEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContentTransferEncoding::SevenBit => f.write_str("7bit"),
+            ContentTransferEncoding::EightBit => f.write_str("8bit"),
+            ContentTransferEncoding::Binary => f.write_str("binary"),
+            ContentTransferEncoding::QuotedPrintable => {
+                f.write_str("quoted-printable")
+            }
+            ContentTransferEncoding::Base64 => f.write_str("base64"),
+            ContentTransferEncoding::Other(ref name) => write_octets(f, name),
+        }
+    }
+}
+
+/// Parses the value of the `Content-Transfer-Encoding` header field.
+///
+/// The mechanism is case-insensitive. Comments around it are allowed.
+/// Unknown mechanisms are returned as `ContentTransferEncoding::Other`
+/// with their original spelling.
+//
+//  encoding := [CFWS] mechanism [CFWS]
+pub fn parse_content_transfer_encoding<B: Buffer>(buf: &mut B)
+                                       -> Poll<ContentTransferEncoding,
+                                               TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(skip_opt_cfws(buf));
+        let mechanism = try_ready!(parse_token(buf));
+        try_ready!(skip_opt_cfws(buf));
+        let res = match &mechanism.to_ascii_lowercase()[..] {
+            b"7bit" => ContentTransferEncoding::SevenBit,
+            b"8bit" => ContentTransferEncoding::EightBit,
+            b"binary" => ContentTransferEncoding::Binary,
+            b"quoted-printable" => ContentTransferEncoding::QuotedPrintable,
+            b"base64" => ContentTransferEncoding::Base64,
+            _ => ContentTransferEncoding::Other(mechanism)
+        };
+        Ok(Async::Ready(res))
+    })
+}
+
+
+//------------ Parameters ----------------------------------------------------
+
+/// Parses a parameter including the semicolon in front of it.
+///
+/// Returns the attribute and the value. For a quoted value, the value is
+/// the content of the quoted string. The parameter isn’t decoded in any
+/// way, use `decode_parameters()` for that.
+//
+//  parameter := ";" [CFWS] attribute [CFWS] "=" [CFWS] value [CFWS]
+//  value     := token / quoted-string
+pub fn parse_parameter<B: Buffer>(buf: &mut B)
+                                  -> Poll<(Bytes, Bytes), TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(token::skip_octet(buf, b';'));
+        try_ready!(skip_opt_cfws(buf));
+        let attribute = try_ready!(parse_token(buf));
+        try_ready!(skip_opt_cfws(buf));
+        try_ready!(token::skip_octet(buf, b'='));
+        try_ready!(skip_opt_cfws(buf));
+        let value = try_ready!(alt!(buf, parse_token, parse_quoted_string));
+        try_ready!(skip_opt_cfws(buf));
+        Ok(Async::Ready((attribute, value)))
+    })
+}
+
+/// Parses a sequence of parameters and decodes them.
+///
+/// The sequence ends with the first octet that doesn’t start a valid
+/// parameter. See `decode_parameters()` for the decoding.
+pub fn parse_parameters<B: Buffer>(buf: &mut B)
+                                   -> Poll<Vec<Parameter>, TokenError> {
+    let mut raw = Vec::new();
+    while let Some(param) = try_ready!(rule::optional(buf,
+                                                      parse_parameter)) {
+        raw.push(param)
+    }
+    Ok(Async::Ready(decode_parameters(raw)))
+}
+
+/// A decoded parameter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Parameter {
+    /// The name of the parameter in lower case.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub name: Bytes,
+
+    /// The character set of the value if one was given.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_opt_bytes))]
+    pub charset: Option<Bytes>,
+
+    /// The language of the value if one was given.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_opt_bytes))]
+    pub language: Option<Bytes>,
+
+    /// The value.
+    ///
+    /// The value is in the original character set. Percent-encoding has
+    /// been removed.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub value: Bytes,
+}
+
+impl Parameter {
+    /// Returns the value as a string.
+    ///
+    /// Values without a character set are taken to be in US-ASCII. Only
+    /// US-ASCII, UTF-8, and ISO-8859-1 are understood. Returns `None` for
+    /// any other character set or if the value isn’t valid in its
+    /// character set.
+    pub fn decode_value(&self) -> Option<String> {
+        let charset = match self.charset {
+            Some(ref charset) => charset.to_ascii_lowercase(),
+            None => b"us-ascii".to_vec()
+        };
+        match &charset[..] {
+            b"us-ascii" => {
+                if self.value.is_ascii() {
+                    String::from_utf8(self.value.to_vec()).ok()
+                }
+                else {
+                    None
+                }
+            }
+            b"utf-8" => String::from_utf8(self.value.to_vec()).ok(),
+            b"iso-8859-1" | b"latin1" => {
+                Some(self.value.iter().map(|&ch| char::from(ch)).collect())
+            }
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    /// Formats the parameter including a semicolon in front of it.
+    ///
+    /// The value is written as a token if possible or as a quoted string
+    /// if it only contains printable US-ASCII characters. Otherwise, or if
+    /// there is a character set or language, the parameter is written as
+    /// an extended parameter as defined by RFC 2231 with the value
+    /// percent-encoded.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("; ")?;
+        write_octets(f, &self.name)?;
+        if self.charset.is_some() || self.language.is_some()
+            || !self.value.iter().all(|&ch| (0x20..0x7F).contains(&ch))
+        {
+            f.write_str("*=")?;
+            if let Some(ref charset) = self.charset {
+                write_octets(f, charset)?;
+            }
+            f.write_str("'")?;
+            if let Some(ref language) = self.language {
+                write_octets(f, language)?;
+            }
+            f.write_str("'")?;
+            for &ch in self.value.iter() {
+                if test_token(ch) && !b"*'%".contains(&ch) {
+                    write!(f, "{}", char::from(ch))?;
+                }
+                else {
+                    write!(f, "%{:02X}", ch)?;
+                }
+            }
+            Ok(())
+        }
+        else if !self.value.is_empty()
+            && self.value.iter().all(|&ch| test_token(ch))
+        {
+            f.write_str("=")?;
+            write_octets(f, &self.value)
+        }
+        else {
+            f.write_str("=\"")?;
+            for &ch in self.value.iter() {
+                if ch == b'"' || ch == b'\\' {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", char::from(ch))?;
+            }
+            f.write_str("\"")
+        }
+    }
+}
+
+/// Decodes a sequence of parameters as defined in RFC 2231.
+///
+/// A parameter named `name*` has an extended value that starts with the
+/// character set and language separated by single quotes, followed by
+/// the percent-encoded value. A value can be split into sections named
+/// `name*0`, `name*1`, etc. which are reassembled in order of their
+/// number. Sections with a trailing asterisk are percent-encoded and the
+/// first of those may carry the character set and language. Sections
+/// after a missing number are dropped.
+///
+/// If a parameter appears both plain and extended, the extended value is
+/// used. Otherwise the first of several parameters of the same name is
+/// used. The parameters are returned in the order their names first
+/// appear.
+pub fn decode_parameters<I>(raw: I) -> Vec<Parameter>
+                         where I: IntoIterator<Item=(Bytes, Bytes)> {
+    let mut order = Vec::new();
+    let mut found: HashMap<Bytes, Found> = HashMap::new();
+    for (attribute, value) in raw {
+        let attribute = attribute.to_ascii_lowercase();
+        let (name, section) = split_attribute(&attribute);
+        let name = Bytes::copy_from_slice(name);
+        let found = found.entry(name.clone()).or_insert_with(|| {
+            order.push(name);
+            Found::default()
+        });
+        match section {
+            None => {
+                if found.plain.is_none() {
+                    found.plain = Some(value)
+                }
+            }
+            Some(Section::Extended) => {
+                if found.extended.is_none() {
+                    found.extended = Some(value)
+                }
+            }
+            Some(Section::Numbered(number, encoded)) => {
+                found.sections.push((number, encoded, value))
+            }
+        }
+    }
+    order.into_iter().map(|name| {
+        let found = found.remove(&name).unwrap_or_default();
+        found.decode(name)
+    }).collect()
+}
+
+/// The raw values found for a parameter name.
+#[derive(Default)]
+struct Found {
+    plain: Option<Bytes>,
+    extended: Option<Bytes>,
+    sections: Vec<(u32, bool, Bytes)>,
+}
+
+impl Found {
+    fn decode(mut self, name: Bytes) -> Parameter {
+        if !self.sections.is_empty() {
+            self.sections.sort_by_key(|section| section.0);
+            let mut res = Parameter {
+                name, charset: None, language: None, value: Bytes::new()
+            };
+            let mut value = Vec::new();
+            let sections = self.sections.into_iter().enumerate();
+            for (i, (number, encoded, section)) in sections {
+                if number as usize != i {
+                    break
+                }
+                if !encoded {
+                    value.extend_from_slice(&section)
+                }
+                else if i == 0 {
+                    let (charset, language, section) = split_extended(section);
+                    res.charset = charset;
+                    res.language = language;
+                    pct_decode(&section, &mut value)
+                }
+                else {
+                    pct_decode(&section, &mut value)
+                }
+            }
+            res.value = value.into();
+            res
+        }
+        else if let Some(extended) = self.extended {
+            let (charset, language, extended) = split_extended(extended);
+            let mut value = Vec::new();
+            pct_decode(&extended, &mut value);
+            Parameter { name, charset, language, value: value.into() }
+        }
+        else {
+            Parameter {
+                name, charset: None, language: None,
+                value: self.plain.unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// The section part of an attribute.
+enum Section {
+    /// The attribute ends in an asterisk.
+    Extended,
+
+    /// The attribute ends in a section number and maybe an asterisk.
+    Numbered(u32, bool),
+}
+
+/// Splits an attribute into the name and section.
+///
+/// An attribute with a malformed section is taken to be a plain name.
+fn split_attribute(attribute: &[u8]) -> (&[u8], Option<Section>) {
+    let (name, section) = match attribute.iter().position(|&ch| ch == b'*') {
+        Some(pos) => (&attribute[..pos], &attribute[pos + 1..]),
+        None => return (attribute, None)
+    };
+    if section.is_empty() {
+        return (name, Some(Section::Extended))
+    }
+    let (digits, encoded) = match section.split_last() {
+        Some((b'*', digits)) => (digits, true),
+        _ => (section, false)
+    };
+    // section := "0" / [1-9] *DIGIT
+    if digits.is_empty() || digits.len() > 9
+        || (digits.len() > 1 && digits[0] == b'0')
+        || !digits.iter().all(|&ch| ch.is_ascii_digit())
+    {
+        return (attribute, None)
+    }
+    let number = digits.iter().fold(0, |res, &ch| {
+        res * 10 + u32::from(ch - b'0')
+    });
+    (name, Some(Section::Numbered(number, encoded)))
+}
+
+/// Splits an extended value into character set, language, and value.
+///
+/// If the value doesn’t contain the two single quotes, all of it is taken
+/// to be the value.
+fn split_extended(value: Bytes)
+                  -> (Option<Bytes>, Option<Bytes>, Bytes) {
+    let first = match value.iter().position(|&ch| ch == b'\'') {
+        Some(pos) => pos,
+        None => return (None, None, value)
+    };
+    let second = match value[first + 1..].iter().position(|&ch| ch == b'\'') {
+        Some(pos) => first + 1 + pos,
+        None => return (None, None, value)
+    };
+    let non_empty = |part: Bytes| {
+        if part.is_empty() { None } else { Some(part) }
+    };
+    (non_empty(value.slice(..first)),
+     non_empty(value.slice(first + 1..second)),
+     value.slice(second + 1..))
+}
+
+/// Appends the percent-decoded `value` to `target`.
+///
+/// A percent sign not followed by two hex digits is kept as is.
+fn pct_decode(value: &[u8], target: &mut Vec<u8>) {
+    let mut pos = 0;
+    while pos < value.len() {
+        let decoded = if value[pos] == b'%' && pos + 2 < value.len() {
+            hex_value(value[pos + 1]).and_then(|high| {
+                hex_value(value[pos + 2]).map(|low| high << 4 | low)
+            })
+        }
+        else {
+            None
+        };
+        match decoded {
+            Some(ch) => {
+                target.push(ch);
+                pos += 3;
+            }
+            None => {
+                target.push(value[pos]);
+                pos += 1;
+            }
+        }
+    }
+}
+
+/// Returns the value of a hex digit.
+fn hex_value(ch: u8) -> Option<u8> {
+    (ch as char).to_digit(16).map(|value| value as u8)
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::test_support::{assert_canonical, assert_roundtrip,
+                         compose_display};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    #[test]
+    fn comments() {
+        let mut data = buf(b"(a (nested \\) comment)\r\n  (b)) x");
+        assert_eq!(skip_opt_cfws(&mut data), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"x");
+        let mut data = buf(b" \r\nx");
+        assert_eq!(skip_opt_cfws(&mut data), Ok(Async::Ready(())));
+        assert_eq!(&data[..], b"\r\nx");
+        assert_eq!(skip_opt_cfws(&mut buf(b"(a (b)")), Ok(Async::NotReady));
+        assert_eq!(skip_opt_cfws(&mut buf(b"(a\x01)")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn mime_version() {
+        assert_eq!(parse_mime_version(&mut buf(b"1.0\r\n\r\n")),
+                   Ok(Async::Ready((1, 0))));
+        // The example from section 4 of RFC 2045.
+        let mut data = buf(b"(produced by MetaSend Vx.x) 1.0\r\n\r\n");
+        assert_eq!(parse_mime_version(&mut data), Ok(Async::Ready((1, 0))));
+        assert_eq!(&data[..], b"\r\n\r\n");
+        assert_eq!(parse_mime_version(&mut buf(b"1.(x)0 (y)\r\n\r\n")),
+                   Ok(Async::Ready((1, 0))));
+        let mut data = buf(b"1,0\r\n\r\n");
+        assert_eq!(parse_mime_version(&mut data), Err(TokenError::Invalid));
+        assert_eq!(data.len(), 7);
+    }
+
+    #[test]
+    fn content_transfer_encoding() {
+        assert_eq!(
+            parse_content_transfer_encoding(&mut buf(b"Base64\r\n\r\n")),
+            Ok(Async::Ready(ContentTransferEncoding::Base64))
+        );
+        assert_eq!(
+            parse_content_transfer_encoding(
+                &mut buf(b" quoted-printable (qp)\r\n\r\n")
+            ),
+            Ok(Async::Ready(ContentTransferEncoding::QuotedPrintable))
+        );
+        assert_eq!(
+            parse_content_transfer_encoding(&mut buf(b"X-UUencode\r\n\r\n")),
+            Ok(Async::Ready(ContentTransferEncoding::Other(
+                Bytes::from(&b"X-UUencode"[..])
+            )))
+        );
+        assert!(!ContentTransferEncoding::EightBit.is_encoded());
+        assert!(ContentTransferEncoding::Base64.is_encoded());
+        assert_eq!(parse_content_transfer_encoding(&mut buf(b"\"7bit\"")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        let mut data = buf(b"\"a \\\"b\\\"\r\n c\" x");
+        assert_eq!(parse_quoted_string(&mut data),
+                   Ok(Async::Ready(Bytes::from(&b"a \"b\" c"[..]))));
+        assert_eq!(&data[..], b" x");
+        assert_eq!(parse_quoted_string(&mut buf(b"\"a")),
+                   Ok(Async::NotReady));
+        assert_eq!(parse_quoted_string(&mut buf(b"\"a\r\nb\"")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn display() {
+        let params = |value: &Vec<Parameter>, target: &mut BytesMut| {
+            for param in value {
+                compose_display(param, target)
+            }
+        };
+
+        for sample in &[&b"7bit"[..], b"8bit", b"binary", b"base64",
+                        b"quoted-printable", b"x-uuencode"] {
+            assert_canonical(sample, parse_content_transfer_encoding,
+                             compose_display, b"\r\n\r\n");
+        }
+        assert_eq!(ContentTransferEncoding::QuotedPrintable.to_string(),
+                   "quoted-printable");
+
+        assert_canonical(b"; charset=us-ascii; name=\"a \\\"b\\\" c\"",
+                         parse_parameters, params, b"\r\n\r\n");
+        assert_canonical(b"; title*=us-ascii'en-us'This%20is%20%2A%2A%2Afun",
+                         parse_parameters, params, b"\r\n\r\n");
+        for value in &[
+            param(b"empty", None, None, b""),
+            param(b"filename", Some(b"utf-8"), None, "ä.txt".as_bytes()),
+            param(b"latin", None, None, b"\xe4 100%"),
+            param(b"lang", None, Some(b"de"), b"a'b"),
+        ] {
+            assert_roundtrip(&vec![value.clone()], params, parse_parameters,
+                             b"\r\n\r\n");
+        }
+    }
+
+    fn param(name: &[u8], charset: Option<&[u8]>, language: Option<&[u8]>,
+             value: &[u8]) -> Parameter {
+        Parameter {
+            name: Bytes::copy_from_slice(name),
+            charset: charset.map(Bytes::copy_from_slice),
+            language: language.map(Bytes::copy_from_slice),
+            value: Bytes::copy_from_slice(value)
+        }
+    }
+
+    #[test]
+    fn parameters() {
+        // The examples from RFC 2231.
+        let mut data = buf(b"; URL*0=\"ftp://\";\r\n \
+                             URL*1=\"cs.utk.edu/pub/moore/bulk-mailer/\
+                             bulk-mailer.tar\"\r\n\r\n");
+        assert_eq!(parse_parameters(&mut data), Ok(Async::Ready(vec![
+            param(b"url", None, None,
+                  b"ftp://cs.utk.edu/pub/moore/bulk-mailer/bulk-mailer.tar")
+        ])));
+        assert_eq!(&data[..], b"\r\n\r\n");
+        let mut data = buf(b";\r\n title*=us-ascii'en-us'\
+                             This%20is%20%2A%2A%2Afun%2A%2A%2A\r\n\r\n");
+        let res = vec![
+            param(b"title", Some(b"us-ascii"), Some(b"en-us"),
+                  b"This is ***fun***")
+        ];
+        assert_eq!(parse_parameters(&mut data), Ok(Async::Ready(res)));
+        let mut data = buf(b"; title*1*=%2A%2A%2Afun%2A%2A%2A%20;\
+                             title*0*=us-ascii'en'This%20is%20even%20more%20;\
+                             title*2=\"isn't it!\"\r\n\r\n");
+        let res = match parse_parameters(&mut data) {
+            Ok(Async::Ready(res)) => res,
+            res => panic!("{:?}", res)
+        };
+        assert_eq!(res, vec![
+            param(b"title", Some(b"us-ascii"), Some(b"en"),
+                  b"This is even more ***fun*** isn't it!")
+        ]);
+        assert_eq!(res[0].decode_value().unwrap(),
+                   "This is even more ***fun*** isn't it!");
+
+        // Mixed plain and extended, gaps, and malformed sections.
+        let res = decode_parameters(vec![
+            (Bytes::from(&b"Filename"[..]), Bytes::from(&b"a.txt"[..])),
+            (Bytes::from(&b"charset"[..]), Bytes::from(&b"x"[..])),
+            (Bytes::from(&b"filename*"[..]),
+             Bytes::from(&b"UTF-8''%C3%A4%2.txt"[..])),
+            (Bytes::from(&b"n*0"[..]), Bytes::from(&b"a"[..])),
+            (Bytes::from(&b"n*2"[..]), Bytes::from(&b"c"[..])),
+            (Bytes::from(&b"m*01"[..]), Bytes::from(&b"d"[..])),
+        ]);
+        assert_eq!(res, vec![
+            param(b"filename", Some(b"UTF-8"), None, b"\xc3\xa4%2.txt"),
+            param(b"charset", None, None, b"x"),
+            param(b"n", None, None, b"a"),
+            param(b"m*01", None, None, b"d"),
+        ]);
+        assert_eq!(res[0].decode_value(), Some(String::from("\u{e4}%2.txt")));
+        assert_eq!(param(b"a", Some(b"ISO-8859-1"), None, b"\xe4")
+                       .decode_value(),
+                   Some(String::from("\u{e4}")));
+        assert_eq!(param(b"a", None, None, b"\xe4").decode_value(), None);
+    }
+}
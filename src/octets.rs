@@ -0,0 +1,138 @@
+//! Sets of octets.
+//!
+//! Many rules boil down to a set of allowed octets. While the `core`
+//! module provides test functions for the core rules, grammars often need
+//! other combinations. An [`OctetSet`] is a cheap way to build those, even
+//! as constants.
+//!
+//! [`OctetSet`]: struct.OctetSet.html
+
+use std::fmt;
+
+
+//------------ OctetSet ------------------------------------------------------
+
+/// A set of octets.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct OctetSet {
+    /// A bitmap with one bit per octet value.
+    bits: [u64; 4],
+}
+
+impl OctetSet {
+    /// Creates a new, empty set.
+    pub const fn new() -> Self {
+        OctetSet { bits: [0; 4] }
+    }
+
+    /// Creates a set containing the octets in `octets`.
+    pub const fn from_bytes(octets: &[u8]) -> Self {
+        let mut res = Self::new();
+        let mut i = 0;
+        while i < octets.len() {
+            res = res.with(octets[i]);
+            i += 1;
+        }
+        res
+    }
+
+    /// Creates a set containing the octets from `start` to `end` inclusive.
+    pub const fn from_range(start: u8, end: u8) -> Self {
+        let mut res = Self::new();
+        let mut ch = start as usize;
+        while ch <= end as usize {
+            res = res.with(ch as u8);
+            ch += 1;
+        }
+        res
+    }
+
+    /// Creates a set containing all octets for which `test` returns true.
+    pub fn from_test<F: Fn(u8) -> bool>(test: F) -> Self {
+        let mut res = Self::new();
+        for ch in 0..=255u8 {
+            if test(ch) {
+                res.insert(ch)
+            }
+        }
+        res
+    }
+
+    /// Returns a copy of the set with `ch` added.
+    pub const fn with(mut self, ch: u8) -> Self {
+        self.bits[(ch >> 6) as usize] |= 1 << (ch & 0x3F);
+        self
+    }
+
+    /// Returns the union of the set and `other`.
+    pub const fn union(self, other: OctetSet) -> Self {
+        OctetSet {
+            bits: [
+                self.bits[0] | other.bits[0], self.bits[1] | other.bits[1],
+                self.bits[2] | other.bits[2], self.bits[3] | other.bits[3],
+            ]
+        }
+    }
+
+    /// Adds `ch` to the set.
+    pub fn insert(&mut self, ch: u8) {
+        *self = self.with(ch)
+    }
+
+    /// Returns whether `ch` is in the set.
+    pub const fn contains(&self, ch: u8) -> bool {
+        self.bits[(ch >> 6) as usize] & (1 << (ch & 0x3F)) != 0
+    }
+
+    /// Returns an iterator over the octets in the set in ascending order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
+        (0..=255u8).filter(move |&ch| self.contains(ch))
+    }
+}
+
+impl fmt::Debug for OctetSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+
+//------------ Common Sets ---------------------------------------------------
+
+/// `ALPHA` of RFC 5234.
+pub const ALPHA: OctetSet = OctetSet::from_range(b'A', b'Z')
+                                .union(OctetSet::from_range(b'a', b'z'));
+
+/// `DIGIT` of RFC 5234.
+pub const DIGIT: OctetSet = OctetSet::from_range(b'0', b'9');
+
+/// `unreserved` of RFC 3986.
+pub const URI_UNRESERVED: OctetSet = ALPHA.union(DIGIT)
+                                    .union(OctetSet::from_bytes(b"-._~"));
+
+/// `sub-delims` of RFC 3986.
+pub const URI_SUB_DELIMS: OctetSet = OctetSet::from_bytes(b"!$&'()*+,;=");
+
+/// `pchar` of RFC 3986 without the percent-encoded octets.
+pub const URI_PCHAR: OctetSet = URI_UNRESERVED.union(URI_SUB_DELIMS)
+                                .union(OctetSet::from_bytes(b":@"));
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use ::core::test_digit;
+    use super::*;
+
+    #[test]
+    fn sets() {
+        assert!(URI_PCHAR.contains(b'~'));
+        assert!(URI_PCHAR.contains(b'@'));
+        assert!(!URI_PCHAR.contains(b'/'));
+        assert!(!URI_PCHAR.contains(0xFF));
+        assert_eq!(OctetSet::from_test(test_digit), DIGIT);
+        assert_eq!(OctetSet::from_range(0, 255).iter().count(), 256);
+        assert_eq!(format!("{:?}", OctetSet::from_bytes(b"ba")), "{97, 98}");
+    }
+}
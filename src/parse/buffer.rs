@@ -0,0 +1,314 @@
+//! Input Buffers.
+//!
+//! All parsing happens atop a buffer of octets received so far. This module
+//! defines the trait `Buffer` that abstracts over the concrete type of such
+//! a buffer. It provides the three operations parsing needs: viewing the
+//! octets currently available, taking a checkpoint in order to rewind to it
+//! later, and draining octets from the beginning once they have been parsed.
+//!
+//! For data that arrives bit by bit, you will normally use [`CursorBuf`],
+//! which is also the buffer the adapters use. The trait is also
+//! implemented for `BytesMut`, for `VecDeque<u8>`, for the
+//! fixed-capacity [`RingBuf`] where data may wrap around, and for
+//! [`RopeBuf`] which keeps data as a sequence of separate segments.
+//!
+//! When all data is available up front, it can be parsed right where it
+//! is: the trait is implemented for `Bytes` and for `&[u8]`, too. Both
+//! drain octets by moving their start forward. While a `Bytes` hands out
+//! the drained octets without copying, a slice has to copy them into a
+//! new `Bytes` value, so rules that only skip over data are cheaper on
+//! slices than those returning tokens.
+//!
+//! Since all rules in this crate are generic over the buffer type, the
+//! same rule can be used for all of these.
+//!
+//! The buffer types differ in the cost of taking a checkpoint, which
+//! happens for every group of rules. For `BytesMut` and `VecDeque<u8>`, a
+//! checkpoint is a copy of the whole buffer: octets drained from them are
+//! gone, so there is no position to return to. Every group thus costs an
+//! allocation and a copy of all remaining octets, which is why the
+//! examples throughout this crate use `CursorBuf` instead. All other types merely
+//! remember a position. `CursorBuf` and `RopeBuf` can do so because they
+//! keep drained data around until new data is added.
+//!
+//! [`RingBuf`]: ../ring/struct.RingBuf.html
+//! [`CursorBuf`]: ../cursor/struct.CursorBuf.html
+//! [`RopeBuf`]: ../rope/struct.RopeBuf.html
+
+use std::collections::VecDeque;
+use bytes::{Bytes, BytesMut};
+use super::limits::Limits;
+use super::metrics::Metrics;
+
+
+//------------ Buffer --------------------------------------------------------
+
+/// A buffer of octets to be parsed.
+///
+/// Parsing always happens at the beginning of the buffer. Octets are viewed
+/// through `get()` and, once parsed, are drained via `split_to()` or
+/// `advance()`. If a parser needs to undo what it has drained, it takes a
+/// checkpoint before starting and rewinds to it if things go wrong.
+pub trait Buffer {
+    /// A value that allows rewinding the buffer to an earlier state.
+    type Checkpoint;
+
+    /// Returns the number of octets currently available.
+    fn len(&self) -> usize;
+
+    /// Returns whether there are no octets available currently.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the octet at position `index` if it is available.
+    fn get(&self, index: usize) -> Option<u8>;
+
+    /// Returns a checkpoint for the current state of the buffer.
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Rewinds the buffer to the state when `checkpoint` was taken.
+    fn rewind(&mut self, checkpoint: Self::Checkpoint);
+
+    /// Drains the first `count` octets from the buffer and returns them.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if `count` is larger than the buffer’s length.
+    fn split_to(&mut self, count: usize) -> Bytes;
+
+    /// Drops the first `count` octets from the buffer.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if `count` is larger than the buffer’s length.
+    fn advance(&mut self, count: usize) {
+        let _ = self.split_to(count);
+    }
+
+    /// Returns the metrics to report parsing events into, if any.
+    ///
+    /// The combinators report every rule invoked and every time a rule
+    /// runs out of data. See the [`metrics`] module for details.
+    ///
+    /// [`metrics`]: ../metrics/index.html
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        None
+    }
+
+    /// Returns the limits parsing should adhere to, if any.
+    ///
+    /// See the [`limits`] module for details.
+    ///
+    /// [`limits`]: ../limits/index.html
+    fn limits(&mut self) -> Option<&mut Limits> {
+        None
+    }
+
+    /// Reports that at least `additional` more octets are needed.
+    ///
+    /// A rule about to return non-ready calls this if it knows how many
+    /// octets beyond those currently available it needs at the very least
+    /// before it can make progress. See the [`hint`] module for details.
+    ///
+    /// [`hint`]: ../hint/index.html
+    fn report_needed(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
+    /// Returns whether no more data will be added to the buffer.
+    ///
+    /// If this returns `true`, rules treat the end of the buffer as the
+    /// end of the input rather than waiting for more data. See the
+    /// [`complete`] module for details.
+    ///
+    /// [`complete`]: ../complete/index.html
+    fn is_final(&self) -> bool {
+        false
+    }
+}
+
+
+//------------ BytesMut ------------------------------------------------------
+
+/// A `BytesMut` drains octets for good.
+///
+/// A checkpoint therefore is a copy of the entire buffer. Since every
+/// group of rules takes a checkpoint, each group costs an allocation and
+/// a copy of all the octets remaining in the buffer. This is fine for
+/// short inputs but quickly adds up for larger ones. Use [`CursorBuf`]
+/// instead which can be created from a `BytesMut` without copying.
+///
+/// [`CursorBuf`]: ../cursor/struct.CursorBuf.html
+impl Buffer for BytesMut {
+    type Checkpoint = BytesMut;
+
+    fn len(&self) -> usize {
+        BytesMut::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self[..].get(index).cloned()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.clone()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        BytesMut::split_to(self, count).freeze()
+    }
+
+    fn advance(&mut self, count: usize) {
+        let _ = BytesMut::split_to(self, count);
+    }
+}
+
+
+//------------ VecDeque ------------------------------------------------------
+
+/// Like a `BytesMut`, a `VecDeque<u8>` drains octets for good.
+///
+/// A checkpoint is a copy of the entire buffer, making each group of
+/// rules as expensive as for a `BytesMut`.
+impl Buffer for VecDeque<u8> {
+    type Checkpoint = VecDeque<u8>;
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        VecDeque::get(self, index).cloned()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.clone()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        assert!(count <= VecDeque::len(self));
+        self.drain(..count).collect::<Vec<u8>>().into()
+    }
+
+    fn advance(&mut self, count: usize) {
+        assert!(count <= VecDeque::len(self));
+        self.drain(..count);
+    }
+}
+
+
+//------------ Bytes ---------------------------------------------------------
+
+impl Buffer for Bytes {
+    type Checkpoint = Bytes;
+
+    fn len(&self) -> usize {
+        Bytes::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self[..].get(index).cloned()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.clone()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        Bytes::split_to(self, count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        let _ = Bytes::split_to(self, count);
+    }
+}
+
+
+//------------ Slice ---------------------------------------------------------
+
+impl<'a> Buffer for &'a [u8] {
+    type Checkpoint = &'a [u8];
+
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        <[u8]>::get(self, index).cloned()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        *self = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        let (head, tail) = self.split_at(count);
+        *self = tail;
+        Bytes::copy_from_slice(head)
+    }
+
+    fn advance(&mut self, count: usize) {
+        *self = &self[count..]
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use futures::{Async, Poll};
+    use ::core::{skip_crlf, u16_digits};
+    use ::parse::rule::group;
+    use ::parse::token::{self, TokenError};
+    use super::*;
+
+    /// Parses a number followed by a line break.
+    fn line<B: Buffer>(buf: &mut B) -> Poll<u16, TokenError> {
+        group(buf, |buf| {
+            let res = try_ready!(u16_digits(buf));
+            try_ready!(skip_crlf(buf));
+            Ok(Async::Ready(res))
+        })
+    }
+
+    /// Runs `line()` and a token parser on a buffer.
+    fn check<B: Buffer>(mut buf: B) {
+        assert_eq!(line(&mut buf), Ok(Async::Ready(12)));
+        assert_eq!(line(&mut buf), Err(TokenError::Invalid));
+        assert_eq!(buf.len(), 5);
+        assert_eq!(token::parse_literal(&mut buf, b"ab"),
+                   Ok(Async::Ready(Bytes::from_static(b"ab"))));
+        assert_eq!(skip_crlf(&mut buf), Ok(Async::Ready(())));
+        assert_eq!(line(&mut buf), Ok(Async::NotReady));
+        assert_eq!(buf.get(0), Some(b'3'));
+        buf.advance(1);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn buffers() {
+        const DATA: &[u8] = b"12\r\nab\r\n3";
+        check(BytesMut::from(DATA));
+        check(DATA.iter().cloned().collect::<VecDeque<u8>>());
+        check(Bytes::from_static(DATA));
+        check(DATA);
+    }
+}
@@ -0,0 +1,217 @@
+//! Parsing up to the end of the input.
+//!
+//! Many rules can only decide that they are done once they see an octet
+//! that doesn’t belong to them anymore. `cats()` and `digits()`, for
+//! instance, need the first octet past the sequence, and a literal needs
+//! all of its octets before it can tell whether they match. If there is no
+//! such octet because the producer of the data has signaled the end of the
+//! input, these rules would wait forever.
+//!
+//! For such cases, wrap the buffer in a [`Complete`] buffer. Its
+//! `Buffer::is_final()` returns `true` once the end of the input has been
+//! signaled, and the rules then treat the end of the buffer like a
+//! non-matching octet: a sequence ends there, an optional element is
+//! absent, and a literal or a required octet doesn’t match. A token that
+//! still can’t be decided is invalid, so parsers on a final buffer never
+//! return non-ready because of missing data.
+//!
+//! A `Complete` buffer can be created for data that is already complete
+//! via `Complete::new()` or for data still arriving via
+//! `Complete::pending()`. In the latter case, call `set_final()` once the
+//! producer has signaled the end of the input and run the parser once
+//! more.
+//!
+//! [`Complete`]: struct.Complete.html
+
+use bytes::Bytes;
+use super::buffer::Buffer;
+use super::limits::Limits;
+use super::metrics::Metrics;
+
+
+//------------ Complete ------------------------------------------------------
+
+/// A buffer that knows whether it has all the input.
+#[derive(Clone, Debug, Default)]
+pub struct Complete<B> {
+    buf: B,
+    is_final: bool,
+}
+
+impl<B> Complete<B> {
+    /// Creates a buffer from one that holds the complete input.
+    pub fn new(buf: B) -> Self {
+        Complete { buf, is_final: true }
+    }
+
+    /// Creates a buffer from one that more input will be added to.
+    pub fn pending(buf: B) -> Self {
+        Complete { buf, is_final: false }
+    }
+
+    /// Signals that no more data will be added to the buffer.
+    pub fn set_final(&mut self) {
+        self.is_final = true
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns a mutable reference to the underlying buffer.
+    ///
+    /// This is used for adding new data to the buffer.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Trades the buffer for the underlying buffer.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+
+impl<B: Buffer> Buffer for Complete<B> {
+    type Checkpoint = B::Checkpoint;
+
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.buf.get(index)
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.buf.checkpoint()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.buf.rewind(checkpoint)
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        self.buf.split_to(count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.buf.advance(count)
+    }
+
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        self.buf.metrics()
+    }
+
+    fn limits(&mut self) -> Option<&mut Limits> {
+        self.buf.limits()
+    }
+
+    fn report_needed(&mut self, additional: usize) {
+        self.buf.report_needed(additional)
+    }
+
+    fn is_final(&self) -> bool {
+        self.is_final
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::{Async, Poll};
+    use ::core::{
+        alphas, digits, parse_line_with, radix_digits, skip_crlf,
+        skip_lwsp, skip_lwsp_strict, u16_digits, u16_digits_strict,
+        LineEnding
+    };
+    use ::parse::rule::{group, optional};
+    use ::parse::token::{
+        self, parse_keyword, skip_literal, skip_octet, Keyword, TokenError
+    };
+    use super::*;
+
+    fn complete(data: &[u8]) -> Complete<BytesMut> {
+        Complete::new(BytesMut::from(data))
+    }
+
+    #[test]
+    fn cats() {
+        assert_eq!(u16_digits(&mut BytesMut::from(&b"12"[..])),
+                   Ok(Async::NotReady));
+        assert_eq!(u16_digits(&mut complete(b"12")), Ok(Async::Ready(12)));
+        assert_eq!(u16_digits(&mut complete(b"")),
+                   Err(TokenError::Invalid));
+        assert_eq!(token::parse(&mut complete(b"ab"), alphas),
+                   Ok(Async::Ready(Bytes::from_static(b"ab"))));
+        assert_eq!(u16_digits_strict(&mut complete(b"0")),
+                   Ok(Async::Ready(0)));
+        assert_eq!(
+            token::skip(&mut complete(b"ff"), |token| {
+                radix_digits(token, 16, 4)
+            }),
+            Ok(Async::Ready(()))
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(skip_literal(&mut complete(b"HEL"), b"hello"),
+                   Err(TokenError::Invalid));
+        assert_eq!(skip_octet(&mut complete(b""), b'.'),
+                   Err(TokenError::Invalid));
+        let table = [
+            Keyword { literal: b"get", value: 1, case_sensitive: false },
+            Keyword { literal: b"getall", value: 2, case_sensitive: false },
+        ];
+        assert_eq!(parse_keyword(&mut complete(b"get"), &table),
+                   Ok(Async::Ready(1)));
+    }
+
+    #[test]
+    fn lines() {
+        let mut buf = complete(b"a\r");
+        assert_eq!(parse_line_with(&mut buf, LineEnding::Any),
+                   Ok(Async::Ready(Bytes::from_static(b"a\r"))));
+        assert_eq!(skip_crlf(&mut complete(b"\r")), Err(TokenError::Invalid));
+        assert_eq!(skip_lwsp(&mut complete(b" \t")), Ok(Async::Ready(())));
+        let mut buf = complete(b" \r\n");
+        assert_eq!(skip_lwsp(&mut buf), Ok(Async::Ready(())));
+        assert_eq!(buf.get_ref(), &b"\r\n"[..]);
+        assert_eq!(skip_lwsp_strict(&mut complete(b" \r")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn rules() {
+        // 1*DIGIT ["." 1*DIGIT] at the very end of the input.
+        fn version<B: Buffer>(buf: &mut B)
+                              -> Poll<(u16, Option<u16>), TokenError> {
+            group(buf, |buf| {
+                let major = try_ready!(u16_digits(buf));
+                let minor = try_ready!(optional(buf, |buf| group(buf, |buf| {
+                    try_ready!(skip_octet(buf, b'.'));
+                    u16_digits(buf)
+                })));
+                Ok(Async::Ready((major, minor)))
+            })
+        }
+
+        let mut buf = Complete::pending(BytesMut::from(&b"1"[..]));
+        assert_eq!(version(&mut buf), Ok(Async::NotReady));
+        buf.get_mut().extend_from_slice(b".2");
+        assert_eq!(version(&mut buf), Ok(Async::NotReady));
+        buf.set_final();
+        assert_eq!(version(&mut buf), Ok(Async::Ready((1, Some(2)))));
+        assert!(buf.get_ref().is_empty());
+
+        let mut buf = complete(b"1.");
+        assert_eq!(version(&mut buf), Ok(Async::Ready((1, None))));
+        assert_eq!(buf.get_ref(), &b"."[..]);
+        assert_eq!(token::skip(&mut complete(b"1"), digits),
+                   Ok(Async::Ready(())));
+    }
+}
@@ -0,0 +1,174 @@
+//! A buffer with cheap checkpoints.
+//!
+//! Every group of rules takes a checkpoint of the buffer before it starts
+//! so it can rewind if the group fails. For `BytesMut`, a checkpoint is a
+//! copy of the entire buffer, since octets drained from it are gone for
+//! good. With deeply nested rules and large buffers, these copies add up.
+//!
+//! This module provides `CursorBuf` which avoids this. It keeps all its
+//! data in place and only moves a cursor forward when octets are drained.
+//! A checkpoint is just the position of the cursor and rewinding moves
+//! the cursor back. Drained tokens share the memory of the buffer, so no
+//! octets are copied while parsing.
+//!
+//! This is why the adapters and the prelude use `CursorBuf` as their
+//! buffer.
+//!
+//! The drained octets are released when new data is added to the
+//! buffer. At that point, any remaining octets are moved to new memory
+//! if tokens drained earlier are still around.
+
+use std::mem;
+use bytes::{Bytes, BytesMut};
+use super::buffer::Buffer;
+
+
+//------------ CursorBuf -----------------------------------------------------
+
+/// A buffer that drains octets by moving a cursor.
+#[derive(Clone, Debug, Default)]
+pub struct CursorBuf {
+    /// The data including octets already drained.
+    data: Bytes,
+
+    /// The position of the first octet not drained yet.
+    pos: usize,
+}
+
+impl CursorBuf {
+    /// Creates a new, empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `data` to the end of the buffer.
+    ///
+    /// This drops the octets drained so far.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.compact();
+        let mut buf = match mem::take(&mut self.data).try_into_mut() {
+            Ok(buf) => buf,
+            Err(shared) => BytesMut::from(shared.as_ref()),
+        };
+        buf.extend_from_slice(data);
+        self.data = buf.freeze();
+    }
+
+    /// Drops the octets drained so far.
+    ///
+    /// This must not be called while parsing since it invalidates all
+    /// checkpoints taken.
+    pub fn compact(&mut self) {
+        self.data.advance(self.pos);
+        self.pos = 0;
+    }
+
+    /// Returns the octets not drained yet.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[self.pos..]
+    }
+
+    /// Converts the buffer into the octets not drained yet.
+    pub fn into_bytes(mut self) -> Bytes {
+        self.compact();
+        self.data
+    }
+}
+
+
+//--- From
+
+impl From<Bytes> for CursorBuf {
+    fn from(data: Bytes) -> Self {
+        CursorBuf { data, pos: 0 }
+    }
+}
+
+impl From<BytesMut> for CursorBuf {
+    fn from(data: BytesMut) -> Self {
+        data.freeze().into()
+    }
+}
+
+impl<'a> From<&'a [u8]> for CursorBuf {
+    fn from(data: &'a [u8]) -> Self {
+        Bytes::copy_from_slice(data).into()
+    }
+}
+
+
+//--- Buffer
+
+impl Buffer for CursorBuf {
+    type Checkpoint = usize;
+
+    fn len(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.as_slice().get(index).cloned()
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.pos
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.pos = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        assert!(count <= self.len());
+        let res = self.data.slice(self.pos..self.pos + count);
+        self.pos += count;
+        res
+    }
+
+    fn advance(&mut self, count: usize) {
+        assert!(count <= self.len());
+        self.pos += count;
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use futures::Async;
+    use ::core::{u16_digits, skip_crlf};
+    use ::parse::rule::group;
+    use ::parse::token::{self, TokenError};
+    use super::*;
+
+    #[test]
+    fn rewind() {
+        let mut buf = CursorBuf::from(&b"123\r\nabc"[..]);
+        assert_eq!(
+            group(&mut buf, |buf| {
+                try_ready!(u16_digits(buf));
+                try_ready!(skip_crlf(buf));
+                token::skip_literal(buf, b"abd")
+            }),
+            Err(TokenError::Invalid)
+        );
+        assert_eq!(buf.as_slice(), b"123\r\nabc");
+        assert_eq!(u16_digits(&mut buf), Ok(Async::Ready(123)));
+        assert_eq!(buf.checkpoint(), 3);
+    }
+
+    #[test]
+    fn split_and_extend() {
+        let mut buf = CursorBuf::from(&b"abc,d"[..]);
+        let token = buf.split_to(3);
+        assert_eq!(token, &b"abc"[..]);
+        assert_eq!(token.as_ptr(), buf.data.as_ptr());
+        buf.advance(1);
+        buf.extend_from_slice(b"ef");
+        assert_eq!(buf.checkpoint(), 0);
+        assert_eq!(buf.as_slice(), b"def");
+        assert_eq!(token, &b"abc"[..]);
+        assert_eq!(buf.into_bytes(), &b"def"[..]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Hints on how much data is needed.
+//!
+//! When a parser returns non-ready, all the driver learns is that more
+//! data is needed. It will then typically read whatever is available and
+//! try again. For large items, this means trying the parser over and over
+//! while the data trickles in.
+//!
+//! Some rules know better. A literal knows exactly how many of its octets
+//! are still missing and a rule for a counted number of octets knows how
+//! many it still needs. Such rules report this number through
+//! `Buffer::report_needed()` before returning non-ready.
+//!
+//! In order to receive the reports, wrap the buffer in a [`Hinted`]
+//! buffer. After the parser returned non-ready, `take_needed()` returns
+//! the number of octets that need to be added to the buffer at the very
+//! least before trying again makes sense. If several rules report before
+//! the parser returns, the smallest number wins.
+//!
+//! A rule must only report a number if it is certain that any smaller
+//! amount of data will result in non-ready again. Token rules that don’t
+//! know report a single octet so that they still take part in the
+//! minimum when one of several alternatives knows better. Other rules
+//! that don’t know simply don’t report anything. If nothing was reported,
+//! the driver has to assume that a single octet may make a difference.
+//!
+//! [`Hinted`]: struct.Hinted.html
+
+use std::cmp;
+use bytes::Bytes;
+use super::buffer::Buffer;
+use super::limits::Limits;
+use super::metrics::Metrics;
+
+
+//------------ Hinted --------------------------------------------------------
+
+/// A buffer collecting hints on how much data is needed.
+///
+/// This type wraps another buffer and keeps the smallest number of
+/// additional octets reported as needed since `take_needed()` was last
+/// called.
+#[derive(Clone, Debug, Default)]
+pub struct Hinted<B> {
+    buf: B,
+    needed: Option<usize>,
+}
+
+impl<B> Hinted<B> {
+    /// Creates a new hinted buffer from a buffer.
+    pub fn new(buf: B) -> Self {
+        Hinted { buf, needed: None }
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns a mutable reference to the underlying buffer.
+    ///
+    /// This is used for adding new data to the buffer.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Returns the number of additional octets needed if known.
+    ///
+    /// The number is relative to the amount of data in the buffer when it
+    /// was reported.
+    pub fn needed(&self) -> Option<usize> {
+        self.needed
+    }
+
+    /// Returns the number of additional octets needed and forgets it.
+    pub fn take_needed(&mut self) -> Option<usize> {
+        self.needed.take()
+    }
+
+    /// Trades the hinted buffer for the underlying buffer.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+
+impl<B: Buffer> Buffer for Hinted<B> {
+    type Checkpoint = B::Checkpoint;
+
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.buf.get(index)
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.buf.checkpoint()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.buf.rewind(checkpoint)
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        self.buf.split_to(count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.buf.advance(count)
+    }
+
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        self.buf.metrics()
+    }
+
+    fn limits(&mut self) -> Option<&mut Limits> {
+        self.buf.limits()
+    }
+
+    fn report_needed(&mut self, additional: usize) {
+        self.needed = Some(match self.needed {
+            Some(needed) => cmp::min(needed, additional),
+            None => additional
+        });
+        self.buf.report_needed(additional)
+    }
+
+    fn is_final(&self) -> bool {
+        self.buf.is_final()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::Async;
+    use ::core::parse_octets_exact;
+    use ::parse::token;
+    use super::*;
+
+    rule!(greeting = "HELLOWORLD" / "x");
+
+    fn buf(slice: &[u8]) -> Hinted<BytesMut> {
+        Hinted::new(BytesMut::from(slice))
+    }
+
+    #[test]
+    fn hints() {
+        let mut data = buf(b"he");
+        assert_eq!(token::skip_literal(&mut data, b"hello"),
+                   Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(3));
+        assert_eq!(data.needed(), None);
+
+        let mut data = buf(b"h");
+        data.report_needed(4);
+        data.report_needed(2);
+        data.report_needed(3);
+        assert_eq!(data.take_needed(), Some(2));
+
+        let mut data = buf(b"abc");
+        assert_eq!(parse_octets_exact(&mut data, 10), Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(7));
+
+        let mut data = buf(b"abc");
+        assert_eq!(::core::parse_line(&mut data), Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(1));
+    }
+
+    #[test]
+    fn alternatives() {
+        let mut data = buf(b"");
+        assert_eq!(alt!(&mut data,
+                        token::skip_literal(b"HELLOWORLD"),
+                        token::skip_octet(b'x')),
+                   Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(1));
+
+        let mut data = buf(b"HELLO");
+        assert_eq!(alt!(&mut data,
+                        token::skip_octet(b'x'),
+                        token::skip_literal(b"HELLOWORLD")),
+                   Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(5));
+
+        let mut data = buf(b"");
+        assert_eq!(token::skip(&mut data, greeting), Ok(Async::NotReady));
+        assert_eq!(data.take_needed(), Some(1));
+    }
+}
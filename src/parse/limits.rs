@@ -0,0 +1,358 @@
+//! Parser limits.
+//!
+//! When parsing data received from the network, it is a good idea to bound
+//! the resources a parser may use. Otherwise, a peer can make the parser
+//! buffer an endless token, recurse ever deeper into nested rules, or
+//! collect an unbounded number of repeated elements.
+//!
+//! In order to enforce such bounds, wrap the buffer in a [`Limited`]
+//! buffer. Like with metrics, the buffer is handed through all the
+//! combinators which get hold of the [`Limits`] via `Buffer::limits()`
+//! and check them in one place each. If a limit is exceeded, parsing fails
+//! with `TokenError::LimitExceeded`. For this reason, the combinators that
+//! enforce limits require their error type to be convertible from
+//! `TokenError`. For the rule combinators, these are the variants with a
+//! `_limited` suffix, such as `rule::group_limited()`, while the plain
+//! combinators keep accepting any error type and don’t check the limits.
+//!
+//! The following limits are available:
+//!
+//! * the maximum length of a token, checked by `token::parse()` and
+//!   friends. Since a token that hasn’t ended yet will eventually be at
+//!   least as long as the data in the buffer, a token is rejected as soon
+//!   as the buffer holds too much data for it,
+//! * the maximum depth of nested groups, checked by
+//!   `rule::group_limited()`,
+//! * the maximum number of elements in a repetition, checked by the
+//!   various repetition combinators, and
+//! * the maximum number of digits in a number, checked by
+//!   `token::digit_cats()` and thus all the integer parsers in `core`.
+//!   Without it, a peer sending an endless run of digits would only be
+//!   noticed once the token gets too long or the value overflows.
+//!
+//! Buffers that aren’t wrapped are not limited at all.
+//!
+//! [`Limited`]: struct.Limited.html
+//! [`Limits`]: struct.Limits.html
+
+use bytes::Bytes;
+use super::buffer::Buffer;
+use super::metrics::Metrics;
+use super::token::TokenError;
+
+
+//------------ Limits --------------------------------------------------------
+
+/// The resource limits for a parser.
+///
+/// The default limits are somewhat generous for line-based protocols.
+/// Tokens may be up to 64 KiB long, groups may be nested 128 levels deep,
+/// repetitions may have up to 65,536 elements, and numbers may have up to
+/// 256 digits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// The maximum length of a token in octets.
+    max_token_len: usize,
+
+    /// The maximum nesting depth of groups.
+    max_depth: usize,
+
+    /// The maximum number of elements in a repetition.
+    max_repeat: usize,
+
+    /// The maximum number of digits in a number.
+    max_digits: usize,
+
+    /// The current nesting depth of groups.
+    depth: usize,
+}
+
+impl Limits {
+    /// Creates limits with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates limits that don’t limit anything.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_token_len: usize::MAX,
+            max_depth: usize::MAX,
+            max_repeat: usize::MAX,
+            max_digits: usize::MAX,
+            depth: 0,
+        }
+    }
+
+    /// Returns the maximum length of a token in octets.
+    pub fn max_token_len(&self) -> usize {
+        self.max_token_len
+    }
+
+    /// Sets the maximum length of a token in octets.
+    pub fn set_max_token_len(&mut self, value: usize) {
+        self.max_token_len = value
+    }
+
+    /// Returns the maximum nesting depth of groups.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the maximum nesting depth of groups.
+    pub fn set_max_depth(&mut self, value: usize) {
+        self.max_depth = value
+    }
+
+    /// Returns the maximum number of elements in a repetition.
+    pub fn max_repeat(&self) -> usize {
+        self.max_repeat
+    }
+
+    /// Sets the maximum number of elements in a repetition.
+    pub fn set_max_repeat(&mut self, value: usize) {
+        self.max_repeat = value
+    }
+
+    /// Returns the maximum number of digits in a number.
+    pub fn max_digits(&self) -> usize {
+        self.max_digits
+    }
+
+    /// Sets the maximum number of digits in a number.
+    pub fn set_max_digits(&mut self, value: usize) {
+        self.max_digits = value
+    }
+
+    /// Returns the current nesting depth of groups.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_token_len: 0x1_0000,
+            max_depth: 128,
+            max_repeat: 0x1_0000,
+            max_digits: 256,
+            depth: 0,
+        }
+    }
+}
+
+
+//------------ Limited -------------------------------------------------------
+
+/// A buffer enforcing parser limits.
+///
+/// This type wraps another buffer and provides the limits to all the
+/// combinators applied to it.
+#[derive(Clone, Debug, Default)]
+pub struct Limited<B> {
+    buf: B,
+    limits: Limits,
+}
+
+impl<B> Limited<B> {
+    /// Creates a new limited buffer from a buffer and the limits.
+    pub fn new(buf: B, limits: Limits) -> Self {
+        Limited { buf, limits }
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns a mutable reference to the underlying buffer.
+    ///
+    /// This is used for adding new data to the buffer.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Returns a reference to the limits.
+    pub fn limits_ref(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Trades the limited buffer for the underlying buffer.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+
+impl<B: Buffer> Buffer for Limited<B> {
+    type Checkpoint = B::Checkpoint;
+
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.buf.get(index)
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.buf.checkpoint()
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.buf.rewind(checkpoint)
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        self.buf.split_to(count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.buf.advance(count)
+    }
+
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        self.buf.metrics()
+    }
+
+    fn limits(&mut self) -> Option<&mut Limits> {
+        Some(&mut self.limits)
+    }
+
+    fn report_needed(&mut self, additional: usize) {
+        self.buf.report_needed(additional)
+    }
+
+    fn is_final(&self) -> bool {
+        self.buf.is_final()
+    }
+}
+
+
+//------------ Checking Limits -----------------------------------------------
+
+/// Enters a group, checking the maximum depth.
+///
+/// If this returns an error, the group must not be entered. Otherwise,
+/// `leave()` must be called once the group is done.
+pub(crate) fn enter<B: Buffer>(buf: &mut B) -> Result<(), TokenError> {
+    if let Some(limits) = buf.limits() {
+        if limits.depth >= limits.max_depth {
+            return Err(TokenError::LimitExceeded)
+        }
+        limits.depth += 1;
+    }
+    Ok(())
+}
+
+/// Leaves a group that was successfully entered.
+pub(crate) fn leave<B: Buffer>(buf: &mut B) {
+    if let Some(limits) = buf.limits() {
+        limits.depth -= 1
+    }
+}
+
+/// Checks that a repetition with `count` elements is acceptable.
+pub(crate) fn check_repeat<B: Buffer>(buf: &mut B, count: usize)
+                                      -> Result<(), TokenError> {
+    match buf.limits() {
+        Some(ref limits) if count > limits.max_repeat => {
+            Err(TokenError::LimitExceeded)
+        }
+        _ => Ok(())
+    }
+}
+
+/// Checks that a number with `count` digits is acceptable.
+pub(crate) fn check_digits<B: Buffer>(buf: &mut B, count: usize)
+                                      -> Result<(), TokenError> {
+    match buf.limits() {
+        Some(ref limits) if count > limits.max_digits => {
+            Err(TokenError::LimitExceeded)
+        }
+        _ => Ok(())
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::{Async, Poll};
+    use ::core::{digit, digits, line, parse_line, u8_digits, u64_digits};
+    use ::parse::rule::group_limited;
+    use ::parse::token::{self, TokenError};
+    use super::*;
+
+    fn buf(slice: &[u8], limits: Limits) -> Limited<BytesMut> {
+        Limited::new(BytesMut::from(slice), limits)
+    }
+
+    #[test]
+    fn token_len() {
+        let mut limits = Limits::new();
+        limits.set_max_token_len(4);
+        assert_eq!(parse_line(&mut buf(b"ab\r\n", limits)),
+                   Ok(Async::Ready((&b"ab\r\n"[..]).into())));
+        assert_eq!(parse_line(&mut buf(b"abc\r\n", limits)),
+                   Err(TokenError::LimitExceeded));
+        assert_eq!(token::skip(&mut buf(b"abcd", limits), line),
+                   Ok(Async::NotReady));
+        assert_eq!(token::skip(&mut buf(b"abcde", limits), line),
+                   Err(TokenError::LimitExceeded));
+        assert_eq!(token::skip(&mut buf(b"12345 ", limits), digits),
+                   Err(TokenError::LimitExceeded));
+    }
+
+    #[test]
+    fn depth() {
+        fn nested(buf: &mut Limited<BytesMut>, level: usize)
+                  -> Poll<u8, TokenError> {
+            group_limited(buf, |buf| {
+                if level == 0 {
+                    u8_digits(buf)
+                }
+                else {
+                    nested(buf, level - 1)
+                }
+            })
+        }
+
+        let mut limits = Limits::new();
+        limits.set_max_depth(3);
+        let mut data = buf(b"12 ", limits);
+        assert_eq!(nested(&mut data, 2), Ok(Async::Ready(12)));
+        assert_eq!(data.limits_ref().depth(), 0);
+        let mut data = buf(b"12 ", limits);
+        assert_eq!(nested(&mut data, 3), Err(TokenError::LimitExceeded));
+        assert_eq!(data.limits_ref().depth(), 0);
+        assert_eq!(&data.get_ref()[..], b"12 ");
+    }
+
+    #[test]
+    fn repeat() {
+        let mut limits = Limits::new();
+        limits.set_max_repeat(3);
+        let digits = |token: &mut token::Token<_>| {
+            token::repeat(token, 1, None, digit)
+        };
+        assert_eq!(token::skip(&mut buf(b"123 ", limits), digits),
+                   Ok(Async::Ready(())));
+        assert_eq!(token::skip(&mut buf(b"1234 ", limits), digits),
+                   Err(TokenError::LimitExceeded));
+    }
+
+    #[test]
+    fn digits_len() {
+        let mut limits = Limits::new();
+        limits.set_max_digits(4);
+        assert_eq!(u64_digits(&mut buf(b"1234 ", limits)),
+                   Ok(Async::Ready(1234)));
+        assert_eq!(u64_digits(&mut buf(b"12345", limits)),
+                   Err(TokenError::LimitExceeded));
+        assert_eq!(u64_digits(&mut buf(b"1234", limits)),
+                   Ok(Async::NotReady));
+    }
+}
@@ -0,0 +1,365 @@
+//! Parser metrics.
+//!
+//! In order to collect statistics about parsing, wrap the buffer in a
+//! [`Metered`] buffer. It reports into a value implementing the trait
+//! [`Metrics`] the number of octets consumed and the number of times the
+//! parser had to backtrack. Since the buffer is handed through all the
+//! combinators, it also serves as the parser context: the combinators
+//! report every rule invoked and every time they ran out of data via
+//! `Buffer::metrics()`.
+//!
+//! The [`Counters`] type provides a simple implementation of `Metrics`
+//! that just counts all events.
+//!
+//! With the `instrument` feature enabled, there are additional events for
+//! performance work: the number of checkpoints taken – which for
+//! `BytesMut` are clones of the buffer –, the number of allocations made
+//! by each rule, and the wall time each rule took. Since there is no
+//! portable way to count allocations, the current count is taken from a
+//! hook function set via [`set_allocation_hook()`], typically reading
+//! the counter of a counting global allocator.
+//!
+//! [`Metered`]: struct.Metered.html
+//! [`Metrics`]: trait.Metrics.html
+//! [`Counters`]: struct.Counters.html
+//! [`set_allocation_hook()`]: fn.set_allocation_hook.html
+
+#[cfg(feature = "instrument")] use std::sync::RwLock;
+#[cfg(feature = "instrument")] use std::time::{Duration, Instant};
+use bytes::Bytes;
+use super::buffer::Buffer;
+use super::limits::Limits;
+
+
+//------------ Metrics -------------------------------------------------------
+
+/// A type collecting parser metrics.
+///
+/// All methods have default implementations that do nothing, so you only
+/// need to implement the ones you are interested in.
+pub trait Metrics {
+    /// Reports that `count` octets have been drained from the buffer.
+    fn consumed(&mut self, count: usize) {
+        let _ = count;
+    }
+
+    /// Reports that a rule has been invoked.
+    fn rule(&mut self) { }
+
+    /// Reports that the parser rewound, returning `count` octets.
+    fn backtrack(&mut self, count: usize) {
+        let _ = count;
+    }
+
+    /// Reports that a rule returned non-ready.
+    fn not_ready(&mut self) { }
+
+    /// Reports that a checkpoint of the buffer was taken.
+    #[cfg(feature = "instrument")]
+    fn checkpoint(&mut self) { }
+
+    /// Reports that an instrumented rule has started.
+    #[cfg(feature = "instrument")]
+    fn rule_started(&mut self) { }
+
+    /// Reports that an instrumented rule has finished.
+    ///
+    /// The rule made `allocations` allocations and took `elapsed` to
+    /// complete, both including all the rules nested within it.
+    #[cfg(feature = "instrument")]
+    fn rule_finished(&mut self, allocations: usize, elapsed: Duration) {
+        let _ = (allocations, elapsed);
+    }
+}
+
+
+//------------ Counters ------------------------------------------------------
+
+/// Metrics that simply count all events.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Counters {
+    /// The number of octets consumed, taking backtracking into account.
+    pub consumed: usize,
+
+    /// The number of rules invoked.
+    pub rules: usize,
+
+    /// The number of times the parser backtracked.
+    pub backtracks: usize,
+
+    /// The number of times a rule returned non-ready.
+    pub not_ready: usize,
+
+    /// The number of checkpoints taken.
+    #[cfg(feature = "instrument")]
+    pub checkpoints: usize,
+
+    /// The number of allocations made by top-level rules.
+    #[cfg(feature = "instrument")]
+    pub allocations: usize,
+
+    /// The time spent in top-level rules.
+    #[cfg(feature = "instrument")]
+    pub elapsed: Duration,
+
+    /// The current nesting depth of rules.
+    #[cfg(feature = "instrument")]
+    depth: usize,
+}
+
+impl Metrics for Counters {
+    fn consumed(&mut self, count: usize) {
+        self.consumed += count
+    }
+
+    fn rule(&mut self) {
+        self.rules += 1
+    }
+
+    fn backtrack(&mut self, count: usize) {
+        self.consumed -= count;
+        self.backtracks += 1;
+    }
+
+    fn not_ready(&mut self) {
+        self.not_ready += 1
+    }
+
+    #[cfg(feature = "instrument")]
+    fn checkpoint(&mut self) {
+        self.checkpoints += 1
+    }
+
+    #[cfg(feature = "instrument")]
+    fn rule_started(&mut self) {
+        self.depth += 1
+    }
+
+    #[cfg(feature = "instrument")]
+    fn rule_finished(&mut self, allocations: usize, elapsed: Duration) {
+        self.depth -= 1;
+        if self.depth == 0 {
+            self.allocations += allocations;
+            self.elapsed += elapsed;
+        }
+    }
+}
+
+
+//------------ Metered -------------------------------------------------------
+
+/// A buffer reporting parser metrics.
+///
+/// This type wraps another buffer and reports all events into a value of
+/// type `M`.
+#[derive(Clone, Debug, Default)]
+pub struct Metered<B, M> {
+    buf: B,
+    metrics: M,
+}
+
+impl<B, M> Metered<B, M> {
+    /// Creates a new metered buffer from a buffer and a metrics value.
+    pub fn new(buf: B, metrics: M) -> Self {
+        Metered { buf, metrics }
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns a mutable reference to the underlying buffer.
+    ///
+    /// This is used for adding new data to the buffer.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Returns a reference to the metrics value.
+    pub fn metrics_ref(&self) -> &M {
+        &self.metrics
+    }
+
+    /// Trades the metered buffer for the buffer and the metrics value.
+    pub fn into_parts(self) -> (B, M) {
+        (self.buf, self.metrics)
+    }
+}
+
+impl<B: Buffer, M: Metrics> Buffer for Metered<B, M> {
+    type Checkpoint = (B::Checkpoint, usize);
+
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.buf.get(index)
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.buf.checkpoint(), self.buf.len())
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        let len = self.buf.len();
+        self.buf.rewind(checkpoint.0);
+        if checkpoint.1 > len {
+            self.metrics.backtrack(checkpoint.1 - len)
+        }
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        self.metrics.consumed(count);
+        self.buf.split_to(count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.metrics.consumed(count);
+        self.buf.advance(count)
+    }
+
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        Some(&mut self.metrics)
+    }
+
+    fn limits(&mut self) -> Option<&mut Limits> {
+        self.buf.limits()
+    }
+
+    fn report_needed(&mut self, additional: usize) {
+        self.buf.report_needed(additional)
+    }
+
+    fn is_final(&self) -> bool {
+        self.buf.is_final()
+    }
+}
+
+
+//------------ Allocation Hook -----------------------------------------------
+
+/// The function returning the current number of allocations.
+#[cfg(feature = "instrument")]
+static ALLOCATION_HOOK: RwLock<Option<fn() -> usize>> = RwLock::new(None);
+
+/// Sets the function returning the current number of allocations.
+///
+/// The hook is global and used by all instrumented rules. Without a
+/// hook, no allocations are reported.
+#[cfg(feature = "instrument")]
+pub fn set_allocation_hook(hook: fn() -> usize) {
+    if let Ok(mut guard) = ALLOCATION_HOOK.write() {
+        *guard = Some(hook)
+    }
+}
+
+/// Returns the current number of allocations according to the hook.
+#[cfg(feature = "instrument")]
+fn allocation_count() -> usize {
+    match ALLOCATION_HOOK.read() {
+        Ok(guard) => guard.map(|hook| hook()).unwrap_or(0),
+        Err(_) => 0
+    }
+}
+
+
+//------------ Probe ---------------------------------------------------------
+
+/// The instrumentation of a single invocation of a rule.
+///
+/// Without the `instrument` feature, this does nothing at all.
+#[cfg(feature = "instrument")]
+pub(crate) struct Probe {
+    start: Instant,
+    allocations: usize,
+}
+
+#[cfg(feature = "instrument")]
+impl Probe {
+    /// Starts instrumenting a rule.
+    pub(crate) fn start<B: Buffer>(buf: &mut B) -> Self {
+        if let Some(metrics) = buf.metrics() {
+            metrics.rule_started()
+        }
+        Probe { start: Instant::now(), allocations: allocation_count() }
+    }
+
+    /// Finishes instrumenting a rule and reports the results.
+    pub(crate) fn finish<B: Buffer>(self, buf: &mut B) {
+        let elapsed = self.start.elapsed();
+        let allocations = allocation_count().saturating_sub(self.allocations);
+        if let Some(metrics) = buf.metrics() {
+            metrics.rule_finished(allocations, elapsed)
+        }
+    }
+}
+
+#[cfg(not(feature = "instrument"))]
+pub(crate) struct Probe;
+
+#[cfg(not(feature = "instrument"))]
+impl Probe {
+    pub(crate) fn start<B: Buffer>(_buf: &mut B) -> Self {
+        Probe
+    }
+
+    pub(crate) fn finish<B: Buffer>(self, _buf: &mut B) { }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::Async;
+    use ::ipaddr::parse_ipv6_addr;
+    use super::*;
+
+    #[test]
+    fn count() {
+        let mut buf = Metered::new(BytesMut::from(&b"1080::8:800 "[..]),
+                                   Counters::default());
+        assert!(parse_ipv6_addr(&mut buf).is_ok());
+        let counters = *buf.metrics_ref();
+        assert_eq!(counters.consumed, 11);
+        assert!(counters.backtracks > 0);
+        assert!(counters.rules > counters.backtracks);
+        assert_eq!(counters.not_ready, 0);
+
+        let mut buf = Metered::new(BytesMut::from(&b"::1"[..]),
+                                   Counters::default());
+        assert_eq!(parse_ipv6_addr(&mut buf), Ok(Async::NotReady));
+        assert_eq!(buf.metrics_ref().consumed, 0);
+        assert!(buf.metrics_ref().not_ready > 0);
+    }
+
+    #[test]
+    #[cfg(feature = "instrument")]
+    fn instrument() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use ::parse::rule::group;
+        use ::parse::token::{skip_octet, TokenError};
+
+        static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+        fn hook() -> usize {
+            ALLOCATIONS.load(Ordering::Relaxed)
+        }
+
+        set_allocation_hook(hook);
+        let mut buf = Metered::new(BytesMut::from(&b"xy"[..]),
+                                   Counters::default());
+        let res: Result<_, TokenError> = group(&mut buf, |buf| {
+            ALLOCATIONS.fetch_add(3, Ordering::Relaxed);
+            group(buf, |buf| skip_octet(buf, b'x'))
+        });
+        assert_eq!(res, Ok(Async::Ready(())));
+        let counters = *buf.metrics_ref();
+        assert_eq!(counters.checkpoints, 2);
+        assert_eq!(counters.allocations, 3);
+        assert_eq!(counters.depth, 0);
+    }
+}
@@ -20,6 +20,7 @@
 //! Since quite a few functions in here are heavy on generic types, here are
 //! a few convention to be followed. First, types are designated as follows:
 //!
+//! * `B` is the buffer type, which needs to implement [`Buffer`],
 //! * `P` and `Q` are parsing closures,
 //! * `C` and `D` are converting closures,
 //! * `O` is an octet test closure (used by the cat family of token functions),
@@ -29,8 +30,26 @@
 //! Type arguments to functions are ordered such that for each closure
 //! appearing in the argument list, the closure type is given first, then its
 //! success type, then its error type following the order of closures and
-//! leaving out repeat types for later closures.
+//! leaving out repeat types for later closures. The buffer type, if
+//! present, always goes first.
+//!
+//! [`Buffer`]: buffer/trait.Buffer.html
 
 
+pub mod buffer;
+pub mod complete;
+pub mod cursor;
+pub mod hint;
+pub mod limits;
+pub mod metrics;
+pub mod parser;
+pub mod record;
+pub mod registry;
+pub mod ring;
+pub mod rope;
 pub mod rule;
+pub mod state;
+pub mod sync;
+#[cfg(feature = "std-poll")] pub mod task;
+pub mod step;
 pub mod token;
@@ -0,0 +1,612 @@
+//! Chainable parsers.
+//!
+//! This module defines the trait [`Parser`] which allows writing rules as
+//! pipelines of combinator methods rather than nested closures. The trait
+//! is implemented for all closures and functions that take a mutable
+//! reference to a buffer and return a `Poll`, which includes all the
+//! buffer-level parsing functions of this crate.
+//!
+//! For instance, a rule `1*3DIGIT ["." 1*3DIGIT]` converted into a pair of
+//! integers could look like this:
+//!
+//! ```
+//! # extern crate abnf;
+//! # extern crate futures;
+//! # use abnf::parse::cursor::CursorBuf;
+//! # use futures::Async;
+//! use abnf::core::u16_digits;
+//! use abnf::parse::parser::Parser;
+//! use abnf::parse::token::skip_octet;
+//!
+//! # fn main() {
+//! let mut buf = CursorBuf::from(&b"12.5 "[..]);
+//! let mut version = u16_digits.then(
+//!     u16_digits.preceded_by(|buf: &mut CursorBuf| skip_octet(buf, b'.'))
+//!               .opt()
+//! );
+//! assert_eq!(version.parse(&mut buf), Ok(Async::Ready((12, Some(5)))));
+//! # }
+//! ```
+//!
+//! Since the combinators are generic types, the resulting parser types
+//! quickly become unnameable. If you need to store a parser in a struct,
+//! for instance to build a table of parsers or for recursive rules, you
+//! can turn it into a [`BoxedParser`] via `Parser::boxed()`.
+//!
+//! [`Parser`]: trait.Parser.html
+//! [`BoxedParser`]: struct.BoxedParser.html
+
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::limits::check_repeat;
+use super::rule::group_limited;
+use super::token::TokenError;
+#[cfg(debug_assertions)] use ::test_support::contents;
+
+
+//------------ Parser --------------------------------------------------------
+
+/// A parser operating on a buffer of type `B`.
+///
+/// Like all parsing closures, a parser must drain exactly what it has
+/// successfully parsed from the buffer and leave the buffer untouched if
+/// it returns non-ready or an error.
+pub trait Parser<B: Buffer> {
+    /// The type of a successfully parsed value.
+    type Output;
+
+    /// The type of a parse error.
+    type Error;
+
+    /// Parses a value from the beginning of `buf`.
+    fn parse(&mut self, buf: &mut B) -> Poll<Self::Output, Self::Error>;
+
+    /// Converts a successfully parsed value via `op`.
+    fn map<C, U>(self, op: C) -> Map<Self, C>
+           where Self: Sized, C: FnMut(Self::Output) -> U {
+        Map { parser: self, op }
+    }
+
+    /// Converts an error via `op`.
+    fn map_err<C, F>(self, op: C) -> MapErr<Self, C>
+               where Self: Sized, C: FnMut(Self::Error) -> F {
+        MapErr { parser: self, op }
+    }
+
+    /// Converts a successfully parsed value via the fallible `op`.
+    ///
+    /// If `op` fails, the parser rewinds.
+    fn and_then<C, U>(self, op: C) -> AndThen<Self, C>
+                where Self: Sized,
+                      C: FnMut(Self::Output) -> Result<U, Self::Error> {
+        AndThen { parser: self, op }
+    }
+
+    /// Tries `other` if this parser fails.
+    fn or<Q>(self, other: Q) -> Or<Self, Q>
+          where Self: Sized, Q: Parser<B, Output = Self::Output> {
+        Or { first: self, second: other }
+    }
+
+    /// Parses this parser followed by `other`, returning both values.
+    fn then<Q>(self, other: Q) -> Then<Self, Q>
+            where Self: Sized, Q: Parser<B, Error = Self::Error> {
+        Then { first: self, second: other }
+    }
+
+    /// Parses `prefix` first but only returns the value of this parser.
+    fn preceded_by<Q>(self, prefix: Q) -> PrecededBy<Self, Q>
+                   where Self: Sized, Q: Parser<B, Error = Self::Error> {
+        PrecededBy { parser: self, prefix }
+    }
+
+    /// Parses `suffix` afterwards but only returns the value of this parser.
+    fn followed_by<Q>(self, suffix: Q) -> FollowedBy<Self, Q>
+                   where Self: Sized, Q: Parser<B, Error = Self::Error> {
+        FollowedBy { parser: self, suffix }
+    }
+
+    /// Makes the parser optional.
+    fn opt(self) -> Opt<Self>
+           where Self: Sized {
+        Opt { parser: self }
+    }
+
+    /// Repeats the parser a number of times within `range`.
+    ///
+    /// This implements the ABNF repetition `<a>*<b>element`. The values of
+    /// all repetitions are collected into a vec. Repetition ends when the
+    /// parser fails or when the upper bound is reached. If the parser
+    /// fails before the lower bound is reached, its error is returned. If
+    /// the parser succeeds without consuming any data, repetition ends,
+    /// too, to avoid looping forever.
+    ///
+    /// If the repetition returns non-ready, it starts over from the first
+    /// element next time. For long repetitions, the `RuleState` of the
+    /// [`state`] module avoids this.
+    ///
+    /// [`state`]: ../state/index.html
+    fn repeat<R: RangeBounds<usize>>(self, range: R) -> Repeat<Self>
+              where Self: Sized {
+        let (min, max) = repeat_bounds(range);
+        Repeat { parser: self, min, max }
+    }
+
+    /// Checks that the parser leaves the buffer untouched when failing.
+    ///
+    /// In debug builds, the returned parser compares the buffer before
+    /// and after calling this parser and panics with a message naming the
+    /// rule `name` if the parser returned non-ready or an error but
+    /// changed the buffer anyway. In release builds, it merely calls this
+    /// parser.
+    fn checked(self, name: &'static str) -> Checked<Self>
+               where Self: Sized {
+        Checked { parser: self, name }
+    }
+
+    /// Borrows the parser rather than consuming it in a combinator.
+    fn by_ref(&mut self) -> ByRef<'_, Self>
+              where Self: Sized {
+        ByRef { parser: self }
+    }
+
+    /// Converts the parser into a type-erased boxed parser.
+    fn boxed(self) -> BoxedParser<B, Self::Output, Self::Error>
+             where Self: Sized + 'static {
+        BoxedParser::new(self)
+    }
+}
+
+impl<B, F, T, E> Parser<B> for F
+                 where B: Buffer, F: FnMut(&mut B) -> Poll<T, E> {
+    type Output = T;
+    type Error = E;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<T, E> {
+        self(buf)
+    }
+}
+
+
+//------------ BoxedParser ---------------------------------------------------
+
+/// A type-erased parser.
+///
+/// This type wraps any parser for buffer type `B` producing values of type
+/// `T` and errors of type `E` into a box. Since it implements `Parser`
+/// itself, it has all the combinator methods, too.
+pub struct BoxedParser<B, T, E> {
+    parser: Box<dyn Parser<B, Output = T, Error = E>>,
+}
+
+impl<B: Buffer, T, E> BoxedParser<B, T, E> {
+    /// Creates a new boxed parser from any parser.
+    pub fn new<P>(parser: P) -> Self
+               where P: Parser<B, Output = T, Error = E> + 'static {
+        BoxedParser { parser: Box::new(parser) }
+    }
+}
+
+impl<B: Buffer, T, E> Parser<B> for BoxedParser<B, T, E> {
+    type Output = T;
+    type Error = E;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<T, E> {
+        self.parser.parse(buf)
+    }
+
+    fn boxed(self) -> BoxedParser<B, T, E> {
+        self
+    }
+}
+
+impl<B, T, E> fmt::Debug for BoxedParser<B, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BoxedParser")
+    }
+}
+
+
+//------------ ByRef ---------------------------------------------------------
+
+/// A parser borrowing another parser.
+///
+/// Values of this type are created via `Parser::by_ref()`.
+#[derive(Debug)]
+pub struct ByRef<'a, P: 'a> {
+    parser: &'a mut P,
+}
+
+impl<'a, B: Buffer, P: Parser<B>> Parser<B> for ByRef<'a, P> {
+    type Output = P::Output;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, P::Error> {
+        self.parser.parse(buf)
+    }
+}
+
+
+//------------ Checked -------------------------------------------------------
+
+/// A parser checking that another parser rewinds on failure.
+#[derive(Clone, Copy, Debug)]
+pub struct Checked<P> {
+    parser: P,
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    name: &'static str,
+}
+
+impl<B: Buffer, P: Parser<B>> Parser<B> for Checked<P> {
+    type Output = P::Output;
+    type Error = P::Error;
+
+    #[cfg(debug_assertions)]
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, P::Error> {
+        let before = contents(buf);
+        let res = self.parser.parse(buf);
+        let outcome = match res {
+            Ok(Async::Ready(_)) => return res,
+            Ok(Async::NotReady) => "non-ready",
+            Err(_) => "an error",
+        };
+        let after = contents(buf);
+        if after != before {
+            panic!("rule '{}' returned {} but changed the buffer from {:?} \
+                    to {:?}",
+                   self.name, outcome, String::from_utf8_lossy(&before),
+                   String::from_utf8_lossy(&after))
+        }
+        res
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, P::Error> {
+        self.parser.parse(buf)
+    }
+}
+
+
+//------------ Map -----------------------------------------------------------
+
+/// A parser converting the value of another parser.
+#[derive(Clone, Copy, Debug)]
+pub struct Map<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<B, P, C, U> Parser<B> for Map<P, C>
+                 where B: Buffer, P: Parser<B>, C: FnMut(P::Output) -> U {
+    type Output = U;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<U, P::Error> {
+        let res = try_ready!(self.parser.parse(buf));
+        Ok(Async::Ready((self.op)(res)))
+    }
+}
+
+
+//------------ MapErr --------------------------------------------------------
+
+/// A parser converting the error of another parser.
+#[derive(Clone, Copy, Debug)]
+pub struct MapErr<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<B, P, C, F> Parser<B> for MapErr<P, C>
+                 where B: Buffer, P: Parser<B>, C: FnMut(P::Error) -> F {
+    type Output = P::Output;
+    type Error = F;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, F> {
+        self.parser.parse(buf).map_err(&mut self.op)
+    }
+}
+
+
+//------------ AndThen -------------------------------------------------------
+
+/// A parser converting the value of another parser fallibly.
+#[derive(Clone, Copy, Debug)]
+pub struct AndThen<P, C> {
+    parser: P,
+    op: C,
+}
+
+impl<B, P, C, U> Parser<B> for AndThen<P, C>
+                 where B: Buffer,
+                       P: Parser<B>,
+                       P::Error: From<TokenError>,
+                       C: FnMut(P::Output) -> Result<U, P::Error> {
+    type Output = U;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<U, P::Error> {
+        let (parser, op) = (&mut self.parser, &mut self.op);
+        group_limited(buf, |buf| {
+            let res = try_ready!(parser.parse(buf));
+            op(res).map(Async::Ready)
+        })
+    }
+}
+
+
+//------------ Or ------------------------------------------------------------
+
+/// A parser trying a second parser if the first one fails.
+#[derive(Clone, Copy, Debug)]
+pub struct Or<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<B, P, Q> Parser<B> for Or<P, Q>
+              where B: Buffer, P: Parser<B>, Q: Parser<B, Output = P::Output> {
+    type Output = P::Output;
+    type Error = Q::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, Q::Error> {
+        match self.first.parse(buf) {
+            Ok(Async::Ready(res)) => Ok(Async::Ready(res)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => self.second.parse(buf)
+        }
+    }
+}
+
+
+//------------ Then ----------------------------------------------------------
+
+/// A parser parsing two parsers in sequence.
+#[derive(Clone, Copy, Debug)]
+pub struct Then<P, Q> {
+    first: P,
+    second: Q,
+}
+
+impl<B, P, Q> Parser<B> for Then<P, Q>
+              where B: Buffer,
+                    P: Parser<B>,
+                    P::Error: From<TokenError>,
+                    Q: Parser<B, Error = P::Error> {
+    type Output = (P::Output, Q::Output);
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<Self::Output, P::Error> {
+        let (first, second) = (&mut self.first, &mut self.second);
+        group_limited(buf, |buf| {
+            let first = try_ready!(first.parse(buf));
+            let second = try_ready!(second.parse(buf));
+            Ok(Async::Ready((first, second)))
+        })
+    }
+}
+
+
+//------------ PrecededBy ----------------------------------------------------
+
+/// A parser parsing a prefix before the actual parser.
+#[derive(Clone, Copy, Debug)]
+pub struct PrecededBy<P, Q> {
+    parser: P,
+    prefix: Q,
+}
+
+impl<B, P, Q> Parser<B> for PrecededBy<P, Q>
+              where B: Buffer,
+                    P: Parser<B>,
+                    P::Error: From<TokenError>,
+                    Q: Parser<B, Error = P::Error> {
+    type Output = P::Output;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, P::Error> {
+        let (parser, prefix) = (&mut self.parser, &mut self.prefix);
+        group_limited(buf, |buf| {
+            try_ready!(prefix.parse(buf));
+            parser.parse(buf)
+        })
+    }
+}
+
+
+//------------ FollowedBy ----------------------------------------------------
+
+/// A parser parsing a suffix after the actual parser.
+#[derive(Clone, Copy, Debug)]
+pub struct FollowedBy<P, Q> {
+    parser: P,
+    suffix: Q,
+}
+
+impl<B, P, Q> Parser<B> for FollowedBy<P, Q>
+              where B: Buffer,
+                    P: Parser<B>,
+                    P::Error: From<TokenError>,
+                    Q: Parser<B, Error = P::Error> {
+    type Output = P::Output;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<P::Output, P::Error> {
+        let (parser, suffix) = (&mut self.parser, &mut self.suffix);
+        group_limited(buf, |buf| {
+            let res = try_ready!(parser.parse(buf));
+            try_ready!(suffix.parse(buf));
+            Ok(Async::Ready(res))
+        })
+    }
+}
+
+
+//------------ Opt -----------------------------------------------------------
+
+/// A parser that is optional.
+#[derive(Clone, Copy, Debug)]
+pub struct Opt<P> {
+    parser: P,
+}
+
+impl<B: Buffer, P: Parser<B>> Parser<B> for Opt<P> {
+    type Output = Option<P::Output>;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<Self::Output, P::Error> {
+        match self.parser.parse(buf) {
+            Ok(Async::Ready(res)) => Ok(Async::Ready(Some(res))),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Ok(Async::Ready(None))
+        }
+    }
+}
+
+
+//------------ Repeat --------------------------------------------------------
+
+/// A parser repeating another parser.
+#[derive(Clone, Copy, Debug)]
+pub struct Repeat<P> {
+    parser: P,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl<B, P> Parser<B> for Repeat<P>
+              where B: Buffer, P: Parser<B>, P::Error: From<TokenError> {
+    type Output = Vec<P::Output>;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<Self::Output, P::Error> {
+        let (parser, min, max) = (&mut self.parser, self.min, self.max);
+        group_limited(buf, |buf| {
+            let mut res = Vec::new();
+            while max.map(|max| res.len() < max).unwrap_or(true) {
+                let len = buf.len();
+                match parser.parse(buf) {
+                    Ok(Async::Ready(item)) => {
+                        res.push(item);
+                        check_repeat(buf, res.len())?;
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(err) => {
+                        if res.len() < min {
+                            return Err(err)
+                        }
+                        break
+                    }
+                }
+                if buf.len() == len {
+                    break
+                }
+            }
+            Ok(Async::Ready(res))
+        })
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// Converts a range into the minimum and maximum number of repetitions.
+pub(super) fn repeat_bounds<R: RangeBounds<usize>>(range: R)
+                                                   -> (usize, Option<usize>) {
+    let min = match range.start_bound() {
+        Bound::Included(&min) => min,
+        Bound::Excluded(&min) => min + 1,
+        Bound::Unbounded => 0,
+    };
+    let max = match range.end_bound() {
+        Bound::Included(&max) => Some(max),
+        Bound::Excluded(&max) => Some(max.saturating_sub(1)),
+        Bound::Unbounded => None,
+    };
+    (min, max)
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use futures::Async;
+    use ::core::{u8_digits, skip_crlf};
+    use ::parse::token::{skip_octet, TokenError};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    fn dot(buf: &mut BytesMut) -> Poll<(), TokenError> {
+        skip_octet(buf, b'.')
+    }
+
+    #[test]
+    fn repeat() {
+        let mut octets = u8_digits.followed_by(dot).repeat(2..=3);
+        let mut data = buf(b"1.2.3.4.");
+        assert_eq!(octets.parse(&mut data), Ok(Async::Ready(vec![1, 2, 3])));
+        assert_eq!(&data[..], b"4.");
+
+        let mut data = buf(b"1.x");
+        assert_eq!(octets.parse(&mut data), Err(TokenError::Invalid));
+        assert_eq!(&data[..], b"1.x");
+
+        let mut data = buf(b"1.2");
+        assert_eq!(octets.parse(&mut data), Ok(Async::NotReady));
+        assert_eq!(&data[..], b"1.2");
+    }
+
+    #[test]
+    fn or_and_then() {
+        let mut even = u8_digits.and_then(|x| {
+            if x % 2 == 0 { Ok(x) } else { Err(TokenError::Invalid) }
+        });
+        let mut data = buf(b"13 ");
+        assert_eq!(even.parse(&mut data), Err(TokenError::Invalid));
+        assert_eq!(&data[..], b"13 ");
+
+        let mut alt = u8_digits.map(Some).or(skip_crlf.map(|_| None));
+        assert_eq!(alt.parse(&mut buf(b"12 ")), Ok(Async::Ready(Some(12))));
+        assert_eq!(alt.parse(&mut buf(b"\r\n")), Ok(Async::Ready(None)));
+        assert_eq!(alt.parse(&mut buf(b"x")), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn checked() {
+        let mut digits = u8_digits.checked("digits");
+        assert_eq!(digits.parse(&mut buf(b"12 ")), Ok(Async::Ready(12)));
+        let mut data = buf(b"12");
+        assert_eq!(digits.parse(&mut data), Ok(Async::NotReady));
+        assert_eq!(&data[..], b"12");
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "rule 'greedy'")]
+    fn checked_violation() {
+        let mut greedy = (|buf: &mut BytesMut| {
+            buf.advance(1);
+            Err::<Async<()>, _>(TokenError::Invalid)
+        }).checked("greedy");
+        let _ = greedy.parse(&mut buf(b"x"));
+    }
+
+    #[test]
+    fn boxed() {
+        let mut table: Vec<(u8, BoxedParser<BytesMut, u8, TokenError>)> = vec![
+            (b'+', u8_digits.boxed()),
+            (b'-', u8_digits.map(|x| 255 - x).boxed()),
+        ];
+        let mut data = buf(b"5 ");
+        let (_, ref mut parser) = table[1];
+        assert_eq!(parser.parse(&mut data), Ok(Async::Ready(250)));
+        let (_, ref mut parser) = table[0];
+        assert_eq!(parser.by_ref().opt().parse(&mut data),
+                   Ok(Async::Ready(None)));
+    }
+}
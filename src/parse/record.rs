@@ -0,0 +1,342 @@
+//! Recording and replaying parser input.
+//!
+//! Bugs in incremental parsers often only show when data arrives split at
+//! a particular point. This module helps tracking down such bugs.
+//!
+//! A [`Recorder`] wraps a buffer and keeps a log of everything that
+//! happens to it: each chunk of data added, each run of octets consumed by
+//! the parser, and each time the parser rewound. From this log, the
+//! sequence of chunks can be taken as a [`Session`] which a [`Replayer`]
+//! later feeds back to a parser chunk by chunk.
+//!
+//! Sessions can be saved to a compact text file and loaded again, so a
+//! chunk sequence that made a parser fail can be kept as a regression
+//! test. The file contains one chunk per line. Printable ASCII characters
+//! and the space appear as they are, `\\` and `#` as well as all other
+//! octets are escaped as `\xHH`. Empty lines and lines starting with `#`
+//! are ignored.
+//!
+//! [`Recorder`]: struct.Recorder.html
+//! [`Session`]: struct.Session.html
+//! [`Replayer`]: struct.Replayer.html
+
+use std::{fmt, fs, io};
+use std::path::Path;
+use bytes::{Bytes, BytesMut};
+use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::limits::Limits;
+use super::metrics::Metrics;
+
+
+//------------ Event ---------------------------------------------------------
+
+/// An entry in the log of a recorder.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// A chunk of data was added to the buffer.
+    Data(Bytes),
+
+    /// The parser consumed these octets.
+    Consumed(Bytes),
+
+    /// The parser rewound, returning this many octets to the buffer.
+    Rewind(usize),
+}
+
+
+//------------ Recorder ------------------------------------------------------
+
+/// A buffer that records everything happening to it.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder<B> {
+    buf: B,
+    log: Vec<Event>,
+}
+
+impl<B> Recorder<B> {
+    /// Creates a new recorder atop the given buffer.
+    pub fn new(buf: B) -> Self {
+        Recorder { buf, log: Vec::new() }
+    }
+
+    /// Returns a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Returns the log of events so far.
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    /// Returns the session recorded so far.
+    pub fn session(&self) -> Session {
+        Session::new(self.log.iter().filter_map(|event| {
+            match *event {
+                Event::Data(ref data) => Some(data.clone()),
+                _ => None
+            }
+        }).collect())
+    }
+
+    /// Trades the recorder for the underlying buffer and the log.
+    pub fn into_parts(self) -> (B, Vec<Event>) {
+        (self.buf, self.log)
+    }
+}
+
+impl<B: Extend<u8>> Recorder<B> {
+    /// Adds a chunk of data to the buffer.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.buf.extend(data.iter().cloned());
+        self.log.push(Event::Data(Bytes::copy_from_slice(data)));
+    }
+}
+
+impl<B: Buffer> Buffer for Recorder<B> {
+    type Checkpoint = (B::Checkpoint, usize);
+
+    fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        self.buf.get(index)
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.buf.checkpoint(), self.buf.len())
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        let len = self.buf.len();
+        self.buf.rewind(checkpoint.0);
+        if checkpoint.1 > len {
+            self.log.push(Event::Rewind(checkpoint.1 - len))
+        }
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        let res = self.buf.split_to(count);
+        self.log.push(Event::Consumed(res.clone()));
+        res
+    }
+
+    fn advance(&mut self, count: usize) {
+        let _ = self.split_to(count);
+    }
+
+    fn metrics(&mut self) -> Option<&mut dyn Metrics> {
+        self.buf.metrics()
+    }
+
+    fn limits(&mut self) -> Option<&mut Limits> {
+        self.buf.limits()
+    }
+
+    fn report_needed(&mut self, additional: usize) {
+        self.buf.report_needed(additional)
+    }
+
+    fn is_final(&self) -> bool {
+        self.buf.is_final()
+    }
+}
+
+
+//------------ Session -------------------------------------------------------
+
+/// The sequence of data chunks that arrived during a parser run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Session {
+    chunks: Vec<Bytes>,
+}
+
+impl Session {
+    /// Creates a session from a sequence of chunks.
+    pub fn new(chunks: Vec<Bytes>) -> Self {
+        Session { chunks }
+    }
+
+    /// Returns the chunks of the session.
+    pub fn chunks(&self) -> &[Bytes] {
+        &self.chunks
+    }
+
+    /// Returns a replayer for the session.
+    pub fn replay(&self) -> Replayer {
+        Replayer::new(self.clone())
+    }
+
+    /// Creates a session from its text form.
+    ///
+    /// Returns `None` if the text contains an invalid escape sequence.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut chunks = Vec::new();
+        for line in text.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let mut chunk = Vec::with_capacity(line.len());
+            let mut octets = line.bytes();
+            while let Some(ch) = octets.next() {
+                if ch != b'\\' {
+                    chunk.push(ch);
+                    continue
+                }
+                if octets.next()? != b'x' {
+                    return None
+                }
+                let hi = (octets.next()? as char).to_digit(16)?;
+                let lo = (octets.next()? as char).to_digit(16)?;
+                chunk.push((hi << 4 | lo) as u8)
+            }
+            chunks.push(chunk.into())
+        }
+        Some(Session::new(chunks))
+    }
+
+    /// Loads a session from the text file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_text(&fs::read_to_string(path)?).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid session")
+        })
+    }
+
+    /// Saves the session in its text form to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in &self.chunks {
+            for &ch in chunk.iter() {
+                if ch.is_ascii() && !ch.is_ascii_control()
+                        && ch != b'\\' && ch != b'#' {
+                    write!(f, "{}", ch as char)?
+                }
+                else {
+                    write!(f, "\\x{:02x}", ch)?
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ Replayer ------------------------------------------------------
+
+/// Feeds a recorded session back to a parser chunk by chunk.
+///
+/// The replayer owns a recording buffer. Each call to `feed()` adds the
+/// next chunk of the session to this buffer, which is accessible via
+/// `buffer_mut()` for applying a parser to it. Alternatively, `run()`
+/// drives a parser over the whole session.
+pub struct Replayer {
+    session: Session,
+    pos: usize,
+    buf: Recorder<BytesMut>,
+}
+
+impl Replayer {
+    /// Creates a new replayer for the given session.
+    pub fn new(session: Session) -> Self {
+        Replayer {
+            session,
+            pos: 0,
+            buf: Recorder::new(BytesMut::new())
+        }
+    }
+
+    /// Adds the next chunk to the buffer.
+    ///
+    /// Returns `false` if there are no more chunks.
+    pub fn feed(&mut self) -> bool {
+        match self.session.chunks.get(self.pos) {
+            Some(chunk) => {
+                self.buf.extend_from_slice(chunk);
+                self.pos += 1;
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Returns a mutable reference to the buffer.
+    pub fn buffer_mut(&mut self) -> &mut Recorder<BytesMut> {
+        &mut self.buf
+    }
+
+    /// Returns the log of the replayed session so far.
+    pub fn log(&self) -> &[Event] {
+        self.buf.log()
+    }
+
+    /// Replays the whole session through `parse`.
+    ///
+    /// After each chunk, `parse` is applied to the buffer until it returns
+    /// non-ready. Returns all the items parsed or the first error.
+    pub fn run<P, T, E>(&mut self, mut parse: P) -> Result<Vec<T>, E>
+               where P: FnMut(&mut Recorder<BytesMut>) -> Poll<T, E> {
+        let mut res = Vec::new();
+        while self.feed() {
+            while let Async::Ready(item) = parse(&mut self.buf)? {
+                res.push(item)
+            }
+        }
+        Ok(res)
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::{Bytes, BytesMut};
+    use futures::Async;
+    use ::core::{parse_line, u8_digits};
+    use super::*;
+
+    #[test]
+    fn record() {
+        let mut buf = Recorder::new(BytesMut::new());
+        buf.extend_from_slice(b"12");
+        assert_eq!(u8_digits(&mut buf), Ok(Async::NotReady));
+        buf.extend_from_slice(b"3\r\n");
+        assert_eq!(u8_digits(&mut buf), Ok(Async::Ready(123)));
+        assert_eq!(buf.log(), &[Event::Data(Bytes::from(&b"12"[..])),
+                                Event::Data(Bytes::from(&b"3\r\n"[..])),
+                                Event::Consumed(Bytes::from(&b"123"[..]))]);
+        assert_eq!(buf.session().chunks().len(), 2);
+    }
+
+    #[test]
+    fn replay() {
+        let session = Session::new(vec![Bytes::from(&b"a\r"[..]),
+                                        Bytes::from(&b"\nb\r\nc"[..])]);
+        let mut replayer = session.replay();
+        assert_eq!(replayer.run(parse_line),
+                   Ok(vec![Bytes::from(&b"a\r\n"[..]),
+                           Bytes::from(&b"b\r\n"[..])]));
+        assert_eq!(replayer.buffer_mut().get_ref().as_ref(), b"c");
+    }
+
+    #[test]
+    fn text() {
+        let session = Session::new(vec![Bytes::from(&b"a #\\"[..]),
+                                        Bytes::from(&b"\r\n\xff"[..])]);
+        let text = session.to_string();
+        assert_eq!(text, "a \\x23\\x5c\n\\x0d\\x0a\\xff\n");
+        assert_eq!(Session::from_text(&text), Some(session));
+        assert_eq!(Session::from_text("# comment\n\nab\nc\\x0d\\x0a\n")
+                       .unwrap().replay().run(parse_line),
+                   Ok(vec![Bytes::from(&b"abc\r\n"[..])]));
+        assert_eq!(Session::from_text("\\xg0"), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! A registry of named rules.
+//!
+//! Protocols are often extensible: SMTP servers learn new verbs, IMAP
+//! servers new capabilities. A dispatcher for such a protocol shouldn’t
+//! have to know all the rules at compile time. Instead, it can keep a
+//! [`RuleRegistry`] into which extensions register their parsers under a
+//! name and then pick the parser to apply at parse time.
+//!
+//! Names are compared ignoring ASCII case since that is what most
+//! protocols do for keywords.
+//!
+//! The same goes for grammars loaded at run time: an [`Interpreter`] can
+//! delegate the rules of a grammar to the parsers registered under their
+//! names via `Interpreter::resolve_registry()`.
+//!
+//! [`RuleRegistry`]: struct.RuleRegistry.html
+//! [`Interpreter`]: ../../grammar/interp/struct.Interpreter.html
+
+use std::collections::HashMap;
+use std::fmt;
+use bytes::Bytes;
+use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::parser::{BoxedParser, Parser};
+use super::rule::group_limited;
+use super::token::TokenError;
+
+
+//------------ RuleRegistry --------------------------------------------------
+
+/// A collection of parsers registered under names.
+///
+/// All parsers operate on buffers of type `B` and produce values of type
+/// `T` and errors of type `E`.
+pub struct RuleRegistry<B, T, E> {
+    rules: HashMap<Vec<u8>, BoxedParser<B, T, E>>,
+}
+
+impl<B: Buffer, T, E> RuleRegistry<B, T, E> {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        RuleRegistry { rules: HashMap::new() }
+    }
+
+    /// Registers `parser` under `name`.
+    ///
+    /// If there already was a parser registered under this name, it is
+    /// replaced and returned.
+    pub fn register<N, P>(&mut self, name: N, parser: P)
+                          -> Option<BoxedParser<B, T, E>>
+                    where N: AsRef<[u8]>,
+                          P: Parser<B, Output = T, Error = E> + 'static {
+        self.rules.insert(Self::key(name.as_ref()), parser.boxed())
+    }
+
+    /// Removes the parser registered under `name` and returns it.
+    pub fn remove<N: AsRef<[u8]>>(&mut self, name: N)
+                                  -> Option<BoxedParser<B, T, E>> {
+        self.rules.remove(&Self::key(name.as_ref()))
+    }
+
+    /// Returns whether there is a parser registered under `name`.
+    pub fn contains<N: AsRef<[u8]>>(&self, name: N) -> bool {
+        self.rules.contains_key(&Self::key(name.as_ref()))
+    }
+
+    /// Returns the number of registered parsers.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns whether there are no parsers registered.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns an iterator over the registered names in lowercase.
+    pub fn names(&self) -> impl Iterator<Item = &[u8]> {
+        self.rules.keys().map(AsRef::as_ref)
+    }
+
+    /// Applies the parser registered under `name` to `buf`.
+    ///
+    /// Returns `None` if there is no such parser.
+    pub fn parse<N: AsRef<[u8]>>(&mut self, name: N, buf: &mut B)
+                                 -> Option<Poll<T, E>> {
+        self.rules.get_mut(&Self::key(name.as_ref())).map(|parser| {
+            parser.parse(buf)
+        })
+    }
+
+    /// Parses a name and then the rule registered under it.
+    ///
+    /// The name is parsed from the beginning of `buf` via `name`. Then the
+    /// parser registered under this name is applied to the remainder of
+    /// the buffer. If there is no such parser, the buffer is rewound and
+    /// `TokenError::Invalid` is returned. The name and the parsed value are
+    /// returned on success.
+    pub fn dispatch<P>(&mut self, buf: &mut B, name: P) -> Poll<(Bytes, T), E>
+                    where P: FnOnce(&mut B) -> Poll<Bytes, E>,
+                          E: From<TokenError> {
+        group_limited(buf, |buf| {
+            let name = try_ready!(name(buf));
+            match self.parse(&name, buf) {
+                Some(res) => Ok(Async::Ready((name, try_ready!(res)))),
+                None => Err(TokenError::Invalid.into())
+            }
+        })
+    }
+
+    /// Returns the key for a name.
+    fn key(name: &[u8]) -> Vec<u8> {
+        name.to_ascii_lowercase()
+    }
+}
+
+impl<B: Buffer, T, E> Default for RuleRegistry<B, T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, T, E> fmt::Debug for RuleRegistry<B, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RuleRegistry")
+         .field("rules", &self.rules.len())
+         .finish()
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::{Bytes, BytesMut};
+    use futures::{Async, Poll};
+    use ::core::{alphas, skip_crlf, skip_wsps, u16_digits};
+    use ::parse::parser::Parser;
+    use ::parse::token::{self, TokenError};
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Command {
+        Quit,
+        Size(u16),
+    }
+
+    fn verb(buf: &mut BytesMut) -> Poll<Bytes, TokenError> {
+        token::parse(buf, alphas)
+    }
+
+    fn registry() -> RuleRegistry<BytesMut, Command, TokenError> {
+        let mut res = RuleRegistry::new();
+        res.register("QUIT", skip_crlf.map(|_| Command::Quit));
+        res.register("size", u16_digits.preceded_by(skip_wsps)
+                                       .followed_by(skip_crlf)
+                                       .map(Command::Size));
+        res
+    }
+
+    #[test]
+    fn dispatch() {
+        let mut registry = registry();
+        assert!(registry.contains("Quit"));
+
+        let mut buf = BytesMut::from(&b"SIZE 1024\r\nquit\r\n"[..]);
+        assert_eq!(registry.dispatch(&mut buf, verb),
+                   Ok(Async::Ready((Bytes::from(&b"SIZE"[..]),
+                                    Command::Size(1024)))));
+        assert_eq!(registry.dispatch(&mut buf, verb),
+                   Ok(Async::Ready((Bytes::from(&b"quit"[..]),
+                                    Command::Quit))));
+
+        let mut buf = BytesMut::from(&b"HELO x\r\n"[..]);
+        assert_eq!(registry.dispatch(&mut buf, verb),
+                   Err(TokenError::Invalid));
+        assert_eq!(&buf[..], b"HELO x\r\n");
+    }
+}
@@ -0,0 +1,222 @@
+//! A fixed-capacity ring buffer.
+//!
+//! This module provides `RingBuf`, a buffer of fixed capacity that wraps
+//! around at its end. It allows to receive data into and parse data from a
+//! fixed region of memory without ever having to move octets around, which
+//! is useful for embedded receive buffers.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use super::buffer::Buffer;
+
+
+//------------ RingBuf -------------------------------------------------------
+
+/// A buffer of fixed capacity where data wraps around at the end.
+///
+/// New data is appended either by copying it in via `extend_from_slice()`
+/// or by receiving it directly into the slice returned by `unfilled_mut()`
+/// and then calling `fill()` with the number of octets received.
+///
+/// Since draining octets only moves the start of the buffer forward,
+/// checkpoints are cheap and rewinding never requires copying data.
+#[derive(Clone, Debug)]
+pub struct RingBuf {
+    /// The underlying storage.
+    data: Box<[u8]>,
+
+    /// The index in `data` of the first octet of the buffer.
+    head: usize,
+
+    /// The number of octets in the buffer.
+    len: usize,
+}
+
+impl RingBuf {
+    /// Creates a new, empty ring buffer with the given capacity.
+    ///
+    /// # Panic
+    ///
+    /// The function panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        RingBuf {
+            data: vec![0; capacity].into_boxed_slice(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the number of octets that can still be added to the buffer.
+    pub fn remaining_mut(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    /// Returns the index in the storage of the octet at `index`.
+    fn pos(&self, index: usize) -> usize {
+        (self.head + index) % self.capacity()
+    }
+
+    /// Appends as much of `data` as fits into the buffer.
+    ///
+    /// Returns the number of octets actually added.
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> usize {
+        let mut written = 0;
+        while written < data.len() && self.remaining_mut() > 0 {
+            let count = {
+                let unfilled = self.unfilled_mut();
+                let count = ::std::cmp::min(unfilled.len(),
+                                            data.len() - written);
+                unfilled[..count].copy_from_slice(
+                    &data[written..written + count]
+                );
+                count
+            };
+            self.fill(count);
+            written += count;
+        }
+        written
+    }
+
+    /// Returns the next contiguous region of unused storage.
+    ///
+    /// Data written into this slice becomes part of the buffer by calling
+    /// `fill()`. Since the free space may wrap around, the returned slice
+    /// may be shorter than `remaining_mut()`.
+    pub fn unfilled_mut(&mut self) -> &mut [u8] {
+        let start = self.pos(self.len);
+        let end = if start < self.head || self.len == self.capacity() {
+            self.head
+        }
+        else {
+            self.capacity()
+        };
+        &mut self.data[start..end]
+    }
+
+    /// Adds `count` octets written via `unfilled_mut()` to the buffer.
+    ///
+    /// # Panic
+    ///
+    /// The method panics if `count` is larger than `remaining_mut()`.
+    pub fn fill(&mut self, count: usize) {
+        assert!(count <= self.remaining_mut());
+        self.len += count;
+    }
+
+    /// Returns the content of the buffer as two slices.
+    ///
+    /// The second slice is non-empty only if the content wraps around.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        let end = self.head + self.len;
+        if end <= self.capacity() {
+            (&self.data[self.head..end], &[])
+        }
+        else {
+            (&self.data[self.head..],
+             &self.data[..end - self.capacity()])
+        }
+    }
+}
+
+
+//--- Buffer
+
+impl Buffer for RingBuf {
+    type Checkpoint = (usize, usize);
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        if index < self.len {
+            Some(self.data[self.pos(index)])
+        }
+        else {
+            None
+        }
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.head, self.len)
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.head = checkpoint.0;
+        self.len = checkpoint.1;
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        assert!(count <= self.len);
+        let mut res = BytesMut::with_capacity(count);
+        {
+            let (first, second) = self.as_slices();
+            if count <= first.len() {
+                res.put_slice(&first[..count]);
+            }
+            else {
+                res.put_slice(first);
+                res.put_slice(&second[..count - first.len()]);
+            }
+        }
+        self.advance(count);
+        res.freeze()
+    }
+
+    fn advance(&mut self, count: usize) {
+        assert!(count <= self.len);
+        self.head = self.pos(count);
+        self.len -= count;
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use futures::Async;
+    use ::core::{u16_digits, skip_crlf};
+    use ::parse::buffer::Buffer;
+    use super::*;
+
+    #[test]
+    fn fill_and_wrap() {
+        let mut buf = RingBuf::with_capacity(8);
+        assert_eq!(buf.extend_from_slice(b"123456"), 6);
+        buf.advance(4);
+        assert_eq!(buf.extend_from_slice(b"abcdefgh"), 6);
+        assert_eq!(buf.len(), 8);
+        assert_eq!(buf.as_slices(), (&b"56ab"[..], &b"cdef"[..]));
+        assert_eq!(buf.split_to(5), &b"56abc"[..]);
+        assert_eq!(buf.get(0), Some(b'd'));
+        assert_eq!(buf.get(3), None);
+    }
+
+    #[test]
+    fn parse_across_wrap() {
+        let mut buf = RingBuf::with_capacity(8);
+        buf.extend_from_slice(b"xxxxx");
+        buf.advance(5);
+        buf.extend_from_slice(b"1234\r\n ");
+        assert_eq!(u16_digits(&mut buf), Ok(Async::Ready(1234)));
+        assert_eq!(skip_crlf(&mut buf), Ok(Async::Ready(())));
+        assert_eq!(buf.len(), 1);
+    }
+
+    #[test]
+    fn rewind() {
+        let mut buf = RingBuf::with_capacity(4);
+        buf.extend_from_slice(b"12");
+        let checkpoint = buf.checkpoint();
+        buf.advance(2);
+        assert!(buf.is_empty());
+        buf.rewind(checkpoint);
+        assert_eq!(buf.split_to(2), &b"12"[..]);
+    }
+}
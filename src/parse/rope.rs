@@ -0,0 +1,293 @@
+//! A buffer made of a sequence of segments.
+//!
+//! Network stacks often hand out received data as a sequence of separate
+//! `Bytes` values rather than one contiguous buffer. This module provides
+//! `RopeBuf` which keeps such segments as they are and presents them as a
+//! single buffer to the parsers. Tokens and the category functions thus
+//! walk across segment boundaries without the data ever being copied
+//! into one place.
+//!
+//! Data can also be added from anything implementing the `Buf` trait of
+//! the bytes crate, including chains of buffers. In turn, `RopeBuf`
+//! implements `Buf` itself, so data remaining after parsing can be
+//! handed on to code expecting a `Buf`.
+
+use std::cmp;
+use std::collections::VecDeque;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use super::buffer::Buffer;
+
+
+//------------ RopeBuf -------------------------------------------------------
+
+/// A buffer consisting of a sequence of segments.
+///
+/// Segments are added at the end via `push()` or `put_buf()`. Draining
+/// octets that lie within one segment returns them without copying.
+/// Only if a drained token spans several segments, its octets are copied
+/// into a new `Bytes` value.
+///
+/// Like [`CursorBuf`], the buffer keeps drained segments around and only
+/// moves a cursor forward, so a checkpoint is just the position of the
+/// cursor. The drained segments are released when a segment is added.
+///
+/// Since locating an octet means walking the segments from the cursor,
+/// the buffer works best with a moderate number of segments.
+///
+/// [`CursorBuf`]: ../cursor/struct.CursorBuf.html
+#[derive(Clone, Debug, Default)]
+pub struct RopeBuf {
+    /// The segments including those already drained. None of them is
+    /// empty.
+    segments: VecDeque<Bytes>,
+
+    /// The position of the cursor.
+    pos: RopePos,
+}
+
+/// The position of the first octet of a rope not drained yet.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RopePos {
+    /// The index of the segment the octet is in.
+    segment: usize,
+
+    /// The index of the octet within its segment.
+    offset: usize,
+
+    /// The number of octets not drained yet.
+    len: usize,
+}
+
+impl RopeBuf {
+    /// Creates a new, empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a segment to the end of the buffer.
+    ///
+    /// This drops the segments drained so far.
+    pub fn push(&mut self, segment: Bytes) {
+        self.compact();
+        if !segment.is_empty() {
+            self.pos.len += segment.len();
+            self.segments.push_back(segment)
+        }
+    }
+
+    /// Appends all the data remaining in `buf` to the end of the buffer.
+    ///
+    /// Each contiguous chunk of `buf` becomes a segment of its own.
+    pub fn put_buf<B: Buf>(&mut self, buf: &mut B) {
+        while buf.has_remaining() {
+            let segment = Bytes::copy_from_slice(buf.chunk());
+            buf.advance(segment.len());
+            self.push(segment)
+        }
+    }
+
+    /// Drops the segments drained so far.
+    ///
+    /// This must not be called while parsing since it invalidates all
+    /// checkpoints taken.
+    pub fn compact(&mut self) {
+        self.segments.drain(..self.pos.segment);
+        if let Some(first) = self.segments.front_mut() {
+            Buf::advance(first, self.pos.offset);
+        }
+        self.pos.segment = 0;
+        self.pos.offset = 0;
+    }
+
+    /// Returns the number of segments not drained yet.
+    pub fn segment_count(&self) -> usize {
+        self.segments.len() - self.pos.segment
+    }
+
+    /// Returns an iterator over the octets of the segments not drained
+    /// yet.
+    pub fn segments(&self) -> impl Iterator<Item = &[u8]> {
+        let offset = self.pos.offset;
+        self.segments.iter().skip(self.pos.segment).enumerate().map(
+            move |(i, segment)| {
+                if i == 0 { &segment[offset..] } else { segment.as_ref() }
+            }
+        )
+    }
+}
+
+
+//--- Buffer
+
+impl Buffer for RopeBuf {
+    type Checkpoint = RopePos;
+
+    fn len(&self) -> usize {
+        self.pos.len
+    }
+
+    fn get(&self, index: usize) -> Option<u8> {
+        let mut index = index + self.pos.offset;
+        for segment in self.segments.iter().skip(self.pos.segment) {
+            if index < segment.len() {
+                return Some(segment[index])
+            }
+            index -= segment.len();
+        }
+        None
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.pos
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.pos = checkpoint
+    }
+
+    fn split_to(&mut self, count: usize) -> Bytes {
+        assert!(count <= self.pos.len);
+        if count == 0 {
+            return Bytes::new()
+        }
+        let start = self.pos.offset;
+        let first = &self.segments[self.pos.segment];
+        if start + count <= first.len() {
+            let res = first.slice(start..start + count);
+            Buffer::advance(self, count);
+            return res
+        }
+        let mut res = BytesMut::with_capacity(count);
+        for segment in self.segments() {
+            let part = cmp::min(count - res.len(), segment.len());
+            res.put_slice(&segment[..part]);
+            if res.len() == count {
+                break
+            }
+        }
+        Buffer::advance(self, count);
+        res.freeze()
+    }
+
+    fn advance(&mut self, count: usize) {
+        assert!(count <= self.pos.len);
+        self.pos.len -= count;
+        let mut offset = self.pos.offset + count;
+        while self.pos.segment < self.segments.len()
+            && offset >= self.segments[self.pos.segment].len()
+        {
+            offset -= self.segments[self.pos.segment].len();
+            self.pos.segment += 1;
+        }
+        self.pos.offset = offset;
+    }
+}
+
+
+//--- Buf
+
+impl Buf for RopeBuf {
+    fn remaining(&self) -> usize {
+        self.pos.len
+    }
+
+    fn chunk(&self) -> &[u8] {
+        match self.segments.get(self.pos.segment) {
+            Some(segment) => &segment[self.pos.offset..],
+            None => &[]
+        }
+    }
+
+    fn advance(&mut self, count: usize) {
+        Buffer::advance(self, count)
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use futures::Async;
+    use ::core::{u16_digits, skip_crlf};
+    use ::parse::token;
+    use super::*;
+
+    fn rope(segments: &[&'static [u8]]) -> RopeBuf {
+        let mut res = RopeBuf::new();
+        for segment in segments {
+            res.push(Bytes::from_static(segment))
+        }
+        res
+    }
+
+    #[test]
+    fn segments() {
+        let mut buf = rope(&[b"12", b"", b"345", b"6"]);
+        assert_eq!(buf.segment_count(), 3);
+        assert_eq!(Buffer::len(&buf), 6);
+        assert_eq!(buf.get(4), Some(b'5'));
+        assert_eq!(buf.get(6), None);
+        assert_eq!(buf.split_to(1), &b"1"[..]);
+        assert_eq!(buf.split_to(1), &b"2"[..]);
+        assert_eq!(buf.segment_count(), 2);
+        assert_eq!(buf.split_to(4), &b"3456"[..]);
+        assert!(Buffer::is_empty(&buf));
+
+        let mut buf = rope(&[b"12", b"345", b"6"]);
+        Buffer::advance(&mut buf, 3);
+        assert_eq!(buf.segments().collect::<Vec<_>>(), [&b"45"[..], b"6"]);
+        assert_eq!(buf.chunk(), b"45");
+    }
+
+    #[test]
+    fn parse_across_segments() {
+        let mut buf = rope(&[b"1", b"23", b"4\r", b"\n "]);
+        assert_eq!(u16_digits(&mut buf), Ok(Async::Ready(1234)));
+        assert_eq!(skip_crlf(&mut buf), Ok(Async::Ready(())));
+        assert_eq!(Buffer::len(&buf), 1);
+
+        let mut buf = rope(&[b"Hel", b"lo", b"!"]);
+        assert_eq!(token::parse_literal(&mut buf, b"hello"),
+                   Ok(Async::Ready(Bytes::from_static(b"Hello"))));
+        assert_eq!(buf.segment_count(), 1);
+    }
+
+    #[test]
+    fn rewind() {
+        let mut buf = rope(&[b"12", b"34"]);
+        let checkpoint = buf.checkpoint();
+        Buffer::advance(&mut buf, 3);
+        buf.rewind(checkpoint);
+        assert_eq!(buf.split_to(4), &b"1234"[..]);
+
+        let mut buf = rope(&[b"12", b"345", b"6"]);
+        assert_eq!(buf.split_to(1), &b"1"[..]);
+        let checkpoint = buf.checkpoint();
+        assert_eq!(buf.split_to(3), &b"234"[..]);
+        assert_eq!(buf.segment_count(), 2);
+        buf.rewind(checkpoint);
+        assert_eq!(Buffer::len(&buf), 5);
+        assert_eq!(buf.segments().collect::<Vec<_>>(),
+                   [&b"2"[..], b"345", b"6"]);
+    }
+
+    #[test]
+    fn compact() {
+        let mut buf = rope(&[b"12", b"345"]);
+        Buffer::advance(&mut buf, 3);
+        buf.push(Bytes::from_static(b"6"));
+        assert_eq!(buf.segments.len(), 2);
+        assert_eq!(buf.segments().collect::<Vec<_>>(), [&b"45"[..], b"6"]);
+        assert_eq!(buf.get(2), Some(b'6'));
+    }
+
+    #[test]
+    fn buf() {
+        let mut buf = RopeBuf::new();
+        buf.put_buf(&mut (&b"12"[..]).chain(&b"34"[..]));
+        assert_eq!(buf.segment_count(), 2);
+        assert_eq!(buf.remaining(), 4);
+        assert_eq!(buf.copy_to_bytes(4), &b"1234"[..]);
+    }
+}
@@ -34,16 +34,15 @@
 //!
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::group;
 //! # struct Res;
 //! # struct E;
-//! # fn rule1(buf: &mut BytesMut) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
-//! # fn rule2(buf: &mut BytesMut) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
-//! fn concat(buf: &mut BytesMut) -> Poll<Res, E> {
+//! # fn rule1(buf: &mut CursorBuf) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
+//! # fn rule2(buf: &mut CursorBuf) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
+//! fn concat(buf: &mut CursorBuf) -> Poll<Res, E> {
 //!     group(buf, |buf| {
 //!         try_ready!(rule1(buf));
 //!         try_ready!(rule2(buf));
@@ -63,22 +62,21 @@
 //! 
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::group;
 //! # struct Res;
 //! # struct E;
-//! fn rule1(buf: &mut BytesMut) -> Poll<Option<Res>, E> {
+//! fn rule1(buf: &mut CursorBuf) -> Poll<Option<Res>, E> {
 //!     unimplemented!()
 //! }
 //!
-//! fn rule2(buf: &mut BytesMut) -> Poll<Option<Res>, E> {
+//! fn rule2(buf: &mut CursorBuf) -> Poll<Option<Res>, E> {
 //!     unimplemented!()
 //! }
 //!
-//! fn alt(buf: &mut BytesMut) -> Poll<Res, E> {
+//! fn alt(buf: &mut CursorBuf) -> Poll<Res, E> {
 //!     try_opt!(rule1(buf));
 //!     try_opt!(rule2(buf));
 //!     Err(E)
@@ -101,15 +99,14 @@
 //!
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::{group, repeat};
 //! # struct Res;
 //! # struct E;
-//! # fn rule(buf: &mut BytesMut) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
-//! fn repeat_rule(buf: &mut BytesMut) -> Poll<Vec<Res>, E> {
+//! # fn rule(buf: &mut CursorBuf) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
+//! fn repeat_rule(buf: &mut CursorBuf) -> Poll<Vec<Res>, E> {
 //!     let mut res = Vec::new();
 //!     try_ready!(repeat(buf, rule, |item| {
 //!         match item {
@@ -135,15 +132,14 @@
 //!
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::{group, repeat};
 //! # struct Res;
 //! # struct E;
-//! # fn rule(buf: &mut BytesMut) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
-//! fn six_rule(buf: &mut BytesMut) -> Poll<Vec<Res>, E> {
+//! # fn rule(buf: &mut CursorBuf) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
+//! fn six_rule(buf: &mut CursorBuf) -> Poll<Vec<Res>, E> {
 //!     let mut res = Vec::new();
 //!     let mut count = 0;
 //!     try_ready!(repeat(buf, rule, |item| {
@@ -176,15 +172,14 @@
 //!
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::{group, at_least_once};
 //! # struct Res;
 //! # struct E;
-//! # fn rule(buf: &mut BytesMut) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
-//! fn rule_at_least_once(buf: &mut BytesMut) -> Poll<Vec<Res>, E> {
+//! # fn rule(buf: &mut CursorBuf) -> Poll<Res, E> { Ok(Async::Ready(Res)) }
+//! fn rule_at_least_once(buf: &mut CursorBuf) -> Poll<Vec<Res>, E> {
 //!     let mut res = Vec::new();
 //!     try_ready!(at_least_once(buf, rule,
 //!         |item| {
@@ -214,16 +209,15 @@
 //!
 //! ```
 //! # #[macro_use] extern crate abnf;
-//! # extern crate bytes;
 //! # extern crate futures;
-//! # use bytes::BytesMut;
+//! # use abnf::parse::cursor::CursorBuf;
 //! # use futures::{Async, Poll};
 //! # use abnf::parse::rule::{group, optional};
 //! # struct Res1; struct Res2;
 //! # struct E;
-//! # fn rule1(buf: &mut BytesMut) -> Poll<Res1, E> { Ok(Async::Ready(Res1)) }
-//! # fn rule2(buf: &mut BytesMut) -> Poll<Res2, E> { Ok(Async::Ready(Res2)) }
-//! fn rule1_opt_rule2(buf: &mut BytesMut) -> Poll<(Res1, Option<Res2>), E> {
+//! # fn rule1(buf: &mut CursorBuf) -> Poll<Res1, E> { Ok(Async::Ready(Res1)) }
+//! # fn rule2(buf: &mut CursorBuf) -> Poll<Res2, E> { Ok(Async::Ready(Res2)) }
+//! fn rule1_opt_rule2(buf: &mut CursorBuf) -> Poll<(Res1, Option<Res2>), E> {
 //!     group(buf, |buf| {
 //!         let res1 = try_ready!(rule1(buf));
 //!         let res2 = try_ready!(optional(buf, rule2));
@@ -233,35 +227,115 @@
 //! # fn main() { }
 //! ```
 
-use bytes::BytesMut;
 use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::limits::{check_repeat, enter, leave};
+use super::metrics::Probe;
+use super::token::TokenError;
 
 
 //------------ Combining Rules -----------------------------------------------
 
 /// Succeeds if parsing within `op` succeeds or rewinds.
-pub fn group<P, T, E>(buf: &mut BytesMut, parse: P) -> Poll<T, E>
-           where P: FnOnce(&mut BytesMut) -> Poll<T, E> {
-    let orig_buf = buf.clone();
+///
+/// In order to be able to rewind, a checkpoint of the buffer is taken
+/// before calling `op`. Depending on the buffer type, this may involve
+/// copying the buffer’s content. See the [`buffer`] module for details.
+///
+/// [`buffer`]: ../buffer/index.html
+pub fn group<B, P, T, E>(buf: &mut B, parse: P) -> Poll<T, E>
+           where B: Buffer, P: FnOnce(&mut B) -> Poll<T, E> {
+    report_rule(buf);
+    let probe = Probe::start(buf);
+    report_checkpoint(buf);
+    let checkpoint = buf.checkpoint();
     let res = parse(buf);
+    probe.finish(buf);
     match res {
-        Ok(Async::NotReady) | Err(_) => *buf = orig_buf,
+        Ok(Async::NotReady) => {
+            buf.rewind(checkpoint);
+            report_not_ready(buf);
+        }
+        Err(_) => buf.rewind(checkpoint),
         _ => {}
     }
     res
 }
 
-pub fn opt_group<P, T, E>(buf: &mut BytesMut, parse: P) -> Poll<Option<T>, E>
-                 where P: FnOnce(&mut BytesMut) -> Poll<Option<T>, E> {
-    let orig_buf = buf.clone();
+pub fn opt_group<B, P, T, E>(buf: &mut B, parse: P) -> Poll<Option<T>, E>
+                 where B: Buffer, P: FnOnce(&mut B) -> Poll<Option<T>, E> {
+    report_rule(buf);
+    let probe = Probe::start(buf);
+    report_checkpoint(buf);
+    let checkpoint = buf.checkpoint();
     let res = parse(buf);
+    probe.finish(buf);
     match res {
         Ok(Async::Ready(Some(_))) => { }
-        _ => *buf = orig_buf,
+        Ok(Async::NotReady) => {
+            buf.rewind(checkpoint);
+            report_not_ready(buf);
+        }
+        _ => buf.rewind(checkpoint),
     }
     res
 }
 
+/// Like `group()` but enforces the maximum nesting depth.
+///
+/// Fails with `TokenError::LimitExceeded` if the group would exceed the
+/// maximum nesting depth of the buffer’s limits. See the [`limits`]
+/// module for details.
+///
+/// [`limits`]: ../limits/index.html
+pub fn group_limited<B, P, T, E>(buf: &mut B, parse: P) -> Poll<T, E>
+                     where B: Buffer,
+                           P: FnOnce(&mut B) -> Poll<T, E>,
+                           E: From<TokenError> {
+    enter(buf)?;
+    let res = group(buf, parse);
+    leave(buf);
+    res
+}
+
+/// Like `opt_group()` but enforces the maximum nesting depth.
+pub fn opt_group_limited<B, P, T, E>(buf: &mut B, parse: P)
+                                     -> Poll<Option<T>, E>
+                         where B: Buffer,
+                               P: FnOnce(&mut B) -> Poll<Option<T>, E>,
+                               E: From<TokenError> {
+    enter(buf)?;
+    let res = opt_group(buf, parse);
+    leave(buf);
+    res
+}
+
+
+/// Reports the invocation of a rule to the buffer’s metrics.
+fn report_rule<B: Buffer>(buf: &mut B) {
+    if let Some(metrics) = buf.metrics() {
+        metrics.rule()
+    }
+}
+
+/// Reports taking a checkpoint to the buffer’s metrics.
+#[cfg(feature = "instrument")]
+fn report_checkpoint<B: Buffer>(buf: &mut B) {
+    if let Some(metrics) = buf.metrics() {
+        metrics.checkpoint()
+    }
+}
+
+#[cfg(not(feature = "instrument"))]
+fn report_checkpoint<B: Buffer>(_buf: &mut B) { }
+
+/// Reports a rule returning non-ready to the buffer’s metrics.
+fn report_not_ready<B: Buffer>(buf: &mut B) {
+    if let Some(metrics) = buf.metrics() {
+        metrics.not_ready()
+    }
+}
+
 
 /// Repetition.
 ///
@@ -276,9 +350,10 @@ pub fn opt_group<P, T, E>(buf: &mut BytesMut, parse: P) -> Poll<Option<T>, E>
 /// next. If it returns an error, the whole repetition rewinds and results
 /// in that error. It it returns a value, the repetition is over producing
 /// this result. If it returns non-ready, another iterations is done.
-pub fn repeat<P, R, E, C, S, F>(buf: &mut BytesMut, parse: P, mut combine: C)
-                          -> Poll<S, F>
-              where P: Fn(&mut BytesMut) -> Poll<R, E>,
+pub fn repeat<B, P, R, E, C, S, F>(buf: &mut B, parse: P, mut combine: C)
+                                   -> Poll<S, F>
+              where B: Buffer,
+                    P: Fn(&mut B) -> Poll<R, E>,
                     C: FnMut(Result<R, E>) -> Poll<S, F> {
     group(buf, |buf| {
         loop {
@@ -292,15 +367,42 @@ pub fn repeat<P, R, E, C, S, F>(buf: &mut BytesMut, parse: P, mut combine: C)
     })
 }
 
+/// Like `repeat()` but enforces the buffer’s limits.
+///
+/// The repetition is a group limited via `group_limited()`. If there are
+/// more iterations than allowed by the buffer’s limits, the repetition
+/// fails with `TokenError::LimitExceeded`.
+pub fn repeat_limited<B, P, R, E, C, S, F>(buf: &mut B, parse: P,
+                                           mut combine: C) -> Poll<S, F>
+                      where B: Buffer,
+                            P: Fn(&mut B) -> Poll<R, E>,
+                            C: FnMut(Result<R, E>) -> Poll<S, F>,
+                            F: From<TokenError> {
+    group_limited(buf, |buf| {
+        let mut count = 0;
+        loop {
+            count += 1;
+            check_repeat(buf, count)?;
+            let item = try_result!(parse(buf));
+            match combine(item) {
+                Ok(Async::Ready(res)) => return Ok(Async::Ready(res)),
+                Err(err) =>  return Err(err),
+                Ok(Async::NotReady) => { }
+            }
+        }
+    })
+}
+
 
 /// Repeat at least once.
 ///
 /// This is like `repeat()`, but if `parse` fails already on the first time,
 /// `combine` isn’t called at all but rather `empty`.
-pub fn at_least_once<P, R, E, C, S, F, D>(buf: &mut BytesMut,
-                                          parse: P, mut combine: C, error: D)
-                                          -> Poll<S, F>
-                     where P: Fn(&mut BytesMut) -> Poll<R, E>,
+pub fn at_least_once<B, P, R, E, C, S, F, D>(buf: &mut B, parse: P,
+                                             mut combine: C, error: D)
+                                             -> Poll<S, F>
+                     where B: Buffer,
+                           P: Fn(&mut B) -> Poll<R, E>,
                            C: FnMut(Result<R, E>) -> Poll<S, F>,
                            D: FnOnce(E) -> F {
     group(buf, |buf| {
@@ -323,14 +425,48 @@ pub fn at_least_once<P, R, E, C, S, F, D>(buf: &mut BytesMut,
     })
 }
 
+/// Like `at_least_once()` but enforces the buffer’s limits.
+///
+/// The limits are enforced the same way as by `repeat_limited()`.
+pub fn at_least_once_limited<B, P, R, E, C, S, F, D>(buf: &mut B, parse: P,
+                                                     mut combine: C,
+                                                     error: D)
+                                                     -> Poll<S, F>
+                             where B: Buffer,
+                                   P: Fn(&mut B) -> Poll<R, E>,
+                                   C: FnMut(Result<R, E>) -> Poll<S, F>,
+                                   D: FnOnce(E) -> F,
+                                   F: From<TokenError> {
+    group_limited(buf, |buf| {
+        match try_result!(parse(buf)) {
+            Err(err) => return Err(error(err)),
+            Ok(item) => match combine(Ok(item)) {
+                Ok(Async::Ready(res)) => return Ok(Async::Ready(res)),
+                Err(err) => return Err(err),
+                Ok(Async::NotReady) => { }
+            }
+        }
+        let mut count = 1;
+        loop {
+            count += 1;
+            check_repeat(buf, count)?;
+            let item = try_result!(parse(buf));
+            match combine(item) {
+                Ok(Async::Ready(res)) => return Ok(Async::Ready(res)),
+                Err(err) =>  return Err(err),
+                Ok(Async::NotReady) => { }
+            }
+        }
+    })
+}
+
 
 /// An optional rule.
-pub fn optional<P, R, E, F>(buf: &mut BytesMut, parse: P) -> Poll<Option<R>, F>
-                where P: FnOnce(&mut BytesMut) -> Poll<R, E> {
+pub fn optional<B, P, R, E, F>(buf: &mut B, parse: P) -> Poll<Option<R>, F>
+                where B: Buffer, P: FnOnce(&mut B) -> Poll<R, E> {
     match parse(buf) {
         Ok(Async::NotReady) => Ok(Async::NotReady),
         Ok(Async::Ready(some)) => Ok(Async::Ready(Some(some))),
         Err(_) => Ok(Async::Ready(None))
     }
 }
-
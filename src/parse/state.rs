@@ -0,0 +1,182 @@
+//! Resumable parsing.
+//!
+//! Parsers return non-ready if the buffer doesn’t contain enough data yet
+//! and are called again from the start once more data has arrived. For
+//! most rules, this is cheap enough. For long repetitions, such as a
+//! header block of a few hundred lines, it isn’t: every time a new chunk
+//! of data arrives, all the lines received so far are parsed again only
+//! to get stuck at the last, incomplete one.
+//!
+//! This module provides [`RuleState`], a repetition that remembers how far
+//! it got. It drains each element from the buffer as soon as it has been
+//! parsed and keeps the resulting value. When it runs out of data, it
+//! returns non-ready and, when called again, continues with the element it
+//! got stuck at.
+//!
+//! The price for this is that a rule state breaks the convention that a
+//! parser leaves the buffer untouched when returning non-ready or an error.
+//! It should therefore only be used at the outermost level of a rule, and
+//! an error should be considered fatal for the data in the buffer.
+//!
+//! [`RuleState`]: struct.RuleState.html
+
+use std::mem;
+use std::ops::RangeBounds;
+use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::limits::check_repeat;
+use super::parser::{repeat_bounds, Parser};
+use super::token::TokenError;
+
+
+//------------ RuleState -----------------------------------------------------
+
+/// A repetition that resumes where it left off.
+///
+/// The state parses elements via the parser `P` and collects their values
+/// of type `T` until the parser fails, the upper bound of repetitions is
+/// reached, or the parser succeeds without consuming any data, just like
+/// `Parser::repeat()` does. Unlike the latter, each element is drained
+/// right away and its value kept across calls that return non-ready.
+///
+/// Once the repetition is complete or has failed, the state is reset and
+/// can be used to parse the next repetition.
+#[derive(Clone, Debug)]
+pub struct RuleState<P, T> {
+    /// The parser for a single element.
+    parser: P,
+
+    /// The minimum number of elements.
+    min: usize,
+
+    /// The maximum number of elements, if any.
+    max: Option<usize>,
+
+    /// The values of the elements parsed so far.
+    items: Vec<T>,
+}
+
+impl<P, T> RuleState<P, T> {
+    /// Creates a new state repeating `parser` a number of times in `range`.
+    pub fn new<R: RangeBounds<usize>>(parser: P, range: R) -> Self {
+        let (min, max) = repeat_bounds(range);
+        RuleState { parser, min, max, items: Vec::new() }
+    }
+
+    /// Returns the values of the elements parsed so far.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Drops the values parsed so far and starts over.
+    ///
+    /// The data of these elements has already been drained from the
+    /// buffer and is not returned to it.
+    pub fn reset(&mut self) {
+        self.items.clear()
+    }
+}
+
+
+//--- Parser
+
+impl<B, P> Parser<B> for RuleState<P, P::Output>
+              where B: Buffer, P: Parser<B>, P::Error: From<TokenError> {
+    type Output = Vec<P::Output>;
+    type Error = P::Error;
+
+    fn parse(&mut self, buf: &mut B) -> Poll<Self::Output, P::Error> {
+        while self.max.map(|max| self.items.len() < max).unwrap_or(true) {
+            let len = buf.len();
+            match self.parser.parse(buf) {
+                Ok(Async::Ready(item)) => {
+                    self.items.push(item);
+                    if let Err(err) = check_repeat(buf, self.items.len()) {
+                        self.reset();
+                        return Err(err.into())
+                    }
+                }
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(err) => {
+                    if self.items.len() < self.min {
+                        self.reset();
+                        return Err(err)
+                    }
+                    break
+                }
+            }
+            if buf.len() == len {
+                break
+            }
+        }
+        Ok(Async::Ready(mem::take(&mut self.items)))
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use bytes::BytesMut;
+    use ::core::{u16_digits, skip_crlf};
+    use ::parse::limits::{Limited, Limits};
+    use ::parse::rule::group;
+    use super::*;
+
+    fn line<B: Buffer>(buf: &mut B) -> Poll<u16, TokenError> {
+        group(buf, |buf| {
+            let res = try_ready!(u16_digits(buf));
+            try_ready!(skip_crlf(buf));
+            Ok(Async::Ready(res))
+        })
+    }
+
+    #[test]
+    fn resume() {
+        let calls = Cell::new(0);
+        let mut state = RuleState::new(
+            |buf: &mut BytesMut| {
+                calls.set(calls.get() + 1);
+                line(buf)
+            },
+            1..
+        );
+        let mut buf = BytesMut::from(&b"1\r\n2\r\n3"[..]);
+        assert_eq!(state.parse(&mut buf), Ok(Async::NotReady));
+        assert_eq!(state.items(), [1, 2]);
+        assert_eq!(&buf[..], b"3");
+        assert_eq!(calls.get(), 3);
+
+        buf.extend_from_slice(b"4\r\n5\r\n.");
+        assert_eq!(state.parse(&mut buf), Ok(Async::Ready(vec![1, 2, 34, 5])));
+        assert_eq!(&buf[..], b".");
+        assert_eq!(calls.get(), 6);
+        assert!(state.items().is_empty());
+    }
+
+    #[test]
+    fn bounds() {
+        let mut state = RuleState::new(line, 2..=3);
+        let mut buf = BytesMut::from(&b"1\r\n2\r\n3\r\n4\r\n"[..]);
+        assert_eq!(state.parse(&mut buf), Ok(Async::Ready(vec![1, 2, 3])));
+        assert_eq!(&buf[..], b"4\r\n");
+
+        let mut buf = BytesMut::from(&b"1\r\n.\r\n"[..]);
+        assert_eq!(state.parse(&mut buf), Err(TokenError::Invalid));
+        assert!(state.items().is_empty());
+    }
+
+    #[test]
+    fn limits() {
+        let mut limits = Limits::new();
+        limits.set_max_repeat(2);
+        let mut buf = Limited::new(
+            BytesMut::from(&b"1\r\n2\r\n3\r\n"[..]), limits
+        );
+        let mut state = RuleState::new(line, ..);
+        assert_eq!(state.parse(&mut buf), Err(TokenError::LimitExceeded));
+        assert!(state.items().is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Writing rules with the question mark operator.
+//!
+//! The macros `try_ready!()`, `try_result!()`, `try_fail!()`, and
+//! `try_opt!()` return early from a function returning a `Poll`. This
+//! module provides the same control flow via the `?` operator instead.
+//!
+//! A rule is written as a function or closure returning a [`Step`]. This
+//! is a `Result` whose error is a [`Stop`], the reason for leaving the
+//! rule early. The adapter methods of the [`PollStep`] and
+//! [`PollOptionStep`] traits turn a `Poll` into a `Result` that can be
+//! used with `?`. Finally, [`poll()`] turns the step back into a `Poll`:
+//!
+//! ```
+//! extern crate abnf;
+//!
+//! use abnf::prelude::*;
+//! use abnf::core::u8_digits;
+//! use abnf::parse::step::{poll, PollStep, Step};
+//! use abnf::parse::token;
+//!
+//! fn pair(buf: &mut CursorBuf) -> Poll<(u8, u8), TokenError> {
+//!     group(buf, |buf| poll(pair_step(buf)))
+//! }
+//!
+//! fn pair_step(buf: &mut CursorBuf) -> Step<(u8, u8), TokenError> {
+//!     let left = u8_digits(buf).ready()?;
+//!     token::skip_octet(buf, b'/').ready()?;
+//!     let right = u8_digits(buf).ready()?;
+//!     Ok((left, right))
+//! }
+//!
+//! # fn main() {
+//! assert_eq_ready!(pair(&mut CursorBuf::from(&b"4/5 "[..])), (4, 5));
+//! # }
+//! ```
+//!
+//! Errors are not converted. If the error type of a parser differs from
+//! that of the rule, use `map_err()` on the `Poll` before the adapter.
+//!
+//! [`Step`]: type.Step.html
+//! [`Stop`]: enum.Stop.html
+//! [`PollStep`]: trait.PollStep.html
+//! [`PollOptionStep`]: trait.PollOptionStep.html
+//! [`poll()`]: fn.poll.html
+
+use futures::{Async, Poll};
+
+
+//------------ Stop ----------------------------------------------------------
+
+/// The reason for leaving a rule early.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stop<T, E> {
+    /// The rule has succeeded with the given value.
+    Ready(T),
+
+    /// There isn’t enough data to decide.
+    NotReady,
+
+    /// The rule has failed with the given error.
+    Failed(E),
+}
+
+impl<T, E> Stop<T, E> {
+    /// Converts the stop into the `Poll` the rule returns.
+    pub fn into_poll(self) -> Poll<T, E> {
+        match self {
+            Stop::Ready(t) => Ok(Async::Ready(t)),
+            Stop::NotReady => Ok(Async::NotReady),
+            Stop::Failed(e) => Err(e),
+        }
+    }
+}
+
+
+//------------ Step ----------------------------------------------------------
+
+/// The result of a rule written with the `?` operator.
+pub type Step<T, E> = Result<T, Stop<T, E>>;
+
+/// Converts a step into a `Poll`.
+pub fn poll<T, E>(step: Step<T, E>) -> Poll<T, E> {
+    match step {
+        Ok(t) => Ok(Async::Ready(t)),
+        Err(stop) => stop.into_poll(),
+    }
+}
+
+
+//------------ PollStep ------------------------------------------------------
+
+/// Adapter methods for using a `Poll` with the `?` operator.
+///
+/// The type argument `U` of the methods is the success type of the rule
+/// the method is used in. It is inferred from the rule’s return type.
+pub trait PollStep<T, E> {
+    /// Returns a ready value, stopping on non-ready or an error.
+    ///
+    /// This is the equivalent of `try_ready!()`.
+    fn ready<U>(self) -> Result<T, Stop<U, E>>;
+
+    /// Returns the result, stopping on non-ready.
+    ///
+    /// This is the equivalent of `try_result!()`.
+    fn result<U>(self) -> Result<Result<T, E>, Stop<U, E>>;
+
+    /// Returns an error, stopping with the value on success or non-ready.
+    ///
+    /// This is the equivalent of `try_fail!()`.
+    fn failed(self) -> Result<E, Stop<T, E>>;
+}
+
+impl<T, E> PollStep<T, E> for Poll<T, E> {
+    fn ready<U>(self) -> Result<T, Stop<U, E>> {
+        match self {
+            Ok(Async::Ready(t)) => Ok(t),
+            Ok(Async::NotReady) => Err(Stop::NotReady),
+            Err(e) => Err(Stop::Failed(e)),
+        }
+    }
+
+    fn result<U>(self) -> Result<Result<T, E>, Stop<U, E>> {
+        match self {
+            Ok(Async::Ready(t)) => Ok(Ok(t)),
+            Ok(Async::NotReady) => Err(Stop::NotReady),
+            Err(e) => Ok(Err(e)),
+        }
+    }
+
+    fn failed(self) -> Result<E, Stop<T, E>> {
+        match self {
+            Ok(Async::Ready(t)) => Err(Stop::Ready(t)),
+            Ok(Async::NotReady) => Err(Stop::NotReady),
+            Err(e) => Ok(e),
+        }
+    }
+}
+
+
+//------------ PollOptionStep ------------------------------------------------
+
+/// Adapter methods for using a `Poll` of an option with the `?` operator.
+pub trait PollOptionStep<T, E> {
+    /// Continues on `None`, stopping on everything else.
+    ///
+    /// This is the equivalent of `try_opt!()`.
+    fn none(self) -> Result<(), Stop<T, E>>;
+}
+
+impl<T, E> PollOptionStep<T, E> for Poll<Option<T>, E> {
+    fn none(self) -> Result<(), Stop<T, E>> {
+        match self {
+            Ok(Async::Ready(None)) => Ok(()),
+            Ok(Async::Ready(Some(t))) => Err(Stop::Ready(t)),
+            Ok(Async::NotReady) => Err(Stop::NotReady),
+            Err(e) => Err(Stop::Failed(e)),
+        }
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::core::{u8_digits, u8_hexdigs};
+    use ::parse::rule::group;
+    use ::parse::token::{self, TokenError};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    fn sum(buf: &mut BytesMut) -> Poll<u16, TokenError> {
+        group(buf, |buf| poll((|| {
+            let left = u8_digits(buf).ready()?;
+            let op = token::skip_octet(buf, b'+').result()?;
+            if op.is_err() {
+                return Ok(u16::from(left))
+            }
+            Ok(u16::from(left) + u16::from(u8_digits(buf).ready()?))
+        })()))
+    }
+
+    fn digits_or_hex(buf: &mut BytesMut) -> Poll<u8, TokenError> {
+        poll((|| {
+            u8_digits(buf).failed()?;
+            let err = u8_hexdigs(buf).failed()?;
+            Err(Stop::Failed(err))
+        })())
+    }
+
+    fn first(buf: &mut BytesMut) -> Poll<u8, TokenError> {
+        poll((|| {
+            u8_digits(buf).map(|res| res.map(|x| Some(x).filter(|&x| x > 0)))
+                          .none()?;
+            Err(Stop::Failed(TokenError::Invalid))
+        })())
+    }
+
+    #[test]
+    fn ready_and_result() {
+        assert_eq!(sum(&mut buf(b"12+3 ")), Ok(Async::Ready(15)));
+        assert_eq!(sum(&mut buf(b"12-3 ")), Ok(Async::Ready(12)));
+        assert_eq!(sum(&mut buf(b"12+")), Ok(Async::NotReady));
+        assert_eq!(sum(&mut buf(b"x")), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn failed_and_none() {
+        assert_eq!(digits_or_hex(&mut buf(b"12 ")), Ok(Async::Ready(12)));
+        assert_eq!(digits_or_hex(&mut buf(b"ff ")), Ok(Async::Ready(255)));
+        assert_eq!(digits_or_hex(&mut buf(b"x")), Err(TokenError::Invalid));
+        assert_eq!(first(&mut buf(b"7 ")), Ok(Async::Ready(7)));
+        assert_eq!(first(&mut buf(b"0 ")), Err(TokenError::Invalid));
+    }
+}
@@ -0,0 +1,179 @@
+//! Parsing complete data without `Poll`.
+//!
+//! When all the data is in memory already, say, a value from a config file
+//! or a test vector, there is no point in dealing with non-ready results.
+//! The functions in this module run the rules of this crate over a complete
+//! `&[u8]` and return a plain `Result` with the parsed value and the number
+//! of octets it took up.
+//!
+//! The data is wrapped in a final [`Complete`] buffer, the type [`Input`],
+//! so the end of the data is the end of the input: trailing sequences such
+//! as digits end there and rules that would need more data fail with
+//! `TokenError::Invalid`.
+//!
+//! Since all rules are generic over the buffer type, any buffer-level
+//! function or [`Parser`] can be used with [`parse()`] and [`parse_all()`]
+//! and any token-level function with [`token()`]:
+//!
+//! ```
+//! # extern crate abnf;
+//! use abnf::core::{alphas, u16_digits};
+//! use abnf::parse::sync;
+//!
+//! # fn main() {
+//! assert_eq!(sync::parse(b"8080", u16_digits), Ok((8080, 4)));
+//! assert_eq!(sync::token(b"abc123", alphas), Ok((&b"abc"[..], 3)));
+//! assert!(sync::parse_all(b"80 ", u16_digits).is_err());
+//! # }
+//! ```
+//!
+//! [`Complete`]: ../complete/struct.Complete.html
+//! [`Input`]: type.Input.html
+//! [`Parser`]: ../parser/trait.Parser.html
+//! [`parse()`]: fn.parse.html
+//! [`parse_all()`]: fn.parse_all.html
+//! [`token()`]: fn.token.html
+
+use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::complete::Complete;
+use super::parser::Parser;
+use super::token::{self, Token, TokenError};
+
+
+//------------ Input ---------------------------------------------------------
+
+/// The buffer the functions of this module parse from.
+pub type Input<'a> = Complete<&'a [u8]>;
+
+
+//------------ Functions -----------------------------------------------------
+
+/// Parses a value from the beginning of `data`.
+///
+/// Returns the value and the number of octets it took up.
+pub fn parse<'a, P>(data: &'a [u8], mut parser: P)
+                    -> Result<(P::Output, usize), P::Error>
+             where P: Parser<Input<'a>>, P::Error: From<TokenError> {
+    let mut buf = Complete::new(data);
+    let res = parser.parse(&mut buf);
+    finish(data, &buf, res)
+}
+
+/// Parses a value from all of `data`.
+///
+/// Returns an error if the value doesn’t take up all of the data.
+pub fn parse_all<'a, P>(data: &'a [u8], parser: P)
+                        -> Result<P::Output, P::Error>
+                 where P: Parser<Input<'a>>, P::Error: From<TokenError> {
+    match parse(data, parser)? {
+        (res, len) if len == data.len() => Ok(res),
+        _ => Err(TokenError::Invalid.into())
+    }
+}
+
+/// Parses a token from the beginning of `data`.
+///
+/// The closure `parseop` is given a token atop the data. Returns the
+/// octets of the token and its length.
+pub fn token<'a, P, E>(data: &'a [u8], parseop: P)
+                       -> Result<(&'a [u8], usize), E>
+             where P: FnOnce(&mut Token<Input<'a>>) -> Poll<(), E>,
+                   E: From<TokenError> {
+    let mut buf = Complete::new(data);
+    let res = token::skip(&mut buf, parseop);
+    let ((), len) = finish(data, &buf, res)?;
+    Ok((&data[..len], len))
+}
+
+/// Converts the result of a parser run over `data` into a plain result.
+fn finish<T, E>(data: &[u8], buf: &Input, res: Poll<T, E>)
+                -> Result<(T, usize), E>
+          where E: From<TokenError> {
+    match res? {
+        Async::Ready(res) => Ok((res, data.len() - buf.get_ref().len())),
+        Async::NotReady => Err(TokenError::Invalid.into())
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use ::core::{alphas, skip_crlf, u16_digits, u8_digits};
+    use ::parse::rule::{group, optional};
+    use ::parse::token::skip_octet;
+    use super::*;
+
+    fn version<B: Buffer>(buf: &mut B)
+                          -> Poll<(u16, Option<u16>), TokenError> {
+        group(buf, |buf| {
+            let major = try_ready!(u16_digits(buf));
+            let minor = try_ready!(optional(buf, |buf| group(buf, |buf| {
+                try_ready!(skip_octet(buf, b'.'));
+                u16_digits(buf)
+            })));
+            Ok(Async::Ready((major, minor)))
+        })
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(super::parse(b"12", u16_digits), Ok((12, 2)));
+        assert_eq!(super::parse(b"12a", u16_digits), Ok((12, 2)));
+        assert_eq!(super::parse(b"a", u16_digits), Err(TokenError::Invalid));
+        assert_eq!(super::parse(b"1.2", version), Ok(((1, Some(2)), 3)));
+        assert_eq!(super::parse(b"1.", version), Ok(((1, None), 1)));
+        assert_eq!(super::parse(b"1\r", skip_crlf),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn parse_all() {
+        assert_eq!(super::parse_all(b"1.2", version), Ok((1, Some(2))));
+        assert_eq!(super::parse_all(b"1.", version),
+                   Err(TokenError::Invalid));
+        let octets = u8_digits.followed_by(|buf: &mut Input| {
+            skip_octet(buf, b'.')
+        }).repeat(..);
+        assert_eq!(super::parse_all(b"1.2.3.", octets), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn end_of_input() {
+        use ::core::{parse_folded_line, u16_qvalue, LineEnding};
+        use ::{dnszone, mime, rtsp};
+
+        assert_eq!(super::parse(b"12", rtsp::parse_cseq), Ok((12, 2)));
+        assert_eq!(super::parse(b"1234567890", rtsp::parse_cseq),
+                   Err(TokenError::Invalid));
+        assert_eq!(super::parse(b"1.0", mime::parse_mime_version),
+                   Ok(((1, 0), 3)));
+        assert_eq!(super::parse(b"1.0 (x", mime::parse_mime_version),
+                   Err(TokenError::Invalid));
+        assert_eq!(
+            super::parse(b"base64", mime::parse_content_transfer_encoding),
+            Ok((mime::ContentTransferEncoding::Base64, 6))
+        );
+        assert_eq!(super::parse(b"0.500", u16_qvalue), Ok((500, 5)));
+        assert_eq!(
+            super::parse(b"a\r\n b\r\n", |buf: &mut Input| {
+                parse_folded_line(buf, LineEnding::Crlf)
+            }).map(|(line, len)| (line.len(), len)),
+            Ok((7, 7))
+        );
+        assert_eq!(super::parse(b"example.com.", dnszone::parse_word)
+                       .map(|(word, len)| (word.len(), len)),
+                   Ok((12, 12)));
+        assert_eq!(super::parse(b"\"open", dnszone::parse_char_string),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn token() {
+        assert_eq!(super::token(b"ab1", alphas), Ok((&b"ab"[..], 2)));
+        assert_eq!(super::token(b"ab", alphas), Ok((&b"ab"[..], 2)));
+        assert_eq!(super::token(b"1", alphas), Err(TokenError::Invalid));
+    }
+}
@@ -0,0 +1,169 @@
+//! Parsing in terms of `std::task::Poll`.
+//!
+//! The parsers of this crate return the `Poll` of futures 0.1, a `Result`
+//! whose success value is an `Async`. The standard library and the
+//! futures 0.3 ecosystem built atop it use `std::task::Poll` instead and
+//! express a fallible poll as `Poll<Result<T, E>>`. This module, available
+//! with the `std-poll` feature, translates between the two so the rules of
+//! this crate can be used from code written against the standard types
+//! without the futures 0.1 shim.
+//!
+//! The trait [`TaskParser`] is implemented for all parsers, so any
+//! combinator pipeline can be run via `poll_parse()`. In the other
+//! direction, [`from_fn()`] turns a rule written against the standard
+//! types into a [`Parser`] so it can be combined with everything else.
+//! The functions [`to_std()`] and [`from_std()`] convert single results.
+//!
+//! ```
+//! # extern crate abnf;
+//! use std::task::Poll;
+//! use abnf::parse::cursor::CursorBuf;
+//! use abnf::core::u16_digits;
+//! use abnf::parse::parser::Parser;
+//! use abnf::parse::task::{from_fn, to_std, TaskParser};
+//! use abnf::parse::token::{skip_octet, TokenError};
+//!
+//! # fn main() {
+//! let dot = from_fn(|buf: &mut CursorBuf| -> Poll<Result<(), TokenError>> {
+//!     to_std(skip_octet(buf, b'.'))
+//! });
+//! let mut version = u16_digits.then(u16_digits.preceded_by(dot));
+//! let mut buf = CursorBuf::from(&b"1."[..]);
+//! assert_eq!(version.poll_parse(&mut buf), Poll::Pending);
+//! buf.extend_from_slice(b"2 ");
+//! assert_eq!(version.poll_parse(&mut buf), Poll::Ready(Ok((1, 2))));
+//! # }
+//! ```
+//!
+//! [`TaskParser`]: trait.TaskParser.html
+//! [`Parser`]: ../parser/trait.Parser.html
+//! [`from_fn()`]: fn.from_fn.html
+//! [`to_std()`]: fn.to_std.html
+//! [`from_std()`]: fn.from_std.html
+
+use std::task;
+use futures::{self, Async};
+use super::buffer::Buffer;
+use super::parser::Parser;
+
+
+//------------ Poll ----------------------------------------------------------
+
+/// The result of a parser in terms of the standard library.
+pub type Poll<T, E> = task::Poll<Result<T, E>>;
+
+/// Converts the result of a parser into its standard library equivalent.
+pub fn to_std<T, E>(poll: futures::Poll<T, E>) -> Poll<T, E> {
+    match poll {
+        Ok(Async::Ready(res)) => task::Poll::Ready(Ok(res)),
+        Ok(Async::NotReady) => task::Poll::Pending,
+        Err(err) => task::Poll::Ready(Err(err)),
+    }
+}
+
+/// Converts a standard library result into the result of a parser.
+pub fn from_std<T, E>(poll: Poll<T, E>) -> futures::Poll<T, E> {
+    match poll {
+        task::Poll::Ready(Ok(res)) => Ok(Async::Ready(res)),
+        task::Poll::Ready(Err(err)) => Err(err),
+        task::Poll::Pending => Ok(Async::NotReady),
+    }
+}
+
+
+//------------ TaskParser ----------------------------------------------------
+
+/// A parser that can be polled via the standard library’s `Poll`.
+///
+/// This is implemented for all parsers.
+pub trait TaskParser<B: Buffer>: Parser<B> {
+    /// Parses a value from the beginning of `buf`.
+    ///
+    /// Returns `Poll::Pending` where the parser returns non-ready.
+    fn poll_parse(&mut self, buf: &mut B) -> Poll<Self::Output, Self::Error>;
+}
+
+impl<B: Buffer, P: Parser<B>> TaskParser<B> for P {
+    fn poll_parse(&mut self, buf: &mut B) -> Poll<Self::Output, Self::Error> {
+        to_std(self.parse(buf))
+    }
+}
+
+
+//------------ FromFn --------------------------------------------------------
+
+/// Creates a parser from a closure returning the standard library’s `Poll`.
+///
+/// Like all parsers, the closure must leave the buffer untouched if it
+/// returns `Poll::Pending` or an error.
+pub fn from_fn<B, F, T, E>(op: F) -> FromFn<F>
+               where B: Buffer, F: FnMut(&mut B) -> Poll<T, E> {
+    FromFn { op }
+}
+
+/// A parser created from a closure via `from_fn()`.
+#[derive(Clone, Debug)]
+pub struct FromFn<F> {
+    op: F,
+}
+
+impl<B, F, T, E> Parser<B> for FromFn<F>
+                 where B: Buffer, F: FnMut(&mut B) -> Poll<T, E> {
+    type Output = T;
+    type Error = E;
+
+    fn parse(&mut self, buf: &mut B) -> futures::Poll<T, E> {
+        from_std((self.op)(buf))
+    }
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::task::ready;
+    use bytes::BytesMut;
+    use ::core::{u8_digits, u16_digits};
+    use ::parse::token::{skip_octet, TokenError};
+    use super::*;
+
+    #[test]
+    fn convert() {
+        let ready: futures::Poll<u8, TokenError> = Ok(Async::Ready(1));
+        let not_ready: futures::Poll<u8, TokenError> = Ok(Async::NotReady);
+        let err: futures::Poll<u8, TokenError> = Err(TokenError::Invalid);
+        for poll in &[ready, not_ready, err] {
+            assert_eq!(from_std(to_std(*poll)), *poll);
+        }
+        assert_eq!(to_std(ready), task::Poll::Ready(Ok(1)));
+        assert_eq!(to_std(not_ready), task::Poll::Pending);
+    }
+
+    #[test]
+    fn poll_parse() {
+        let mut buf = BytesMut::from(&b"12"[..]);
+        assert_eq!(u16_digits.poll_parse(&mut buf), task::Poll::Pending);
+        buf.extend_from_slice(b".");
+        assert_eq!(u16_digits.poll_parse(&mut buf), task::Poll::Ready(Ok(12)));
+        assert_eq!(u16_digits.poll_parse(&mut buf),
+                   task::Poll::Ready(Err(TokenError::Invalid)));
+    }
+
+    #[test]
+    fn from_fn() {
+        // A rule written with `?` on the standard types.
+        fn octet(buf: &mut BytesMut) -> Poll<u8, TokenError> {
+            let res = ready!(to_std(u8_digits(buf)))?;
+            ready!(to_std(skip_octet(buf, b'.')))?;
+            task::Poll::Ready(Ok(res))
+        }
+
+        let mut octets = super::from_fn(octet).repeat(2..=2);
+        let mut buf = BytesMut::from(&b"1.2"[..]);
+        assert_eq!(octets.parse(&mut buf), Ok(Async::NotReady));
+        buf.extend_from_slice(b".3");
+        assert_eq!(octets.parse(&mut buf), Ok(Async::Ready(vec![1, 2])));
+        assert_eq!(&buf[..], b"3");
+    }
+}
@@ -12,8 +12,10 @@
 //! token either as a buffer or some other appropriate type and `skip_` for
 //! functions that silently skip over the token.
 
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
 use futures::{Async, Poll};
+use super::buffer::Buffer;
+use super::limits::{check_digits, check_repeat};
 
 
 //============ Basic Token Parsing ===========================================
@@ -22,27 +24,32 @@ use futures::{Async, Poll};
 
 /// A token in the process of being parsed.
 ///
-/// A token is parsed from the beginning of a `BytesMut` by advancing over
+/// A token is parsed from the beginning of a buffer by advancing over
 /// octets until the token’s end is discoverd at which point the token can
 /// be drained from the buffer and converted into a `Bytes` of its own.
-pub struct Token<'a> {
-    bytes: &'a mut BytesMut,
-    end: usize
+pub struct Token<'a, B: 'a> {
+    buf: &'a mut B,
+    end: usize,
+    reported: bool,
 }
 
 
-impl<'a> Token<'a> {
+impl<'a, B: Buffer> Token<'a, B> {
     /// Creates a new token atop the given buffer.
-    pub fn new(bytes: &'a mut BytesMut) -> Self {
-        Token {
-            bytes: bytes,
-            end: 0
-        }
+    pub fn new(buf: &'a mut B) -> Self {
+        Token { buf, end: 0, reported: false }
+    }
+
+    /// Returns the number of octets that haven’t been advanced over yet.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.end
     }
 
-    /// Returns a bytes slice of what hasn’t been advanced over yet.
-    pub fn remaining(&self) -> &[u8] {
-        &(&self.bytes)[self.end..]
+    /// Returns the octet `offset` octets past the token’s current end.
+    ///
+    /// Returns `None` if the octet isn’t available (yet).
+    pub fn peek(&self, offset: usize) -> Option<u8> {
+        self.buf.get(self.end + offset)
     }
 
     /// Advances the token by `count` octets.
@@ -52,19 +59,53 @@ impl<'a> Token<'a> {
     /// The method panics if `count` would advance beyond the end of the
     /// underlying buffer.
     pub fn advance(&mut self, count: usize) {
-        println!("{} + {} <= {}", self.end, count, self.bytes.len());
-        assert!(self.end + count <= self.bytes.len());
+        assert!(self.end + count <= self.buf.len());
         self.end += count;
     }
 
+    /// Returns whether the underlying buffer is final.
+    ///
+    /// If so, octets that aren’t available now never will be.
+    pub fn is_final(&self) -> bool {
+        self.buf.is_final()
+    }
+
+    /// Returns whether the token has reached the end of the input.
+    ///
+    /// This is the case if there are no octets remaining and the buffer
+    /// is final.
+    pub fn at_end(&self) -> bool {
+        self.remaining() == 0 && self.is_final()
+    }
+
+    /// Reports that the token needs `count` octets past its current end.
+    ///
+    /// This is used by token rules that know exactly how much data they
+    /// need before returning non-ready. The number of octets missing is
+    /// reported to the buffer via `Buffer::report_needed()`.
+    pub fn need(&mut self, count: usize) {
+        let remaining = self.remaining();
+        if count > remaining {
+            self.buf.report_needed(count - remaining);
+            self.reported = true;
+        }
+    }
+
     /// Advances one octet if `test` returned `true` for it.
     ///
     /// Ready-returns if there was at least one octet available with the
     /// result of the test closure. Returns non-ready if there are no more
-    /// octets in the buffer. Never returns an error.
+    /// octets in the buffer unless the token is at the end of the input
+    /// in which case it ready-returns `false`. Never returns an error.
     pub fn advance_if<F, E>(&mut self, test: F) -> Poll<bool, E>
                       where F: FnOnce(u8) -> bool {
-        let res = test(try_ready!(self.first()));
+        if self.at_end() {
+            return Ok(Async::Ready(false))
+        }
+        let res = match self.peek(0) {
+            Some(ch) => test(ch),
+            None => return Ok(Async::NotReady)
+        };
         if res {
             self.advance(1)
         }
@@ -73,12 +114,19 @@ impl<'a> Token<'a> {
 
     /// Advances one octet if `test` succeeds, producing an error otherwise.
     ///
-    /// This behaves like `advance()` except that if `test` returns false,
-    /// the closure `error` is called and its result returned.
+    /// This behaves like `advance()` except that if `test` returns false
+    /// or the token is at the end of the input, the closure `error` is
+    /// called and its result returned.
     pub fn expect<P, Q, E>(&mut self, test: P, error: Q) -> Poll<(), E>
                   where P: FnOnce(u8) -> bool,
                         Q: FnOnce() -> E {
-        let res = test(try_ready!(self.first()));
+        if self.at_end() {
+            return Err(error())
+        }
+        let res = match self.peek(0) {
+            Some(ch) => test(ch),
+            None => return Ok(Async::NotReady)
+        };
         if res {
             self.advance(1);
             Ok(Async::Ready(()))
@@ -88,22 +136,76 @@ impl<'a> Token<'a> {
         }
     }
 
+    /// Returns a checkpoint for the token’s current end.
+    pub fn checkpoint(&self) -> usize {
+        self.end
+    }
+
+    /// Moves the token’s end back to where it was at `checkpoint`.
+    pub fn rewind(&mut self, checkpoint: usize) {
+        assert!(checkpoint <= self.end);
+        self.end = checkpoint
+    }
+
     /// Returns the first remaining character of the buffer if available.
-    pub fn first<E>(&self) -> Poll<u8, E> {
-        match self.bytes.get(self.end) {
+    ///
+    /// Returns non-ready if there are no more octets in the buffer unless
+    /// the token is at the end of the input in which case it returns an
+    /// error. Rules that may end at the end of the input need to check
+    /// `at_end()` first.
+    pub fn first<E: From<TokenError>>(&self) -> Poll<u8, E> {
+        match self.peek(0) {
+            None if self.is_final() => Err(TokenError::Invalid.into()),
             None => Ok(Async::NotReady),
-            Some(ch) => Ok(Async::Ready(*ch)),
+            Some(ch) => Ok(Async::Ready(ch)),
+        }
+    }
+
+    /// Runs `parseop` on the token.
+    ///
+    /// Reports to the buffer’s metrics and enforces the maximum token
+    /// length of the buffer’s limits. If the buffer is final, a token that
+    /// would need more data is invalid. If the token returns non-ready
+    /// without having called `need()`, a single additional octet is
+    /// reported as needed.
+    fn run<P, E>(&mut self, parseop: P) -> Poll<(), E>
+           where P: FnOnce(&mut Self) -> Poll<(), E>, E: From<TokenError> {
+        if let Some(metrics) = self.buf.metrics() {
+            metrics.rule()
+        }
+        let res = parseop(self);
+        let len = match res {
+            Ok(Async::Ready(())) => self.end,
+            Ok(Async::NotReady) if self.is_final() => {
+                return Err(TokenError::Invalid.into())
+            }
+            Ok(Async::NotReady) => {
+                if !self.reported {
+                    self.buf.report_needed(1)
+                }
+                if let Some(metrics) = self.buf.metrics() {
+                    metrics.not_ready()
+                }
+                self.buf.len()
+            }
+            Err(_) => return res
+        };
+        if let Some(limits) = self.buf.limits() {
+            if len > limits.max_token_len() {
+                return Err(TokenError::LimitExceeded.into())
+            }
         }
+        res
     }
 
     /// Drains the token from the underlying buffer.
     pub fn split(self) -> Bytes {
-        self.bytes.split_to(self.end).freeze()
+        self.buf.split_to(self.end)
     }
 
     /// Drops the token from the underlying buffer.
     pub fn skip(self) {
-        let _  = self.bytes.split_to(self.end);
+        self.buf.advance(self.end)
     }
 }
 
@@ -115,10 +217,12 @@ impl<'a> Token<'a> {
 /// The closure `parseop` is given a token atop `bytes`. If the closure returns
 /// ready, the token is drained from the buffer and returned. Otherwise, the
 /// result of the closure is returned and nothing else happens.
-pub fn parse<P, E>(bytes: &mut BytesMut, parseop: P) -> Poll<Bytes, E>
-             where P: FnOnce(&mut Token) -> Poll<(), E> {
+pub fn parse<B, P, E>(bytes: &mut B, parseop: P) -> Poll<Bytes, E>
+             where B: Buffer,
+                   P: FnOnce(&mut Token<B>) -> Poll<(), E>,
+                   E: From<TokenError> {
     let mut token = Token::new(bytes);
-    try_ready!(parseop(&mut token));
+    try_ready!(token.run(parseop));
     Ok(Async::Ready(token.split()))
 }
 
@@ -128,31 +232,38 @@ pub fn parse<P, E>(bytes: &mut BytesMut, parseop: P) -> Poll<Bytes, E>
 /// This starts out as `parse()`. If that returns either ready or with an
 /// error, the result is given to the closure `convertop` which converts it
 /// into whatever it likes.
-pub fn convert<P, E, C, R, F>(bytes: &mut BytesMut, parseop: P, convertop: C)
-                              -> Poll<R, F>
-               where P: FnOnce(&mut Token) -> Poll<(), E>,
+pub fn convert<B, P, E, C, R, F>(bytes: &mut B, parseop: P, convertop: C)
+                                 -> Poll<R, F>
+               where B: Buffer,
+                     P: FnOnce(&mut Token<B>) -> Poll<(), E>,
+                     E: From<TokenError>,
                      C: FnOnce(Result<&[u8], E>) -> Result<R, F> {
     // XXX Convert to ultimately using Token::skip()
     let res = match try_result!(parse(bytes, parseop)) {
         Ok(bytes) => convertop(Ok(&bytes)),
         Err(err) => convertop(Err(err))
     };
-    res.map(|res| Async::Ready(res))
+    res.map(Async::Ready)
 }
 
 /// Skips over a token.
-pub fn skip<P, E>(bytes: &mut BytesMut, parsef: P) -> Poll<(), E>
-            where P: FnOnce(&mut Token) -> Poll<(), E> {
-    // XXX Convert to ultimately using Token::skip()
-    try_ready!(parse(bytes, parsef));
+pub fn skip<B, P, E>(bytes: &mut B, parsef: P) -> Poll<(), E>
+            where B: Buffer,
+                  P: FnOnce(&mut Token<B>) -> Poll<(), E>,
+                  E: From<TokenError> {
+    let mut token = Token::new(bytes);
+    try_ready!(token.run(parsef));
+    token.skip();
     Ok(Async::Ready(()))
 }
 
 /// Skips over an optional token.
 ///
 /// If successful, returns whether there was a token or not.
-pub fn skip_opt<P, E>(bytes: &mut BytesMut, parsef: P) -> Poll<bool, E>
-                where P: FnOnce(&mut Token) -> Poll<(), E> {
+pub fn skip_opt<B, P, E>(bytes: &mut B, parsef: P) -> Poll<bool, E>
+                where B: Buffer,
+                      P: FnOnce(&mut Token<B>) -> Poll<(), E>,
+                      E: From<TokenError> {
     match try_result!(skip(bytes, parsef)) {
         Ok(()) => Ok(Async::Ready(true)),
         Err(_) => Ok(Async::Ready(false))
@@ -160,6 +271,57 @@ pub fn skip_opt<P, E>(bytes: &mut BytesMut, parsef: P) -> Poll<bool, E>
 }
 
 
+//------------ Combining Token Rules -----------------------------------------
+
+/// Advances the token if `parseop` succeeds or rewinds it otherwise.
+///
+/// This is the token-level equivalent of `rule::group()`.
+pub fn group<B, P, E>(token: &mut Token<B>, parseop: P) -> Poll<(), E>
+             where B: Buffer, P: FnOnce(&mut Token<B>) -> Poll<(), E> {
+    let checkpoint = token.checkpoint();
+    let res = parseop(token);
+    match res {
+        Ok(Async::Ready(())) => { }
+        _ => token.rewind(checkpoint)
+    }
+    res
+}
+
+/// Advances the token over between `min` and `max` repetitions.
+///
+/// This implements the ABNF repetition `<min>*<max>element` with `parseop`
+/// parsing one element. If `max` is `None`, there is no upper bound.
+/// Repetition ends when `parseop` fails, when it succeeds without
+/// advancing the token, or when `max` is reached. If it ends before `min`
+/// repetitions, the token is rewound and an error returned.
+pub fn repeat<B, P>(token: &mut Token<B>, min: usize, max: Option<usize>,
+                    mut parseop: P) -> Poll<(), TokenError>
+              where B: Buffer,
+                    P: FnMut(&mut Token<B>) -> Poll<(), TokenError> {
+    group(token, |token| {
+        let mut count = 0;
+        while max.map(|max| count < max).unwrap_or(true) {
+            let checkpoint = token.checkpoint();
+            match try_result!(group(token, &mut parseop)) {
+                Ok(()) => count += 1,
+                Err(TokenError::Invalid) => break,
+                Err(err) => return Err(err)
+            }
+            check_repeat(token.buf, count)?;
+            if token.checkpoint() == checkpoint {
+                break
+            }
+        }
+        if count < min {
+            Err(TokenError::Invalid)
+        }
+        else {
+            Ok(Async::Ready(()))
+        }
+    })
+}
+
+
 //============ Concrete Token Parsers ========================================
 
 //------------ Specific Octets -----------------------------------------------
@@ -167,43 +329,32 @@ pub fn skip_opt<P, E>(bytes: &mut BytesMut, parsef: P) -> Poll<bool, E>
 /// Expects the first octet of the token to be `value`.
 ///
 /// If it is, advances over it. If it isn’t, returns an error.
-pub fn octet(token: &mut Token, value: u8) -> Poll<(), TokenError> {
-    let first = try_ready!(token.first());
-    if first == value {
-        token.advance(1);
-        Ok(Async::Ready(()))
-    }
-    else {
-        Err(TokenError)
-    }
+pub fn octet<B: Buffer>(token: &mut Token<B>, value: u8)
+                        -> Poll<(), TokenError> {
+    token.expect(|ch| ch == value, || TokenError::Invalid)
 }
 
 /// Advances the token if the first octet is `value`.
 ///
 /// Returns whether it advanced or not.
-pub fn opt_octet<E>(token: &mut Token, value: u8) -> Poll<bool, E> {
-    let first = try_ready!(token.first());
-    if first == value {
-        token.advance(1);
-        Ok(Async::Ready(true))
-    }
-    else {
-        Ok(Async::Ready(false))
-    }
+pub fn opt_octet<B: Buffer, E>(token: &mut Token<B>, value: u8)
+                               -> Poll<bool, E> {
+    token.advance_if(|ch| ch == value)
 }
 
 /// Skips over the first octet in `bytes` which must be `value`.
 ///
 /// Returns an error if the first octet is anything else.
-pub fn skip_octet(bytes: &mut BytesMut, value: u8) -> Poll<(), TokenError> {
+pub fn skip_octet<B: Buffer>(bytes: &mut B, value: u8)
+                              -> Poll<(), TokenError> {
     skip(bytes, |token| octet(token, value))
 }
 
 /// Skips over the first octet in `bytes` if it is `value`.
 ///
 /// On success, returns whether it skipped an octet or not.
-pub fn skip_opt_octet(bytes: &mut BytesMut, value: u8)
-                      -> Poll<bool, TokenError> {
+pub fn skip_opt_octet<B: Buffer>(bytes: &mut B, value: u8)
+                                  -> Poll<bool, TokenError> {
     match try_result!(skip_octet(bytes, value)) {
         Ok(()) => Ok(Async::Ready(true)),
         Err(_) => Ok(Async::Ready(false))
@@ -218,11 +369,11 @@ pub fn skip_opt_octet(bytes: &mut BytesMut, value: u8)
 /// If the token is empty, returns non-ready. If `test` returns `true` for the
 /// first octet in the token, advances over the octet and returns ready. If
 /// `test` returns `false`, returns an error.
-pub fn cat<O>(token: &mut Token, test: O) -> Poll<(), TokenError>
-           where O: FnOnce(u8) -> bool {
+pub fn cat<B, O>(token: &mut Token<B>, test: O) -> Poll<(), TokenError>
+           where B: Buffer, O: FnOnce(u8) -> bool {
     match try_ready!(token.advance_if(test)) {
         true => Ok(Async::Ready(())),
-        false => Err(TokenError),
+        false => Err(TokenError::Invalid),
     }
 }
 
@@ -230,11 +381,29 @@ pub fn cat<O>(token: &mut Token, test: O) -> Poll<(), TokenError>
 ///
 /// In order to decide whether the sequence is complete, this function always
 /// needs at least one octet that does not meet `test`. It will return
-/// non-ready if it can’t.
-pub fn cats<O>(token: &mut Token, test: O) -> Poll<(), TokenError>
-            where O: Fn(u8) -> bool {
-    try_ready!(cat(token, |ch| test(ch)));
-    try_ready!(opt_cats(token, |ch| test(ch)));
+/// non-ready if it can’t unless the buffer is final, in which case the end
+/// of the input ends the sequence.
+pub fn cats<B, O>(token: &mut Token<B>, test: O) -> Poll<(), TokenError>
+            where B: Buffer, O: Fn(u8) -> bool {
+    try_ready!(cat(token, &test));
+    try_ready!(opt_cats(token, &test));
+    Ok(Async::Ready(()))
+}
+
+/// Advances over a non-empty sequence of digits that meet `test`.
+///
+/// This behaves like `cats()` but enforces the maximum number of digits
+/// of the buffer’s limits. It returns an error as soon as there are too
+/// many digits rather than waiting for the sequence to end.
+pub fn digit_cats<B, O>(token: &mut Token<B>, test: O)
+                        -> Poll<(), TokenError>
+                  where B: Buffer, O: Fn(u8) -> bool {
+    try_ready!(cat(token, &test));
+    let mut count = 1;
+    while try_ready!(token.advance_if(&test)) {
+        count += 1;
+        check_digits(token.buf, count)?;
+    }
     Ok(Async::Ready(()))
 }
 
@@ -242,15 +411,17 @@ pub fn cats<O>(token: &mut Token, test: O) -> Poll<(), TokenError>
 ///
 /// In order to decide whether the sequence is complete, this function always
 /// needs at least one octet that does not meet `test`. It will return
-/// non-ready if it can’t.
+/// non-ready if it can’t unless the buffer is final, in which case the end
+/// of the input ends the sequence.
 /// Upon success, returns whether the sequence was non-empty.
-pub fn opt_cats<O>(token: &mut Token, test: O) -> Poll<bool, TokenError>
-                where O: Fn(u8) -> bool {
-    if !try_ready!(token.advance_if(|ch| test(ch))) {
+pub fn opt_cats<B, O>(token: &mut Token<B>, test: O)
+                      -> Poll<bool, TokenError>
+                where B: Buffer, O: Fn(u8) -> bool {
+    if !try_ready!(token.advance_if(&test)) {
         return Ok(Async::Ready(false))
     }
     loop {
-        if !try_ready!(token.advance_if(|ch| test(ch))) {
+        if !try_ready!(token.advance_if(&test)) {
             return Ok(Async::Ready(true))
         }
     }
@@ -268,40 +439,65 @@ pub fn opt_cats<O>(token: &mut Token, test: O) -> Poll<bool, TokenError>
 /// token by as many octets as `lit` and return ready. Unlike `cat()` and
 /// friends, `literal()` will not wait for at least one more octet but
 /// succeed right away if it finds the literal.
-pub fn literal(token: &mut Token, lit: &[u8]) -> Poll<(), TokenError> {
-    use std::cmp::min;
-    use std::ascii::AsciiExt;
-
-    let litlen = {
-        let len = token.remaining().len();
-        let litlen = lit.len();
-        let minlen = min(len, litlen);
-        let reduced = &token.remaining()[..minlen];
-        let litreduced = &lit[..minlen];
-
-        if !reduced.eq_ignore_ascii_case(litreduced) {
-            return Err(TokenError)
-        }
-        else if minlen < litlen {
-            return Ok(Async::NotReady)
+pub fn literal<B: Buffer>(token: &mut Token<B>, lit: &[u8])
+                          -> Poll<(), TokenError> {
+    literal_with(token, lit, |ch, item| ch.eq_ignore_ascii_case(item))
+}
+
+/// Advances a token over a literal that has to match exactly.
+///
+/// This is the same as `literal()` except that case matters. It
+/// implements the case-sensitive strings introduced by RFC 7405.
+pub fn literal_exact<B: Buffer>(token: &mut Token<B>, lit: &[u8])
+                                -> Poll<(), TokenError> {
+    literal_with(token, lit, |ch, item| ch == *item)
+}
+
+fn literal_with<B, F>(token: &mut Token<B>, lit: &[u8], eq: F)
+                      -> Poll<(), TokenError>
+                where B: Buffer, F: Fn(u8, &u8) -> bool {
+    for (i, item) in lit.iter().enumerate() {
+        match token.peek(i) {
+            None if token.is_final() => return Err(TokenError::Invalid),
+            None => {
+                token.need(lit.len());
+                return Ok(Async::NotReady)
+            }
+            Some(ch) => {
+                if !eq(ch, item) {
+                    return Err(TokenError::Invalid)
+                }
+            }
         }
-        litlen
-    };
-    token.advance(litlen);
+    }
+    token.advance(lit.len());
     Ok(Async::Ready(()))
 }
 
 /// Parse a literal from a buffer.
-pub fn parse_literal(bytes: &mut BytesMut, lit: &[u8])
-                     -> Poll<Bytes, TokenError> {
+pub fn parse_literal<B: Buffer>(bytes: &mut B, lit: &[u8])
+                                -> Poll<Bytes, TokenError> {
     parse(bytes, |token| literal(token, lit))
 }
 
 /// Skip over a literal in a buffer.
-pub fn skip_literal(bytes: &mut BytesMut, lit: &[u8]) -> Poll<(), TokenError> {
+pub fn skip_literal<B: Buffer>(bytes: &mut B, lit: &[u8])
+                               -> Poll<(), TokenError> {
     skip(bytes, |token| literal(token, lit))
 }
 
+/// Parse a literal matching exactly from a buffer.
+pub fn parse_literal_exact<B: Buffer>(bytes: &mut B, lit: &[u8])
+                                      -> Poll<Bytes, TokenError> {
+    parse(bytes, |token| literal_exact(token, lit))
+}
+
+/// Skip over a literal matching exactly in a buffer.
+pub fn skip_literal_exact<B: Buffer>(bytes: &mut B, lit: &[u8])
+                                     -> Poll<(), TokenError> {
+    skip(bytes, |token| literal_exact(token, lit))
+}
+
 /// If the buffer starts with `lit`, return `res`.
 ///
 /// If there isn’t enough data to decide, returns non-ready. If the buffer
@@ -311,9 +507,8 @@ pub fn skip_literal(bytes: &mut BytesMut, lit: &[u8]) -> Poll<(), TokenError> {
 ///
 /// ```
 /// # #[macro_use] extern crate abnf;
-/// # extern crate bytes;
 /// # extern crate futures;
-/// # use bytes::BytesMut;
+/// # use abnf::parse::cursor::CursorBuf;
 /// # use futures::{Async, Poll};
 /// # use abnf::parse::token::translate_literal;
 /// enum Command {
@@ -323,15 +518,16 @@ pub fn skip_literal(bytes: &mut BytesMut, lit: &[u8]) -> Poll<(), TokenError> {
 ///
 /// struct CommandError;
 ///
-/// fn parse_command(bytes: &mut BytesMut) -> Poll<Command, CommandError> {
+/// fn parse_command(bytes: &mut CursorBuf) -> Poll<Command, CommandError> {
 ///     try_opt!(translate_literal(bytes, b"echo", Command::Echo));
 ///     try_opt!(translate_literal(bytes, b"quit", Command::Quit));
 ///     Err(CommandError)
 /// }
 /// # fn main() { }
 /// ```
-pub fn translate_literal<T, E>(bytes: &mut BytesMut, lit: &[u8], res: T)
-                            -> Poll<Option<T>, E> {
+pub fn translate_literal<B, T, E>(bytes: &mut B, lit: &[u8], res: T)
+                                  -> Poll<Option<T>, E>
+                         where B: Buffer {
     match skip_literal(bytes, lit) {
         Ok(Async::NotReady) => Ok(Async::NotReady),
         Ok(Async::Ready(())) => Ok(Async::Ready(Some(res))),
@@ -340,9 +536,105 @@ pub fn translate_literal<T, E>(bytes: &mut BytesMut, lit: &[u8], res: T)
 }
 
 
+/// An entry in a keyword table for `parse_keyword()`.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyword<T> {
+    /// The spelling of the keyword.
+    pub literal: &'static [u8],
+
+    /// The value to return if the keyword matches.
+    pub value: T,
+
+    /// Whether the keyword has to match exactly rather than ignoring case.
+    pub case_sensitive: bool,
+}
+
+/// Parses the longest of the keywords in `table` from a buffer.
+///
+/// If the buffer starts with one or more of the keywords, the longest of
+/// them is drained from the buffer and its value returned. Since a longer
+/// keyword may still match once more data arrives, this returns non-ready
+/// if the data in the buffer is a prefix of any of the keywords unless the
+/// buffer is final. If none of the keywords match, returns an error.
+///
+/// This is a faster and more convenient alternative to a chain of
+/// `translate_literal()` calls.
+pub fn parse_keyword<B, T>(bytes: &mut B, table: &[Keyword<T>])
+                           -> Poll<T, TokenError>
+                     where B: Buffer, T: Clone {
+    let mut best: Option<&Keyword<T>> = None;
+    for keyword in table {
+        let mut matched = true;
+        for (i, &ch) in keyword.literal.iter().enumerate() {
+            let matches = match bytes.get(i) {
+                Some(data) if keyword.case_sensitive => data == ch,
+                Some(data) => data.eq_ignore_ascii_case(&ch),
+                None if bytes.is_final() => false,
+                None => return Ok(Async::NotReady)
+            };
+            if !matches {
+                matched = false;
+                break
+            }
+        }
+        if matched && best.map(|best| {
+            best.literal.len() < keyword.literal.len()
+        }).unwrap_or(true) {
+            best = Some(keyword)
+        }
+    }
+    match best {
+        Some(keyword) => {
+            bytes.advance(keyword.literal.len());
+            Ok(Async::Ready(keyword.value.clone()))
+        }
+        None => Err(TokenError::Invalid)
+    }
+}
+
+
 //============ Errors ========================================================
 
 /// An error happend while parsing a token.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct TokenError;
+pub enum TokenError {
+    /// The data doesn’t match the rule.
+    Invalid,
+
+    /// One of the limits set for parsing was exceeded.
+    ///
+    /// See the [`limits`] module for details.
+    ///
+    /// [`limits`]: ../limits/index.html
+    LimitExceeded,
 
+    /// A line was longer than allowed.
+    ///
+    /// This is returned by the length-limited line rules in `core`.
+    LineTooLong,
+}
+
+
+//------------ MergeError ----------------------------------------------------
+
+/// An error that can be combined with the error of an alternative.
+///
+/// If all alternatives of `alt!()` fail, their errors are merged via this
+/// trait into the single error reported for the whole rule.
+pub trait MergeError {
+    /// Merges `self` with the error of a later alternative.
+    fn merge(self, other: Self) -> Self;
+}
+
+impl MergeError for TokenError {
+    /// Keeps the first error that isn’t `TokenError::Invalid`.
+    ///
+    /// An alternative that hit a limit or an overlong line is more telling
+    /// than one that simply didn’t match.
+    fn merge(self, other: Self) -> Self {
+        match self {
+            TokenError::Invalid => other,
+            _ => self
+        }
+    }
+}
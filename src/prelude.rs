@@ -0,0 +1,50 @@
+//! The prelude.
+//!
+//! This module re-exports the things most commonly used when writing
+//! parsers with this crate: the buffer types of the `bytes` crate and
+//! `CursorBuf`, the buffer used by the adapters, the `Poll` type and its
+//! friends, the macros, the character class tests from the `core`
+//! module, and the basic combinators. Import it all with
+//!
+//! ```
+//! use abnf::prelude::*;
+//! ```
+//!
+//! and you don’t need to depend on `bytes` and `futures` yourself just to
+//! write a simple rule:
+//!
+//! ```
+//! extern crate abnf;
+//!
+//! use abnf::prelude::*;
+//! use abnf::core::u8_digits;
+//! use abnf::parse::token;
+//!
+//! fn pair<B: Buffer>(buf: &mut B) -> Poll<(u8, u8), TokenError> {
+//!     let left = try_ready!(u8_digits(buf));
+//!     try_ready!(token::skip_octet(buf, b'/'));
+//!     let right = try_ready!(u8_digits(buf));
+//!     Ok(Async::Ready((left, right)))
+//! }
+//!
+//! # fn main() {
+//! assert_eq_ready!(pair(&mut CursorBuf::from(&b"4/5 "[..])), (4, 5));
+//! # }
+//! ```
+
+pub use bytes::{Bytes, BytesMut};
+pub use futures::{Async, Poll};
+
+pub use ::{alt, assert_consumed, assert_eq_ready, assert_not_ready,
+           assert_parse_err, repeat_abnf, rule, seq, try_fail, try_opt,
+           try_ready, try_result};
+
+pub use ::core::{test_alpha, test_bit, test_char, test_cr, test_ctl,
+                 test_digit, test_dquote, test_hexdig, test_htab, test_lf,
+                 test_sp, test_vchar, test_wsp};
+pub use ::parse::buffer::Buffer;
+pub use ::parse::cursor::CursorBuf;
+pub use ::parse::parser::{BoxedParser, Parser};
+pub use ::parse::rule::{group, opt_group, optional};
+pub use ::parse::step::{PollOptionStep, PollStep, Step, Stop};
+pub use ::parse::token::{MergeError, Token, TokenError};
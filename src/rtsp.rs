@@ -0,0 +1,778 @@
+//! RTSP start lines and header fields.
+//!
+//! This module implements parts of the syntax of the Real-Time Streaming
+//! Protocol as defined in RFC 7826: the request and status lines and the
+//! values of the `CSeq`, `Transport`, and `Session` header fields. The
+//! transport parameters of RTSP 1.0 defined in RFC 2326 are understood,
+//! too, since they are still common.
+//!
+//! RTSP reuses the HTTP header syntax, so the rules of the [`http`] module
+//! apply to all other header fields.
+//!
+//! [`http`]: ../http/index.html
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use bytes::Bytes;
+use futures::{Async, Poll};
+use ::compose::write_octets;
+use ::core::{digit, parse_line_content, skip_crlf, skip_dquote,
+             skip_opt_wsps, test_digit, test_vchar, u16_digits,
+             u16_digits_exact, u32_hexdigs, u64_digits, u8_digits};
+use ::http::{parse_list, parse_token, parse_token_or_quoted,
+             write_token_or_quoted};
+use ::ipaddr::{parse_ipv4_addr, parse_ipv6_addr};
+use ::octets::{URI_SUB_DELIMS, URI_UNRESERVED};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::TokenError;
+#[cfg(feature = "arbitrary")] use arbitrary::Arbitrary;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+
+//------------ Start Lines ---------------------------------------------------
+
+/// The request line of an RTSP request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RequestLine {
+    /// The method.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub method: Bytes,
+
+    /// The request URI.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub uri: Bytes,
+
+    /// The major and minor protocol version.
+    pub version: (u8, u8),
+}
+
+/// The status line of an RTSP response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StatusLine {
+    /// The major and minor protocol version.
+    pub version: (u8, u8),
+
+    /// The status code.
+    pub code: u16,
+
+    /// The reason phrase.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub reason: Bytes,
+}
+
+impl fmt::Display for RequestLine {
+    /// Formats the request line without the final CRLF.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.method)?;
+        f.write_str(" ")?;
+        write_octets(f, &self.uri)?;
+        write!(f, " RTSP/{}.{}", self.version.0, self.version.1)
+    }
+}
+
+impl fmt::Display for StatusLine {
+    /// Formats the status line without the final CRLF.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RTSP/{}.{} {:03} ", self.version.0, self.version.1,
+               self.code)?;
+        write_octets(f, &self.reason)
+    }
+}
+
+/// Parses a request line including the final CRLF.
+//
+//  Request-Line = Method SP Request-URI SP RTSP-Version CRLF
+pub fn parse_request_line<B: Buffer>(buf: &mut B)
+                                     -> Poll<RequestLine, TokenError> {
+    seq!(buf, parse_token, token::skip_octet(b' '), parse_uri,
+         token::skip_octet(b' '), parse_version, skip_crlf)
+    .map(|res| res.map(|(method, _, uri, _, version, _)| {
+        RequestLine { method, uri, version }
+    }))
+}
+
+/// Parses a status line including the final CRLF.
+//
+//  Status-Line = RTSP-Version SP Status-Code SP Reason-Phrase CRLF
+pub fn parse_status_line<B: Buffer>(buf: &mut B)
+                                    -> Poll<StatusLine, TokenError> {
+    seq!(buf, parse_version, token::skip_octet(b' '), parse_status_code,
+         token::skip_octet(b' '), parse_line_content)
+    .map(|res| res.map(|(version, _, code, _, reason)| {
+        StatusLine { version, code, reason }
+    }))
+}
+
+//  RTSP-Version = "RTSP/" 1*DIGIT "." 1*DIGIT
+fn parse_version<B: Buffer>(buf: &mut B) -> Poll<(u8, u8), TokenError> {
+    seq!(buf, token::skip_literal(b"RTSP/"), u8_digits,
+         token::skip_octet(b'.'), u8_digits)
+    .map(|res| res.map(|(_, major, _, minor)| (major, minor)))
+}
+
+//  Status-Code = 3DIGIT
+fn parse_status_code<B: Buffer>(buf: &mut B) -> Poll<u16, TokenError> {
+    u16_digits_exact(buf, 3)
+}
+
+/// Parses the request URI as a run of visible characters.
+fn parse_uri<B: Buffer>(buf: &mut B) -> Poll<Bytes, TokenError> {
+    token::parse(buf, |token| token::cats(token, test_vchar))
+}
+
+
+//------------ CSeq ----------------------------------------------------------
+
+/// Parses the value of the `CSeq` header field.
+//
+//  CSeq = cseq-nr
+//  cseq-nr = 1*9DIGIT
+pub fn parse_cseq<B: Buffer>(buf: &mut B) -> Poll<u32, TokenError> {
+    token::convert(buf, |token| {
+        try_ready!(token::repeat(token, 1, Some(9), digit));
+        if token.at_end() {
+            return Ok(Async::Ready(()))
+        }
+        match try_ready!(token.first()) {
+            ch if test_digit(ch) => Err(TokenError::Invalid),
+            _ => Ok(Async::Ready(()))
+        }
+    }, |digits| {
+        Ok(digits?.iter().fold(0, |res, &ch| res * 10 + u32::from(ch - b'0')))
+    })
+}
+
+
+//------------ Transport -----------------------------------------------------
+
+/// A transport specification of the `Transport` header field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transport {
+    /// The transport protocol, such as `RTP`.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub protocol: Bytes,
+
+    /// The profile, such as `AVP`.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub profile: Bytes,
+
+    /// The lower transport, such as `UDP` or `TCP`, if given.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_opt_bytes))]
+    pub lower: Option<Bytes>,
+
+    /// The parameters in the order they appeared.
+    pub params: Vec<TransportParam>,
+}
+
+/// A parameter of a transport specification.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TransportParam {
+    /// `unicast`
+    Unicast,
+
+    /// `multicast`
+    Multicast,
+
+    /// `interleaved`, the first and optional last channel.
+    Interleaved(u8, Option<u8>),
+
+    /// `ttl`
+    Ttl(u8),
+
+    /// `ssrc`, one or more synchronization sources.
+    Ssrc(Vec<u32>),
+
+    /// `client_port` of RFC 2326, the first and optional last port.
+    ClientPort(u16, Option<u16>),
+
+    /// `server_port` of RFC 2326, the first and optional last port.
+    ServerPort(u16, Option<u16>),
+
+    /// `dest_addr`, a list of addresses.
+    DestAddr(Vec<TransportAddr>),
+
+    /// `src_addr`, a list of addresses.
+    SrcAddr(Vec<TransportAddr>),
+
+    /// `mode`
+    Mode(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+
+    /// Any other parameter with its optional value.
+    Other(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes,
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_opt_bytes))]
+        Option<Bytes>
+    ),
+}
+
+/// An address of the `dest_addr` and `src_addr` transport parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransportAddr {
+    /// The host.
+    pub host: Host,
+
+    /// The port if given.
+    pub port: Option<u16>,
+}
+
+/// A host as defined by RFC 3986.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Host {
+    /// An IPv4 address.
+    Ipv4(Ipv4Addr),
+
+    /// An IPv6 address given in square brackets.
+    Ipv6(Ipv6Addr),
+
+    /// A registered name, without percent-decoding applied.
+    Name(
+        #[cfg_attr(feature = "arbitrary",
+                   arbitrary(with = ::fuzz::arbitrary_bytes))]
+        Bytes
+    ),
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.protocol)?;
+        f.write_str("/")?;
+        write_octets(f, &self.profile)?;
+        if let Some(ref lower) = self.lower {
+            f.write_str("/")?;
+            write_octets(f, lower)?;
+        }
+        for param in &self.params {
+            write!(f, ";{}", param)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TransportParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransportParam::Unicast => f.write_str("unicast"),
+            TransportParam::Multicast => f.write_str("multicast"),
+            TransportParam::Interleaved(first, last) => {
+                write_range(f, "interleaved", first, last)
+            }
+            TransportParam::Ttl(ttl) => write!(f, "ttl={}", ttl),
+            TransportParam::Ssrc(ref ssrc) => {
+                f.write_str("ssrc=")?;
+                write_slash_list(f, ssrc, |f, ssrc| write!(f, "{:08X}", ssrc))
+            }
+            TransportParam::ClientPort(first, last) => {
+                write_range(f, "client_port", first, last)
+            }
+            TransportParam::ServerPort(first, last) => {
+                write_range(f, "server_port", first, last)
+            }
+            TransportParam::DestAddr(ref addrs) => {
+                f.write_str("dest_addr=")?;
+                write_slash_list(f, addrs, |f, addr| addr.fmt(f))
+            }
+            TransportParam::SrcAddr(ref addrs) => {
+                f.write_str("src_addr=")?;
+                write_slash_list(f, addrs, |f, addr| addr.fmt(f))
+            }
+            TransportParam::Mode(ref mode) => {
+                f.write_str("mode=")?;
+                write_token_or_quoted(f, mode)
+            }
+            TransportParam::Other(ref name, ref value) => {
+                write_octets(f, name)?;
+                if let Some(ref value) = *value {
+                    f.write_str("=")?;
+                    write_token_or_quoted(f, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TransportAddr {
+    /// Formats the address including the surrounding double quotes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        f.write_str("\"")
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Host::Ipv4(addr) => addr.fmt(f),
+            Host::Ipv6(addr) => write!(f, "[{}]", addr),
+            Host::Name(ref name) => write_octets(f, name),
+        }
+    }
+}
+
+/// Writes a parameter with a value and an optional second value.
+fn write_range<T: fmt::Display>(f: &mut fmt::Formatter, name: &str,
+                                first: T, last: Option<T>) -> fmt::Result {
+    write!(f, "{}={}", name, first)?;
+    if let Some(last) = last {
+        write!(f, "-{}", last)?;
+    }
+    Ok(())
+}
+
+/// Writes a list of values separated by slashes.
+fn write_slash_list<T, F>(f: &mut fmt::Formatter, items: &[T], mut write: F)
+                          -> fmt::Result
+                    where F: FnMut(&mut fmt::Formatter, &T) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str("/")?;
+        }
+        write(f, item)?;
+    }
+    Ok(())
+}
+
+/// Parses the value of the `Transport` header field.
+///
+/// The value is a non-empty, comma-separated list of transport
+/// specifications.
+pub fn parse_transports<B: Buffer>(buf: &mut B)
+                                   -> Poll<Vec<Transport>, TokenError> {
+    parse_list(buf, 1, parse_transport)
+}
+
+//  transport-spec = transport-id *parameter
+//  transport-id   = trans-id-prot SLASH profile [SLASH lower-transport]
+//  parameter      = SEMI trn-param
+fn parse_transport<B: Buffer>(buf: &mut B) -> Poll<Transport, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let protocol = try_ready!(parse_token(buf));
+        try_ready!(token::skip_octet(buf, b'/'));
+        let profile = try_ready!(parse_token(buf));
+        let lower = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(token::skip_octet(buf, b'/'));
+                parse_token(buf)
+            })
+        }));
+        let mut params = Vec::new();
+        while let Some(param) = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_semi(buf));
+                parse_transport_param(buf)
+            })
+        })) {
+            params.push(param)
+        }
+        Ok(Async::Ready(Transport { protocol, profile, lower, params }))
+    })
+}
+
+fn parse_transport_param<B: Buffer>(buf: &mut B)
+                                    -> Poll<TransportParam, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_token(buf));
+        if !try_ready!(token::skip_opt_octet(buf, b'=')) {
+            return Ok(Async::Ready(match &name.to_ascii_lowercase()[..] {
+                b"unicast" => TransportParam::Unicast,
+                b"multicast" => TransportParam::Multicast,
+                _ => TransportParam::Other(name, None)
+            }))
+        }
+        let res = match &name.to_ascii_lowercase()[..] {
+            b"interleaved" => {
+                let (first, last) = try_ready!(parse_range(buf, u8_digits));
+                TransportParam::Interleaved(first, last)
+            }
+            b"ttl" => TransportParam::Ttl(try_ready!(u8_digits(buf))),
+            b"ssrc" => {
+                TransportParam::Ssrc(
+                    try_ready!(parse_slash_list(buf, u32_hexdigs))
+                )
+            }
+            b"client_port" => {
+                let (first, last) = try_ready!(parse_range(buf, u16_digits));
+                TransportParam::ClientPort(first, last)
+            }
+            b"server_port" => {
+                let (first, last) = try_ready!(parse_range(buf, u16_digits));
+                TransportParam::ServerPort(first, last)
+            }
+            b"dest_addr" => {
+                TransportParam::DestAddr(
+                    try_ready!(parse_slash_list(buf, parse_transport_addr))
+                )
+            }
+            b"src_addr" => {
+                TransportParam::SrcAddr(
+                    try_ready!(parse_slash_list(buf, parse_transport_addr))
+                )
+            }
+            b"mode" => TransportParam::Mode(
+                try_ready!(parse_token_or_quoted(buf))
+            ),
+            _ => TransportParam::Other(
+                name, Some(try_ready!(parse_token_or_quoted(buf)))
+            )
+        };
+        Ok(Async::Ready(res))
+    })
+}
+
+/// Skips over a semicolon surrounded by optional white space.
+//
+//  SEMI = SWS ";" SWS
+fn skip_semi<B: Buffer>(buf: &mut B) -> Poll<(), TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(skip_opt_wsps(buf));
+        try_ready!(token::skip_octet(buf, b';'));
+        try_ready!(skip_opt_wsps(buf));
+        Ok(Async::Ready(()))
+    })
+}
+
+/// Parses a value with an optional second value following a hyphen.
+fn parse_range<B, P, T>(buf: &mut B, mut parse: P)
+                        -> Poll<(T, Option<T>), TokenError>
+               where B: Buffer, P: FnMut(&mut B) -> Poll<T, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let first = try_ready!(parse(buf));
+        let last = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(token::skip_octet(buf, b'-'));
+                parse(buf)
+            })
+        }));
+        Ok(Async::Ready((first, last)))
+    })
+}
+
+/// Parses a non-empty list of values separated by slashes.
+fn parse_slash_list<B, P, T>(buf: &mut B, mut parse: P)
+                             -> Poll<Vec<T>, TokenError>
+                    where B: Buffer,
+                          P: FnMut(&mut B) -> Poll<T, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let mut res = vec![try_ready!(parse(buf))];
+        while let Some(item) = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(token::skip_octet(buf, b'/'));
+                parse(buf)
+            })
+        })) {
+            res.push(item)
+        }
+        Ok(Async::Ready(res))
+    })
+}
+
+//  addr = DQUOTE ( host [":" port] / ":" port ) DQUOTE
+//
+//  The variant with only a port is not supported.
+fn parse_transport_addr<B: Buffer>(buf: &mut B)
+                                   -> Poll<TransportAddr, TokenError> {
+    rule::group_limited(buf, |buf| {
+        try_ready!(skip_dquote(buf));
+        let host = try_ready!(parse_host(buf));
+        let port = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(token::skip_octet(buf, b':'));
+                u16_digits(buf)
+            })
+        }));
+        try_ready!(skip_dquote(buf));
+        Ok(Async::Ready(TransportAddr { host, port }))
+    })
+}
+
+/// Parses a host.
+//
+//  host = IP-literal / IPv4address / reg-name
+pub fn parse_host<B: Buffer>(buf: &mut B) -> Poll<Host, TokenError> {
+    alt!(buf, host_ipv6, host_ipv4, host_name)
+}
+
+fn host_ipv6<B: Buffer>(buf: &mut B) -> Poll<Host, TokenError> {
+    seq!(buf, token::skip_octet(b'['), parse_ipv6_addr,
+         token::skip_octet(b']'))
+    .map(|res| res.map(|(_, addr, _)| Host::Ipv6(addr)))
+}
+
+fn host_ipv4<B: Buffer>(buf: &mut B) -> Poll<Host, TokenError> {
+    let addr = try_ready!(parse_ipv4_addr(buf));
+    // An IPv4 address followed by more name characters is a name, so
+    // we need to peek. The address has been drained already which is
+    // fine since alt!() rewinds us on error.
+    match buf.get(0) {
+        None if buf.is_final() => Ok(Async::Ready(Host::Ipv4(addr))),
+        None => Ok(Async::NotReady),
+        Some(ch) if test_reg_name(ch) => Err(TokenError::Invalid),
+        Some(_) => Ok(Async::Ready(Host::Ipv4(addr)))
+    }
+}
+
+fn host_name<B: Buffer>(buf: &mut B) -> Poll<Host, TokenError> {
+    token::parse(buf, |token| token::cats(token, test_reg_name))
+        .map(|res| res.map(Host::Name))
+}
+
+/// Returns whether `ch` can appear in a `reg-name` of RFC 3986.
+fn test_reg_name(ch: u8) -> bool {
+    URI_UNRESERVED.contains(ch) || URI_SUB_DELIMS.contains(ch) || ch == b'%'
+}
+
+
+//------------ Session -------------------------------------------------------
+
+/// The value of the `Session` header field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Session {
+    /// The session identifier.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub id: Bytes,
+
+    /// The timeout in seconds if given.
+    pub timeout: Option<u64>,
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.id)?;
+        if let Some(timeout) = self.timeout {
+            write!(f, ";timeout={}", timeout)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the value of the `Session` header field.
+//
+//  Session    = session-id [ SEMI "timeout" EQUAL delta-seconds ]
+//  session-id = 1*256( ALPHA / DIGIT / safe )
+//  safe       = "$" / "-" / "_" / "." / "+"
+pub fn parse_session<B: Buffer>(buf: &mut B) -> Poll<Session, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let id = try_ready!(token::parse(buf, |token| {
+            token::repeat(token, 1, Some(256), |token| {
+                token::cat(token, test_session_id)
+            })
+        }));
+        let timeout = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_semi(buf));
+                try_ready!(token::skip_literal(buf, b"timeout"));
+                try_ready!(skip_opt_wsps(buf));
+                try_ready!(token::skip_octet(buf, b'='));
+                try_ready!(skip_opt_wsps(buf));
+                u64_digits(buf)
+            })
+        }));
+        Ok(Async::Ready(Session { id, timeout }))
+    })
+}
+
+/// Returns whether `ch` can appear in a session identifier.
+fn test_session_id(ch: u8) -> bool {
+    ch.is_ascii_alphanumeric() || b"$-_.+".contains(&ch)
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::parse::sync;
+    use ::test_support::{assert_canonical, assert_roundtrip,
+                         compose_display};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    fn bytes(slice: &[u8]) -> Bytes { Bytes::copy_from_slice(slice) }
+
+    #[test]
+    fn start_lines() {
+        assert_eq!(parse_request_line(&mut buf(
+                       b"PLAY rtsp://example.com/media RTSP/2.0\r\n"
+                   )),
+                   Ok(Async::Ready(RequestLine {
+                       method: bytes(b"PLAY"),
+                       uri: bytes(b"rtsp://example.com/media"),
+                       version: (2, 0)
+                   })));
+        assert_eq!(parse_status_line(&mut buf(
+                       b"RTSP/1.0 454 Session Not Found\r\nCSeq"
+                   )),
+                   Ok(Async::Ready(StatusLine {
+                       version: (1, 0), code: 454,
+                       reason: bytes(b"Session Not Found")
+                   })));
+        assert_eq!(parse_status_line(&mut buf(b"RTSP/1.0 20 OK\r\n")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn cseq() {
+        assert_eq!(parse_cseq(&mut buf(b"302\r\n")), Ok(Async::Ready(302)));
+        assert_eq!(parse_cseq(&mut buf(b"1234567890\r\n")),
+                   Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn transports() {
+        let mut data = buf(
+            b"RTP/AVP;unicast;client_port=4588-4589;ttl=16, \
+              RTP/AVP/TCP;interleaved=0-1;ssrc=0A13C760/1b2c3d4e;\
+              dest_addr=\"192.0.2.5:4588\"/\"[2001:db8::1]\";\
+              src_addr=\"media.example.com\";mode=\"PLAY\";x-foo\r\n"
+        );
+        assert_eq!(parse_transports(&mut data), Ok(Async::Ready(vec![
+            Transport {
+                protocol: bytes(b"RTP"), profile: bytes(b"AVP"),
+                lower: None,
+                params: vec![
+                    TransportParam::Unicast,
+                    TransportParam::ClientPort(4588, Some(4589)),
+                    TransportParam::Ttl(16),
+                ]
+            },
+            Transport {
+                protocol: bytes(b"RTP"), profile: bytes(b"AVP"),
+                lower: Some(bytes(b"TCP")),
+                params: vec![
+                    TransportParam::Interleaved(0, Some(1)),
+                    TransportParam::Ssrc(vec![0x0A13_C760, 0x1b2c_3d4e]),
+                    TransportParam::DestAddr(vec![
+                        TransportAddr {
+                            host: Host::Ipv4(Ipv4Addr::new(192, 0, 2, 5)),
+                            port: Some(4588)
+                        },
+                        TransportAddr {
+                            host: Host::Ipv6("2001:db8::1".parse().unwrap()),
+                            port: None
+                        },
+                    ]),
+                    TransportParam::SrcAddr(vec![TransportAddr {
+                        host: Host::Name(bytes(b"media.example.com")),
+                        port: None
+                    }]),
+                    TransportParam::Mode(bytes(b"PLAY")),
+                    TransportParam::Other(bytes(b"x-foo"), None),
+                ]
+            }
+        ])));
+        assert_eq!(&data[..], b"\r\n");
+
+        // An invalid parameter ends the specification.
+        let mut data = buf(b"RTP/AVP;ttl=x\r\n");
+        assert!(parse_transports(&mut data).is_ok());
+        assert_eq!(&data[..], b";ttl=x\r\n");
+    }
+
+    #[test]
+    fn hosts() {
+        assert_eq!(sync::parse(b"192.0.2.1", parse_host),
+                   Ok((Host::Ipv4(Ipv4Addr::new(192, 0, 2, 1)), 9)));
+        assert_eq!(sync::parse(b"192.0.2.1a", parse_host),
+                   Ok((Host::Name(bytes(b"192.0.2.1a")), 10)));
+        assert_eq!(sync::parse(b"[2001:db8::1]", parse_host),
+                   Ok((Host::Ipv6("2001:db8::1".parse().unwrap()), 13)));
+        assert_eq!(parse_host(&mut buf(b"192.0.2.1")),
+                   Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn display() {
+        fn line<T: fmt::Display>(value: &T, target: &mut BytesMut) {
+            compose_display(value, target);
+            target.extend_from_slice(b"\r\n")
+        }
+
+        let transports = |value: &Vec<Transport>, target: &mut BytesMut| {
+            for (i, transport) in value.iter().enumerate() {
+                if i > 0 {
+                    target.extend_from_slice(b", ")
+                }
+                compose_display(transport, target)
+            }
+        };
+
+        assert_canonical(b"PLAY rtsp://example.com/media RTSP/2.0\r\n",
+                         parse_request_line, line, b"");
+        assert_canonical(b"RTSP/1.0 454 Session Not Found\r\n",
+                         parse_status_line, line, b"");
+        assert_roundtrip(&StatusLine {
+                             version: (2, 0), code: 99, reason: bytes(b"")
+                         },
+                         line, parse_status_line, b"");
+        assert_canonical(
+            b"RTP/AVP/TCP;unicast;interleaved=0-1;ttl=16;\
+              ssrc=0A13C760/1B2C3D4E;client_port=4588;server_port=6256-6257;\
+              dest_addr=\"192.0.2.5:4588\"/\"[2001:db8::1]\";\
+              src_addr=\"media.example.com\";mode=PLAY;x-foo;x-bar=\"a b\", \
+              RTP/AVP;multicast",
+            parse_transports, transports, b"\r\n"
+        );
+        assert_roundtrip(&vec![Transport {
+                             protocol: bytes(b"RTP"), profile: bytes(b"AVP"),
+                             lower: None,
+                             params: vec![
+                                 TransportParam::Mode(bytes(b"\"RECORD\"")),
+                                 TransportParam::Other(
+                                     bytes(b"x"), Some(bytes(b""))
+                                 ),
+                             ]
+                         }],
+                         transports, parse_transports, b"\r\n");
+        for host in &[&b"192.0.2.1"[..], b"[2001:db8::1]", b"example.com"] {
+            assert_canonical(host, parse_host, compose_display, b"\"");
+        }
+        assert_canonical(b"12345678;timeout=60", parse_session,
+                         compose_display, b"\r\n");
+    }
+
+    #[test]
+    fn session() {
+        assert_eq!(parse_session(&mut buf(b"12345678;timeout=60\r\n")),
+                   Ok(Async::Ready(Session {
+                       id: bytes(b"12345678"), timeout: Some(60)
+                   })));
+        assert_eq!(parse_session(&mut buf(b"abc.$x\r\n")),
+                   Ok(Async::Ready(Session {
+                       id: bytes(b"abc.$x"), timeout: None
+                   })));
+    }
+}
@@ -0,0 +1,165 @@
+//! Property testing with data generated from sets of octets.
+//!
+//! This module is only available with the `proptest` feature. It provides
+//! strategies for the `proptest` crate that produce octets matching an
+//! [`OctetSet`] as well as near misses, sequences of matching octets with
+//! a single octet outside the set mixed in. The test functions of the
+//! `core` module are turned into sets via `OctetSet::from_test()`. A
+//! property test can then check that a parser accepts all the former and
+//! rejects all the latter:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn rejects_non_digits(
+//!         data in near_miss(&OctetSet::from_test(test_digit), 1..8)
+//!                     .unwrap()
+//!     ) {
+//!         prop_assert!(u64_digits_exact(&mut &data[..], data.len())
+//!                          .is_err());
+//!     }
+//! }
+//! ```
+//!
+//! Strategies producing data for the rules of a grammar are provided by
+//! the [`grammar::strategy`] module.
+//!
+//! [`OctetSet`]: ../octets/struct.OctetSet.html
+//! [`grammar::strategy`]: ../grammar/strategy/index.html
+
+use proptest::collection::{self, SizeRange, VecStrategy};
+use proptest::sample::{self, Index, Select};
+use proptest::strategy::{BoxedStrategy, Strategy};
+use ::octets::OctetSet;
+
+
+//------------ Single Octets -------------------------------------------------
+
+/// Returns a strategy producing an octet contained in `set`.
+///
+/// Returns `None` if the set is empty.
+pub fn octet(set: &OctetSet) -> Option<Select<u8>> {
+    let octets: Vec<u8> = set.iter().collect();
+    if octets.is_empty() {
+        None
+    }
+    else {
+        Some(sample::select(octets))
+    }
+}
+
+/// Returns a strategy producing an octet not contained in `set`.
+///
+/// Returns `None` if the set contains all octets.
+pub fn other_octet(set: &OctetSet) -> Option<Select<u8>> {
+    let octets: Vec<u8> = (0..=255u8).filter(|&ch| {
+        !set.contains(ch)
+    }).collect();
+    if octets.is_empty() {
+        None
+    }
+    else {
+        Some(sample::select(octets))
+    }
+}
+
+
+//------------ Sequences -----------------------------------------------------
+
+/// Returns a strategy producing a sequence of octets contained in `set`.
+///
+/// The length of the sequence is drawn from `size`. Returns `None` if the
+/// set is empty.
+pub fn octets<S: Into<SizeRange>>(set: &OctetSet, size: S)
+                                  -> Option<VecStrategy<Select<u8>>> {
+    octet(set).map(|octet| collection::vec(octet, size))
+}
+
+/// Returns a strategy producing a near miss of a sequence of `set`.
+///
+/// A near miss is a sequence of octets contained in `set` with its length
+/// drawn from `size` into which one octet not contained in `set` has been
+/// inserted at a random position. It is thus one octet longer than the
+/// drawn length. Returns `None` if the set is empty or contains all
+/// octets.
+pub fn near_miss<S: Into<SizeRange>>(set: &OctetSet, size: S)
+                                     -> Option<BoxedStrategy<Vec<u8>>> {
+    let octets = octets(set, size)?;
+    let other = other_octet(set)?;
+    Some((octets, other, proptest::arbitrary::any::<Index>()).prop_map(
+        |(mut octets, other, index)| {
+            let pos = index.index(octets.len() + 1);
+            octets.insert(pos, other);
+            octets
+        }
+    ).boxed())
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use proptest::test_runner::{TestCaseError, TestRunner};
+    use ::core::{test_digit, test_vchar, u64_digits_exact};
+    use ::octets::URI_UNRESERVED;
+    use super::*;
+
+    #[test]
+    fn matching() {
+        let set = OctetSet::from_test(test_vchar);
+        TestRunner::default().run(&octets(&set, 0..16).unwrap(), |data| {
+            if !data.iter().all(|&ch| set.contains(ch)) {
+                return Err(TestCaseError::fail("octet outside the set"))
+            }
+            Ok(())
+        }).unwrap();
+        TestRunner::default().run(&other_octet(&set).unwrap(), |ch| {
+            if set.contains(ch) {
+                return Err(TestCaseError::fail("octet inside the set"))
+            }
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn near_misses() {
+        let strategy = near_miss(&URI_UNRESERVED, 0..16).unwrap();
+        TestRunner::default().run(&strategy, |data| {
+            let misses = data.iter().filter(|&&ch| {
+                !URI_UNRESERVED.contains(ch)
+            }).count();
+            if misses != 1 {
+                return Err(TestCaseError::fail("not exactly one miss"))
+            }
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn digits() {
+        let digits = OctetSet::from_test(test_digit);
+        TestRunner::default().run(&octets(&digits, 1..16).unwrap(), |data| {
+            if u64_digits_exact(&mut &data[..], data.len()).is_err() {
+                return Err(TestCaseError::fail("digits rejected"))
+            }
+            Ok(())
+        }).unwrap();
+        TestRunner::default().run(&near_miss(&digits, 0..16).unwrap(),
+                                  |data| {
+            if u64_digits_exact(&mut &data[..], data.len()).is_ok() {
+                return Err(TestCaseError::fail("near miss accepted"))
+            }
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn impossible() {
+        let all = OctetSet::from_range(0, 255);
+        assert!(octet(&OctetSet::new()).is_none());
+        assert!(other_octet(&all).is_none());
+        assert!(near_miss(&all, 1..2).is_none());
+        assert!(near_miss(&OctetSet::new(), 1..2).is_none());
+    }
+}
@@ -0,0 +1,491 @@
+//! Support for testing parsers.
+//!
+//! The functions in this module help with testing parsers and composers
+//! against each other. They panic with a hopefully helpful message if a
+//! check fails and are thus meant to be used in tests, both in this crate
+//! and downstream.
+//!
+//! Since many rules can only decide that they are complete once they see
+//! the octet following them, all functions take a `suffix` that is
+//! appended after the composed or sample data. It needs to be left in the
+//! buffer by the parser.
+
+use std::fmt;
+use bytes::BytesMut;
+use futures::{Async, Poll};
+use ::parse::buffer::Buffer;
+
+
+//------------ Round Trips ---------------------------------------------------
+
+/// Asserts that composing `value` and parsing it again results in `value`.
+///
+/// The closure `compose` appends the value to the buffer. The closure
+/// `parse` is then applied to the composed data followed by `suffix`. It
+/// has to return ready with a value equal to `value` and leave exactly
+/// `suffix` in the buffer.
+pub fn assert_roundtrip<T, C, P, E>(value: &T, compose: C, parse: P,
+                                    suffix: &[u8])
+                        where T: fmt::Debug + PartialEq,
+                              C: FnOnce(&T, &mut BytesMut),
+                              P: FnOnce(&mut BytesMut) -> Poll<T, E>,
+                              E: fmt::Debug {
+    let mut buf = BytesMut::new();
+    compose(value, &mut buf);
+    let composed = buf.clone();
+    buf.extend_from_slice(suffix);
+    match parse(&mut buf) {
+        Ok(Async::Ready(ref parsed)) if parsed == value => { }
+        res => {
+            panic!("parsing composed {:?} from {:?} resulted in {:?}",
+                   value, composed, res)
+        }
+    }
+    assert_eq!(&buf[..], suffix,
+               "parsing composed {:?} left unexpected data", value);
+}
+
+/// Asserts that parsing `sample` and composing the result reproduces it.
+///
+/// The closure `parse` is applied to `sample` followed by `suffix`. It
+/// has to return ready and leave exactly `suffix` in the buffer. The
+/// closure `compose` then appends the parsed value to an empty buffer
+/// which needs to be equal to `sample` afterwards.
+///
+/// This only works for samples that already are in canonical form.
+pub fn assert_canonical<T, P, E, C>(sample: &[u8], parse: P, compose: C,
+                                    suffix: &[u8])
+                        where T: fmt::Debug,
+                              P: FnOnce(&mut BytesMut) -> Poll<T, E>,
+                              E: fmt::Debug,
+                              C: FnOnce(&T, &mut BytesMut) {
+    let mut buf = BytesMut::from(sample);
+    buf.extend_from_slice(suffix);
+    let value = match parse(&mut buf) {
+        Ok(Async::Ready(value)) => value,
+        res => panic!("parsing {:?} resulted in {:?}", sample, res)
+    };
+    assert_eq!(&buf[..], suffix, "parsing {:?} left unexpected data",
+               sample);
+    let mut composed = BytesMut::new();
+    compose(&value, &mut composed);
+    assert_eq!(&composed[..], sample,
+               "composing {:?} parsed from {:?} differs", value, sample);
+}
+
+/// Appends the `Display` output of `value`.
+///
+/// This can be used as the compose closure for types that format
+/// themselves in their wire form.
+pub fn compose_display<T: fmt::Display>(value: &T, target: &mut BytesMut) {
+    target.extend_from_slice(value.to_string().as_bytes())
+}
+
+
+//------------ Chunk Boundaries ----------------------------------------------
+
+/// Asserts that a parser behaves the same no matter how data arrives.
+///
+/// The parser is first applied to all of `input` at once. Then `input` is
+/// fed to it octet by octet and finally split into two parts at every
+/// possible position. Each time the parser returns non-ready, it must
+/// leave the buffer untouched. The final result and the data left in the
+/// buffer must be the same as when parsing all at once.
+///
+/// Returns the result of parsing all at once.
+pub fn feed_in_chunks<P, T, E>(mut parser: P, input: &[u8]) -> Poll<T, E>
+                      where P: FnMut(&mut BytesMut) -> Poll<T, E>,
+                            T: fmt::Debug + PartialEq,
+                            E: fmt::Debug + PartialEq {
+    let (expected, rest) = feed(&mut parser, input, &[]);
+    let octets: Vec<usize> = (1..input.len()).collect();
+    let splits = Some(octets).into_iter().chain(
+        (0..input.len()).map(|pos| vec![pos])
+    );
+    for splits in splits {
+        let (res, res_rest) = feed(&mut parser, input, &splits);
+        assert_eq!(res, expected,
+                   "different result for {:?} split at {:?}", input, splits);
+        assert_eq!(res_rest, rest,
+                   "different remaining data for {:?} split at {:?}",
+                   input, splits);
+    }
+    expected
+}
+
+/// Feeds `input` to `parser` in parts ending at `splits`.
+///
+/// Returns the result of the parser and the data remaining in the buffer
+/// with the rest of the input appended.
+fn feed<P, T, E>(parser: &mut P, input: &[u8], splits: &[usize])
+                 -> (Poll<T, E>, BytesMut)
+        where P: FnMut(&mut BytesMut) -> Poll<T, E> {
+    let mut buf = BytesMut::new();
+    let mut start = 0;
+    for &end in splits.iter().chain(Some(&input.len())) {
+        buf.extend_from_slice(&input[start..end]);
+        start = end;
+        let before = buf.clone();
+        match parser(&mut buf) {
+            Ok(Async::NotReady) => {
+                assert_eq!(buf, before,
+                           "non-ready after {} octets of {:?} changed the \
+                            buffer", end, input);
+            }
+            res => {
+                buf.extend_from_slice(&input[end..]);
+                return (res, buf)
+            }
+        }
+    }
+    (Ok(Async::NotReady), buf)
+}
+
+
+//------------ Corpora -------------------------------------------------------
+
+/// Checks a parser against a corpus of samples.
+///
+/// The corpus is a text with one sample per line. Empty lines and lines
+/// starting with `#` are ignored. Each sample line starts with a keyword
+/// followed by the input as a double-quoted string which may contain the
+/// escape sequences `\r`, `\n`, `\t`, `\\`, `\"`, and `\xHH`:
+///
+/// * `ok "…" => …` expects the parser to return ready with a value which
+///   `encode` turns into the text after the arrow,
+/// * `err "…"` expects the parser to return an error, and
+/// * `pending "…"` expects the parser to return non-ready.
+///
+/// The closure `encode` should produce a stable serialization of the
+/// value, such as that of a serde format, rather than its `Debug`
+/// formatting which may change between Rust versions and platforms.
+///
+/// All samples are checked and all failures reported together in a
+/// panic, as is an invalid line in the corpus. For values that differ,
+/// the report contains their encoding.
+pub fn check_corpus<P, T, E, F>(corpus: &str, mut parser: P, mut encode: F)
+                    where P: FnMut(&mut BytesMut) -> Poll<T, E>,
+                          T: fmt::Debug,
+                          E: fmt::Debug,
+                          F: FnMut(&T) -> String {
+    let mut failures = Vec::new();
+    for (num, line) in corpus.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let (keyword, input, expected) = match parse_sample(line) {
+            Some(sample) => sample,
+            None => panic!("line {}: invalid corpus line {:?}", num + 1, line)
+        };
+        let res = parser(&mut BytesMut::from(&input[..]));
+        let failure = match (keyword, &res) {
+            ("ok", &Ok(Async::Ready(ref value))) => {
+                let encoded = encode(value);
+                if encoded == expected { None } else { Some(encoded) }
+            }
+            ("err", &Err(_)) | ("pending", &Ok(Async::NotReady)) => None,
+            _ => Some(format!("{:?}", res))
+        };
+        if let Some(failure) = failure {
+            failures.push(format!("line {}: {} \"{}\" resulted in {}",
+                                  num + 1, keyword, escape(&input),
+                                  failure));
+        }
+    }
+    if !failures.is_empty() {
+        panic!("corpus failures:\n{}", failures.join("\n"))
+    }
+}
+
+/// Parses a sample line into keyword, input, and expected result.
+fn parse_sample(line: &str) -> Option<(&str, Vec<u8>, &str)> {
+    let (keyword, rest) = line.split_at(line.find(' ')?);
+    let mut chars = rest.trim_start().char_indices();
+    if chars.next()?.1 != '"' {
+        return None
+    }
+    let mut input = Vec::new();
+    let end = loop {
+        match chars.next()? {
+            (pos, '"') => break pos,
+            (_, '\\') => {
+                match chars.next()?.1 {
+                    'r' => input.push(b'\r'),
+                    'n' => input.push(b'\n'),
+                    't' => input.push(b'\t'),
+                    '\\' => input.push(b'\\'),
+                    '"' => input.push(b'"'),
+                    'x' => {
+                        let hi = chars.next()?.1.to_digit(16)?;
+                        let lo = chars.next()?.1.to_digit(16)?;
+                        input.push((hi << 4 | lo) as u8)
+                    }
+                    _ => return None
+                }
+            }
+            (_, ch) => {
+                let mut utf8 = [0; 4];
+                input.extend_from_slice(ch.encode_utf8(&mut utf8).as_bytes())
+            }
+        }
+    };
+    let rest = rest.trim_start()[end + 1..].trim();
+    match keyword {
+        "ok" if rest.starts_with("=>") => {
+            Some((keyword, input, rest[2..].trim()))
+        }
+        "err" | "pending" if rest.is_empty() => Some((keyword, input, "")),
+        _ => None
+    }
+}
+
+
+//------------ Formatting ----------------------------------------------------
+
+/// Returns a copy of the data in a buffer.
+pub fn contents<B: Buffer>(buf: &B) -> Vec<u8> {
+    (0..buf.len()).filter_map(|i| buf.get(i)).collect()
+}
+
+/// Returns data as a string with non-printable octets escaped.
+///
+/// The escape sequences are those of the corpus format used by
+/// `check_corpus()`.
+pub fn escape(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len());
+    for &ch in data {
+        match ch {
+            b'\r' => res.push_str("\\r"),
+            b'\n' => res.push_str("\\n"),
+            b'\t' => res.push_str("\\t"),
+            b'\\' => res.push_str("\\\\"),
+            b'"' => res.push_str("\\\""),
+            b' '..=b'~' => res.push(ch as char),
+            _ => res.push_str(&format!("\\x{:02x}", ch)),
+        }
+    }
+    res
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use bytes::{Bytes, BytesMut};
+    use ::{compose, http, mime, websocket};
+    use ::compose::base64::Base64Encoder;
+    use ::compose::chunked::ChunkedEncoder;
+    use ::core::{parse_line, skip_lwsp, u64_digits, u64_digits_exact,
+                 u64_hexdigs};
+    use ::grammar::RuleList;
+    use ::grammar::interp::Interpreter;
+    use ::octets::URI_UNRESERVED;
+    use ::parse::token::TokenError;
+    use ::ipaddr::{parse_ipv4_addr, parse_ipv6_addr};
+    use super::*;
+
+    fn digits(value: &u64, buf: &mut BytesMut) {
+        compose::digits(buf, *value)
+    }
+
+    fn hexdigs(value: &u64, buf: &mut BytesMut) {
+        compose::hexdigs(buf, *value)
+    }
+
+    fn display<T: fmt::Display>(value: &T, buf: &mut BytesMut) {
+        buf.extend_from_slice(value.to_string().as_bytes())
+    }
+
+    #[test]
+    fn integers() {
+        for &value in &[0, 1, 99, 1024, u64::MAX] {
+            assert_roundtrip(&value, digits, u64_digits, b" ");
+            assert_roundtrip(&value, hexdigs, u64_hexdigs, b" ");
+        }
+        assert_roundtrip(&7, |value, buf| {
+            compose::digits_exact(buf, *value, 3).unwrap()
+        }, |buf| u64_digits_exact(buf, 3), b"");
+        assert_canonical(b"1024", u64_digits, digits, b"\r\n");
+        assert_canonical(b"BEEF", u64_hexdigs, hexdigs, b"\r\n");
+    }
+
+    #[test]
+    fn ipaddrs() {
+        assert_roundtrip(&Ipv4Addr::new(192, 0, 2, 1), display,
+                         parse_ipv4_addr, b" ");
+        assert_canonical(b"2001:db8::1", parse_ipv6_addr,
+                         display::<Ipv6Addr>, b" ");
+    }
+
+    #[test]
+    fn quoted_strings() {
+        fn quoted(value: &Bytes, buf: &mut BytesMut) {
+            compose::quoted_string(buf, value).unwrap()
+        }
+
+        for value in &[&b""[..], b"foo bar", b"\tsay \"hi\"", b"a\\b\"\\"] {
+            let value = Bytes::copy_from_slice(value);
+            assert_roundtrip(&value, quoted, http::parse_quoted_string, b" ");
+            assert_roundtrip(&value, quoted, mime::parse_quoted_string,
+                             b"\r\n");
+        }
+        assert_canonical(b"\"a\\\"b\"", http::parse_quoted_string, quoted,
+                         b" ");
+    }
+
+    #[test]
+    fn dot_atoms() {
+        // The definition from RFC 5322.
+        let rules = RuleList::from_slice(
+            b"atext = ALPHA / DIGIT / \"!\" / \"#\" / \"$\" / \"%\" /\n \
+                      \"&\" / \"'\" / \"*\" / \"+\" / \"-\" / \"/\" /\n \
+                      \"=\" / \"?\" / \"^\" / \"_\" / \"`\" / \"{\" /\n \
+                      \"|\" / \"}\" / \"~\"\n\
+              dot-atom-text = 1*atext *(\".\" 1*atext)\n"
+        ).unwrap();
+        let interp = Interpreter::new(&rules);
+        for value in &[&b"a"[..], b"john.doe", b"x.y.z",
+                       b"{!#$%&'*+-/=?^_`|~}"] {
+            assert_roundtrip(&Bytes::copy_from_slice(value), |value, buf| {
+                compose::dot_atom(buf, value).unwrap()
+            }, |buf| interp.parse("dot-atom-text", buf), b" ");
+        }
+    }
+
+    #[test]
+    fn pct_encoded() {
+        // Percent-decoding happens for RFC 2231 extended parameters.
+        fn encode(value: &Bytes, buf: &mut BytesMut) {
+            buf.extend_from_slice(b"; x*=utf-8''");
+            compose::pct_encode(buf, value, &URI_UNRESERVED);
+        }
+
+        fn decode(buf: &mut BytesMut) -> Poll<Bytes, TokenError> {
+            let mut params = try_ready!(mime::parse_parameters(buf));
+            assert_eq!(params.len(), 1);
+            Ok(Async::Ready(params.remove(0).value))
+        }
+
+        for value in &[&b""[..], b"plain", b"100% sure", b"\x00\xff=;\"'"] {
+            assert_roundtrip(&Bytes::copy_from_slice(value), encode, decode,
+                             b"\r\n\r\n");
+        }
+    }
+
+    #[test]
+    fn chunked() {
+        type Body = (Vec<u8>, Vec<(Bytes, Bytes)>);
+
+        fn encode(value: &Body, buf: &mut BytesMut) {
+            let mut encoder = ChunkedEncoder::new();
+            encoder.set_max_chunk_size(3);
+            let (first, second) = value.0.split_at(value.0.len() / 2);
+            encoder.encode(first, buf);
+            encoder.encode(second, buf);
+            let trailers: Vec<_> = value.1.iter().map(|item| {
+                (item.0.as_ref(), item.1.as_ref())
+            }).collect();
+            encoder.finish(&trailers, buf).unwrap();
+        }
+
+        fn decode(buf: &mut BytesMut) -> Poll<Body, TokenError> {
+            let mut decoder = http::ChunkedDecoder::new();
+            let mut data = Vec::new();
+            loop {
+                match try_ready!(decoder.decode(buf)) {
+                    http::ChunkedPart::Data(part) => {
+                        data.extend_from_slice(&part)
+                    }
+                    http::ChunkedPart::End(trailers) => {
+                        return Ok(Async::Ready((data, trailers)))
+                    }
+                }
+            }
+        }
+
+        assert_roundtrip(&(Vec::new(), Vec::new()), encode, decode, b"");
+        assert_roundtrip(&(b"Hello, world!".to_vec(), Vec::new()),
+                         encode, decode, b"HTTP");
+        assert_roundtrip(&(b"a\r\n0\r\n".to_vec(), vec![
+            (Bytes::from_static(b"Expires"), Bytes::from_static(b"never")),
+            (Bytes::from_static(b"X-Sum"), Bytes::from_static(b"a b")),
+        ]), encode, decode, b"");
+    }
+
+    #[test]
+    fn base64() {
+        // The only Base64 decoder is that of the WebSocket key.
+        fn encode(value: &[u8; 16], buf: &mut BytesMut) {
+            let mut encoder = Base64Encoder::new();
+            encoder.encode(&value[..5], buf);
+            encoder.encode(&value[5..], buf);
+            encoder.finish(buf);
+        }
+
+        for value in &[[0u8; 16], [0xFF; 16], *b"the sample nonce"] {
+            assert_roundtrip(value, encode, websocket::parse_key, b"\r\n");
+        }
+        assert_canonical(b"dGhlIHNhbXBsZSBub25jZQ==", websocket::parse_key,
+                         encode, b"\r\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn not_canonical() {
+        assert_canonical(b"007", u64_digits, digits, b" ");
+    }
+
+    #[test]
+    fn chunks() {
+        assert_eq!(feed_in_chunks(u64_digits, b"1234 "),
+                   Ok(Async::Ready(1234)));
+        assert_eq!(feed_in_chunks(parse_line, b"foo\r\nbar"),
+                   Ok(Async::Ready((&b"foo\r\n"[..]).into())));
+        assert_eq!(feed_in_chunks(skip_lwsp, b" \r\n\tx"),
+                   Ok(Async::Ready(())));
+        assert_eq!(feed_in_chunks(parse_ipv6_addr, b"::ffff:1.2.3.4 "),
+                   Ok(Async::Ready("::ffff:1.2.3.4".parse().unwrap())));
+        assert!(feed_in_chunks(parse_ipv4_addr, b"1.2.3 ").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunks_consuming() {
+        // Consumes data even when it doesn’t have enough.
+        fn greedy(buf: &mut BytesMut) -> Poll<(), TokenError> {
+            if buf.len() < 2 {
+                buf.clear();
+                return Ok(Async::NotReady)
+            }
+            buf.clear();
+            Ok(Async::Ready(()))
+        }
+        let _ = feed_in_chunks(greedy, b"ab");
+    }
+
+    #[test]
+    fn escaped() {
+        assert_eq!(escape(b"a \"\\\r\n\t\x00\xff"),
+                   "a \\\"\\\\\\r\\n\\t\\x00\\xff");
+    }
+
+    #[test]
+    fn corpus() {
+        check_corpus("
+            # Some numbers.
+            ok \"12 \" => 12
+            ok \"\\x31\\x32\\r\\n\" => 12
+            err \"x\"
+            pending \"12\"
+        ", u64_digits, u64::to_string);
+    }
+
+    #[test]
+    #[should_panic]
+    fn corpus_failure() {
+        check_corpus("ok \"12 \" => 13", u64_digits, u64::to_string);
+    }
+}
@@ -0,0 +1,240 @@
+//! The header fields of the WebSocket opening handshake.
+//!
+//! This module implements the values of the `Sec-WebSocket-*` header
+//! fields defined in section 11.3 of RFC 6455. The values use the HTTP
+//! list syntax and are parsed with the rules of the [`http`] module.
+//!
+//! [`http`]: ../http/index.html
+
+use std::fmt;
+use bytes::Bytes;
+use futures::{Async, Poll};
+use ::compose::write_octets;
+use ::core::skip_opt_wsps;
+use ::http::{parse_list, parse_token, parse_token_or_quoted,
+             write_token_or_quoted};
+use ::parse::{rule, token};
+use ::parse::buffer::Buffer;
+use ::parse::token::TokenError;
+#[cfg(feature = "arbitrary")] use arbitrary::Arbitrary;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+
+//------------ Sec-WebSocket-Key ---------------------------------------------
+
+/// Parses the value of the `Sec-WebSocket-Key` header field.
+///
+/// The value is the Base64 encoding of a 16 octet nonce. Returns the
+/// decoded nonce. Since the encoding of 16 octets always ends in two
+/// padding characters, the rule ends with those and doesn’t need to see
+/// the octet following the value.
+pub fn parse_key<B: Buffer>(buf: &mut B) -> Poll<[u8; 16], TokenError> {
+    token::convert(buf, |token| {
+        try_ready!(token::repeat(token, 22, Some(22), |token| {
+            token::cat(token, |ch| base64_value(ch).is_some())
+        }));
+        token::literal(token, b"==")
+    }, |res| {
+        let encoded = res?;
+        let mut bits = 0u32;
+        let mut res = [0u8; 16];
+        let mut pos = 0;
+        for (i, &ch) in encoded[..22].iter().enumerate() {
+            bits = bits << 6 | u32::from(base64_value(ch).unwrap_or(0));
+            if i % 4 == 3 {
+                res[pos..pos + 3].copy_from_slice(&bits.to_be_bytes()[1..]);
+                bits = 0;
+                pos += 3;
+            }
+        }
+        // Two characters with twelve bits are left, four of which must
+        // be zero.
+        if bits & 0x0F != 0 {
+            return Err(TokenError::Invalid)
+        }
+        res[15] = (bits >> 4) as u8;
+        Ok(res)
+    })
+}
+
+/// Returns the value of a character of the Base64 alphabet.
+fn base64_value(ch: u8) -> Option<u8> {
+    match ch {
+        b'A'..=b'Z' => Some(ch - b'A'),
+        b'a'..=b'z' => Some(ch - b'a' + 26),
+        b'0'..=b'9' => Some(ch - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None
+    }
+}
+
+
+//------------ Sec-WebSocket-Protocol ----------------------------------------
+
+/// Parses the value of the `Sec-WebSocket-Protocol` header field.
+///
+/// The value is a non-empty list of subprotocol tokens.
+pub fn parse_protocols<B: Buffer>(buf: &mut B)
+                                  -> Poll<Vec<Bytes>, TokenError> {
+    parse_list(buf, 1, parse_token)
+}
+
+
+//------------ Sec-WebSocket-Extensions --------------------------------------
+
+/// An extension with its parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Extension {
+    /// The name of the extension.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_bytes))]
+    pub name: Bytes,
+
+    /// The parameters in the order they appeared.
+    ///
+    /// Parameters without a value have `None` as their value. For quoted
+    /// values, the value is unescaped.
+    #[cfg_attr(feature = "arbitrary",
+               arbitrary(with = ::fuzz::arbitrary_params))]
+    pub params: Vec<(Bytes, Option<Bytes>)>,
+}
+
+impl fmt::Display for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(f, &self.name)?;
+        for (name, value) in &self.params {
+            f.write_str("; ")?;
+            write_octets(f, name)?;
+            if let Some(ref value) = *value {
+                f.write_str("=")?;
+                write_token_or_quoted(f, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the value of the `Sec-WebSocket-Extensions` header field.
+///
+/// The value is a non-empty list of extensions, each with an optional
+/// list of parameters separated by semicolons.
+pub fn parse_extensions<B: Buffer>(buf: &mut B)
+                                   -> Poll<Vec<Extension>, TokenError> {
+    parse_list(buf, 1, parse_extension)
+}
+
+//  extension = extension-token *( ";" extension-param )
+fn parse_extension<B: Buffer>(buf: &mut B) -> Poll<Extension, TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_token(buf));
+        let mut params = Vec::new();
+        while let Some(param) = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(skip_opt_wsps(buf));
+                try_ready!(token::skip_octet(buf, b';'));
+                try_ready!(skip_opt_wsps(buf));
+                parse_extension_param(buf)
+            })
+        })) {
+            params.push(param)
+        }
+        Ok(Async::Ready(Extension { name, params }))
+    })
+}
+
+//  extension-param = token [ "=" (token | quoted-string) ]
+fn parse_extension_param<B: Buffer>(buf: &mut B)
+                                    -> Poll<(Bytes, Option<Bytes>),
+                                            TokenError> {
+    rule::group_limited(buf, |buf| {
+        let name = try_ready!(parse_token(buf));
+        let value = try_ready!(rule::optional(buf, |buf| {
+            rule::group_limited(buf, |buf| {
+                try_ready!(token::skip_octet(buf, b'='));
+                parse_token_or_quoted(buf)
+            })
+        }));
+        Ok(Async::Ready((name, value)))
+    })
+}
+
+
+//============ Test =========================================================
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use ::test_support::{assert_canonical, assert_roundtrip,
+                         compose_display};
+    use super::*;
+
+    fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+    #[test]
+    fn key() {
+        // The example from section 1.3 of RFC 6455.
+        assert_eq!(parse_key(&mut buf(b"dGhlIHNhbXBsZSBub25jZQ==")),
+                   Ok(Async::Ready(*b"the sample nonce")));
+        assert_eq!(parse_key(&mut buf(b"dGhlIHNhbXBsZSBub25jZR==")),
+                   Err(TokenError::Invalid));
+        assert_eq!(parse_key(&mut buf(b"dGhlIHNhbXBsZSBub25jZQ=")),
+                   Ok(Async::NotReady));
+        assert_eq!(parse_key(&mut buf(b"dGhlIHNhbXBsZSBub25j")),
+                   Ok(Async::NotReady));
+    }
+
+    #[test]
+    fn protocols() {
+        assert_eq!(parse_protocols(&mut buf(b"chat, superchat\r\n")),
+                   Ok(Async::Ready(vec![Bytes::from(&b"chat"[..]),
+                                        Bytes::from(&b"superchat"[..])])));
+    }
+
+    #[test]
+    fn extensions() {
+        let mut data = buf(b"permessage-deflate; client_max_window_bits, \
+                             foo; bar=\"1\";baz=2\r\n");
+        assert_eq!(parse_extensions(&mut data), Ok(Async::Ready(vec![
+            Extension {
+                name: Bytes::from(&b"permessage-deflate"[..]),
+                params: vec![
+                    (Bytes::from(&b"client_max_window_bits"[..]), None)
+                ]
+            },
+            Extension {
+                name: Bytes::from(&b"foo"[..]),
+                params: vec![
+                    (Bytes::from(&b"bar"[..]), Some(Bytes::from(&b"1"[..]))),
+                    (Bytes::from(&b"baz"[..]), Some(Bytes::from(&b"2"[..]))),
+                ]
+            }
+        ])));
+        assert_eq!(&data[..], b"\r\n");
+    }
+
+    #[test]
+    fn display() {
+        let extensions = |value: &Vec<Extension>, target: &mut BytesMut| {
+            for (i, extension) in value.iter().enumerate() {
+                if i > 0 {
+                    target.extend_from_slice(b", ")
+                }
+                compose_display(extension, target)
+            }
+        };
+
+        assert_canonical(b"permessage-deflate; client_max_window_bits, \
+                           foo; bar=1; baz=\"a b\"",
+                         parse_extensions, extensions, b"\r\n");
+        assert_roundtrip(&vec![Extension {
+            name: Bytes::from(&b"x"[..]),
+            params: vec![
+                (Bytes::from(&b"empty"[..]), Some(Bytes::new())),
+                (Bytes::from(&b"quote"[..]), Some(Bytes::from(&b"\"\\"[..]))),
+            ]
+        }], extensions, parse_extensions, b"\r\n");
+    }
+}
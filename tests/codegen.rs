@@ -0,0 +1,64 @@
+//! Checks the code generated for the grammar in `tests/codegen`.
+//!
+//! The generated code is checked in as `tests/codegen/mail.rs`. If the
+//! generator changes, `up_to_date` fails and the file needs to be
+//! regenerated.
+
+extern crate abnf;
+extern crate bytes;
+
+use abnf::grammar::RuleList;
+use abnf::grammar::codegen;
+use abnf::parse::token;
+use abnf::Async;
+use bytes::BytesMut;
+
+
+mod mail {
+    include!("codegen/mail.rs");
+
+    /// The rule left undefined by the grammar.
+    pub fn ip<B: ::abnf::parse::buffer::Buffer>(
+        token: &mut ::abnf::parse::token::Token<B>
+    ) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+        ::abnf::parse::token::repeat(token, 1, None, |token| {
+            ::abnf::parse::token::cat(token, |ch| {
+                ch == b'.' || ch.is_ascii_digit()
+            })
+        })
+    }
+}
+
+
+#[test]
+fn up_to_date() {
+    let rules = RuleList::from_slice(
+        include_bytes!("codegen/mail.abnf")
+    ).unwrap();
+    assert_eq!(codegen::generate(&rules), include_str!("codegen/mail.rs"));
+}
+
+#[test]
+fn generated() {
+    fn check<P>(data: &[u8], rule: P, rest: Option<&[u8]>)
+             where P: FnMut(&mut token::Token<BytesMut>)
+                            -> abnf::Poll<(), token::TokenError> {
+        let mut buf = BytesMut::from(data);
+        match token::parse(&mut buf, rule) {
+            Ok(Async::Ready(_)) => {
+                assert_eq!(Some(buf.as_ref()), rest, "{:?}", data)
+            }
+            Ok(Async::NotReady) => panic!("not ready: {:?}", data),
+            Err(_) => assert_eq!(rest, None, "{:?}", data),
+        }
+    }
+
+    check(b"foo.bar@example.com ", mail::address, Some(b" "));
+    check(b"a+b@[192.0.2.1]", mail::address, Some(b""));
+    check(b"@example.com ", mail::address, None);
+    check(b"ex-ample- x", mail::label, Some(b" x"));
+    check(b"TYPE 7\r\nx", mail::type_, Some(b"x"));
+    check(b"type 7\r\nx", mail::type_, None);
+    check(b"\xc4\x80", mail::type_, None);
+    check(b"some text\r\n", mail::line, Some(b""));
+}
@@ -0,0 +1,221 @@
+// Generated from an ABNF grammar. Do not edit.
+
+//  address = local "@" domain
+pub fn address<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match self::local(token) {
+        Ok(::abnf::Async::Ready(())) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::literal(token, b"@") {
+        Ok(::abnf::Async::Ready(())) => { }
+        res => return res
+    }
+    self::domain(token)
+}
+
+//  local = token *("." token)
+pub fn local<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match self::token(token) {
+        Ok(::abnf::Async::Ready(())) => { }
+        res => return res
+    }
+    ::abnf::parse::token::repeat(token, 0, None, |token| {
+        ::abnf::parse::token::group(token, |token| {
+            match ::abnf::parse::token::literal(token, b".") {
+                Ok(::abnf::Async::Ready(())) => { }
+                res => return res
+            }
+            self::token(token)
+        })
+    })
+}
+
+//  token = 1*atext
+pub fn token<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    ::abnf::parse::token::repeat(token, 1, None, self::atext)
+}
+
+//  atext = ALPHA / DIGIT / %x21 / %x23-27 / "+" / "-" / "_"
+pub fn atext<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match ::abnf::parse::token::group(token, ::abnf::core::alpha) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, ::abnf::core::digit) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::literal_exact(token, b"\x21")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::cat(token, |ch| (0x23..=0x27).contains(&ch))
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::literal(token, b"+")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::literal(token, b"-")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::literal(token, b"_")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    Err(::abnf::parse::token::TokenError::Invalid)
+}
+
+//  domain = label *("." label) / "[" ip "]"
+pub fn domain<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match ::abnf::parse::token::group(token, |token| {
+        match self::label(token) {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        ::abnf::parse::token::repeat(token, 0, None, |token| {
+            ::abnf::parse::token::group(token, |token| {
+                match ::abnf::parse::token::literal(token, b".") {
+                    Ok(::abnf::Async::Ready(())) => { }
+                    res => return res
+                }
+                self::label(token)
+            })
+        })
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        match ::abnf::parse::token::literal(token, b"[") {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        match self::ip(token) {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        ::abnf::parse::token::literal(token, b"]")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    Err(::abnf::parse::token::TokenError::Invalid)
+}
+
+//  label = let-dig [*61(let-dig / "-")]
+pub fn label<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match self::let_dig(token) {
+        Ok(::abnf::Async::Ready(())) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, |token| {
+        ::abnf::parse::token::repeat(token, 0, Some(61), |token| {
+            ::abnf::parse::token::group(token, |token| {
+                match ::abnf::parse::token::group(token, self::let_dig) {
+                    Err(::abnf::parse::token::TokenError::Invalid) => { }
+                    res => return res
+                }
+                match ::abnf::parse::token::group(token, |token| {
+                    ::abnf::parse::token::literal(token, b"-")
+                }) {
+                    Err(::abnf::parse::token::TokenError::Invalid) => { }
+                    res => return res
+                }
+                Err(::abnf::parse::token::TokenError::Invalid)
+            })
+        })
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => {
+            Ok(::abnf::Async::Ready(()))
+        }
+        res => res
+    }
+}
+
+//  let-dig = ALPHA / DIGIT
+pub fn let_dig<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match ::abnf::parse::token::group(token, ::abnf::core::alpha) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    match ::abnf::parse::token::group(token, ::abnf::core::digit) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    Err(::abnf::parse::token::TokenError::Invalid)
+}
+
+//  type = %s"TYPE" SP %d48-57 %d13.10 / %x100-1FF / <none>
+pub fn type_<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match ::abnf::parse::token::group(token, |token| {
+        match ::abnf::parse::token::literal_exact(token, b"TYPE") {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        match ::abnf::core::sp(token) {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        match ::abnf::parse::token::cat(token, |ch| (0x30..=0x39).contains(&ch)) {
+            Ok(::abnf::Async::Ready(())) => { }
+            res => return res
+        }
+        ::abnf::parse::token::literal_exact(token, b"\x0D\x0A")
+    }) {
+        Err(::abnf::parse::token::TokenError::Invalid) => { }
+        res => return res
+    }
+    Err(::abnf::parse::token::TokenError::Invalid)
+}
+
+//  line = *(VCHAR / WSP) CRLF
+pub fn line<B: ::abnf::parse::buffer::Buffer>(
+    token: &mut ::abnf::parse::token::Token<B>
+) -> ::abnf::Poll<(), ::abnf::parse::token::TokenError> {
+    match ::abnf::parse::token::repeat(token, 0, None, |token| {
+        ::abnf::parse::token::group(token, |token| {
+            match ::abnf::parse::token::group(token, ::abnf::core::vchar) {
+                Err(::abnf::parse::token::TokenError::Invalid) => { }
+                res => return res
+            }
+            match ::abnf::parse::token::group(token, ::abnf::core::wsp) {
+                Err(::abnf::parse::token::TokenError::Invalid) => { }
+                res => return res
+            }
+            Err(::abnf::parse::token::TokenError::Invalid)
+        })
+    }) {
+        Ok(::abnf::Async::Ready(())) => { }
+        res => return res
+    }
+    ::abnf::core::crlf(token)
+}
@@ -0,0 +1,100 @@
+//! Runs the bundled parsers against the corpora in `tests/corpus`.
+//!
+//! Each corpus line is prefixed with the name of the parser it is meant
+//! for. See `abnf::test_support::check_corpus()` for the format of the
+//! remainder. Expected values are given in their serde serialization as
+//! compact RON, which is why this test needs the `serde` feature.
+
+#![cfg(feature = "serde")]
+
+extern crate abnf;
+extern crate bytes;
+extern crate futures;
+extern crate ron;
+extern crate serde;
+
+use std::fmt;
+use abnf::{core, dnszone, http, ipaddr, mime, rtsp, websocket};
+use abnf::test_support::check_corpus;
+use bytes::BytesMut;
+use futures::Poll;
+use serde::Serialize;
+
+
+/// Checks the samples for the parser `name` in `corpus`.
+fn check<P, T, E>(corpus: &str, name: &str, parser: P)
+         where P: FnMut(&mut BytesMut) -> Poll<T, E>,
+               T: fmt::Debug + Serialize,
+               E: fmt::Debug {
+    let prefix = format!("{} ", name);
+    let samples: Vec<&str> = corpus.lines().filter_map(|line| {
+        line.strip_prefix(&prefix[..])
+    }).collect();
+    assert!(!samples.is_empty(), "no samples for {}", name);
+    check_corpus(&samples.join("\n"), parser, |value| {
+        ron::to_string(value).expect("value can’t be serialized")
+    })
+}
+
+#[test]
+fn ipaddr() {
+    let corpus = include_str!("corpus/ipaddr.txt");
+    check(corpus, "ipv4", ipaddr::parse_ipv4_addr);
+    check(corpus, "ipv6", ipaddr::parse_ipv6_addr);
+}
+
+#[test]
+fn core() {
+    let corpus = include_str!("corpus/core.txt");
+    check(corpus, "u8", core::u8_digits);
+    check(corpus, "u8_strict", core::u8_digits_strict);
+    check(corpus, "i16", core::i16_digits);
+    check(corpus, "float", core::f64_float);
+    check(corpus, "qvalue", core::u16_qvalue);
+    check(corpus, "line", core::parse_line_content);
+}
+
+#[test]
+fn http() {
+    let corpus = include_str!("corpus/http.txt");
+    check(corpus, "token", http::parse_token);
+    check(corpus, "quoted", http::parse_quoted_string);
+    check(corpus, "date", http::parse_http_date);
+    check(corpus, "date_time", http::parse_http_date_time);
+    check(corpus, "delta", http::parse_delta_seconds);
+    check(corpus, "retry_after", http::parse_retry_after);
+    check(corpus, "field_line", http::parse_field_line);
+}
+
+#[test]
+fn mime() {
+    let corpus = include_str!("corpus/mime.txt");
+    check(corpus, "version", mime::parse_mime_version);
+    check(corpus, "cte", mime::parse_content_transfer_encoding);
+    check(corpus, "params", mime::parse_parameters);
+}
+
+#[test]
+fn rtsp() {
+    let corpus = include_str!("corpus/rtsp.txt");
+    check(corpus, "request_line", rtsp::parse_request_line);
+    check(corpus, "status_line", rtsp::parse_status_line);
+    check(corpus, "cseq", rtsp::parse_cseq);
+    check(corpus, "transport", rtsp::parse_transports);
+    check(corpus, "session", rtsp::parse_session);
+}
+
+#[test]
+fn websocket() {
+    let corpus = include_str!("corpus/websocket.txt");
+    check(corpus, "key", websocket::parse_key);
+    check(corpus, "protocols", websocket::parse_protocols);
+    check(corpus, "extensions", websocket::parse_extensions);
+}
+
+#[test]
+fn dnszone() {
+    let corpus = include_str!("corpus/dnszone.txt");
+    check(corpus, "entry", dnszone::parse_entry);
+    check(corpus, "char_string", dnszone::parse_char_string);
+}
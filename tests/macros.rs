@@ -0,0 +1,69 @@
+//! Uses the exported macros the way a downstream crate would.
+//!
+//! The macros are imported by path and the crate is the only dependency
+//! in scope, so the macros must resolve everything through `$crate`.
+
+extern crate abnf;
+
+use abnf::{alt, assert_consumed, assert_eq_ready, assert_not_ready,
+           assert_parse_err, repeat_abnf, rule, seq, try_fail, try_opt,
+           try_ready, try_result};
+use abnf::core::{alpha, digit, u8_digits};
+use abnf::parse::token::{self, TokenError};
+use abnf::prelude::BytesMut;
+use abnf::{Async, Poll};
+
+
+fn buf(slice: &[u8]) -> BytesMut { BytesMut::from(slice) }
+
+rule!(word = 1* alpha);
+rule!(number = 1* digit);
+
+fn pair(buf: &mut BytesMut) -> Poll<(u8, u8), TokenError> {
+    let (left, _, right) = try_ready!(seq!(buf, u8_digits,
+                                           token::skip_octet(b'/'),
+                                           u8_digits));
+    Ok(Async::Ready((left, right)))
+}
+
+fn word_or_number(buf: &mut BytesMut) -> Poll<(), TokenError> {
+    alt!(buf, skip_word, skip_number)
+}
+
+fn skip_word(buf: &mut BytesMut) -> Poll<(), TokenError> {
+    token::skip(buf, word)
+}
+
+fn skip_number(buf: &mut BytesMut) -> Poll<(), TokenError> {
+    token::skip(buf, number)
+}
+
+fn digits_or_zero(buf: &mut BytesMut) -> Poll<u8, TokenError> {
+    let _ = try_fail!(u8_digits(buf));
+    match try_result!(token::skip_octet(buf, b'-')) {
+        Ok(()) => Ok(Async::Ready(0)),
+        Err(err) => Err(err)
+    }
+}
+
+fn nonzero(buf: &mut BytesMut) -> Poll<u8, TokenError> {
+    try_opt!(u8_digits(buf).map(|res| {
+        res.map(|x| if x > 0 { Some(x) } else { None })
+    }));
+    Err(TokenError::Invalid)
+}
+
+#[test]
+fn macros() {
+    assert_eq_ready!(pair(&mut buf(b"4/5 ")), (4, 5));
+    assert_eq_ready!(word_or_number(&mut buf(b"12 ")), ());
+    assert_parse_err!(word_or_number(&mut buf(b"- ")), TokenError::Invalid);
+    assert_eq_ready!(digits_or_zero(&mut buf(b"-")), 0);
+    assert_eq_ready!(nonzero(&mut buf(b"7 ")), 7);
+    assert_not_ready!(pair(&mut buf(b"4/")));
+
+    let mut data = buf(b"1.2.3");
+    let res = assert_consumed!(data, 4, repeat_abnf!(&mut data,
+                                                     2 (u8_digits ".")));
+    assert_eq!(res, Ok(Async::Ready(vec![1, 2])));
+}